@@ -1,9 +1,11 @@
 use once_cell::sync::OnceCell;
-use orm::{schoology_request_tokens, sessions};
+use orm::{notifications, schoology_request_tokens, sessions};
 use sea_orm::{
     ColumnTrait, ConnectOptions, Database, DatabaseConnection, EntityTrait, QueryFilter,
 };
 
+use crate::{schoology::cache, utils::notifications::NOTIFICATION_TTL_DAYS};
+
 static CLIENT: OnceCell<DatabaseConnection> = OnceCell::new();
 
 pub fn get_db_client() -> &'static DatabaseConnection {
@@ -69,4 +71,24 @@ pub async fn cronjob_clear_old() {
     if let Err(err) = result {
         error!("Failed to delete expired sessions: {:?}", err);
     }
+
+    info!("Clearing old notifications...");
+
+    let result = notifications::Entity::delete_many()
+        .filter(
+            notifications::Column::CreatedAt
+                .lt(chrono::Utc::now() - chrono::Duration::days(NOTIFICATION_TTL_DAYS)),
+        )
+        .exec(db_client)
+        .await;
+
+    if let Err(err) = result {
+        error!("Failed to delete old notifications: {:?}", err);
+    }
+
+    info!("Clearing expired Schoology cache entries...");
+
+    if cache::clear_expired(db_client).await.is_err() {
+        error!("Failed to delete expired Schoology cache entries");
+    }
 }
@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+use once_cell::sync::OnceCell;
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+    rand::{SecureRandom, SystemRandom},
+};
+
+static KEYRING: OnceCell<Keyring> = OnceCell::new();
+
+/// The master keys (key encryption keys) used for envelope encryption
+pub struct Keyring {
+    /// The id of the key used to encrypt new data
+    current: String,
+    keys: HashMap<String, LessSafeKey>,
+}
+
+/// A per-row data key, stored encrypted (wrapped) with one of the master keys
+pub struct DataKey {
+    key: LessSafeKey,
+}
+
+/// A newly generated data key and it's stored representation
+pub struct Envelope {
+    /// The id of the master key used to wrap the data key
+    pub key_id: String,
+    /// The wrapped data key (base64 of `nonce || ciphertext || tag`)
+    pub wrapped_key: String,
+    pub data_key: DataKey,
+}
+
+pub fn get_keyring() -> &'static Keyring {
+    KEYRING.get().expect("Encryption keyring not initialized")
+}
+
+/// Creates the keyring from a list of `<key id>:<base64 key>` pairs separated by commas
+/// The first key is used to encrypt new data, the rest are only used to decrypt
+pub async fn create_keyring(keys: String) -> Result<(), String> {
+    info!("Creating encryption keyring...");
+
+    let mut current = None;
+    let mut keyring = HashMap::new();
+
+    for entry in keys.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (id, key) = entry
+            .split_once(':')
+            .ok_or_else(|| "Keys must be in the format `<key id>:<base64 key>`".to_string())?;
+
+        let key = STANDARD_NO_PAD
+            .decode(key.trim_end_matches('='))
+            .map_err(|e| format!("Failed to decode key {}: {}", id, e))?;
+
+        let key = UnboundKey::new(&AES_256_GCM, &key)
+            .map_err(|_| format!("Key {} must be 32 bytes long", id))?;
+
+        if keyring
+            .insert(id.to_string(), LessSafeKey::new(key))
+            .is_some()
+        {
+            return Err(format!("Duplicate key id {}", id));
+        }
+
+        current.get_or_insert_with(|| id.to_string());
+    }
+
+    let current = current.ok_or_else(|| "At least one key is required".to_string())?;
+
+    info!("Using encryption key {}", current);
+
+    KEYRING
+        .set(Keyring {
+            current,
+            keys: keyring,
+        })
+        .map_err(|_| "Failed to set encryption keyring".to_string())
+}
+
+/// Encrypts with AES-256-GCM using a random nonce, returns base64 of `nonce || ciphertext || tag`
+fn seal(key: &LessSafeKey, plaintext: &[u8], aad: &str) -> Result<String, ()> {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new().fill(&mut nonce).map_err(|err| {
+        error!("Failed to generate nonce: {:?}", err);
+    })?;
+
+    let mut in_out = plaintext.to_vec();
+    key.seal_in_place_append_tag(
+        Nonce::assume_unique_for_key(nonce),
+        Aad::from(aad.as_bytes()),
+        &mut in_out,
+    )
+    .map_err(|err| {
+        error!("Failed to encrypt: {:?}", err);
+    })?;
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend(in_out);
+
+    Ok(STANDARD_NO_PAD.encode(sealed))
+}
+
+/// Decrypts the output of `seal`
+fn open(key: &LessSafeKey, sealed: &str, aad: &str) -> Result<Vec<u8>, ()> {
+    let sealed = STANDARD_NO_PAD.decode(sealed).map_err(|err| {
+        warn!("Failed to decode encrypted data: {:?}", err);
+    })?;
+
+    if sealed.len() < NONCE_LEN {
+        warn!("Encrypted data is too short");
+        return Err(());
+    }
+
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| ())?;
+
+    let mut in_out = ciphertext.to_vec();
+    let plaintext = key
+        .open_in_place(nonce, Aad::from(aad.as_bytes()), &mut in_out)
+        .map_err(|err| {
+            warn!("Failed to decrypt: {:?}", err);
+        })?;
+
+    Ok(plaintext.to_vec())
+}
+
+impl Keyring {
+    /// Generates a new data key wrapped with the current master key
+    /// `aad` binds the wrapped key to where it is stored
+    pub fn new_envelope(&self, aad: &str) -> Result<Envelope, ()> {
+        let mut key = [0u8; 32];
+        SystemRandom::new().fill(&mut key).map_err(|err| {
+            error!("Failed to generate data key: {:?}", err);
+        })?;
+
+        let master_key = self.keys.get(&self.current).ok_or(())?;
+        let wrapped_key = seal(master_key, &key, aad)?;
+
+        let key = UnboundKey::new(&AES_256_GCM, &key).map_err(|_| ())?;
+
+        Ok(Envelope {
+            key_id: self.current.clone(),
+            wrapped_key,
+            data_key: DataKey {
+                key: LessSafeKey::new(key),
+            },
+        })
+    }
+
+    /// Unwraps a stored data key
+    pub fn open_envelope(&self, key_id: &str, wrapped_key: &str, aad: &str) -> Result<DataKey, ()> {
+        let master_key = self.keys.get(key_id).ok_or_else(|| {
+            error!("Unknown encryption key {}", key_id);
+        })?;
+
+        let key = open(master_key, wrapped_key, aad)?;
+        let key = UnboundKey::new(&AES_256_GCM, &key).map_err(|_| {
+            warn!("Unwrapped data key has an invalid length");
+        })?;
+
+        Ok(DataKey {
+            key: LessSafeKey::new(key),
+        })
+    }
+}
+
+impl DataKey {
+    /// Encrypts a string, `aad` binds the ciphertext to where it is stored
+    pub fn encrypt(&self, plaintext: &str, aad: &str) -> Result<String, ()> {
+        seal(&self.key, plaintext.as_bytes(), aad)
+    }
+
+    /// Decrypts a string encrypted with `encrypt`
+    pub fn decrypt(&self, ciphertext: &str, aad: &str) -> Result<String, ()> {
+        String::from_utf8(open(&self.key, ciphertext, aad)?).map_err(|err| {
+            warn!("Decrypted data is not valid UTF-8: {:?}", err);
+        })
+    }
+}
@@ -0,0 +1,205 @@
+//! Liveness (`/healthz`) and readiness (`/readyz`) checks, see /docs/development/health.md
+
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use actix_web::{web, HttpResponse};
+use migrations::{seaql_migrations, Migrator, MigratorTrait};
+use sea_orm::{EntityTrait, QueryOrder};
+use serde_json::{json, Value};
+
+use crate::{database::get_db_client, schoology::get_schoology_client};
+
+/// How long a single check can take before it fails
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What `/readyz` checks besides the database
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReadinessChecks {
+    /// Whether Schoology has to be reachable, off by default as the API works without it
+    pub schoology: bool,
+}
+
+/// The applied migrations compared to the ones the server was built with
+#[derive(Debug, Clone)]
+pub struct MigrationState {
+    /// The latest migration of the server
+    pub expected: Option<String>,
+    /// The latest migration applied to the database
+    pub applied: Option<String>,
+    /// Migrations the server needs that are not applied yet
+    pub pending: Vec<String>,
+    /// Migrations applied by a newer version of the server
+    pub unknown: Vec<String>,
+}
+
+impl MigrationState {
+    /// The database has every table the server needs (a newer schema is fine, migrations run before deploys)
+    pub fn is_current(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// The connections of the database pool
+#[derive(Debug, Clone, Copy)]
+pub struct PoolUsage {
+    /// Open connections (idle or in use)
+    pub size: u32,
+    pub idle: usize,
+    pub max: u32,
+}
+
+async fn timed<F, T, E>(check: F) -> Result<Duration, String>
+where
+    F: Future<Output = Result<T, E>>,
+    E: std::fmt::Display,
+{
+    let start = Instant::now();
+
+    match tokio::time::timeout(CHECK_TIMEOUT, check).await {
+        Ok(Ok(_)) => Ok(start.elapsed()),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err(format!("Timed out after {:?}", CHECK_TIMEOUT)),
+    }
+}
+
+/// Pings the database, returns how long it took
+pub async fn check_database() -> Result<Duration, String> {
+    timed(get_db_client().ping()).await.map_err(|e| {
+        warn!("Database health check failed: {}", e);
+        e
+    })
+}
+
+/// Compares the applied migrations to `Migrator`
+pub async fn check_migrations() -> Result<MigrationState, ()> {
+    let expected = Migrator::migrations()
+        .iter()
+        .map(|migration| migration.name().to_string())
+        .collect::<Vec<String>>();
+
+    let query = seaql_migrations::Entity::find()
+        .order_by_asc(seaql_migrations::Column::Version)
+        .all(get_db_client());
+
+    let applied = match tokio::time::timeout(CHECK_TIMEOUT, query).await {
+        Ok(Ok(applied)) => applied
+            .into_iter()
+            .map(|migration| migration.version)
+            .collect::<Vec<String>>(),
+        Ok(Err(e)) => {
+            warn!("Failed to get the applied migrations: {:?}", e);
+            return Err(());
+        }
+        Err(_) => {
+            warn!("Getting the applied migrations timed out");
+            return Err(());
+        }
+    };
+
+    Ok(MigrationState {
+        pending: expected
+            .iter()
+            .filter(|version| !applied.contains(version))
+            .cloned()
+            .collect(),
+        unknown: applied
+            .iter()
+            .filter(|version| !expected.contains(version))
+            .cloned()
+            .collect(),
+        expected: expected.last().cloned(),
+        applied: applied.last().cloned(),
+    })
+}
+
+/// Checks that Schoology is reachable, returns how long it took
+pub async fn check_schoology() -> Result<Duration, String> {
+    timed(get_schoology_client().ping()).await.map_err(|e| {
+        warn!("Schoology health check failed: {}", e);
+        e
+    })
+}
+
+/// The connections of the database pool
+pub fn pool_usage() -> PoolUsage {
+    // The pool is only exposed with the `sea-orm-internal` feature
+    let pool = get_db_client().get_postgres_connection_pool();
+
+    PoolUsage {
+        size: pool.size(),
+        idle: pool.num_idle(),
+        max: pool.options().get_max_connections(),
+    }
+}
+
+fn check_value(result: &Result<Duration, String>) -> Value {
+    match result {
+        Ok(latency) => json!({ "ok": true, "latency_ms": latency.as_millis() as u64 }),
+        Err(error) => json!({ "ok": false, "error": error }),
+    }
+}
+
+/// The server is running (it doesn't check anything else, a restart wouldn't fix the database)
+async fn healthz() -> HttpResponse {
+    HttpResponse::Ok().json(json!({ "status": "ok" }))
+}
+
+/// The server can handle requests
+async fn readyz(checks: web::Data<ReadinessChecks>) -> HttpResponse {
+    let database = check_database().await;
+    let mut ready = database.is_ok();
+
+    let mut response = json!({
+        "database": check_value(&database),
+    });
+
+    // Without the database there is nothing else to check
+    let migrations = match database {
+        Ok(_) => check_migrations().await,
+        Err(_) => Err(()),
+    };
+
+    response["migrations"] = match migrations {
+        Ok(migrations) => {
+            ready &= migrations.is_current();
+
+            json!({
+                "ok": migrations.is_current(),
+                "expected": migrations.expected,
+                "applied": migrations.applied,
+                "pending": migrations.pending,
+            })
+        }
+        Err(_) => {
+            ready = false;
+            json!({ "ok": false })
+        }
+    };
+
+    if checks.schoology {
+        let schoology = check_schoology().await;
+        ready &= schoology.is_ok();
+        response["schoology"] = check_value(&schoology);
+    }
+
+    let mut builder = if ready {
+        HttpResponse::Ok()
+    } else {
+        HttpResponse::ServiceUnavailable()
+    };
+
+    builder.json(json!({
+        "status": if ready { "ready" } else { "not_ready" },
+        "checks": response,
+    }))
+}
+
+/// Adds `/healthz` and `/readyz`
+pub fn configure(cfg: &mut web::ServiceConfig, checks: ReadinessChecks) {
+    cfg.app_data(web::Data::new(checks))
+        .route("/healthz", web::get().to(healthz))
+        .route("/readyz", web::get().to(readyz));
+}
@@ -0,0 +1,110 @@
+//! The cronjobs, and what they are doing (for the status endpoint)
+
+use std::{
+    future::Future,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use tokio_cron_scheduler::{Job, JobScheduler};
+
+static JOBS: Lazy<Mutex<Vec<JobState>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// The state of a job
+#[derive(Debug, Clone)]
+pub struct JobState {
+    pub name: &'static str,
+    pub interval: Duration,
+    /// Whether a run is in progress
+    pub running: bool,
+    /// How many runs finished
+    pub runs: u64,
+    pub last_started: Option<DateTime<Utc>>,
+    pub last_finished: Option<DateTime<Utc>>,
+    /// How long the last finished run took
+    pub last_duration: Option<Duration>,
+}
+
+fn lock() -> MutexGuard<'static, Vec<JobState>> {
+    JOBS.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Tracks a job, it's runs are recorded by `run`
+pub fn register(name: &'static str, interval: Duration) {
+    let mut jobs = lock();
+
+    if jobs.iter().any(|job| job.name == name) {
+        warn!("Job {} is already registered", name);
+        return;
+    }
+
+    jobs.push(JobState {
+        name,
+        interval,
+        running: false,
+        runs: 0,
+        last_started: None,
+        last_finished: None,
+        last_duration: None,
+    });
+}
+
+fn update(name: &str, update: impl FnOnce(&mut JobState)) {
+    match lock().iter_mut().find(|job| job.name == name) {
+        Some(job) => update(job),
+        None => debug!("Job {} is not registered", name),
+    }
+}
+
+/// Runs a job, recording when it started and finished
+pub async fn run<F>(name: &'static str, job: F)
+where
+    F: Future<Output = ()>,
+{
+    let start = Instant::now();
+
+    update(name, |state| {
+        state.running = true;
+        state.last_started = Some(Utc::now());
+    });
+
+    job.await;
+
+    update(name, |state| {
+        state.running = false;
+        state.runs += 1;
+        state.last_finished = Some(Utc::now());
+        state.last_duration = Some(start.elapsed());
+    });
+}
+
+/// Adds a job that runs every `interval` to the scheduler
+pub async fn add_job<F, Fut>(
+    scheduler: &JobScheduler,
+    name: &'static str,
+    interval: Duration,
+    job: F,
+) -> Result<(), String>
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let job = Job::new_repeated_async(interval, move |_uuid, _lock| Box::pin(run(name, job())))
+        .map_err(|e| format!("Failed to create job {}: {}", name, e))?;
+
+    scheduler
+        .add(job)
+        .await
+        .map_err(|e| format!("Failed to add job {}: {}", name, e))?;
+
+    register(name, interval);
+
+    Ok(())
+}
+
+/// Every registered job
+pub fn list() -> Vec<JobState> {
+    lock().clone()
+}
@@ -9,16 +9,36 @@ use actix_web::{
 use chrono::Duration;
 use glob_match::glob_match;
 use tokio::signal::unix::SignalKind;
-use tokio_cron_scheduler::{Job, JobScheduler};
+use tokio_cron_scheduler::JobScheduler;
 
 use crate::{
-    database::{create_db_client, cronjob_clear_old},
-    schoology::create_schoology_client,
-    v1::create_v1_service,
+    database::{create_db_client, cronjob_clear_old, get_db_client},
+    encryption::create_keyring,
+    health::ReadinessChecks,
+    jobs::add_job,
+    push::{create_push_client, generate_vapid_key, DEFAULT_ENDPOINT_HOSTS},
+    schoology::{
+        cache::{create_cache, CacheBackend},
+        create_schoology_client,
+    },
+    utils::{
+        grade_poll::cronjob_poll_grades, message_poll::cronjob_poll_messages,
+        push::cronjob_deliver_pushes,
+    },
+    v1::{
+        calendar::redact_feed_token, create_v1_service, rate_limit::RateLimit, set_proxy_hops,
+        DEFAULT_PROXY_HOPS,
+    },
 };
 
 mod database;
+mod encryption;
+mod health;
+mod jobs;
+mod push;
 mod schoology;
+#[cfg(test)]
+mod testing;
 pub mod utils;
 mod v1;
 
@@ -28,7 +48,7 @@ async fn not_found() -> actix_web::HttpResponse {
         .body("Rawr 🦖! This page was not found!")
 }
 
-async fn server() -> Result<(), String> {
+async fn server(readiness_checks: ReadinessChecks) -> Result<(), String> {
     // Get the port from the environment
     let port = std::env::var("PORT")
         .unwrap_or("8080".to_string())
@@ -37,9 +57,17 @@ async fn server() -> Result<(), String> {
 
     info!("Starting server at [::1]:{}", port);
 
-    let server = HttpServer::new(|| {
+    // Shared by every worker
+    let rate_limit = RateLimit::default();
+
+    let server = HttpServer::new(move || {
         App::new()
-            .service(web::scope("/api").service(create_v1_service()))
+            .configure(|cfg| health::configure(cfg, readiness_checks))
+            .service(
+                web::scope("/api")
+                    .wrap(rate_limit.clone())
+                    .service(create_v1_service()),
+            )
             .default_service(web::route().to(not_found))
             // Strip the `Forwarded` header cause GCP uses `X-Forwarded-For`. See: https://cloud.google.com/load-balancing/docs/https
             // This prevents ip spoofing
@@ -50,7 +78,20 @@ async fn server() -> Result<(), String> {
 
                 srv.call(req)
             })
-            .wrap(middleware::Logger::default())
+            // The default format, but without the tokens of calendar feeds
+            .wrap(
+                middleware::Logger::new(
+                    r#"%a "%{request_line}xi" %s %b "%{Referer}i" "%{User-Agent}i" %T"#,
+                )
+                .custom_request_replace("request_line", |req| {
+                    let path = redact_feed_token(req.path());
+
+                    match req.query_string() {
+                        "" => format!("{} {} {:?}", req.method(), path, req.version()),
+                        query => format!("{} {}?{} {:?}", req.method(), path, query, req.version()),
+                    }
+                }),
+            )
             .wrap(middleware::Compress::default())
             .wrap(Cors::default().allowed_origin_fn(|origin, _re_head| {
                 // Match the glob for cors origins
@@ -81,6 +122,21 @@ async fn main() {
 
     pretty_env_logger::init();
 
+    // `app generate-vapid-key` prints a new key pair for `VAPID_PRIVATE_KEY` and exits
+    if std::env::args().nth(1).as_deref() == Some("generate-vapid-key") {
+        match generate_vapid_key() {
+            Ok((private_key, public_key)) => {
+                println!("VAPID_PRIVATE_KEY={}", private_key);
+                println!("# Public key: {}", public_key);
+                std::process::exit(0);
+            }
+            Err(e) => {
+                error!("Failed to generate VAPID key: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
     // Database stuff
     let max_connections = std::env::var("DB_MAX_CONNECTIONS")
         .unwrap_or("10".to_string())
@@ -107,13 +163,93 @@ async fn main() {
         }
     };
 
+    // Encryption stuff
+    let encryption_keys = std::env::var("ENCRYPTION_KEYS").expect("ENCRYPTION_KEYS must be set");
+
+    match create_keyring(encryption_keys).await {
+        Ok(_) => info!("Encryption keyring created"),
+        Err(e) => {
+            error!("Failed to create encryption keyring: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // `app rotate-keys` re-encrypts all the stored Schoology tokens with the current key and exits
+    if std::env::args().nth(1).as_deref() == Some("rotate-keys") {
+        match utils::schoology_link::rotate_keys(get_db_client()).await {
+            Ok(count) => {
+                info!("Re-encrypted {} Schoology links", count);
+                std::process::exit(0);
+            }
+            Err(_) => {
+                error!("Failed to re-encrypt Schoology links");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Plaintext tokens must not stay in the database, so the server does not start without encrypting them
+    match utils::schoology_link::encrypt_all_legacy(get_db_client()).await {
+        Ok(count) => info!("Encrypted {} legacy Schoology links", count),
+        Err(_) => {
+            error!("Failed to encrypt legacy Schoology links");
+            std::process::exit(1);
+        }
+    };
+
+    // Session stuff
+    let session_pepper = std::env::var("SESSION_PEPPER").expect("SESSION_PEPPER must be set");
+
+    if let Err(e) = utils::sessions::set_pepper(session_pepper) {
+        error!("Failed to set session pepper: {}", e);
+        std::process::exit(1);
+    }
+
+    // Raw tokens must not stay in the database, so the server does not start without hashing them
+    match utils::sessions::upgrade_all_legacy(get_db_client()).await {
+        Ok(count) => info!("Hashed {} legacy session tokens", count),
+        Err(_) => {
+            error!("Failed to hash legacy session tokens");
+            std::process::exit(1);
+        }
+    };
+
     // Schoology stuff
     let consumer_key =
         std::env::var("SCHOOLOGY_CONSUMER_KEY").expect("SCHOOLOGY_CONSUMER_KEY must be set");
     let consumer_secret =
         std::env::var("SCHOOLOGY_CONSUMER_SECRET").expect("SCHOOLOGY_CONSUMER_SECRET must be set");
 
-    match create_schoology_client(consumer_key, consumer_secret).await {
+    let schoology_base_url = std::env::var("SCHOOLOGY_BASE_URL").ok();
+    let schoology_timeout = std::env::var("SCHOOLOGY_TIMEOUT")
+        .unwrap_or("30".to_string())
+        .parse::<usize>()
+        .unwrap_or(30);
+    let schoology_rate_limit = std::env::var("SCHOOLOGY_RATE_LIMIT")
+        .unwrap_or(::schoology::limiter::DEFAULT_REQUESTS.to_string())
+        .parse::<u32>()
+        .unwrap_or(::schoology::limiter::DEFAULT_REQUESTS);
+
+    // `memory` (the default) or `postgres` to share the cache between instances
+    let cache_backend = std::env::var("SCHOOLOGY_CACHE").unwrap_or("memory".to_string());
+
+    match CacheBackend::from_name(&cache_backend).and_then(create_cache) {
+        Ok(_) => info!("Schoology cache created ({})", cache_backend),
+        Err(e) => {
+            error!("Failed to create Schoology cache: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    match create_schoology_client(
+        consumer_key,
+        consumer_secret,
+        schoology_base_url,
+        schoology_timeout,
+        schoology_rate_limit,
+    )
+    .await
+    {
         Ok(_) => info!("Schoology client created"),
         Err(e) => {
             error!("Failed to create Schoology client: {}", e);
@@ -121,6 +257,72 @@ async fn main() {
         }
     }
 
+    // How many `X-Forwarded-For` entries the proxies (the load balancer) append, the client IP is the first of them
+    let proxy_hops = std::env::var("TRUSTED_PROXY_HOPS")
+        .unwrap_or(DEFAULT_PROXY_HOPS.to_string())
+        .parse::<usize>()
+        .unwrap_or(DEFAULT_PROXY_HOPS);
+
+    if let Err(e) = set_proxy_hops(proxy_hops) {
+        error!("Failed to set proxy hops: {}", e);
+        std::process::exit(1);
+    }
+
+    // Schedule stuff
+    let school_timezone = std::env::var("SCHOOL_TIMEZONE").expect("SCHOOL_TIMEZONE must be set");
+
+    if let Err(e) = utils::schedule::set_timezone(&school_timezone) {
+        error!("Failed to set school timezone: {}", e);
+        std::process::exit(1);
+    }
+
+    // Push stuff (optional, push notifications are disabled without a VAPID key)
+    let push_enabled = match std::env::var("VAPID_PRIVATE_KEY").ok() {
+        Some(vapid_key) => {
+            let vapid_subject = std::env::var("VAPID_SUBJECT")
+                .expect("VAPID_SUBJECT must be set with VAPID_PRIVATE_KEY");
+
+            let endpoint_hosts = match std::env::var("PUSH_ENDPOINT_HOSTS") {
+                Ok(hosts) => hosts
+                    .split(',')
+                    .map(|host| host.trim().to_string())
+                    .filter(|host| !host.is_empty())
+                    .collect(),
+                Err(_) => DEFAULT_ENDPOINT_HOSTS
+                    .iter()
+                    .map(|host| host.to_string())
+                    .collect(),
+            };
+
+            let push_timeout = std::env::var("PUSH_TIMEOUT")
+                .unwrap_or("10".to_string())
+                .parse::<usize>()
+                .unwrap_or(10);
+
+            match create_push_client(
+                vapid_key,
+                vapid_subject,
+                endpoint_hosts,
+                push_timeout,
+                false,
+            )
+            .await
+            {
+                Ok(_) => info!("Push client created"),
+                Err(e) => {
+                    error!("Failed to create push client: {}", e);
+                    std::process::exit(1);
+                }
+            }
+
+            true
+        }
+        None => {
+            warn!("VAPID_PRIVATE_KEY is not set, push notifications are disabled");
+            false
+        }
+    };
+
     // Cronjob stuff
     let scheduler = match JobScheduler::new().await {
         Ok(scheduler) => scheduler,
@@ -131,26 +333,60 @@ async fn main() {
     };
 
     // Every 5 minutes
-    let job =
-        match Job::new_repeated_async(Duration::minutes(5).to_std().unwrap(), |_uuid, _lock| {
-            Box::pin(async {
-                cronjob_clear_old().await;
-            })
-        }) {
-            Ok(job) => job,
-            Err(e) => {
-                error!("Failed to create job: {}", e);
-                std::process::exit(1);
-            }
-        };
+    if let Err(e) = add_job(
+        &scheduler,
+        "clear_old",
+        Duration::minutes(5).to_std().unwrap(),
+        cronjob_clear_old,
+    )
+    .await
+    {
+        error!("{}", e);
+        std::process::exit(1);
+    }
 
-    match scheduler.add(job).await {
-        Ok(_) => info!("Job added"),
-        Err(e) => {
-            error!("Failed to add job: {}", e);
+    // Every minute, each user is only polled when they are due
+    if let Err(e) = add_job(
+        &scheduler,
+        "poll_grades",
+        Duration::minutes(1).to_std().unwrap(),
+        cronjob_poll_grades,
+    )
+    .await
+    {
+        error!("{}", e);
+        std::process::exit(1);
+    }
+
+    // Every minute, each inbox is only checked when it is due
+    if let Err(e) = add_job(
+        &scheduler,
+        "poll_messages",
+        Duration::minutes(1).to_std().unwrap(),
+        cronjob_poll_messages,
+    )
+    .await
+    {
+        error!("{}", e);
+        std::process::exit(1);
+    }
+
+    // Every 15 seconds
+    if push_enabled {
+        if let Err(e) = add_job(
+            &scheduler,
+            "deliver_pushes",
+            Duration::seconds(15).to_std().unwrap(),
+            cronjob_deliver_pushes,
+        )
+        .await
+        {
+            error!("{}", e);
             std::process::exit(1);
         }
-    };
+    }
+
+    info!("Jobs added");
 
     // Shutdown on SIGINT and SIGTERM and SIGQUIT
     scheduler.shutdown_on_signal(SignalKind::terminate());
@@ -163,7 +399,14 @@ async fn main() {
         Err(e) => error!("Scheduler stopped with error: {}", e),
     };
 
-    match server().await {
+    // Whether `/readyz` also checks that Schoology is reachable
+    let readiness_checks = ReadinessChecks {
+        schoology: std::env::var("READY_CHECK_SCHOOLOGY")
+            .map(|check| check == "true")
+            .unwrap_or(false),
+    };
+
+    match server(readiness_checks).await {
         Ok(_) => info!("Server stopped"),
         Err(e) => error!("Server stopped with error: {}", e),
     };
@@ -0,0 +1,344 @@
+//! Web Push (RFC 8030) with VAPID (RFC 8292) and message encryption (RFC 8291)
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use glob_match::glob_match;
+use once_cell::sync::OnceCell;
+use orm::push_subscriptions;
+use reqwest::{header, redirect, StatusCode};
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_128_GCM},
+    agreement::{self, EphemeralPrivateKey, UnparsedPublicKey, ECDH_P256},
+    hkdf::{self, HKDF_SHA256},
+    rand::{SecureRandom, SystemRandom},
+    signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING},
+};
+use serde_json::json;
+use url::Url;
+
+static CLIENT: OnceCell<PushClient> = OnceCell::new();
+
+/// The push services of the major browsers
+pub const DEFAULT_ENDPOINT_HOSTS: &[&str] = &[
+    "fcm.googleapis.com",
+    "updates.push.services.mozilla.com",
+    "*.push.apple.com",
+    "*.notify.windows.com",
+];
+
+/// The record size of the encrypted message (a message is always a single record)
+const RECORD_SIZE: u32 = 4096;
+/// Push services accept at most 4096 bytes, minus the header (86), the tag (16) and the padding delimiter (1)
+pub const MAX_PAYLOAD_SIZE: usize = 3993;
+/// How long a VAPID token is valid for (in hours, at most 24)
+const VAPID_TTL_HOURS: i64 = 12;
+
+/// What happened to a push message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOutcome {
+    /// The push service accepted the message
+    Delivered,
+    /// The subscription expired or the browser unsubscribed, it should be removed
+    Gone,
+    /// A temporary error (rate limited, push service down, ...), try again later
+    Retry,
+    /// The message was rejected and won't be accepted later either
+    Rejected,
+}
+
+pub struct PushClient {
+    http_client: reqwest::Client,
+    key_pair: EcdsaKeyPair,
+    /// The uncompressed public key (base64url), browsers need it as the `applicationServerKey`
+    public_key: String,
+    /// A `mailto:` or `https:` URL the push services can use to contact us
+    subject: String,
+    /// Globs of the hosts subscriptions can point to
+    endpoint_hosts: Vec<String>,
+    /// Whether `http` endpoints are allowed (only for testing against a local sink)
+    allow_http: bool,
+}
+
+/// Gets the push client (`None` if push notifications are not configured)
+pub fn get_push_client() -> Option<&'static PushClient> {
+    CLIENT.get()
+}
+
+/// Creates the push client from a base64url encoded PKCS#8 P-256 key
+pub async fn create_push_client(
+    vapid_key: String,
+    subject: String,
+    endpoint_hosts: Vec<String>,
+    timeout: usize,
+    allow_http: bool,
+) -> Result<(), String> {
+    info!("Creating push client...");
+
+    let vapid_key = URL_SAFE_NO_PAD
+        .decode(vapid_key.trim().trim_end_matches('='))
+        .map_err(|e| format!("Failed to decode VAPID key: {}", e))?;
+
+    let key_pair = EcdsaKeyPair::from_pkcs8(
+        &ECDSA_P256_SHA256_FIXED_SIGNING,
+        &vapid_key,
+        &SystemRandom::new(),
+    )
+    .map_err(|e| format!("Invalid VAPID key: {}", e))?;
+
+    if !subject.starts_with("mailto:") && !subject.starts_with("https:") {
+        return Err("The VAPID subject must be a `mailto:` or `https:` URL".to_string());
+    }
+
+    let http_client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(timeout as u64))
+        // The endpoint was checked, where it redirects to was not
+        .redirect(redirect::Policy::none())
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+    let public_key = URL_SAFE_NO_PAD.encode(key_pair.public_key().as_ref());
+
+    info!("Using VAPID public key {}", public_key);
+
+    CLIENT
+        .set(PushClient {
+            http_client,
+            key_pair,
+            public_key,
+            subject,
+            endpoint_hosts,
+            allow_http,
+        })
+        .map_err(|_| "Failed to set push client".to_string())
+}
+
+/// Generates a new VAPID key, returns the private key (for `VAPID_PRIVATE_KEY`) and the public key
+pub fn generate_vapid_key() -> Result<(String, String), String> {
+    let rng = SystemRandom::new();
+
+    let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+        .map_err(|e| format!("Failed to generate key: {}", e))?;
+
+    let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+        .map_err(|e| format!("Failed to read generated key: {}", e))?;
+
+    Ok((
+        URL_SAFE_NO_PAD.encode(pkcs8.as_ref()),
+        URL_SAFE_NO_PAD.encode(key_pair.public_key().as_ref()),
+    ))
+}
+
+/// The output length of a HKDF expansion
+struct Len(usize);
+
+impl hkdf::KeyType for Len {
+    fn len(&self) -> usize {
+        self.0
+    }
+}
+
+/// HKDF-SHA256 extract and expand
+fn hkdf(salt: &[u8], ikm: &[u8], info: &[u8], len: usize) -> Result<Vec<u8>, ()> {
+    let prk = hkdf::Salt::new(HKDF_SHA256, salt).extract(ikm);
+
+    let mut out = vec![0u8; len];
+    prk.expand(&[info], Len(len))
+        .and_then(|okm| okm.fill(&mut out))
+        .map_err(|err| {
+            error!("Failed to derive key: {:?}", err);
+        })?;
+
+    Ok(out)
+}
+
+/// Decodes a base64url value of a subscription (browsers may or may not pad it)
+fn decode_key(value: &str) -> Option<Vec<u8>> {
+    URL_SAFE_NO_PAD.decode(value.trim_end_matches('=')).ok()
+}
+
+/// Checks the keys of a subscription (`p256dh` is an uncompressed P-256 point and `auth` is 16 bytes)
+pub fn is_valid_keys(p256dh: &str, auth: &str) -> bool {
+    matches!(decode_key(p256dh), Some(key) if key.len() == 65 && key[0] == 0x04)
+        && matches!(decode_key(auth), Some(auth) if auth.len() == 16)
+}
+
+impl PushClient {
+    /// The public key browsers need to subscribe
+    pub fn public_key(&self) -> &str {
+        &self.public_key
+    }
+
+    /// Checks that an endpoint points to a push service (and not somewhere internal)
+    pub fn is_valid_endpoint(&self, endpoint: &str) -> bool {
+        let endpoint = match Url::parse(endpoint) {
+            Ok(endpoint) => endpoint,
+            Err(_) => return false,
+        };
+
+        let scheme_allowed = match endpoint.scheme() {
+            "https" => true,
+            "http" => self.allow_http,
+            _ => false,
+        };
+
+        match endpoint.host_str() {
+            Some(host) if scheme_allowed => self
+                .endpoint_hosts
+                .iter()
+                .any(|glob| glob_match(glob, host)),
+            _ => false,
+        }
+    }
+
+    /// Creates the `Authorization` header for an endpoint (a JWT for the push service's origin)
+    fn vapid_authorization(&self, endpoint: &Url) -> Result<String, ()> {
+        let header = URL_SAFE_NO_PAD.encode(json!({ "typ": "JWT", "alg": "ES256" }).to_string());
+        let claims = URL_SAFE_NO_PAD.encode(
+            json!({
+                "aud": endpoint.origin().ascii_serialization(),
+                "exp": (chrono::Utc::now() + chrono::Duration::hours(VAPID_TTL_HOURS)).timestamp(),
+                "sub": self.subject,
+            })
+            .to_string(),
+        );
+
+        let signing_input = format!("{}.{}", header, claims);
+
+        let signature = self
+            .key_pair
+            .sign(&SystemRandom::new(), signing_input.as_bytes())
+            .map_err(|err| {
+                error!("Failed to sign VAPID token: {:?}", err);
+            })?;
+
+        Ok(format!(
+            "vapid t={}.{}, k={}",
+            signing_input,
+            URL_SAFE_NO_PAD.encode(signature.as_ref()),
+            self.public_key
+        ))
+    }
+
+    /// Encrypts a message for a subscription (`aes128gcm` content coding, RFC 8291)
+    fn encrypt(&self, payload: &[u8], p256dh: &str, auth: &str) -> Result<Vec<u8>, ()> {
+        if payload.len() > MAX_PAYLOAD_SIZE {
+            warn!("Push payload is too large ({} bytes)", payload.len());
+            return Err(());
+        }
+
+        let (ua_public, auth_secret) = match (decode_key(p256dh), decode_key(auth)) {
+            (Some(ua_public), Some(auth_secret)) => (ua_public, auth_secret),
+            _ => {
+                warn!("Failed to decode subscription keys");
+                return Err(());
+            }
+        };
+
+        let rng = SystemRandom::new();
+
+        // A new key pair for every message
+        let as_private = EphemeralPrivateKey::generate(&ECDH_P256, &rng).map_err(|err| {
+            error!("Failed to generate key: {:?}", err);
+        })?;
+        let as_public = as_private.compute_public_key().map_err(|err| {
+            error!("Failed to compute public key: {:?}", err);
+        })?;
+
+        let ecdh_secret = agreement::agree_ephemeral(
+            as_private,
+            &UnparsedPublicKey::new(&ECDH_P256, &ua_public),
+            |secret| secret.to_vec(),
+        )
+        .map_err(|err| {
+            warn!("Failed to agree on a key (invalid p256dh?): {:?}", err);
+        })?;
+
+        let key_info = [b"WebPush: info\0", ua_public.as_slice(), as_public.as_ref()].concat();
+        let ikm = hkdf(&auth_secret, &ecdh_secret, &key_info, 32)?;
+
+        let mut salt = [0u8; 16];
+        rng.fill(&mut salt).map_err(|err| {
+            error!("Failed to generate salt: {:?}", err);
+        })?;
+
+        let cek = hkdf(&salt, &ikm, b"Content-Encoding: aes128gcm\0", 16)?;
+        let nonce = hkdf(&salt, &ikm, b"Content-Encoding: nonce\0", 12)?;
+
+        let key = UnboundKey::new(&AES_128_GCM, &cek).map_err(|err| {
+            error!("Failed to create key: {:?}", err);
+        })?;
+        let nonce = Nonce::try_assume_unique_for_key(&nonce).map_err(|err| {
+            error!("Failed to create nonce: {:?}", err);
+        })?;
+
+        // The last (and only) record is delimited by 0x02
+        let mut record = [payload, &[0x02]].concat();
+        LessSafeKey::new(key)
+            .seal_in_place_append_tag(nonce, Aad::empty(), &mut record)
+            .map_err(|err| {
+                error!("Failed to encrypt push message: {:?}", err);
+            })?;
+
+        // salt || rs || idlen || keyid || ciphertext
+        Ok([
+            salt.as_slice(),
+            &RECORD_SIZE.to_be_bytes(),
+            &[as_public.as_ref().len() as u8],
+            as_public.as_ref(),
+            &record,
+        ]
+        .concat())
+    }
+
+    /// Sends a message to a subscription
+    /// `ttl` is how long the push service should keep the message for an offline browser (in seconds)
+    pub async fn send(
+        &self,
+        subscription: &push_subscriptions::Model,
+        payload: &[u8],
+        ttl: u32,
+    ) -> PushOutcome {
+        let endpoint = match Url::parse(&subscription.endpoint) {
+            Ok(endpoint) => endpoint,
+            Err(_) => return PushOutcome::Gone,
+        };
+
+        let (body, authorization) = match (
+            self.encrypt(payload, &subscription.p256dh, &subscription.auth),
+            self.vapid_authorization(&endpoint),
+        ) {
+            (Ok(body), Ok(authorization)) => (body, authorization),
+            _ => return PushOutcome::Rejected,
+        };
+
+        let response = self
+            .http_client
+            .post(endpoint)
+            .header(header::AUTHORIZATION, authorization)
+            .header(header::CONTENT_ENCODING, "aes128gcm")
+            .header(header::CONTENT_TYPE, "application/octet-stream")
+            .header("TTL", ttl.to_string())
+            .header("Urgency", "normal")
+            .body(body)
+            .send()
+            .await;
+
+        let status = match response {
+            Ok(response) => response.status(),
+            Err(err) => {
+                debug!("Failed to send push message: {}", err);
+                return PushOutcome::Retry;
+            }
+        };
+
+        match status {
+            status if status.is_success() => PushOutcome::Delivered,
+            StatusCode::NOT_FOUND | StatusCode::GONE => PushOutcome::Gone,
+            StatusCode::REQUEST_TIMEOUT | StatusCode::TOO_MANY_REQUESTS => PushOutcome::Retry,
+            status if status.is_server_error() => PushOutcome::Retry,
+            status => {
+                warn!("Push service rejected message: {}", status);
+                PushOutcome::Rejected
+            }
+        }
+    }
+}
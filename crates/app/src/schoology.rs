@@ -1,19 +1,45 @@
+use std::sync::Arc;
+
 use once_cell::sync::OnceCell;
-use schoology::SchoologyClient;
+use schoology::{http_client_builder, limiter, RateLimiter, SchoologyClient};
+
+pub mod cache;
 
 static CLIENT: OnceCell<SchoologyClient> = OnceCell::new();
 
 pub fn get_schoology_client() -> &'static SchoologyClient {
-    CLIENT.get().expect("Database client not initialized")
+    CLIENT.get().expect("Schoology client not initialized")
 }
 
 pub async fn create_schoology_client(
     consumer_key: String,
     consumer_secret: String,
+    base_url: Option<String>,
+    timeout: usize,
+    rate_limit: u32,
 ) -> Result<(), String> {
     info!("Creating Schoology client...");
 
-    let client = SchoologyClient::new(consumer_key, consumer_secret);
+    let http_client = http_client_builder()
+        .timeout(std::time::Duration::from_secs(timeout as u64))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+    // `rate_limit` requests per 5 seconds, the window Schoology uses
+    let rate_limiter = RateLimiter::new(rate_limit, limiter::DEFAULT_PERIOD);
+
+    let mut client = SchoologyClient::new(consumer_key, consumer_secret)
+        .with_http_client(http_client)
+        .with_rate_limiter(Arc::new(rate_limiter));
+
+    if let Some(base_url) = base_url {
+        let base_url =
+            url::Url::parse(&base_url).map_err(|e| format!("Invalid Schoology base URL: {}", e))?;
+
+        info!("Using Schoology API at {}", base_url);
+
+        client = client.with_base_url(base_url);
+    }
 
     // Set the schoology client
     CLIENT
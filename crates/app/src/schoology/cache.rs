@@ -0,0 +1,317 @@
+//! A cache of Schoology responses, so opening the app doesn't make a signed Schoology request every time
+//!
+//! Entries are per user and endpoint. A fresh entry is returned as is, a stale one is returned while
+//! it's refreshed in the background, and requests for the same entry that miss at the same time share
+//! a single Schoology request.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+
+use chrono::{NaiveDateTime, Utc};
+use futures::future::{BoxFuture, FutureExt, Shared};
+use once_cell::sync::OnceCell;
+use orm::schoology_cache;
+use schoology::SchoologyError;
+use sea_orm::{
+    sea_query::OnConflict, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::database::get_db_client;
+
+/// The most entries kept in memory, the expired ones are dropped once it's reached
+const MAX_MEMORY_ENTRIES: usize = 10000;
+
+static CACHE: OnceCell<Cache> = OnceCell::new();
+
+/// How long an entry is used for
+#[derive(Debug, Clone, Copy)]
+pub struct CachePolicy {
+    /// How long an entry is returned without asking Schoology
+    pub ttl: Duration,
+    /// How long after the TTL an entry is still returned while it's refreshed
+    pub stale: Duration,
+}
+
+/// A stored response
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    value: Value,
+    stored_at: NaiveDateTime,
+    expires_at: NaiveDateTime,
+}
+
+/// Where the entries are stored
+pub enum CacheBackend {
+    /// In the process, every instance has it's own entries
+    Memory(Mutex<HashMap<(i32, String), CacheEntry>>),
+    /// In the `schoology_cache` table, shared by every instance
+    Postgres,
+}
+
+impl CacheBackend {
+    pub fn memory() -> Self {
+        CacheBackend::Memory(Mutex::new(HashMap::new()))
+    }
+
+    /// Parses `SCHOOLOGY_CACHE` (`memory` or `postgres`)
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name.trim().to_lowercase().as_str() {
+            "memory" => Ok(CacheBackend::memory()),
+            "postgres" => Ok(CacheBackend::Postgres),
+            _ => Err(format!("Unknown cache backend: {}", name)),
+        }
+    }
+}
+
+type Fetch = Shared<BoxFuture<'static, Result<Value, SchoologyError>>>;
+
+pub struct Cache {
+    backend: CacheBackend,
+    /// The requests that are running, by user and key
+    in_flight: Mutex<HashMap<(i32, String), Fetch>>,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // A panicking request should not take the cache down with it
+    mutex.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+impl Cache {
+    pub fn new(backend: CacheBackend) -> Self {
+        Self {
+            backend,
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn load(&self, user_id: i32, key: &str) -> Option<CacheEntry> {
+        match &self.backend {
+            CacheBackend::Memory(entries) => {
+                lock(entries).get(&(user_id, key.to_string())).cloned()
+            }
+            CacheBackend::Postgres => {
+                let entry = schoology_cache::Entity::find_by_id((user_id, key.to_string()))
+                    .one(get_db_client())
+                    .await
+                    .map_err(|err| {
+                        warn!("Failed to get cache entry: {:?}", err);
+                    })
+                    .ok()??;
+
+                Some(CacheEntry {
+                    value: entry.value,
+                    stored_at: entry.stored_at,
+                    expires_at: entry.expires_at,
+                })
+            }
+        }
+    }
+
+    async fn store(&self, user_id: i32, key: &str, entry: CacheEntry) {
+        match &self.backend {
+            CacheBackend::Memory(entries) => {
+                let mut entries = lock(entries);
+
+                if entries.len() >= MAX_MEMORY_ENTRIES {
+                    let now = Utc::now().naive_utc();
+                    entries.retain(|_, entry| entry.expires_at > now);
+
+                    // Everything is still in use, start over rather than growing forever
+                    if entries.len() >= MAX_MEMORY_ENTRIES {
+                        warn!("Schoology cache is full, clearing it");
+                        entries.clear();
+                    }
+                }
+
+                entries.insert((user_id, key.to_string()), entry);
+            }
+            CacheBackend::Postgres => {
+                let model = schoology_cache::ActiveModel {
+                    user_id: ActiveValue::Set(user_id),
+                    key: ActiveValue::Set(key.to_string()),
+                    value: ActiveValue::Set(entry.value),
+                    stored_at: ActiveValue::Set(entry.stored_at),
+                    expires_at: ActiveValue::Set(entry.expires_at),
+                };
+
+                let result = schoology_cache::Entity::insert(model)
+                    .on_conflict(
+                        OnConflict::columns([
+                            schoology_cache::Column::UserId,
+                            schoology_cache::Column::Key,
+                        ])
+                        .update_columns([
+                            schoology_cache::Column::Value,
+                            schoology_cache::Column::StoredAt,
+                            schoology_cache::Column::ExpiresAt,
+                        ])
+                        .to_owned(),
+                    )
+                    .exec_without_returning(get_db_client())
+                    .await;
+
+                if let Err(err) = result {
+                    warn!("Failed to store cache entry: {:?}", err);
+                }
+            }
+        }
+    }
+
+    /// Drops every entry of a user, e.g. when they linked their account again
+    pub async fn invalidate(&self, user_id: i32) {
+        match &self.backend {
+            CacheBackend::Memory(entries) => {
+                lock(entries).retain(|(entry_user_id, _), _| *entry_user_id != user_id)
+            }
+            CacheBackend::Postgres => {
+                let result = schoology_cache::Entity::delete_many()
+                    .filter(schoology_cache::Column::UserId.eq(user_id))
+                    .exec(get_db_client())
+                    .await;
+
+                if let Err(err) = result {
+                    warn!("Failed to invalidate cache entries: {:?}", err);
+                }
+            }
+        }
+    }
+
+    /// Starts fetching an entry (or joins the request that is already running)
+    /// The request runs to the end even if nobody waits for it anymore, so it's always stored.
+    fn fetch<T, F, Fut>(
+        &'static self,
+        user_id: i32,
+        key: &str,
+        policy: CachePolicy,
+        fetch: F,
+    ) -> Fetch
+    where
+        T: Serialize,
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<T, SchoologyError>> + Send + 'static,
+    {
+        let mut in_flight = lock(&self.in_flight);
+
+        if let Some(request) = in_flight.get(&(user_id, key.to_string())) {
+            debug!(
+                "Joining the request for cache entry {} of user {}",
+                key, user_id
+            );
+            return request.clone();
+        }
+
+        let key = key.to_string();
+        let request_key = (user_id, key.clone());
+
+        let request = async move {
+            let result = fetch().await.and_then(|value| {
+                serde_json::to_value(value).map_err(SchoologyError::invalid_response)
+            });
+
+            match &result {
+                Ok(value) => {
+                    let now = Utc::now().naive_utc();
+                    let expires_at = now
+                        + chrono::Duration::from_std(policy.ttl + policy.stale)
+                            .unwrap_or_else(|_| chrono::Duration::zero());
+
+                    self.store(
+                        user_id,
+                        &key,
+                        CacheEntry {
+                            value: value.clone(),
+                            stored_at: now,
+                            expires_at,
+                        },
+                    )
+                    .await;
+                }
+                // The user removed the app, what they could see before should not be shown anymore
+                Err(err) if err.is_unauthorized() => self.invalidate(user_id).await,
+                Err(_) => {}
+            }
+
+            lock(&self.in_flight).remove(&(user_id, key));
+
+            result
+        }
+        .boxed()
+        .shared();
+
+        in_flight.insert(request_key, request.clone());
+
+        tokio::spawn(request.clone());
+
+        request
+    }
+
+    /// Gets an entry, fetching it with `fetch` if there is none (or it's too old)
+    /// `key` has to include everything the response depends on (e.g. the query).
+    pub async fn get<T, F, Fut>(
+        &'static self,
+        user_id: i32,
+        key: &str,
+        policy: CachePolicy,
+        fetch: F,
+    ) -> Result<T, SchoologyError>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<T, SchoologyError>> + Send + 'static,
+    {
+        if let Some(entry) = self.load(user_id, key).await {
+            let age = (Utc::now().naive_utc() - entry.stored_at)
+                .to_std()
+                .unwrap_or_default();
+
+            if age < policy.ttl + policy.stale {
+                // Entries written by an older version may not fit anymore
+                match serde_json::from_value::<T>(entry.value) {
+                    Ok(value) => {
+                        if age >= policy.ttl {
+                            debug!("Refreshing stale cache entry {} of user {}", key, user_id);
+                            drop(self.fetch(user_id, key, policy, fetch));
+                        }
+
+                        return Ok(value);
+                    }
+                    Err(err) => debug!("Ignoring cache entry {}: {}", key, err),
+                }
+            }
+        }
+
+        let value = self.fetch(user_id, key, policy, fetch).await?;
+
+        serde_json::from_value(value).map_err(SchoologyError::invalid_response)
+    }
+}
+
+/// Gets the cache, in memory unless `create_cache` chose another backend
+pub fn get_cache() -> &'static Cache {
+    CACHE.get_or_init(|| Cache::new(CacheBackend::memory()))
+}
+
+pub fn create_cache(backend: CacheBackend) -> Result<(), String> {
+    CACHE
+        .set(Cache::new(backend))
+        .map_err(|_| "Failed to set Schoology cache".to_string())
+}
+
+/// Deletes the entries that can't be used anymore (only the Postgres backend stores them)
+pub async fn clear_expired(db_client: &DatabaseConnection) -> Result<u64, ()> {
+    let result = schoology_cache::Entity::delete_many()
+        .filter(schoology_cache::Column::ExpiresAt.lt(Utc::now().naive_utc()))
+        .exec(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to delete expired cache entries: {:?}", err);
+        })?;
+
+    Ok(result.rows_affected)
+}
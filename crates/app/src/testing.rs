@@ -0,0 +1,252 @@
+//! Shared setup for tests that need the database and the mock Schoology server
+//! These tests are skipped unless `TEST_DATABASE_URL` points to a (disposable) Postgres database.
+
+use std::{collections::HashMap, future::Future, net::TcpListener, sync::Mutex};
+
+use actix_web::{dev::ServerHandle, web, App, HttpRequest, HttpResponse, HttpServer};
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+use migrations::{Migrator, MigratorTrait};
+use once_cell::sync::Lazy;
+use ring::rand::{SecureRandom, SystemRandom};
+use schoology_mock::{MockSchoology, MockUser};
+use sea_orm::{ActiveModelTrait, ActiveValue};
+use tokio::{runtime::Runtime, sync::OnceCell};
+
+use crate::{
+    database::{create_db_client, get_db_client},
+    encryption::create_keyring,
+    push::{create_push_client, generate_vapid_key},
+    schoology::create_schoology_client,
+    utils,
+};
+
+/// The globals (database, Schoology client, ...) are bound to the runtime they were created on,
+/// so every test runs on the same one
+static RUNTIME: Lazy<Runtime> = Lazy::new(|| Runtime::new().expect("Failed to create runtime"));
+
+static MOCK: OnceCell<MockSchoology> = OnceCell::const_new();
+
+async fn setup(db_url: String) -> MockSchoology {
+    create_db_client(&db_url, 5, 1, 10).await.unwrap();
+
+    Migrator::up(get_db_client(), None).await.unwrap();
+
+    // A fixed key, rotating the keys has to read the links of previous runs
+    create_keyring(format!("test:{}", STANDARD_NO_PAD.encode([7u8; 32])))
+        .await
+        .unwrap();
+
+    let mut pepper = [0u8; 32];
+    SystemRandom::new().fill(&mut pepper).unwrap();
+    utils::sessions::set_pepper(STANDARD_NO_PAD.encode(pepper)).unwrap();
+
+    utils::schedule::set_timezone("America/Los_Angeles").unwrap();
+
+    let mock = MockSchoology::start().await.unwrap();
+
+    create_schoology_client(
+        mock.consumer_key().to_string(),
+        mock.consumer_secret().to_string(),
+        Some(mock.base_url().to_string()),
+        10,
+        // Every test shares the consumer key, they shouldn't wait for each other
+        1000,
+    )
+    .await
+    .unwrap();
+
+    let (vapid_key, _) = generate_vapid_key().unwrap();
+    create_push_client(
+        vapid_key,
+        "mailto:test@example.com".to_string(),
+        vec!["127.0.0.1".to_string()],
+        10,
+        true,
+    )
+    .await
+    .unwrap();
+
+    mock
+}
+
+/// Runs a test against the database and the mock Schoology server
+pub fn run<F, Fut>(test: F)
+where
+    F: FnOnce(&'static MockSchoology) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let db_url = match std::env::var("TEST_DATABASE_URL") {
+        Ok(db_url) => db_url,
+        Err(_) => {
+            eprintln!("TEST_DATABASE_URL is not set, skipping");
+            return;
+        }
+    };
+
+    RUNTIME.block_on(async {
+        let mock = MOCK.get_or_init(|| setup(db_url)).await;
+
+        test(mock).await
+    })
+}
+
+/// A Schoology id that is not used by any other test (or previous run)
+pub fn new_schoology_id() -> usize {
+    let mut id = [0u8; 4];
+    SystemRandom::new().fill(&mut id).unwrap();
+
+    (u32::from_le_bytes(id) >> 1) as usize
+}
+
+/// Creates a user linked to a new mock Schoology user and logs them in
+/// Returns the session token and the Schoology id
+pub async fn linked_user(mock: &MockSchoology) -> (String, usize) {
+    let db_client = get_db_client();
+
+    let schoology_id = new_schoology_id();
+    mock.add_user(MockUser::new(schoology_id));
+
+    let (access_token, token_secret) = mock.issue_access_token(schoology_id);
+
+    let user = utils::users::create(db_client).await.unwrap();
+
+    utils::schoology_link::create(
+        db_client,
+        user.id,
+        schoology_id as i32,
+        None,
+        None,
+        None,
+        None,
+        Some(access_token),
+        Some(token_secret),
+    )
+    .await
+    .unwrap();
+
+    let session = utils::sessions::create(db_client, user.id, "127.0.0.1".to_string())
+        .await
+        .unwrap();
+
+    (session.access_token, schoology_id)
+}
+
+/// Creates an admin user (not linked to Schoology) and logs them in
+/// Returns the session token
+pub async fn admin_user() -> String {
+    let db_client = get_db_client();
+
+    let user = utils::users::create(db_client).await.unwrap();
+
+    orm::users::ActiveModel {
+        id: ActiveValue::Set(user.id),
+        is_admin: ActiveValue::Set(true),
+        ..Default::default()
+    }
+    .update(db_client)
+    .await
+    .unwrap();
+
+    let session = utils::sessions::create(db_client, user.id, "127.0.0.1".to_string())
+        .await
+        .unwrap();
+
+    session.access_token
+}
+
+/// A push message received by the [`PushSink`]
+pub struct SinkRequest {
+    pub path: String,
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+struct SinkState {
+    requests: Vec<SinkRequest>,
+    status: u16,
+}
+
+/// A local push service that records every message and answers with a configurable status
+pub struct PushSink {
+    state: web::Data<Mutex<SinkState>>,
+    base_url: String,
+    handle: ServerHandle,
+}
+
+async fn sink(
+    req: HttpRequest,
+    body: web::Bytes,
+    state: web::Data<Mutex<SinkState>>,
+) -> HttpResponse {
+    let mut state = state.lock().unwrap_or_else(|err| err.into_inner());
+
+    state.requests.push(SinkRequest {
+        path: req.path().to_string(),
+        headers: req
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                Some((name.as_str().to_string(), value.to_str().ok()?.to_string()))
+            })
+            .collect(),
+        body: body.to_vec(),
+    });
+
+    HttpResponse::build(actix_web::http::StatusCode::from_u16(state.status).unwrap()).finish()
+}
+
+impl PushSink {
+    /// Starts a sink that accepts every message (`201 Created`)
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let state = web::Data::new(Mutex::new(SinkState {
+            requests: Vec::new(),
+            status: 201,
+        }));
+
+        let data = state.clone();
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(data.clone())
+                .default_service(web::route().to(sink))
+        })
+        .workers(1)
+        .disable_signals()
+        .listen(listener)
+        .unwrap()
+        .run();
+
+        let handle = server.handle();
+        tokio::spawn(server);
+
+        Self {
+            state,
+            base_url: format!("http://127.0.0.1:{}", port),
+            handle,
+        }
+    }
+
+    /// An endpoint of a subscription
+    pub fn endpoint(&self, name: &str) -> String {
+        format!("{}/push/{}", self.base_url, name)
+    }
+
+    /// Sets the status the sink answers with (e.g. `410` for a subscription that is gone)
+    pub fn set_status(&self, status: u16) {
+        self.state.lock().unwrap().status = status;
+    }
+
+    /// Takes the messages received so far
+    pub fn take_requests(&self) -> Vec<SinkRequest> {
+        std::mem::take(&mut self.state.lock().unwrap().requests)
+    }
+}
+
+impl Drop for PushSink {
+    fn drop(&mut self) {
+        // Stop accepting connections (the future doesn't need to be awaited)
+        drop(self.handle.stop(false));
+    }
+}
@@ -0,0 +1,112 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use orm::calendar_feeds;
+use ring::rand::{SecureRandom, SystemRandom};
+use sea_orm::{
+    sea_query::OnConflict, ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection,
+    EntityTrait, QueryFilter,
+};
+
+use crate::utils::sessions::encode_secret_hash;
+
+/// Hashes a feed token, the prefix keeps it from ever matching a session hash
+fn hash_token(token: &str) -> Result<String, ()> {
+    encode_secret_hash(&format!("calendar:{}", token))
+}
+
+/// Generates a random token that can be used in a URL
+fn generate_token() -> Result<String, ()> {
+    let mut token = [0u8; 32];
+    SystemRandom::new().fill(&mut token).map_err(|err| {
+        error!("Failed to generate calendar feed token: {:?}", err);
+    })?;
+
+    Ok(URL_SAFE_NO_PAD.encode(token))
+}
+
+/// Gets the calendar feed of a user
+pub async fn get_by_user_id(
+    db_client: &DatabaseConnection,
+    user_id: i32,
+) -> Result<Option<calendar_feeds::Model>, ()> {
+    calendar_feeds::Entity::find_by_id(user_id)
+        .one(db_client)
+        .await
+        .map_err(|err| {
+            debug!("Failed to get calendar feed: {:?}", err);
+        })
+}
+
+/// Creates the calendar feed of a user, replacing the old one (so it's URL stops working)
+/// Returns the token, only it's hash is stored
+pub async fn create(db_client: &DatabaseConnection, user_id: i32) -> Result<String, ()> {
+    let token = generate_token()?;
+
+    let feed = calendar_feeds::ActiveModel {
+        user_id: ActiveValue::Set(user_id),
+        token_hash: ActiveValue::Set(hash_token(&token)?),
+        created_at: ActiveValue::Set(chrono::Utc::now().naive_utc()),
+        last_used_at: ActiveValue::Set(None),
+    };
+
+    calendar_feeds::Entity::insert(feed)
+        .on_conflict(
+            OnConflict::column(calendar_feeds::Column::UserId)
+                .update_columns([
+                    calendar_feeds::Column::TokenHash,
+                    calendar_feeds::Column::CreatedAt,
+                    calendar_feeds::Column::LastUsedAt,
+                ])
+                .to_owned(),
+        )
+        .exec_without_returning(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to create calendar feed: {:?}", err);
+        })?;
+
+    Ok(token)
+}
+
+/// Gets the calendar feed of a token and records that it was used
+pub async fn verify(
+    db_client: &DatabaseConnection,
+    token: &str,
+) -> Result<Option<calendar_feeds::Model>, ()> {
+    let feed = calendar_feeds::Entity::find()
+        .filter(calendar_feeds::Column::TokenHash.eq(hash_token(token)?))
+        .one(db_client)
+        .await
+        .map_err(|err| {
+            debug!("Failed to get calendar feed: {:?}", err);
+        })?;
+
+    let feed = match feed {
+        Some(feed) => feed,
+        None => return Ok(None),
+    };
+
+    let feed = calendar_feeds::ActiveModel {
+        user_id: ActiveValue::Set(feed.user_id),
+        last_used_at: ActiveValue::Set(Some(chrono::Utc::now().naive_utc())),
+        ..Default::default()
+    }
+    .update(db_client)
+    .await
+    .map_err(|err| {
+        warn!("Failed to update calendar feed: {:?}", err);
+    })?;
+
+    Ok(Some(feed))
+}
+
+/// Revokes the calendar feed of a user, returns whether the user had one
+pub async fn revoke(db_client: &DatabaseConnection, user_id: i32) -> Result<bool, ()> {
+    let result = calendar_feeds::Entity::delete_by_id(user_id)
+        .exec(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to revoke calendar feed: {:?}", err);
+        })?;
+
+    Ok(result.rows_affected > 0)
+}
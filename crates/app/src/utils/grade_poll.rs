@@ -0,0 +1,443 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::Duration;
+use orm::{grade_poll_state, grade_snapshots};
+use schoology::{
+    grades::{GradeException, SectionGrades},
+    SchoologyClient,
+};
+use sea_orm::{
+    sea_query::OnConflict, ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection,
+    EntityTrait, QueryFilter, TransactionTrait,
+};
+use serde_json::{json, Value};
+
+use crate::{
+    database::get_db_client,
+    schoology::get_schoology_client,
+    utils::{
+        notifications::{self, NotificationKind},
+        poll_schedule::{self, PollState, Schedule},
+        schoology_link::{self as link_utils, LinkedAccount},
+    },
+};
+
+/// How often the grades of a user are polled unless they change it (in minutes)
+pub const DEFAULT_INTERVAL_MINUTES: i32 = 30;
+/// The shortest polling interval a user can choose (in minutes)
+pub const MIN_INTERVAL_MINUTES: i32 = 15;
+/// The longest polling interval a user can choose (in minutes)
+pub const MAX_INTERVAL_MINUTES: i32 = 1440;
+/// How many snapshots are written per query
+const SNAPSHOT_CHUNK_SIZE: usize = 1000;
+
+/// A grade that was posted or changed since the last poll
+pub struct GradeChange {
+    pub section_id: usize,
+    pub assignment_id: usize,
+    pub period_id: String,
+    pub grade: Option<f64>,
+    pub max_points: Option<f64>,
+    pub exception: i32,
+    /// The last seen grade (`None` if the assignment was not graded before)
+    pub previous: Option<grade_snapshots::Model>,
+}
+
+/// A notification that has not been written yet
+struct NotificationDraft {
+    kind: NotificationKind,
+    title: String,
+    body: String,
+    data: Value,
+}
+
+fn exception_code(exception: GradeException) -> i32 {
+    match exception {
+        GradeException::None => 0,
+        GradeException::Excused => 1,
+        GradeException::Incomplete => 2,
+        GradeException::Missing => 3,
+    }
+}
+
+fn exception_name(code: i32) -> Option<&'static str> {
+    match code {
+        1 => Some("Excused"),
+        2 => Some("Incomplete"),
+        3 => Some("Missing"),
+        _ => None,
+    }
+}
+
+/// Formats a grade for the body of a notification (e.g. `9/10` or `Missing`)
+fn display_grade(grade: Option<f64>, max_points: Option<f64>, exception: i32) -> String {
+    if let Some(name) = exception_name(exception) {
+        return name.to_string();
+    }
+
+    match (grade, max_points) {
+        (Some(grade), Some(max_points)) => format!("{}/{}", grade, max_points),
+        (Some(grade), None) => grade.to_string(),
+        _ => "-".to_string(),
+    }
+}
+
+impl PollState for grade_poll_state::Entity {
+    type ActiveModel = grade_poll_state::ActiveModel;
+
+    const NAME: &'static str = "grade poll state";
+    const USER_ID: grade_poll_state::Column = grade_poll_state::Column::UserId;
+    const FAILURES: grade_poll_state::Column = grade_poll_state::Column::Failures;
+    const NEXT_AT: grade_poll_state::Column = grade_poll_state::Column::NextPollAt;
+
+    fn new_state(user_id: i32) -> grade_poll_state::ActiveModel {
+        grade_poll_state::ActiveModel {
+            user_id: ActiveValue::Set(user_id),
+            interval_minutes: ActiveValue::Set(DEFAULT_INTERVAL_MINUTES),
+            failures: ActiveValue::Set(0),
+            next_poll_at: ActiveValue::Set(chrono::Utc::now().naive_utc()),
+            last_polled_at: ActiveValue::Set(None),
+        }
+    }
+
+    fn schedule(state: &grade_poll_state::Model) -> Schedule {
+        Schedule {
+            user_id: state.user_id,
+            interval_minutes: state.interval_minutes,
+            failures: state.failures,
+            next_at: state.next_poll_at,
+        }
+    }
+}
+
+/// Gets the polling state of a user
+pub async fn get(
+    db_client: &DatabaseConnection,
+    user_id: i32,
+) -> Result<Option<grade_poll_state::Model>, ()> {
+    poll_schedule::get::<grade_poll_state::Entity>(db_client, user_id).await
+}
+
+/// Gets the polling state of a user, creating it (due right away) if there is none
+pub async fn ensure(
+    db_client: &DatabaseConnection,
+    user_id: i32,
+) -> Result<grade_poll_state::Model, ()> {
+    poll_schedule::ensure::<grade_poll_state::Entity>(db_client, user_id).await
+}
+
+/// Sets how often the grades of a user are polled (in minutes)
+pub async fn set_interval(
+    db_client: &DatabaseConnection,
+    user_id: i32,
+    interval_minutes: i32,
+) -> Result<grade_poll_state::Model, ()> {
+    let state = ensure(db_client, user_id).await?;
+
+    // A shorter interval should apply now rather than after the current one ran out
+    let next_poll_at = match state.failures {
+        0 => state
+            .next_poll_at
+            .min((chrono::Utc::now() + Duration::minutes(interval_minutes as i64)).naive_utc()),
+        _ => state.next_poll_at,
+    };
+
+    let state = grade_poll_state::ActiveModel {
+        user_id: ActiveValue::Set(user_id),
+        interval_minutes: ActiveValue::Set(interval_minutes),
+        next_poll_at: ActiveValue::Set(next_poll_at),
+        ..Default::default()
+    };
+
+    state.update(db_client).await.map_err(|err| {
+        warn!("Failed to update grade poll state: {:?}", err);
+    })
+}
+
+/// Clears the backoff of a user and makes them due right away (e.g. after they logged in again)
+pub async fn reset(db_client: &DatabaseConnection, user_id: i32) -> Result<(), ()> {
+    poll_schedule::reset::<grade_poll_state::Entity>(db_client, user_id).await
+}
+
+/// Compares the grades from Schoology with the last seen ones
+/// Assignments without a grade (or exception) are ignored.
+pub fn diff(snapshots: &[grade_snapshots::Model], grades: &[SectionGrades]) -> Vec<GradeChange> {
+    let snapshots = snapshots
+        .iter()
+        .map(|snapshot| ((snapshot.section_id, snapshot.assignment_id), snapshot))
+        .collect::<HashMap<(i64, i64), &grade_snapshots::Model>>();
+
+    let mut seen = HashSet::new();
+    let mut changes = Vec::new();
+
+    for section in grades {
+        for period in &section.periods {
+            for grade in &period.assignments {
+                let exception = exception_code(grade.exception);
+
+                if grade.grade.is_none() && exception == 0 {
+                    continue;
+                }
+
+                let key = (section.section_id as i64, grade.assignment_id as i64);
+
+                // An assignment can only be in one period, but better safe than sorry
+                if !seen.insert(key) {
+                    continue;
+                }
+
+                let previous = snapshots.get(&key);
+
+                if let Some(previous) = previous {
+                    if previous.grade == grade.grade
+                        && previous.max_points == grade.max_points
+                        && previous.exception == exception
+                    {
+                        continue;
+                    }
+                }
+
+                changes.push(GradeChange {
+                    section_id: section.section_id,
+                    assignment_id: grade.assignment_id,
+                    period_id: period.period_id.clone(),
+                    grade: grade.grade,
+                    max_points: grade.max_points,
+                    exception,
+                    previous: previous.map(|previous| (*previous).clone()),
+                });
+            }
+        }
+    }
+
+    changes
+}
+
+/// Writes the notifications of the changes with the names of the courses and assignments
+async fn describe(
+    client: &SchoologyClient,
+    account: &LinkedAccount,
+    changes: &[GradeChange],
+) -> Result<Vec<NotificationDraft>, schoology::SchoologyError> {
+    let sections =
+        schoology::sections::get_user_sections(client, &account.tokens, account.schoology_id)
+            .await?;
+
+    // Only the sections with changes
+    let mut titles = HashMap::new();
+    for section_id in changes
+        .iter()
+        .map(|change| change.section_id)
+        .collect::<HashSet<usize>>()
+    {
+        let assignments =
+            schoology::assignments::get_section_assignments(client, &account.tokens, section_id)
+                .await?;
+
+        for assignment in assignments {
+            titles.insert((section_id, assignment.id), assignment.title);
+        }
+    }
+
+    Ok(changes
+        .iter()
+        .map(|change| {
+            let course_title = sections
+                .iter()
+                .find(|section| section.id == change.section_id)
+                .map(|section| section.course_title.clone())
+                .unwrap_or_else(|| "a course".to_string());
+
+            let assignment_title = titles
+                .get(&(change.section_id, change.assignment_id))
+                .cloned()
+                .unwrap_or_else(|| "An assignment".to_string());
+
+            let grade = display_grade(change.grade, change.max_points, change.exception);
+
+            let (kind, title, body) = match &change.previous {
+                Some(previous) => (
+                    NotificationKind::GradeChanged,
+                    format!("Grade changed in {}", course_title),
+                    format!(
+                        "{}: {} → {}",
+                        assignment_title,
+                        display_grade(previous.grade, previous.max_points, previous.exception),
+                        grade
+                    ),
+                ),
+                None => (
+                    NotificationKind::GradePosted,
+                    format!("New grade in {}", course_title),
+                    format!("{}: {}", assignment_title, grade),
+                ),
+            };
+
+            NotificationDraft {
+                kind,
+                title,
+                body,
+                data: json!({
+                    "section_id": change.section_id,
+                    "assignment_id": change.assignment_id,
+                    "period_id": change.period_id,
+                    "grade": change.grade,
+                    "max_points": change.max_points,
+                    "exception": exception_name(change.exception),
+                    "previous_grade": change.previous.as_ref().and_then(|previous| previous.grade),
+                    "previous_max_points": change.previous.as_ref().and_then(|previous| previous.max_points),
+                    "previous_exception": change.previous.as_ref().and_then(|previous| exception_name(previous.exception)),
+                }),
+            }
+        })
+        .collect())
+}
+
+/// Polls the grades of a user, records the changes and schedules the next poll
+/// The first poll of a user only records the grades, so linking an account doesn't flood the feed.
+/// Returns the number of notifications that were created
+pub async fn poll(
+    db_client: &DatabaseConnection,
+    client: &SchoologyClient,
+    state: grade_poll_state::Model,
+) -> Result<usize, ()> {
+    let account = match link_utils::get_account(db_client, state.user_id).await? {
+        Some(account) => account,
+        None => {
+            debug!("User {} has no Schoology tokens", state.user_id);
+            poll_schedule::record_failure::<grade_poll_state::Entity>(db_client, &state).await?;
+            return Err(());
+        }
+    };
+
+    let grades =
+        match schoology::grades::get_user_grades(client, &account.tokens, account.schoology_id)
+            .await
+        {
+            Ok(grades) => grades,
+            Err(err) => {
+                warn!("Failed to get grades of user {}: {}", state.user_id, err);
+                poll_schedule::record_failure::<grade_poll_state::Entity>(db_client, &state)
+                    .await?;
+                return Err(());
+            }
+        };
+
+    let snapshots = grade_snapshots::Entity::find()
+        .filter(grade_snapshots::Column::UserId.eq(state.user_id))
+        .all(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to get grade snapshots: {:?}", err);
+        })?;
+
+    let changes = diff(&snapshots, &grades);
+
+    let drafts = match state.last_polled_at {
+        Some(_) if !changes.is_empty() => match describe(client, &account, &changes).await {
+            Ok(drafts) => drafts,
+            Err(err) => {
+                warn!(
+                    "Failed to describe grade changes of user {}: {}",
+                    state.user_id, err
+                );
+                poll_schedule::record_failure::<grade_poll_state::Entity>(db_client, &state)
+                    .await?;
+                return Err(());
+            }
+        },
+        _ => Vec::new(),
+    };
+
+    // The notifications and the snapshots are written together so a change is never reported twice (or lost)
+    let txn = db_client.begin().await.map_err(|err| {
+        warn!("Failed to start transaction: {:?}", err);
+    })?;
+
+    for draft in &drafts {
+        notifications::create(
+            &txn,
+            state.user_id,
+            draft.kind,
+            draft.title.clone(),
+            draft.body.clone(),
+            draft.data.clone(),
+        )
+        .await?;
+    }
+
+    let now = chrono::Utc::now().naive_utc();
+
+    for chunk in changes.chunks(SNAPSHOT_CHUNK_SIZE) {
+        grade_snapshots::Entity::insert_many(chunk.iter().map(|change| {
+            grade_snapshots::ActiveModel {
+                user_id: ActiveValue::Set(state.user_id),
+                section_id: ActiveValue::Set(change.section_id as i64),
+                assignment_id: ActiveValue::Set(change.assignment_id as i64),
+                grade: ActiveValue::Set(change.grade),
+                max_points: ActiveValue::Set(change.max_points),
+                exception: ActiveValue::Set(change.exception),
+                updated_at: ActiveValue::Set(now),
+            }
+        }))
+        .on_conflict(
+            OnConflict::columns([
+                grade_snapshots::Column::UserId,
+                grade_snapshots::Column::SectionId,
+                grade_snapshots::Column::AssignmentId,
+            ])
+            .update_columns([
+                grade_snapshots::Column::Grade,
+                grade_snapshots::Column::MaxPoints,
+                grade_snapshots::Column::Exception,
+                grade_snapshots::Column::UpdatedAt,
+            ])
+            .to_owned(),
+        )
+        .exec_without_returning(&txn)
+        .await
+        .map_err(|err| {
+            warn!("Failed to write grade snapshots: {:?}", err);
+        })?;
+    }
+
+    grade_poll_state::ActiveModel {
+        user_id: ActiveValue::Set(state.user_id),
+        failures: ActiveValue::Set(0),
+        next_poll_at: ActiveValue::Set(now + Duration::minutes(state.interval_minutes as i64)),
+        last_polled_at: ActiveValue::Set(Some(now)),
+        ..Default::default()
+    }
+    .update(&txn)
+    .await
+    .map_err(|err| {
+        warn!("Failed to update grade poll state: {:?}", err);
+    })?;
+
+    txn.commit().await.map_err(|err| {
+        warn!("Failed to commit grade poll: {:?}", err);
+    })?;
+
+    Ok(drafts.len())
+}
+
+/// Polls the users that are due
+/// Returns the number of users that were polled
+pub async fn poll_due(
+    db_client: &DatabaseConnection,
+    client: &SchoologyClient,
+) -> Result<usize, ()> {
+    poll_schedule::poll_due::<grade_poll_state::Entity, _, _>(db_client, |state| {
+        poll(db_client, client, state)
+    })
+    .await
+}
+
+pub async fn cronjob_poll_grades() {
+    info!("Polling grades...");
+
+    match poll_due(get_db_client(), get_schoology_client()).await {
+        Ok(count) => info!("Polled the grades of {} users", count),
+        Err(_) => error!("Failed to poll grades"),
+    }
+}
@@ -0,0 +1,130 @@
+//! A minimal iCalendar (RFC 5545) writer for the calendar feeds
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// The longest line in octets (without the line break), longer lines are folded
+const MAX_LINE_OCTETS: usize = 75;
+
+/// The start (and end) of an event
+pub enum EventTime {
+    /// A time, ends at `end`
+    Timed {
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    },
+    /// A whole day
+    AllDay(NaiveDate),
+}
+
+/// A `VEVENT`
+pub struct Event {
+    /// Unique and stable across renders, so calendar apps update the event instead of duplicating it
+    pub uid: String,
+    pub time: EventTime,
+    pub summary: String,
+    pub description: Option<String>,
+    pub categories: Vec<String>,
+}
+
+/// Escapes a `TEXT` value
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Writes a content line, folding it every 75 octets (without splitting a character)
+fn write_line(out: &mut String, line: &str) {
+    let mut octets = 0;
+
+    for c in line.chars() {
+        // The space of a folded line counts towards the limit
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            out.push_str("\r\n ");
+            octets = 1;
+        }
+
+        out.push(c);
+        octets += c.len_utf8();
+    }
+
+    out.push_str("\r\n");
+}
+
+fn format_time(time: &DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn format_date(date: &NaiveDate) -> String {
+    date.format("%Y%m%d").to_string()
+}
+
+/// Renders a calendar
+pub fn render(name: &str, events: &[Event], now: DateTime<Utc>) -> String {
+    let mut out = String::new();
+
+    write_line(&mut out, "BEGIN:VCALENDAR");
+    write_line(&mut out, "VERSION:2.0");
+    write_line(&mut out, "PRODID:-//LearnityUs//Tuwa API//EN");
+    write_line(&mut out, "CALSCALE:GREGORIAN");
+    write_line(&mut out, "METHOD:PUBLISH");
+    write_line(&mut out, &format!("X-WR-CALNAME:{}", escape(name)));
+    // How often calendar apps should check for changes
+    write_line(&mut out, "REFRESH-INTERVAL;VALUE=DURATION:PT1H");
+    write_line(&mut out, "X-PUBLISHED-TTL:PT1H");
+
+    for event in events {
+        write_line(&mut out, "BEGIN:VEVENT");
+        write_line(&mut out, &format!("UID:{}", escape(&event.uid)));
+        write_line(&mut out, &format!("DTSTAMP:{}", format_time(&now)));
+
+        match &event.time {
+            EventTime::Timed { start, end } => {
+                write_line(&mut out, &format!("DTSTART:{}", format_time(start)));
+                write_line(&mut out, &format!("DTEND:{}", format_time(end)));
+            }
+            EventTime::AllDay(date) => {
+                write_line(
+                    &mut out,
+                    &format!("DTSTART;VALUE=DATE:{}", format_date(date)),
+                );
+                write_line(
+                    &mut out,
+                    &format!(
+                        "DTEND;VALUE=DATE:{}",
+                        format_date(&(*date + chrono::Duration::days(1)))
+                    ),
+                );
+            }
+        }
+
+        write_line(&mut out, &format!("SUMMARY:{}", escape(&event.summary)));
+
+        if let Some(description) = event.description.as_ref().filter(|d| !d.is_empty()) {
+            write_line(&mut out, &format!("DESCRIPTION:{}", escape(description)));
+        }
+
+        if !event.categories.is_empty() {
+            let categories: Vec<String> = event.categories.iter().map(|c| escape(c)).collect();
+            write_line(&mut out, &format!("CATEGORIES:{}", categories.join(",")));
+        }
+
+        write_line(&mut out, "TRANSP:TRANSPARENT");
+        write_line(&mut out, "END:VEVENT");
+    }
+
+    write_line(&mut out, "END:VCALENDAR");
+
+    out
+}
@@ -0,0 +1,232 @@
+use chrono::{Duration, NaiveDateTime};
+use orm::message_poll_state;
+use schoology::{messages::MessageThread, SchoologyClient};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, TransactionTrait,
+};
+use serde_json::json;
+
+use crate::{
+    database::get_db_client,
+    schoology::get_schoology_client,
+    utils::{
+        notifications::{self, NotificationKind},
+        poll_schedule::{self, PollState, Schedule},
+        schoology_link as link_utils,
+    },
+};
+
+/// How often the inbox of a user is checked (in minutes)
+const CHECK_INTERVAL_MINUTES: i32 = 15;
+
+impl PollState for message_poll_state::Entity {
+    type ActiveModel = message_poll_state::ActiveModel;
+
+    const NAME: &'static str = "message poll state";
+    const USER_ID: message_poll_state::Column = message_poll_state::Column::UserId;
+    const FAILURES: message_poll_state::Column = message_poll_state::Column::Failures;
+    const NEXT_AT: message_poll_state::Column = message_poll_state::Column::NextCheckAt;
+
+    /// A state that was never checked
+    fn new_state(user_id: i32) -> message_poll_state::ActiveModel {
+        let now = chrono::Utc::now().naive_utc();
+
+        message_poll_state::ActiveModel {
+            user_id: ActiveValue::Set(user_id),
+            last_message_at: ActiveValue::Set(None),
+            unread_count: ActiveValue::Set(0),
+            checked_at: ActiveValue::Set(now),
+            failures: ActiveValue::Set(0),
+            next_check_at: ActiveValue::Set(now),
+            initialized: ActiveValue::Set(false),
+        }
+    }
+
+    fn schedule(state: &message_poll_state::Model) -> Schedule {
+        Schedule {
+            user_id: state.user_id,
+            interval_minutes: CHECK_INTERVAL_MINUTES,
+            failures: state.failures,
+            next_at: state.next_check_at,
+        }
+    }
+}
+
+/// Gets the message state of a user (`initialized` is false if their inbox was never checked)
+pub async fn get(
+    db_client: &DatabaseConnection,
+    user_id: i32,
+) -> Result<Option<message_poll_state::Model>, ()> {
+    poll_schedule::get::<message_poll_state::Entity>(db_client, user_id).await
+}
+
+/// Gets the message state of a user, creating it (due right away) if there is none
+pub async fn ensure(
+    db_client: &DatabaseConnection,
+    user_id: i32,
+) -> Result<message_poll_state::Model, ()> {
+    poll_schedule::ensure::<message_poll_state::Entity>(db_client, user_id).await
+}
+
+/// Clears the backoff of a user and makes them due right away (e.g. after they logged in again)
+pub async fn reset(db_client: &DatabaseConnection, user_id: i32) -> Result<(), ()> {
+    poll_schedule::reset::<message_poll_state::Entity>(db_client, user_id).await
+}
+
+/// When the newest message of the inbox was sent
+fn newest_message(inbox: &[MessageThread]) -> Option<NaiveDateTime> {
+    inbox
+        .iter()
+        .filter_map(|thread| thread.last_updated)
+        .max()
+        .map(|time| time.naive_utc())
+}
+
+/// Updates the unread count of a user from an inbox that was fetched for something else
+/// New messages are still notified by the next check.
+pub async fn update_unread_count(
+    db_client: &DatabaseConnection,
+    user_id: i32,
+    inbox: &[MessageThread],
+) -> Result<(), ()> {
+    // Before the first check nothing was seen yet
+    message_poll_state::Entity::update_many()
+        .col_expr(
+            message_poll_state::Column::UnreadCount,
+            Expr::value(inbox.iter().filter(|thread| thread.unread).count() as i32),
+        )
+        .filter(message_poll_state::Column::UserId.eq(user_id))
+        .filter(message_poll_state::Column::Initialized.eq(true))
+        .exec(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to update message poll state: {:?}", err);
+        })?;
+
+    Ok(())
+}
+
+/// Checks the inbox of a user, notifies them of the threads with new unread messages and
+/// schedules the next check
+/// The first check only records the newest message, so linking an account doesn't flood the feed.
+/// Returns the number of notifications that were created
+pub async fn check(
+    db_client: &DatabaseConnection,
+    client: &SchoologyClient,
+    state: message_poll_state::Model,
+) -> Result<usize, ()> {
+    let user_id = state.user_id;
+
+    let account = match link_utils::get_account(db_client, user_id).await? {
+        Some(account) => account,
+        None => {
+            debug!("User {} has no Schoology tokens", user_id);
+            poll_schedule::record_failure::<message_poll_state::Entity>(db_client, &state).await?;
+            return Err(());
+        }
+    };
+
+    let inbox = match schoology::messages::get_inbox(client, &account.tokens).await {
+        Ok(inbox) => inbox,
+        Err(err) => {
+            warn!("Failed to get inbox of user {}: {}", user_id, err);
+            poll_schedule::record_failure::<message_poll_state::Entity>(db_client, &state).await?;
+            return Err(());
+        }
+    };
+
+    let new_threads = match state.initialized {
+        true => inbox
+            .iter()
+            .filter(|thread| thread.unread)
+            .filter(|thread| {
+                match (thread.last_updated, state.last_message_at) {
+                    (Some(last_updated), Some(last_message_at)) => {
+                        last_updated.naive_utc() > last_message_at
+                    }
+                    (Some(_), None) => true,
+                    // Can't tell if it is new
+                    (None, _) => false,
+                }
+            })
+            .collect(),
+        false => Vec::new(),
+    };
+
+    // The newest message never goes back, threads may be deleted in Schoology
+    let last_message_at = match (state.last_message_at, newest_message(&inbox)) {
+        (Some(seen), Some(newest)) => Some(seen.max(newest)),
+        (seen, newest) => seen.or(newest),
+    };
+
+    // The notifications and the state are written together so a message is never notified twice
+    let txn = db_client.begin().await.map_err(|err| {
+        warn!("Failed to start transaction: {:?}", err);
+    })?;
+
+    for thread in &new_threads {
+        let subject = match thread.subject.trim() {
+            "" => "(No subject)",
+            subject => subject,
+        };
+
+        notifications::create(
+            &txn,
+            user_id,
+            NotificationKind::NewMessage,
+            "New message".to_string(),
+            subject.to_string(),
+            json!({
+                "thread_id": thread.id,
+                "author_id": thread.author_id,
+                "subject": thread.subject,
+            }),
+        )
+        .await?;
+    }
+
+    let now = chrono::Utc::now().naive_utc();
+
+    message_poll_state::ActiveModel {
+        user_id: ActiveValue::Set(user_id),
+        last_message_at: ActiveValue::Set(last_message_at),
+        unread_count: ActiveValue::Set(inbox.iter().filter(|thread| thread.unread).count() as i32),
+        checked_at: ActiveValue::Set(now),
+        failures: ActiveValue::Set(0),
+        next_check_at: ActiveValue::Set(now + Duration::minutes(CHECK_INTERVAL_MINUTES as i64)),
+        initialized: ActiveValue::Set(true),
+    }
+    .update(&txn)
+    .await
+    .map_err(|err| {
+        warn!("Failed to update message poll state: {:?}", err);
+    })?;
+
+    txn.commit().await.map_err(|err| {
+        warn!("Failed to commit message poll: {:?}", err);
+    })?;
+
+    Ok(new_threads.len())
+}
+
+/// Checks the inboxes of the users that are due
+/// Returns the number of users that were checked
+pub async fn check_due(
+    db_client: &DatabaseConnection,
+    client: &SchoologyClient,
+) -> Result<usize, ()> {
+    poll_schedule::poll_due::<message_poll_state::Entity, _, _>(db_client, |state| {
+        check(db_client, client, state)
+    })
+    .await
+}
+
+pub async fn cronjob_poll_messages() {
+    info!("Checking inboxes...");
+
+    match check_due(get_db_client(), get_schoology_client()).await {
+        Ok(count) => info!("Checked the inboxes of {} users", count),
+        Err(_) => error!("Failed to check inboxes"),
+    }
+}
@@ -1,3 +1,11 @@
+pub mod calendar_feeds;
+pub mod grade_poll;
+pub mod ics;
+pub mod message_poll;
+pub mod notifications;
+pub mod poll_schedule;
+pub mod push;
+pub mod schedule;
 pub mod schoology_link;
 pub mod schoology_request_tokens;
 pub mod sessions;
@@ -0,0 +1,148 @@
+use orm::notifications;
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ActiveValue, ColumnTrait, Condition, ConnectionTrait,
+    DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::utils::push;
+
+/// How long notifications are kept (in days)
+pub const NOTIFICATION_TTL_DAYS: i64 = 90;
+
+/// The kind of a notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationKind {
+    /// An assignment was graded for the first time
+    GradePosted,
+    /// The grade of an assignment changed
+    GradeChanged,
+    /// A message thread in the Schoology inbox has a new message
+    NewMessage,
+}
+
+impl NotificationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationKind::GradePosted => "GradePosted",
+            NotificationKind::GradeChanged => "GradeChanged",
+            NotificationKind::NewMessage => "NewMessage",
+        }
+    }
+}
+
+/// Creates a notification and queues it for the user's push subscriptions
+/// Takes any connection so it can be created in the same transaction as what caused it
+pub async fn create<C>(
+    db_client: &C,
+    user_id: i32,
+    kind: NotificationKind,
+    title: String,
+    body: String,
+    data: Value,
+) -> Result<notifications::Model, ()>
+where
+    C: ConnectionTrait,
+{
+    let notification = notifications::ActiveModel {
+        id: ActiveValue::Set(Uuid::new_v4()),
+        user_id: ActiveValue::Set(user_id),
+        kind: ActiveValue::Set(kind.as_str().to_string()),
+        title: ActiveValue::Set(title),
+        body: ActiveValue::Set(body),
+        data: ActiveValue::Set(data),
+        created_at: ActiveValue::Set(chrono::Utc::now().naive_utc()),
+        read_at: ActiveValue::Set(None),
+    };
+
+    let notification = notification.insert(db_client).await.map_err(|err| {
+        warn!("Failed to create notification: {:?}", err);
+    })?;
+
+    push::enqueue(db_client, &notification).await?;
+
+    Ok(notification)
+}
+
+/// Gets a notification from the database
+pub async fn get(
+    db_client: &DatabaseConnection,
+    id: Uuid,
+) -> Result<Option<notifications::Model>, ()> {
+    notifications::Entity::find_by_id(id)
+        .one(db_client)
+        .await
+        .map_err(|err| {
+            debug!("Failed to get notification: {:?}", err);
+        })
+}
+
+/// Lists the notifications of a user (newest first)
+/// `before` is the last notification of the previous page
+pub async fn list_by_user(
+    db_client: &DatabaseConnection,
+    user_id: i32,
+    before: Option<&notifications::Model>,
+    limit: u64,
+) -> Result<Vec<notifications::Model>, ()> {
+    let mut query = notifications::Entity::find()
+        .filter(notifications::Column::UserId.eq(user_id))
+        .order_by_desc(notifications::Column::CreatedAt)
+        .order_by_desc(notifications::Column::Id)
+        .limit(limit);
+
+    if let Some(before) = before {
+        query = query.filter(
+            Condition::any()
+                .add(notifications::Column::CreatedAt.lt(before.created_at))
+                .add(
+                    Condition::all()
+                        .add(notifications::Column::CreatedAt.eq(before.created_at))
+                        .add(notifications::Column::Id.lt(before.id)),
+                ),
+        );
+    }
+
+    query.all(db_client).await.map_err(|err| {
+        debug!("Failed to list notifications: {:?}", err);
+    })
+}
+
+/// Counts the unread notifications of a user
+pub async fn count_unread(db_client: &DatabaseConnection, user_id: i32) -> Result<u64, ()> {
+    notifications::Entity::find()
+        .filter(notifications::Column::UserId.eq(user_id))
+        .filter(notifications::Column::ReadAt.is_null())
+        .count(db_client)
+        .await
+        .map_err(|err| {
+            debug!("Failed to count notifications: {:?}", err);
+        })
+}
+
+/// Marks notifications of a user as read (all of them if `ids` is `None`)
+/// Returns the number of notifications that were marked
+pub async fn mark_read(
+    db_client: &DatabaseConnection,
+    user_id: i32,
+    ids: Option<Vec<Uuid>>,
+) -> Result<u64, ()> {
+    let mut query = notifications::Entity::update_many()
+        .col_expr(
+            notifications::Column::ReadAt,
+            Expr::value(chrono::Utc::now().naive_utc()),
+        )
+        .filter(notifications::Column::UserId.eq(user_id))
+        .filter(notifications::Column::ReadAt.is_null());
+
+    if let Some(ids) = ids {
+        query = query.filter(notifications::Column::Id.is_in(ids));
+    }
+
+    let result = query.exec(db_client).await.map_err(|err| {
+        warn!("Failed to mark notifications as read: {:?}", err);
+    })?;
+
+    Ok(result.rows_affected)
+}
@@ -0,0 +1,237 @@
+//! The schedule shared by the jobs that poll Schoology for every linked user
+//! Every user has a time they are due at, is claimed by one run of a job and is backed off after failed polls.
+
+use std::future::Future;
+
+use chrono::{Duration, NaiveDateTime};
+use orm::schoology_link;
+use sea_orm::{
+    sea_query::{Expr, OnConflict, Query},
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel, QueryFilter,
+    QueryOrder, QuerySelect,
+};
+
+/// The longest a user is backed off for after failed polls (in minutes)
+const MAX_BACKOFF_MINUTES: i64 = 1440;
+/// How many users are polled per run of a job
+const BATCH_SIZE: u64 = 25;
+
+/// When a user is polled
+pub struct Schedule {
+    pub user_id: i32,
+    /// How often the user is polled (in minutes)
+    pub interval_minutes: i32,
+    /// How many polls failed in a row
+    pub failures: i32,
+    /// When the user is due
+    pub next_at: NaiveDateTime,
+}
+
+/// A table with the polling state of every linked user
+pub trait PollState: EntityTrait {
+    type ActiveModel: ActiveModelTrait<Entity = Self>;
+
+    /// What the state is called in the logs
+    const NAME: &'static str;
+    const USER_ID: Self::Column;
+    const FAILURES: Self::Column;
+    /// The column with the time the user is due at
+    const NEXT_AT: Self::Column;
+
+    /// A state that is due right away
+    fn new_state(user_id: i32) -> Self::ActiveModel;
+
+    fn schedule(state: &Self::Model) -> Schedule;
+}
+
+/// How long to wait before polling again
+/// The interval is doubled for every failed poll in a row (up to a day)
+fn backoff(interval_minutes: i32, failures: i32) -> Duration {
+    let minutes = (interval_minutes.max(1) as i64)
+        .saturating_mul(1 << failures.clamp(0, 16))
+        .min(MAX_BACKOFF_MINUTES.max(interval_minutes as i64));
+
+    Duration::minutes(minutes)
+}
+
+/// Gets the polling state of a user
+pub async fn get<E: PollState>(
+    db_client: &DatabaseConnection,
+    user_id: i32,
+) -> Result<Option<E::Model>, ()> {
+    E::find()
+        .filter(E::USER_ID.eq(user_id))
+        .one(db_client)
+        .await
+        .map_err(|err| {
+            debug!("Failed to get {}: {:?}", E::NAME, err);
+        })
+}
+
+/// Gets the polling state of a user, creating it (due right away) if there is none
+pub async fn ensure<E: PollState>(
+    db_client: &DatabaseConnection,
+    user_id: i32,
+) -> Result<E::Model, ()>
+where
+    E::Model: IntoActiveModel<E::ActiveModel>,
+{
+    if let Some(state) = get::<E>(db_client, user_id).await? {
+        return Ok(state);
+    }
+
+    // Another request may have created it in the meantime
+    E::insert(E::new_state(user_id))
+        .on_conflict(OnConflict::column(E::USER_ID).do_nothing().to_owned())
+        .exec_without_returning(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to create {}: {:?}", E::NAME, err);
+        })?;
+
+    get::<E>(db_client, user_id).await?.ok_or_else(|| {
+        warn!("No {} for user {}", E::NAME, user_id);
+    })
+}
+
+/// Clears the backoff of a user and makes them due right away (e.g. after they logged in again)
+pub async fn reset<E: PollState>(db_client: &DatabaseConnection, user_id: i32) -> Result<(), ()> {
+    E::update_many()
+        .col_expr(E::FAILURES, Expr::value(0))
+        .col_expr(E::NEXT_AT, Expr::value(chrono::Utc::now().naive_utc()))
+        .filter(E::USER_ID.eq(user_id))
+        .exec(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to reset {}: {:?}", E::NAME, err);
+        })?;
+
+    Ok(())
+}
+
+/// Creates the polling state of every linked user that does not have one yet
+/// Returns the number of users that were added
+async fn schedule_new_links<E: PollState>(db_client: &DatabaseConnection) -> Result<u64, ()>
+where
+    E::Model: IntoActiveModel<E::ActiveModel>,
+{
+    let user_ids: Vec<i32> = schoology_link::Entity::find()
+        .select_only()
+        .column(schoology_link::Column::UserId)
+        .filter(
+            schoology_link::Column::UserId.not_in_subquery(
+                Query::select()
+                    .column(E::USER_ID)
+                    .from(E::default())
+                    .to_owned(),
+            ),
+        )
+        .into_tuple()
+        .all(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to get links without a {}: {:?}", E::NAME, err);
+        })?;
+
+    if user_ids.is_empty() {
+        return Ok(0);
+    }
+
+    E::insert_many(user_ids.into_iter().map(E::new_state))
+        .on_conflict(OnConflict::column(E::USER_ID).do_nothing().to_owned())
+        .exec_without_returning(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to create {}s: {:?}", E::NAME, err);
+        })
+}
+
+/// Backs off a user after a failed poll
+pub async fn record_failure<E: PollState>(
+    db_client: &DatabaseConnection,
+    state: &E::Model,
+) -> Result<(), ()> {
+    let schedule = E::schedule(state);
+    let failures = schedule.failures.saturating_add(1);
+
+    E::update_many()
+        .col_expr(E::FAILURES, Expr::value(failures))
+        .col_expr(
+            E::NEXT_AT,
+            Expr::value(
+                (chrono::Utc::now() + backoff(schedule.interval_minutes, failures)).naive_utc(),
+            ),
+        )
+        .filter(E::USER_ID.eq(schedule.user_id))
+        .exec(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to update {}: {:?}", E::NAME, err);
+        })?;
+
+    Ok(())
+}
+
+/// Claims a due user so no other run polls them at the same time
+/// Until the poll finishes, the user is due again after the backoff (in case the run dies)
+async fn claim<E: PollState>(db_client: &DatabaseConnection, state: &E::Model) -> Result<bool, ()> {
+    let schedule = E::schedule(state);
+
+    let result = E::update_many()
+        .col_expr(
+            E::NEXT_AT,
+            Expr::value(
+                (chrono::Utc::now() + backoff(schedule.interval_minutes, schedule.failures))
+                    .naive_utc(),
+            ),
+        )
+        .filter(E::USER_ID.eq(schedule.user_id))
+        .filter(E::NEXT_AT.eq(schedule.next_at))
+        .exec(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to claim {}: {:?}", E::NAME, err);
+        })?;
+
+    Ok(result.rows_affected == 1)
+}
+
+/// Polls the users that are due (a batch per run), the poll has to schedule the next one
+/// Returns the number of users that were polled
+pub async fn poll_due<E, F, Fut>(db_client: &DatabaseConnection, poll: F) -> Result<usize, ()>
+where
+    E: PollState,
+    E::Model: IntoActiveModel<E::ActiveModel>,
+    F: Fn(E::Model) -> Fut,
+    Fut: Future<Output = Result<usize, ()>>,
+{
+    schedule_new_links::<E>(db_client).await?;
+
+    let due = E::find()
+        .filter(E::NEXT_AT.lte(chrono::Utc::now().naive_utc()))
+        .order_by_asc(E::NEXT_AT)
+        .limit(BATCH_SIZE)
+        .all(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to get due {}s: {:?}", E::NAME, err);
+        })?;
+
+    let mut polled = 0;
+
+    for state in due {
+        if !claim::<E>(db_client, &state).await? {
+            continue;
+        }
+
+        let user_id = E::schedule(&state).user_id;
+
+        if let Ok(count) = poll(state).await {
+            debug!("Created {} notifications for user {}", count, user_id);
+        }
+
+        polled += 1;
+    }
+
+    Ok(polled)
+}
@@ -0,0 +1,324 @@
+use chrono::Duration;
+use orm::{notifications, push_deliveries, push_subscriptions};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait,
+    DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    database::get_db_client,
+    push::{get_push_client, PushClient, PushOutcome, MAX_PAYLOAD_SIZE},
+};
+
+/// How many subscriptions a user can have (the oldest ones are removed after that)
+const MAX_SUBSCRIPTIONS: u64 = 10;
+/// How many times a delivery is attempted before giving up
+const MAX_ATTEMPTS: i32 = 8;
+/// The delay before the first retry (in seconds), it is doubled for every attempt
+const RETRY_BASE_SECONDS: i64 = 30;
+/// How long the push service should keep a message for an offline browser (in seconds)
+const PUSH_TTL_SECONDS: u32 = 60 * 60 * 24;
+/// How many deliveries are sent per run of the job
+const BATCH_SIZE: u64 = 100;
+
+/// Subscribes a browser to the notifications of a user
+/// A browser only has one subscription per endpoint, so subscribing again replaces it (and it's pending deliveries)
+pub async fn subscribe(
+    db_client: &DatabaseConnection,
+    user_id: i32,
+    endpoint: String,
+    p256dh: String,
+    auth: String,
+) -> Result<push_subscriptions::Model, ()> {
+    push_subscriptions::Entity::delete_many()
+        .filter(push_subscriptions::Column::Endpoint.eq(endpoint.as_str()))
+        .exec(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to delete push subscription: {:?}", err);
+        })?;
+
+    let subscription = push_subscriptions::ActiveModel {
+        id: ActiveValue::Set(Uuid::new_v4()),
+        user_id: ActiveValue::Set(user_id),
+        endpoint: ActiveValue::Set(endpoint),
+        p256dh: ActiveValue::Set(p256dh),
+        auth: ActiveValue::Set(auth),
+        created_at: ActiveValue::Set(chrono::Utc::now().naive_utc()),
+    }
+    .insert(db_client)
+    .await
+    .map_err(|err| {
+        warn!("Failed to create push subscription: {:?}", err);
+    })?;
+
+    // Remove the oldest subscriptions (most likely browsers that are not used anymore)
+    let old: Vec<Uuid> = push_subscriptions::Entity::find()
+        .select_only()
+        .column(push_subscriptions::Column::Id)
+        .filter(push_subscriptions::Column::UserId.eq(user_id))
+        .order_by_desc(push_subscriptions::Column::CreatedAt)
+        .offset(MAX_SUBSCRIPTIONS)
+        .into_tuple()
+        .all(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to get old push subscriptions: {:?}", err);
+        })?;
+
+    if !old.is_empty() {
+        push_subscriptions::Entity::delete_many()
+            .filter(push_subscriptions::Column::Id.is_in(old))
+            .exec(db_client)
+            .await
+            .map_err(|err| {
+                warn!("Failed to delete old push subscriptions: {:?}", err);
+            })?;
+    }
+
+    Ok(subscription)
+}
+
+/// Unsubscribes a browser, returns whether the user had a subscription with the endpoint
+pub async fn unsubscribe(
+    db_client: &DatabaseConnection,
+    user_id: i32,
+    endpoint: &str,
+) -> Result<bool, ()> {
+    let result = push_subscriptions::Entity::delete_many()
+        .filter(push_subscriptions::Column::UserId.eq(user_id))
+        .filter(push_subscriptions::Column::Endpoint.eq(endpoint))
+        .exec(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to delete push subscription: {:?}", err);
+        })?;
+
+    Ok(result.rows_affected > 0)
+}
+
+/// Queues a notification for delivery to every subscription of it's user
+/// Returns the number of deliveries that were queued
+pub async fn enqueue<C>(db_client: &C, notification: &notifications::Model) -> Result<u64, ()>
+where
+    C: ConnectionTrait,
+{
+    let subscriptions: Vec<Uuid> = push_subscriptions::Entity::find()
+        .select_only()
+        .column(push_subscriptions::Column::Id)
+        .filter(push_subscriptions::Column::UserId.eq(notification.user_id))
+        .into_tuple()
+        .all(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to get push subscriptions: {:?}", err);
+        })?;
+
+    if subscriptions.is_empty() {
+        return Ok(0);
+    }
+
+    let now = chrono::Utc::now().naive_utc();
+
+    push_deliveries::Entity::insert_many(subscriptions.into_iter().map(|subscription_id| {
+        push_deliveries::ActiveModel {
+            id: ActiveValue::Set(Uuid::new_v4()),
+            subscription_id: ActiveValue::Set(subscription_id),
+            notification_id: ActiveValue::Set(notification.id),
+            attempts: ActiveValue::Set(0),
+            next_attempt_at: ActiveValue::Set(now),
+            created_at: ActiveValue::Set(now),
+        }
+    }))
+    .exec_without_returning(db_client)
+    .await
+    .map_err(|err| {
+        warn!("Failed to queue push deliveries: {:?}", err);
+    })
+}
+
+/// The message sent to the browser, the same fields as the notification feed
+fn payload(notification: &notifications::Model) -> Vec<u8> {
+    let payload = json!({
+        "id": notification.id,
+        "type": notification.kind,
+        "title": notification.title,
+        "body": notification.body,
+        "data": notification.data,
+        "created_at": notification.created_at.and_utc(),
+    })
+    .to_string();
+
+    if payload.len() <= MAX_PAYLOAD_SIZE {
+        return payload.into_bytes();
+    }
+
+    // The service worker can still get the rest from the feed
+    json!({
+        "id": notification.id,
+        "type": notification.kind,
+        "title": notification.title,
+        "created_at": notification.created_at.and_utc(),
+    })
+    .to_string()
+    .into_bytes()
+}
+
+/// Claims a due delivery so no other run sends it at the same time
+/// The delivery is pushed back as if it failed, so it is retried if the server stops while sending.
+async fn claim(
+    db_client: &DatabaseConnection,
+    delivery: &push_deliveries::Model,
+) -> Result<bool, ()> {
+    let result = push_deliveries::Entity::update_many()
+        .col_expr(
+            push_deliveries::Column::NextAttemptAt,
+            Expr::value((chrono::Utc::now() + retry_delay(delivery.attempts)).naive_utc()),
+        )
+        .filter(push_deliveries::Column::Id.eq(delivery.id))
+        .filter(push_deliveries::Column::NextAttemptAt.eq(delivery.next_attempt_at))
+        .exec(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to claim push delivery: {:?}", err);
+        })?;
+
+    Ok(result.rows_affected == 1)
+}
+
+/// How long to wait before retrying a delivery that failed `attempts` times
+fn retry_delay(attempts: i32) -> Duration {
+    Duration::seconds(RETRY_BASE_SECONDS << attempts.clamp(0, 16))
+}
+
+async fn delete_delivery(db_client: &DatabaseConnection, id: Uuid) -> Result<(), ()> {
+    push_deliveries::Entity::delete_by_id(id)
+        .exec(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to delete push delivery: {:?}", err);
+        })?;
+
+    Ok(())
+}
+
+/// Sends a delivery and updates the queue with the outcome
+/// Subscriptions that are gone are removed, temporary errors are retried with a backoff.
+pub async fn deliver(
+    db_client: &DatabaseConnection,
+    client: &PushClient,
+    delivery: push_deliveries::Model,
+) -> Result<PushOutcome, ()> {
+    let subscription = push_subscriptions::Entity::find_by_id(delivery.subscription_id)
+        .one(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to get push subscription: {:?}", err);
+        })?;
+
+    let notification = notifications::Entity::find_by_id(delivery.notification_id)
+        .one(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to get notification: {:?}", err);
+        })?;
+
+    let (subscription, notification) = match (subscription, notification) {
+        (Some(subscription), Some(notification)) => (subscription, notification),
+        _ => {
+            // Removed while it was queued
+            delete_delivery(db_client, delivery.id).await?;
+            return Ok(PushOutcome::Gone);
+        }
+    };
+
+    let outcome = client
+        .send(&subscription, &payload(&notification), PUSH_TTL_SECONDS)
+        .await;
+
+    match outcome {
+        PushOutcome::Delivered => delete_delivery(db_client, delivery.id).await?,
+        PushOutcome::Gone => {
+            debug!("Push subscription {} is gone", subscription.id);
+
+            // Also removes it's other deliveries
+            push_subscriptions::Entity::delete_by_id(subscription.id)
+                .exec(db_client)
+                .await
+                .map_err(|err| {
+                    warn!("Failed to delete push subscription: {:?}", err);
+                })?;
+        }
+        PushOutcome::Rejected => delete_delivery(db_client, delivery.id).await?,
+        PushOutcome::Retry => {
+            let attempts = delivery.attempts.saturating_add(1);
+
+            if attempts >= MAX_ATTEMPTS {
+                warn!(
+                    "Giving up on push delivery {} after {} attempts",
+                    delivery.id, attempts
+                );
+                delete_delivery(db_client, delivery.id).await?;
+            } else {
+                push_deliveries::ActiveModel {
+                    id: ActiveValue::Set(delivery.id),
+                    attempts: ActiveValue::Set(attempts),
+                    next_attempt_at: ActiveValue::Set(
+                        (chrono::Utc::now() + retry_delay(attempts)).naive_utc(),
+                    ),
+                    ..Default::default()
+                }
+                .update(db_client)
+                .await
+                .map_err(|err| {
+                    warn!("Failed to update push delivery: {:?}", err);
+                })?;
+            }
+        }
+    }
+
+    Ok(outcome)
+}
+
+/// Sends the deliveries that are due
+/// Returns the number of deliveries that were attempted
+pub async fn deliver_due(db_client: &DatabaseConnection, client: &PushClient) -> Result<usize, ()> {
+    let due = push_deliveries::Entity::find()
+        .filter(push_deliveries::Column::NextAttemptAt.lte(chrono::Utc::now().naive_utc()))
+        .order_by_asc(push_deliveries::Column::NextAttemptAt)
+        .limit(BATCH_SIZE)
+        .all(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to get due push deliveries: {:?}", err);
+        })?;
+
+    let mut attempted = 0;
+
+    for delivery in due {
+        if !claim(db_client, &delivery).await? {
+            continue;
+        }
+
+        deliver(db_client, client, delivery).await?;
+
+        attempted += 1;
+    }
+
+    Ok(attempted)
+}
+
+pub async fn cronjob_deliver_pushes() {
+    let client = match get_push_client() {
+        Some(client) => client,
+        None => return,
+    };
+
+    match deliver_due(get_db_client(), client).await {
+        Ok(0) => {}
+        Ok(count) => info!("Sent {} push messages", count),
+        Err(_) => error!("Failed to send push messages"),
+    }
+}
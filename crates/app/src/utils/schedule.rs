@@ -0,0 +1,562 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use once_cell::sync::OnceCell;
+use orm::{bell_periods, bell_schedules, schedule_overrides, schedule_weekdays, user_periods};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait,
+    QueryFilter, QueryOrder, TransactionTrait,
+};
+use uuid::Uuid;
+
+static TIMEZONE: OnceCell<Tz> = OnceCell::new();
+
+/// The longest range of dates that can be overridden or listed at once (in days)
+pub const MAX_RANGE_DAYS: i64 = 366;
+/// How many periods a bell schedule can have
+pub const MAX_PERIODS: usize = 24;
+/// How many periods a user can name
+pub const MAX_USER_PERIODS: usize = 32;
+/// The longest name of a schedule, override or period (in characters)
+pub const MAX_NAME_LENGTH: usize = 64;
+/// How many days ahead the next period is looked for (long enough for winter break)
+const NEXT_PERIOD_LOOKAHEAD_DAYS: i64 = 21;
+
+/// Sets the timezone of the school (an IANA name like `America/Los_Angeles`)
+pub fn set_timezone(name: &str) -> Result<(), String> {
+    let timezone: Tz = name
+        .parse()
+        .map_err(|e| format!("Invalid school timezone: {}", e))?;
+
+    TIMEZONE
+        .set(timezone)
+        .map_err(|_| "Failed to set school timezone".to_string())
+}
+
+/// The timezone of the school, all the times of the schedule are in it
+pub fn timezone() -> Tz {
+    TIMEZONE.get().copied().unwrap_or(Tz::UTC)
+}
+
+/// The current date at the school
+pub fn today() -> NaiveDate {
+    Utc::now().with_timezone(&timezone()).date_naive()
+}
+
+/// Converts a time at the school on a date to UTC
+pub fn to_utc(date: NaiveDate, time: NaiveTime) -> DateTime<Utc> {
+    let local = date.and_time(time);
+
+    // Only times skipped by daylight saving (at night) don't exist, they are treated as UTC
+    timezone()
+        .from_local_datetime(&local)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+        .unwrap_or_else(|| local.and_utc())
+}
+
+/// Checks the name of a schedule, override or period
+pub fn is_valid_name(name: &str) -> bool {
+    !name.trim().is_empty() && name.chars().count() <= MAX_NAME_LENGTH
+}
+
+/// Checks a color (`#rrggbb`)
+pub fn is_valid_color(color: &str) -> bool {
+    color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// A period of a bell schedule that is being saved
+pub struct NewPeriod {
+    /// What identifies the period across schedules (`1`, `lunch`, ...)
+    pub period: String,
+    pub name: String,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+/// Checks the periods of a bell schedule (named, in order and not overlapping)
+pub fn is_valid_periods(periods: &[NewPeriod]) -> bool {
+    periods.len() <= MAX_PERIODS
+        && periods.iter().all(|period| {
+            is_valid_name(&period.period)
+                && is_valid_name(&period.name)
+                && period.start < period.end
+        })
+        && periods.windows(2).all(|pair| pair[0].end <= pair[1].start)
+}
+
+/// A period on a date
+#[derive(Debug, Clone)]
+pub struct DayPeriod {
+    pub period: String,
+    pub name: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+/// The schedule of a date
+pub struct DaySchedule {
+    pub date: NaiveDate,
+    /// The name of the override (`None` on a regular day)
+    pub name: Option<String>,
+    /// The bell schedule (`None` if there is no school)
+    pub schedule: Option<bell_schedules::Model>,
+    pub periods: Vec<DayPeriod>,
+}
+
+/// What period it is at a time
+pub struct Now {
+    pub day: DaySchedule,
+    pub current: Option<DayPeriod>,
+    /// The next period to start (can be on a later date)
+    pub next: Option<DayPeriod>,
+}
+
+/// Lists the bell schedules (by name) with their periods
+pub async fn list_schedules(
+    db_client: &DatabaseConnection,
+) -> Result<Vec<(bell_schedules::Model, Vec<bell_periods::Model>)>, ()> {
+    let schedules = bell_schedules::Entity::find()
+        .find_with_related(bell_periods::Entity)
+        .order_by_asc(bell_schedules::Column::Name)
+        .order_by_asc(bell_schedules::Column::Id)
+        .order_by_asc(bell_periods::Column::Position)
+        .all(db_client)
+        .await
+        .map_err(|err| {
+            debug!("Failed to list bell schedules: {:?}", err);
+        })?;
+
+    Ok(schedules)
+}
+
+/// Checks that all of the bell schedules exist
+pub async fn schedules_exist(db_client: &DatabaseConnection, ids: &[Uuid]) -> Result<bool, ()> {
+    let ids: HashSet<Uuid> = ids.iter().copied().collect();
+
+    if ids.is_empty() {
+        return Ok(true);
+    }
+
+    let count = bell_schedules::Entity::find()
+        .filter(bell_schedules::Column::Id.is_in(ids.iter().copied()))
+        .count(db_client)
+        .await
+        .map_err(|err| {
+            debug!("Failed to count bell schedules: {:?}", err);
+        })?;
+
+    Ok(count == ids.len() as u64)
+}
+
+/// Creates a bell schedule or replaces an existing one (with all of it's periods)
+/// Returns `None` if `id` is not a bell schedule
+pub async fn save_schedule(
+    db_client: &DatabaseConnection,
+    id: Option<Uuid>,
+    name: String,
+    periods: Vec<NewPeriod>,
+) -> Result<Option<bell_schedules::Model>, ()> {
+    let txn = db_client.begin().await.map_err(|err| {
+        warn!("Failed to start transaction: {:?}", err);
+    })?;
+
+    let now = Utc::now().naive_utc();
+
+    let schedule = match id {
+        Some(id) => {
+            let exists = bell_schedules::Entity::find_by_id(id)
+                .one(&txn)
+                .await
+                .map_err(|err| {
+                    warn!("Failed to get bell schedule: {:?}", err);
+                })?
+                .is_some();
+
+            if !exists {
+                return Ok(None);
+            }
+
+            bell_schedules::ActiveModel {
+                id: ActiveValue::Set(id),
+                name: ActiveValue::Set(name),
+                updated_at: ActiveValue::Set(now),
+                ..Default::default()
+            }
+            .update(&txn)
+            .await
+        }
+        None => {
+            bell_schedules::ActiveModel {
+                id: ActiveValue::Set(Uuid::new_v4()),
+                name: ActiveValue::Set(name),
+                created_at: ActiveValue::Set(now),
+                updated_at: ActiveValue::Set(now),
+            }
+            .insert(&txn)
+            .await
+        }
+    }
+    .map_err(|err| {
+        warn!("Failed to save bell schedule: {:?}", err);
+    })?;
+
+    bell_periods::Entity::delete_many()
+        .filter(bell_periods::Column::ScheduleId.eq(schedule.id))
+        .exec(&txn)
+        .await
+        .map_err(|err| {
+            warn!("Failed to delete bell periods: {:?}", err);
+        })?;
+
+    if !periods.is_empty() {
+        bell_periods::Entity::insert_many(periods.into_iter().enumerate().map(
+            |(position, period)| bell_periods::ActiveModel {
+                schedule_id: ActiveValue::Set(schedule.id),
+                position: ActiveValue::Set(position as i32),
+                period: ActiveValue::Set(period.period),
+                name: ActiveValue::Set(period.name),
+                start_time: ActiveValue::Set(period.start),
+                end_time: ActiveValue::Set(period.end),
+            },
+        ))
+        .exec_without_returning(&txn)
+        .await
+        .map_err(|err| {
+            warn!("Failed to create bell periods: {:?}", err);
+        })?;
+    }
+
+    txn.commit().await.map_err(|err| {
+        warn!("Failed to commit bell schedule: {:?}", err);
+    })?;
+
+    Ok(Some(schedule))
+}
+
+/// Gets the bell schedule of every weekday (Monday first)
+pub async fn get_weekdays(db_client: &DatabaseConnection) -> Result<[Option<Uuid>; 7], ()> {
+    let weekdays = schedule_weekdays::Entity::find()
+        .all(db_client)
+        .await
+        .map_err(|err| {
+            debug!("Failed to get schedule weekdays: {:?}", err);
+        })?;
+
+    let mut schedules = [None; 7];
+
+    for weekday in weekdays {
+        if let Some(schedule) = schedules.get_mut(weekday.weekday as usize) {
+            *schedule = Some(weekday.schedule_id);
+        }
+    }
+
+    Ok(schedules)
+}
+
+/// Sets the bell schedule of every weekday (Monday first, `None` if there is no school)
+pub async fn set_weekdays(
+    db_client: &DatabaseConnection,
+    schedules: [Option<Uuid>; 7],
+) -> Result<(), ()> {
+    let txn = db_client.begin().await.map_err(|err| {
+        warn!("Failed to start transaction: {:?}", err);
+    })?;
+
+    schedule_weekdays::Entity::delete_many()
+        .exec(&txn)
+        .await
+        .map_err(|err| {
+            warn!("Failed to delete schedule weekdays: {:?}", err);
+        })?;
+
+    let weekdays: Vec<schedule_weekdays::ActiveModel> = schedules
+        .iter()
+        .enumerate()
+        .filter_map(|(weekday, schedule_id)| {
+            schedule_id.map(|schedule_id| schedule_weekdays::ActiveModel {
+                weekday: ActiveValue::Set(weekday as i16),
+                schedule_id: ActiveValue::Set(schedule_id),
+            })
+        })
+        .collect();
+
+    if !weekdays.is_empty() {
+        schedule_weekdays::Entity::insert_many(weekdays)
+            .exec_without_returning(&txn)
+            .await
+            .map_err(|err| {
+                warn!("Failed to create schedule weekdays: {:?}", err);
+            })?;
+    }
+
+    txn.commit().await.map_err(|err| {
+        warn!("Failed to commit schedule weekdays: {:?}", err);
+    })
+}
+
+/// Lists the overrides between two dates (inclusive)
+pub async fn list_overrides(
+    db_client: &DatabaseConnection,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<schedule_overrides::Model>, ()> {
+    schedule_overrides::Entity::find()
+        .filter(schedule_overrides::Column::Date.between(from, to))
+        .order_by_asc(schedule_overrides::Column::Date)
+        .all(db_client)
+        .await
+        .map_err(|err| {
+            debug!("Failed to list schedule overrides: {:?}", err);
+        })
+}
+
+/// Overrides the schedule of every date between two dates (inclusive), replacing existing overrides
+/// Without a bell schedule there is no school on the dates. Returns the number of dates.
+pub async fn set_overrides(
+    db_client: &DatabaseConnection,
+    from: NaiveDate,
+    to: NaiveDate,
+    schedule_id: Option<Uuid>,
+    name: String,
+) -> Result<u64, ()> {
+    let txn = db_client.begin().await.map_err(|err| {
+        warn!("Failed to start transaction: {:?}", err);
+    })?;
+
+    schedule_overrides::Entity::delete_many()
+        .filter(schedule_overrides::Column::Date.between(from, to))
+        .exec(&txn)
+        .await
+        .map_err(|err| {
+            warn!("Failed to delete schedule overrides: {:?}", err);
+        })?;
+
+    let now = Utc::now().naive_utc();
+    let overrides: Vec<schedule_overrides::ActiveModel> = from
+        .iter_days()
+        .take_while(|date| *date <= to)
+        .map(|date| schedule_overrides::ActiveModel {
+            date: ActiveValue::Set(date),
+            schedule_id: ActiveValue::Set(schedule_id),
+            name: ActiveValue::Set(name.clone()),
+            created_at: ActiveValue::Set(now),
+        })
+        .collect();
+
+    let count = overrides.len() as u64;
+
+    if !overrides.is_empty() {
+        schedule_overrides::Entity::insert_many(overrides)
+            .exec_without_returning(&txn)
+            .await
+            .map_err(|err| {
+                warn!("Failed to create schedule overrides: {:?}", err);
+            })?;
+    }
+
+    txn.commit().await.map_err(|err| {
+        warn!("Failed to commit schedule overrides: {:?}", err);
+    })?;
+
+    Ok(count)
+}
+
+/// Removes the overrides between two dates (inclusive), returns the number that were removed
+pub async fn delete_overrides(
+    db_client: &DatabaseConnection,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<u64, ()> {
+    let result = schedule_overrides::Entity::delete_many()
+        .filter(schedule_overrides::Column::Date.between(from, to))
+        .exec(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to delete schedule overrides: {:?}", err);
+        })?;
+
+    Ok(result.rows_affected)
+}
+
+/// Gets the schedule of every date between two dates (inclusive), the override or else the weekly
+/// default. The overrides, weekdays and bell schedules are each loaded once for the whole range.
+pub async fn for_range(
+    db_client: &DatabaseConnection,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<DaySchedule>, ()> {
+    let overrides: HashMap<NaiveDate, schedule_overrides::Model> =
+        list_overrides(db_client, from, to)
+            .await?
+            .into_iter()
+            .map(|day_override| (day_override.date, day_override))
+            .collect();
+
+    let weekdays = get_weekdays(db_client).await?;
+
+    let days: Vec<(NaiveDate, Option<String>, Option<Uuid>)> = from
+        .iter_days()
+        .take_while(|date| *date <= to)
+        .map(|date| match overrides.get(&date) {
+            Some(day_override) => (
+                date,
+                Some(day_override.name.clone()),
+                day_override.schedule_id,
+            ),
+            None => (
+                date,
+                None,
+                weekdays[date.weekday().num_days_from_monday() as usize],
+            ),
+        })
+        .collect();
+
+    let schedule_ids: HashSet<Uuid> = days
+        .iter()
+        .filter_map(|(_, _, schedule_id)| *schedule_id)
+        .collect();
+
+    let schedules: HashMap<Uuid, (bell_schedules::Model, Vec<bell_periods::Model>)> =
+        if schedule_ids.is_empty() {
+            HashMap::new()
+        } else {
+            bell_schedules::Entity::find()
+                .filter(bell_schedules::Column::Id.is_in(schedule_ids))
+                .find_with_related(bell_periods::Entity)
+                .order_by_asc(bell_schedules::Column::Id)
+                .order_by_asc(bell_periods::Column::Position)
+                .all(db_client)
+                .await
+                .map_err(|err| {
+                    debug!("Failed to get bell schedules: {:?}", err);
+                })?
+                .into_iter()
+                .map(|(schedule, periods)| (schedule.id, (schedule, periods)))
+                .collect()
+        };
+
+    Ok(days
+        .into_iter()
+        .map(|(date, name, schedule_id)| {
+            match schedule_id.and_then(|schedule_id| schedules.get(&schedule_id)) {
+                Some((schedule, periods)) => DaySchedule {
+                    date,
+                    name,
+                    schedule: Some(schedule.clone()),
+                    periods: periods
+                        .iter()
+                        .map(|period| DayPeriod {
+                            period: period.period.clone(),
+                            name: period.name.clone(),
+                            start: to_utc(date, period.start_time),
+                            end: to_utc(date, period.end_time),
+                        })
+                        .collect(),
+                },
+                None => DaySchedule {
+                    date,
+                    name,
+                    schedule: None,
+                    periods: Vec::new(),
+                },
+            }
+        })
+        .collect())
+}
+
+/// Gets the schedule of a date (the override or else the weekly default)
+pub async fn for_date(db_client: &DatabaseConnection, date: NaiveDate) -> Result<DaySchedule, ()> {
+    for_range(db_client, date, date)
+        .await?
+        .pop()
+        .ok_or_else(|| {
+            debug!("No schedule for {}", date);
+        })
+}
+
+/// Gets what period it is at a time and which one is next
+pub async fn at(db_client: &DatabaseConnection, time: DateTime<Utc>) -> Result<Now, ()> {
+    let date = time.with_timezone(&timezone()).date_naive();
+    let day = for_date(db_client, date).await?;
+
+    let current = day
+        .periods
+        .iter()
+        .find(|period| period.start <= time && time < period.end)
+        .cloned();
+
+    let mut next = day
+        .periods
+        .iter()
+        .find(|period| period.start > time)
+        .cloned();
+
+    // Otherwise it's the first period of the next day with school
+    if next.is_none() {
+        let from = date + Duration::days(1);
+        let to = date + Duration::days(NEXT_PERIOD_LOOKAHEAD_DAYS);
+
+        next = for_range(db_client, from, to)
+            .await?
+            .into_iter()
+            .find_map(|day| day.periods.into_iter().next());
+    }
+
+    Ok(Now { day, current, next })
+}
+
+/// Gets the names and colors a user gave to their periods
+pub async fn get_user_periods(
+    db_client: &DatabaseConnection,
+    user_id: i32,
+) -> Result<Vec<user_periods::Model>, ()> {
+    user_periods::Entity::find()
+        .filter(user_periods::Column::UserId.eq(user_id))
+        .order_by_asc(user_periods::Column::Period)
+        .all(db_client)
+        .await
+        .map_err(|err| {
+            debug!("Failed to get user periods: {:?}", err);
+        })
+}
+
+/// Replaces the names and colors a user gave to their periods
+pub async fn set_user_periods(
+    db_client: &DatabaseConnection,
+    user_id: i32,
+    periods: Vec<user_periods::Model>,
+) -> Result<(), ()> {
+    let txn = db_client.begin().await.map_err(|err| {
+        warn!("Failed to start transaction: {:?}", err);
+    })?;
+
+    user_periods::Entity::delete_many()
+        .filter(user_periods::Column::UserId.eq(user_id))
+        .exec(&txn)
+        .await
+        .map_err(|err| {
+            warn!("Failed to delete user periods: {:?}", err);
+        })?;
+
+    if !periods.is_empty() {
+        user_periods::Entity::insert_many(periods.into_iter().map(|period| {
+            user_periods::ActiveModel {
+                user_id: ActiveValue::Set(user_id),
+                period: ActiveValue::Set(period.period),
+                name: ActiveValue::Set(period.name),
+                color: ActiveValue::Set(period.color),
+            }
+        }))
+        .exec_without_returning(&txn)
+        .await
+        .map_err(|err| {
+            warn!("Failed to create user periods: {:?}", err);
+        })?;
+    }
+
+    txn.commit().await.map_err(|err| {
+        warn!("Failed to commit user periods: {:?}", err);
+    })
+}
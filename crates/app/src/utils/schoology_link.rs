@@ -1,22 +1,98 @@
 use orm::schoology_link;
+use schoology::SchoologyTokenPair;
 use sea_orm::{
-    ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter,
+    sea_query::Expr, ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect,
 };
 
+use crate::encryption::get_keyring;
+
+/// The tokens of a link encrypted with a new data key
+struct EncryptedTokens {
+    access_token: Option<String>,
+    token_secret: Option<String>,
+    key_id: String,
+    wrapped_key: String,
+}
+
+/// Binds an encrypted value to it's row and column so it can't be moved to another one
+fn aad(user_id: i32, column: &str) -> String {
+    format!("schoology_link:{}:{}", user_id, column)
+}
+
+/// Encrypts the tokens of a link with a new data key
+fn encrypt_tokens(
+    user_id: i32,
+    access_token: Option<String>,
+    token_secret: Option<String>,
+) -> Result<EncryptedTokens, ()> {
+    let envelope = get_keyring().new_envelope(&aad(user_id, "wrapped_key"))?;
+
+    Ok(EncryptedTokens {
+        access_token: access_token
+            .map(|token| {
+                envelope
+                    .data_key
+                    .encrypt(&token, &aad(user_id, "access_token"))
+            })
+            .transpose()?,
+        token_secret: token_secret
+            .map(|token| {
+                envelope
+                    .data_key
+                    .encrypt(&token, &aad(user_id, "token_secret"))
+            })
+            .transpose()?,
+        key_id: envelope.key_id,
+        wrapped_key: envelope.wrapped_key,
+    })
+}
+
+/// Decrypts the tokens of a link
+/// Links without a key id were stored before tokens were encrypted, they are still in plaintext
+/// until `encrypt_all_legacy` runs at startup
+fn decrypt_tokens(link: schoology_link::Model) -> Result<schoology_link::Model, ()> {
+    let data_key = match (&link.key_id, &link.wrapped_key) {
+        (Some(key_id), Some(wrapped_key)) => {
+            get_keyring().open_envelope(key_id, wrapped_key, &aad(link.user_id, "wrapped_key"))?
+        }
+        _ => return Ok(link),
+    };
+
+    let access_token = link
+        .access_token
+        .as_ref()
+        .map(|token| data_key.decrypt(token, &aad(link.user_id, "access_token")))
+        .transpose()?;
+
+    let token_secret = link
+        .token_secret
+        .as_ref()
+        .map(|token| data_key.decrypt(token, &aad(link.user_id, "token_secret")))
+        .transpose()?;
+
+    Ok(schoology_link::Model {
+        access_token,
+        token_secret,
+        ..link
+    })
+}
+
 /// Gets a Schoology link from the database by the schhology id
 pub async fn get(
     db_client: &DatabaseConnection,
     id: i32,
 ) -> Result<Option<schoology_link::Model>, ()> {
     // Query the database
-    match schoology_link::Entity::find_by_id(id)
+    match schoology_link::Entity::find()
+        .filter(schoology_link::Column::SchoologyId.eq(id))
         .one(db_client)
         .await
         .map_err(|err| {
             debug!("Failed to get user: {:?}", err);
         })? {
-        Some(user) => Ok(Some(user)),
-        None => return Ok(None),
+        Some(user) => Ok(Some(decrypt_tokens(user)?)),
+        None => Ok(None),
     }
 }
 
@@ -33,12 +109,41 @@ pub async fn get_by_user_id(
         .map_err(|err| {
             debug!("Failed to get user: {:?}", err);
         })? {
-        Some(user) => Ok(Some(user)),
+        Some(user) => Ok(Some(decrypt_tokens(user)?)),
+        None => Ok(None),
+    }
+}
+
+/// The Schoology account of a user and the tokens to access it
+pub struct LinkedAccount {
+    pub schoology_id: usize,
+    pub tokens: SchoologyTokenPair,
+}
+
+/// Gets the Schoology account of a user (`None` if the user has not linked one)
+pub async fn get_account(
+    db_client: &DatabaseConnection,
+    user_id: i32,
+) -> Result<Option<LinkedAccount>, ()> {
+    let link = match get_by_user_id(db_client, user_id).await? {
+        Some(link) => link,
         None => return Ok(None),
+    };
+
+    match (link.access_token, link.token_secret) {
+        (Some(access_token), Some(token_secret)) => Ok(Some(LinkedAccount {
+            schoology_id: link.schoology_id as usize,
+            tokens: SchoologyTokenPair {
+                access_token,
+                token_secret,
+            },
+        })),
+        _ => Ok(None),
     }
 }
 
 /// Creates a user in the database
+#[allow(clippy::too_many_arguments)]
 pub async fn create(
     db_client: &DatabaseConnection,
     user_id: i32,
@@ -50,6 +155,9 @@ pub async fn create(
     access_token: Option<String>,
     token_secret: Option<String>,
 ) -> Result<schoology_link::Model, ()> {
+    // Encrypt the tokens
+    let tokens = encrypt_tokens(user_id, access_token, token_secret)?;
+
     // Create the user
     let user = schoology_link::ActiveModel {
         user_id: ActiveValue::Set(user_id),
@@ -58,15 +166,17 @@ pub async fn create(
         last_name: ActiveValue::Set(last_name),
         email: ActiveValue::Set(email),
         picture_url: ActiveValue::Set(picture_url),
-        access_token: ActiveValue::Set(access_token),
-        token_secret: ActiveValue::Set(token_secret),
+        access_token: ActiveValue::Set(tokens.access_token),
+        token_secret: ActiveValue::Set(tokens.token_secret),
+        key_id: ActiveValue::Set(Some(tokens.key_id)),
+        wrapped_key: ActiveValue::Set(Some(tokens.wrapped_key)),
     };
 
     // Insert the user into the database
     let user = user.insert(db_client).await;
 
     match user {
-        Ok(user) => Ok(user),
+        Ok(user) => decrypt_tokens(user),
         Err(err) => {
             warn!("Failed to create user: {:?}", err);
             Err(())
@@ -75,9 +185,10 @@ pub async fn create(
 }
 
 /// Updates a user in the database
+#[allow(clippy::too_many_arguments)]
 pub async fn update(
     db_client: &DatabaseConnection,
-    schoology_id: i32,
+    user_id: i32,
     first_name: Option<String>,
     last_name: Option<String>,
     email: Option<String>,
@@ -92,19 +203,125 @@ pub async fn update(
         }
     }
 
+    // Both tokens share a data key, so the one that isn't changed is re-encrypted with the new key
+    let current = get_by_user_id(db_client, user_id).await?.ok_or_else(|| {
+        warn!("Failed to update user: link for user {} not found", user_id);
+    })?;
+
+    let tokens = encrypt_tokens(
+        user_id,
+        access_token.or(current.access_token),
+        token_secret.or(current.token_secret),
+    )?;
+
     // Update the user
     let user = schoology_link::ActiveModel {
-        user_id: ActiveValue::NotSet,
-        schoology_id: ActiveValue::Set(schoology_id),
+        user_id: ActiveValue::Set(user_id),
+        schoology_id: ActiveValue::NotSet,
         first_name: convert_to_active_value(first_name),
         last_name: convert_to_active_value(last_name),
         email: convert_to_active_value(email),
         picture_url: convert_to_active_value(picture_url),
-        access_token: convert_to_active_value(access_token),
-        token_secret: convert_to_active_value(token_secret),
+        access_token: ActiveValue::Set(tokens.access_token),
+        token_secret: ActiveValue::Set(tokens.token_secret),
+        key_id: ActiveValue::Set(Some(tokens.key_id)),
+        wrapped_key: ActiveValue::Set(Some(tokens.wrapped_key)),
     };
 
-    user.update(db_client).await.map_err(|err| {
+    let user = user.update(db_client).await.map_err(|err| {
         warn!("Failed to update user: {:?}", err);
-    })
+    })?;
+
+    decrypt_tokens(user)
+}
+
+/// Re-encrypts the tokens of a link with a new data key wrapped with the current master key
+/// Returns `false` if the link was changed in the meantime (the change encrypted it again)
+async fn reencrypt(
+    db_client: &DatabaseConnection,
+    link: schoology_link::Model,
+) -> Result<bool, ()> {
+    let wrapped_key = link.wrapped_key.clone();
+
+    let link = decrypt_tokens(link)?;
+    let tokens = encrypt_tokens(link.user_id, link.access_token, link.token_secret)?;
+
+    // Only update the row if it still has the data key the tokens were decrypted with
+    let result = schoology_link::Entity::update_many()
+        .col_expr(
+            schoology_link::Column::AccessToken,
+            Expr::value(tokens.access_token),
+        )
+        .col_expr(
+            schoology_link::Column::TokenSecret,
+            Expr::value(tokens.token_secret),
+        )
+        .col_expr(schoology_link::Column::KeyId, Expr::value(tokens.key_id))
+        .col_expr(
+            schoology_link::Column::WrappedKey,
+            Expr::value(tokens.wrapped_key),
+        )
+        .filter(schoology_link::Column::UserId.eq(link.user_id))
+        .filter(match wrapped_key {
+            Some(wrapped_key) => schoology_link::Column::WrappedKey.eq(wrapped_key),
+            None => schoology_link::Column::WrappedKey.is_null(),
+        })
+        .exec(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to re-encrypt link: {:?}", err);
+        })?;
+
+    Ok(result.rows_affected > 0)
+}
+
+/// Re-encrypts the tokens of every link with a new data key wrapped with the current master key
+/// Returns the number of links that were re-encrypted
+pub async fn rotate_keys(db_client: &DatabaseConnection) -> Result<usize, ()> {
+    let mut pages = schoology_link::Entity::find()
+        .order_by_asc(schoology_link::Column::UserId)
+        .paginate(db_client, 100);
+
+    let mut count = 0;
+
+    while let Some(links) = pages.fetch_and_next().await.map_err(|err| {
+        warn!("Failed to get links: {:?}", err);
+    })? {
+        for link in links {
+            if reencrypt(db_client, link).await? {
+                count += 1;
+            }
+        }
+    }
+
+    Ok(count)
+}
+
+/// Encrypts the tokens of all the links stored before tokens were encrypted
+/// Returns the number of links that were encrypted
+pub async fn encrypt_all_legacy(db_client: &DatabaseConnection) -> Result<usize, ()> {
+    let mut count = 0;
+
+    // Encrypted links drop out of the query, so the first page is fetched until it is empty
+    loop {
+        let links = schoology_link::Entity::find()
+            .filter(schoology_link::Column::KeyId.is_null())
+            .order_by_asc(schoology_link::Column::UserId)
+            .limit(100)
+            .all(db_client)
+            .await
+            .map_err(|err| {
+                warn!("Failed to get legacy links: {:?}", err);
+            })?;
+
+        if links.is_empty() {
+            return Ok(count);
+        }
+
+        for link in links {
+            if reencrypt(db_client, link).await? {
+                count += 1;
+            }
+        }
+    }
 }
@@ -6,6 +6,7 @@ use sha2::Sha512;
 use uuid::Uuid;
 
 /// Creates a signature for a request token
+#[allow(clippy::result_unit_err)]
 pub fn sign(uuid: Uuid, token_secret: String) -> Result<String, ()> {
     // Hash the uuid with the signature
     let hash = match Hmac::<Sha512>::new_from_slice(token_secret.as_bytes()) {
@@ -20,7 +21,7 @@ pub fn sign(uuid: Uuid, token_secret: String) -> Result<String, ()> {
     };
 
     // Base64 encode the hash
-    let hash = general_purpose::STANDARD_NO_PAD.encode(&hash.into_bytes());
+    let hash = general_purpose::STANDARD_NO_PAD.encode(hash.into_bytes());
 
     Ok(hash)
 }
@@ -38,7 +39,7 @@ pub async fn get(
             debug!("Failed to get schoology request token: {:?}", err);
         })? {
         Some(schoology_request_token) => Ok(Some(schoology_request_token)),
-        None => return Ok(None),
+        None => Ok(None),
     }
 }
 
@@ -1,10 +1,96 @@
 use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use once_cell::sync::OnceCell;
 use orm::sessions;
 use ring::rand::{SecureRandom, SystemRandom};
-use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection, EntityTrait};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, QueryOrder,
+};
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 use uuid::Uuid;
 
+/// How long an access token is valid for (in minutes)
+const ACCESS_TOKEN_TTL_MINUTES: i64 = 60;
+/// How long a session lasts without any activity (in days)
+const SESSION_TTL_DAYS: i64 = 30;
+/// How far behind the sliding expiry may fall before it is written again (in hours)
+const SESSION_EXTEND_INTERVAL_HOURS: i64 = 24;
+
+/// The server pepper used to hash session secrets
+static PEPPER: OnceCell<Vec<u8>> = OnceCell::new();
+
+/// Sets the server pepper used to hash session secrets
+pub fn set_pepper(pepper: String) -> Result<(), String> {
+    if pepper.len() < 32 {
+        return Err("The session pepper must be at least 32 characters long".to_string());
+    }
+
+    PEPPER
+        .set(pepper.into_bytes())
+        .map_err(|_| "Failed to set session pepper".to_string())
+}
+
+/// Creates a HMAC-SHA256 of a session secret keyed with the server pepper
+fn hash_secret(secret: &str) -> Result<Hmac<Sha256>, ()> {
+    let pepper = PEPPER.get().ok_or_else(|| {
+        error!("Session pepper not initialized");
+    })?;
+
+    let mut hmac = Hmac::<Sha256>::new_from_slice(pepper).map_err(|err| {
+        error!("Failed to hash session secret: {:?}", err);
+    })?;
+    hmac.update(secret.as_bytes());
+
+    Ok(hmac)
+}
+
+/// Hashes a session secret to it's stored representation
+#[allow(clippy::result_unit_err)]
+pub fn encode_secret_hash(secret: &str) -> Result<String, ()> {
+    let hash = hash_secret(secret)?.finalize();
+
+    Ok(STANDARD_NO_PAD.encode(hash.into_bytes()))
+}
+
+/// Checks a session secret against a stored hash (in constant time)
+fn verify_secret_hash(secret: &str, hash: &str) -> Result<bool, ()> {
+    let hash = match STANDARD_NO_PAD.decode(hash) {
+        Ok(hash) => hash,
+        Err(err) => {
+            warn!("Failed to decode stored session hash: {:?}", err);
+            return Ok(false);
+        }
+    };
+
+    Ok(hash_secret(secret)?.verify_slice(&hash).is_ok())
+}
+
+/// Generates a random base64 encoded secret
+fn generate_secret() -> Result<String, ()> {
+    let engine = SystemRandom::new();
+    let mut secret = [0u8; 32];
+    engine.fill(&mut secret).map_err(|err| {
+        error!("Failed to generate secret: {:?}", err);
+    })?;
+
+    Ok(STANDARD_NO_PAD.encode(secret))
+}
+
+/// Creates the signature of a refresh token
+/// Refresh tokens are derived from the session's refresh key so every generation can be checked without storing it
+fn refresh_signature(
+    session_id: Uuid,
+    generation: i32,
+    refresh_key: &str,
+) -> Result<Hmac<Sha256>, ()> {
+    hash_secret(&format!(
+        "refresh:{}:{}:{}",
+        session_id, generation, refresh_key
+    ))
+}
+
 /// The access token struct
 #[derive(Serialize, Deserialize)]
 pub struct AccessTokenUser {
@@ -12,11 +98,20 @@ pub struct AccessTokenUser {
     signature: String,
 }
 
+/// The refresh token struct
+#[derive(Serialize, Deserialize)]
+pub struct AccessTokenRefresh {
+    id: String,
+    generation: i32,
+    signature: String,
+}
+
 /// The token enum
 #[derive(Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum AccessToken {
     User(AccessTokenUser),
+    Refresh(AccessTokenRefresh),
 }
 
 impl AccessToken {
@@ -27,6 +122,15 @@ impl AccessToken {
             signature,
         })
     }
+
+    /// Creates a new refresh token
+    pub fn refresh(id: Uuid, generation: i32, signature: String) -> AccessToken {
+        AccessToken::Refresh(AccessTokenRefresh {
+            id: id.to_string(),
+            generation,
+            signature,
+        })
+    }
 }
 
 /// Decodes a session from it's string representation
@@ -77,35 +181,314 @@ pub async fn get(
     Ok(session)
 }
 
+/// A newly created (or refreshed) session
+pub struct NewSession {
+    pub session: sessions::Model,
+    /// The encoded access token, only a hash of it's secret is stored in the database
+    pub access_token: String,
+    /// The encoded refresh token
+    pub refresh_token: String,
+}
+
+impl NewSession {
+    /// Encodes the tokens of a session
+    async fn new(session: sessions::Model, secret: String) -> Result<Self, ()> {
+        let refresh_key = session.refresh_key.as_ref().ok_or_else(|| {
+            error!("Session {} has no refresh key", session.id);
+        })?;
+
+        let signature = refresh_signature(session.id, session.refresh_generation, refresh_key)?;
+
+        Ok(NewSession {
+            access_token: encode(AccessToken::user(session.id, secret)).await?,
+            refresh_token: encode(AccessToken::refresh(
+                session.id,
+                session.refresh_generation,
+                STANDARD_NO_PAD.encode(signature.finalize().into_bytes()),
+            ))
+            .await?,
+            session,
+        })
+    }
+}
+
 /// Creates a session for a user
 pub async fn create(
     db_client: &DatabaseConnection,
     user_id: i32,
     ip: String,
-) -> Result<sessions::Model, ()> {
-    // Generate a new secret
-    let engine = SystemRandom::new();
-    let mut secret = [0u8; 32];
-    engine.fill(&mut secret).map_err(|err| {
-        error!("Failed to generate secret: {:?}", err);
-    })?;
+) -> Result<NewSession, ()> {
+    // Generate a new secret and refresh key
+    let secret = generate_secret()?;
+    let refresh_key = generate_secret()?;
 
-    // Base64 encode the secret
-    let secret = STANDARD_NO_PAD.encode(&secret);
+    let now = chrono::Utc::now();
 
     let session = sessions::ActiveModel {
         id: ActiveValue::Set(Uuid::new_v4()),
         user_id: ActiveValue::Set(user_id),
-        token: ActiveValue::Set(secret),
+        token: ActiveValue::Set(None),
+        token_hash: ActiveValue::Set(Some(encode_secret_hash(&secret)?)),
         initial_ip: ActiveValue::Set(ip),
-        expires_at: ActiveValue::Set((chrono::Utc::now() + chrono::Duration::days(30)).naive_utc()),
+        expires_at: ActiveValue::Set((now + chrono::Duration::days(SESSION_TTL_DAYS)).naive_utc()),
+        access_expires_at: ActiveValue::Set(Some(
+            (now + chrono::Duration::minutes(ACCESS_TOKEN_TTL_MINUTES)).naive_utc(),
+        )),
+        refresh_key: ActiveValue::Set(Some(refresh_key)),
+        refresh_generation: ActiveValue::Set(0),
     };
 
     let session = session.insert(db_client).await.map_err(|err| {
         debug!("Failed to create session: {:?}", err);
     })?;
 
-    Ok(session)
+    NewSession::new(session, secret).await
+}
+
+/// Checks the secret of an access token against a session
+/// Sessions created before hashing are compared to the hash of the raw token
+fn check_secret(session: &sessions::Model, secret: &str) -> Result<bool, ()> {
+    match (&session.token_hash, &session.token) {
+        (Some(hash), _) => verify_secret_hash(secret, hash),
+        (None, Some(token)) => verify_secret_hash(secret, &encode_secret_hash(token)?),
+        (None, None) => Ok(false),
+    }
+}
+
+/// Exchanges a refresh token for a new access and refresh token
+/// Using a refresh token that was already exchanged revokes the whole session (the token family)
+/// Sessions created before refresh tokens existed only have an access token, it can be exchanged once
+/// Returns `Ok(None)` if the token is malformed, unknown, expired or was reused
+pub async fn refresh(
+    db_client: &DatabaseConnection,
+    token: &str,
+) -> Result<Option<NewSession>, ()> {
+    let refresh = match decode(token).await {
+        Ok(AccessToken::Refresh(refresh)) => refresh,
+        Ok(AccessToken::User(user)) => return refresh_legacy(db_client, user).await,
+        Err(_) => return Ok(None),
+    };
+
+    let session_id = match Uuid::parse_str(&refresh.id) {
+        Ok(session_id) => session_id,
+        Err(err) => {
+            debug!("Failed to parse session id: {:?}", err);
+            return Ok(None);
+        }
+    };
+
+    let session = match get(db_client, session_id).await? {
+        Some(session) => session,
+        None => {
+            debug!("Session not found");
+            return Ok(None);
+        }
+    };
+
+    let now = chrono::Utc::now();
+
+    if session.expires_at < now.naive_utc() {
+        debug!("Session expired");
+        return Ok(None);
+    }
+
+    // Sessions created before refresh tokens existed are exchanged with their access token
+    let refresh_key = match session.refresh_key {
+        Some(ref refresh_key) => refresh_key,
+        None => {
+            debug!("Session has no refresh key");
+            return Ok(None);
+        }
+    };
+
+    // Check the signature (in constant time)
+    let signature = match STANDARD_NO_PAD.decode(&refresh.signature) {
+        Ok(signature) => signature,
+        Err(err) => {
+            debug!("Failed to decode refresh token signature: {:?}", err);
+            return Ok(None);
+        }
+    };
+
+    if refresh_signature(session.id, refresh.generation, refresh_key)?
+        .verify_slice(&signature)
+        .is_err()
+    {
+        debug!("Invalid refresh token");
+        return Ok(None);
+    }
+
+    // An older generation means the token was already exchanged, so it has probably been stolen
+    if refresh.generation != session.refresh_generation {
+        warn!(
+            "Refresh token reuse detected for session {}, revoking it",
+            session.id
+        );
+        delete(db_client, session.id).await?;
+        return Ok(None);
+    }
+
+    // Rotate the tokens
+    let secret = generate_secret()?;
+
+    // Only update the row if no other request rotated it in the meantime
+    let result = sessions::Entity::update_many()
+        .col_expr(
+            sessions::Column::TokenHash,
+            Expr::value(encode_secret_hash(&secret)?),
+        )
+        .col_expr(sessions::Column::Token, Expr::value(Option::<String>::None))
+        .col_expr(
+            sessions::Column::AccessExpiresAt,
+            Expr::value((now + chrono::Duration::minutes(ACCESS_TOKEN_TTL_MINUTES)).naive_utc()),
+        )
+        .col_expr(
+            sessions::Column::ExpiresAt,
+            Expr::value((now + chrono::Duration::days(SESSION_TTL_DAYS)).naive_utc()),
+        )
+        .col_expr(
+            sessions::Column::RefreshGeneration,
+            Expr::value(session.refresh_generation + 1),
+        )
+        .filter(sessions::Column::Id.eq(session.id))
+        .filter(sessions::Column::RefreshGeneration.eq(session.refresh_generation))
+        .exec(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to rotate session tokens: {:?}", err);
+        })?;
+
+    if result.rows_affected == 0 {
+        warn!(
+            "Refresh token reuse detected for session {}, revoking it",
+            session.id
+        );
+        delete(db_client, session.id).await?;
+        return Ok(None);
+    }
+
+    let session = get(db_client, session.id).await?.ok_or(())?;
+
+    NewSession::new(session, secret).await.map(Some)
+}
+
+/// Exchanges the access token of a session created before refresh tokens existed
+/// The session gets a refresh key, so this only works once
+async fn refresh_legacy(
+    db_client: &DatabaseConnection,
+    user: AccessTokenUser,
+) -> Result<Option<NewSession>, ()> {
+    let session_id = match Uuid::parse_str(&user.id) {
+        Ok(session_id) => session_id,
+        Err(err) => {
+            debug!("Failed to parse session id: {:?}", err);
+            return Ok(None);
+        }
+    };
+
+    let session = match get(db_client, session_id).await? {
+        Some(session) => session,
+        None => {
+            debug!("Session not found");
+            return Ok(None);
+        }
+    };
+
+    // Access tokens of newer sessions can't be used as refresh tokens
+    if session.refresh_key.is_some() {
+        debug!("Not a refresh token");
+        return Ok(None);
+    }
+
+    if !check_secret(&session, &user.signature)? {
+        debug!("Invalid token");
+        return Ok(None);
+    }
+
+    let now = chrono::Utc::now();
+
+    if session.expires_at < now.naive_utc() {
+        debug!("Session expired");
+        return Ok(None);
+    }
+
+    // Rotate the secret and add a refresh key
+    let secret = generate_secret()?;
+
+    // Only update the row if no other request exchanged it in the meantime
+    let result = sessions::Entity::update_many()
+        .col_expr(
+            sessions::Column::TokenHash,
+            Expr::value(encode_secret_hash(&secret)?),
+        )
+        .col_expr(sessions::Column::Token, Expr::value(Option::<String>::None))
+        .col_expr(
+            sessions::Column::AccessExpiresAt,
+            Expr::value((now + chrono::Duration::minutes(ACCESS_TOKEN_TTL_MINUTES)).naive_utc()),
+        )
+        .col_expr(
+            sessions::Column::ExpiresAt,
+            Expr::value((now + chrono::Duration::days(SESSION_TTL_DAYS)).naive_utc()),
+        )
+        .col_expr(
+            sessions::Column::RefreshKey,
+            Expr::value(generate_secret()?),
+        )
+        .col_expr(sessions::Column::RefreshGeneration, Expr::value(0))
+        .filter(sessions::Column::Id.eq(session.id))
+        .filter(sessions::Column::RefreshKey.is_null())
+        .exec(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to add a refresh key to the session: {:?}", err);
+        })?;
+
+    if result.rows_affected == 0 {
+        debug!("Session was already exchanged");
+        return Ok(None);
+    }
+
+    let session = get(db_client, session.id).await?.ok_or(())?;
+
+    NewSession::new(session, secret).await.map(Some)
+}
+
+/// Replaces the raw token of a session created before tokens were hashed with it's hash
+async fn upgrade_legacy(
+    db_client: &DatabaseConnection,
+    session: sessions::Model,
+) -> Result<sessions::Model, ()> {
+    let token = match session.token {
+        Some(ref token) => token.clone(),
+        None => return Ok(session),
+    };
+
+    let mut session: sessions::ActiveModel = session.into();
+    session.token = ActiveValue::Set(None);
+    session.token_hash = ActiveValue::Set(Some(encode_secret_hash(&token)?));
+
+    session.update(db_client).await.map_err(|err| {
+        warn!("Failed to hash legacy session token: {:?}", err);
+    })
+}
+
+/// Hashes the tokens of all the sessions created before tokens were hashed
+pub async fn upgrade_all_legacy(db_client: &DatabaseConnection) -> Result<usize, ()> {
+    let sessions = sessions::Entity::find()
+        .filter(sessions::Column::Token.is_not_null())
+        .all(db_client)
+        .await
+        .map_err(|err| {
+            warn!("Failed to get legacy sessions: {:?}", err);
+        })?;
+
+    let count = sessions.len();
+
+    for session in sessions {
+        upgrade_legacy(db_client, session).await?;
+    }
+
+    Ok(count)
 }
 
 /// Deletes a session from the database
@@ -114,40 +497,131 @@ pub async fn delete(db_client: &DatabaseConnection, session_id: Uuid) -> Result<
         .exec(db_client)
         .await
         .map_err(|err| {
-            debug!("Failed to get session: {:?}", err);
+            debug!("Failed to delete session: {:?}", err);
         })?;
 
     Ok(())
 }
 
+/// Lists all the active sessions of a user (newest expiry first)
+pub async fn list_by_user(
+    db_client: &DatabaseConnection,
+    user_id: i32,
+) -> Result<Vec<sessions::Model>, ()> {
+    sessions::Entity::find()
+        .filter(sessions::Column::UserId.eq(user_id))
+        .filter(sessions::Column::ExpiresAt.gt(chrono::Utc::now().naive_utc()))
+        .order_by_desc(sessions::Column::ExpiresAt)
+        .all(db_client)
+        .await
+        .map_err(|err| {
+            debug!("Failed to list sessions: {:?}", err);
+        })
+}
+
+/// Deletes all the sessions of a user, optionally keeping one of them
+/// Returns the number of deleted sessions
+pub async fn delete_by_user(
+    db_client: &DatabaseConnection,
+    user_id: i32,
+    keep: Option<Uuid>,
+) -> Result<u64, ()> {
+    let mut query = sessions::Entity::delete_many().filter(sessions::Column::UserId.eq(user_id));
+
+    if let Some(keep) = keep {
+        query = query.filter(sessions::Column::Id.ne(keep));
+    }
+
+    let result = query.exec(db_client).await.map_err(|err| {
+        debug!("Failed to delete sessions: {:?}", err);
+    })?;
+
+    Ok(result.rows_affected)
+}
+
 /// Verifies a session
+/// Returns `Ok(None)` if the token is malformed, unknown, expired or does not match
 pub async fn verify(
     db_client: &DatabaseConnection,
     token: &str,
 ) -> Result<Option<sessions::Model>, ()> {
     // Decode the session
-    let access_token = decode(token).await?;
+    let access_token = match decode(token).await {
+        Ok(access_token) => access_token,
+        Err(_) => return Ok(None),
+    };
 
     // Get the session
     match access_token {
         AccessToken::User(user) => {
-            // Get the session
-            let session = get(
-                db_client,
-                Uuid::parse_str(&user.id).map_err(|err| {
+            let session_id = match Uuid::parse_str(&user.id) {
+                Ok(session_id) => session_id,
+                Err(err) => {
                     debug!("Failed to parse session id: {:?}", err);
-                })?,
-            )
-            .await?
-            .ok_or(())?;
+                    return Ok(None);
+                }
+            };
+
+            // Get the session
+            let session = match get(db_client, session_id).await? {
+                Some(session) => session,
+                None => {
+                    debug!("Session not found");
+                    return Ok(None);
+                }
+            };
 
             // Check token
-            if session.token != user.signature {
+            if !check_secret(&session, &user.signature)? {
                 debug!("Invalid token");
                 return Ok(None);
             }
 
+            let now = chrono::Utc::now();
+
+            // Check expiration (expired sessions are only cleared every few minutes)
+            if session.expires_at < now.naive_utc() {
+                debug!("Session expired");
+                return Ok(None);
+            }
+
+            // Check the access token expiration (sessions created before refresh tokens don't have
+            // one, they are treated as expired and their token has to be exchanged with `refresh`)
+            match session.access_expires_at {
+                Some(access_expires_at) if access_expires_at >= now.naive_utc() => {}
+                _ => {
+                    debug!("Access token expired");
+                    return Ok(None);
+                }
+            }
+
+            // Carry over sessions created before tokens were hashed
+            let session = match session.token {
+                Some(_) => upgrade_legacy(db_client, session).await?,
+                None => session,
+            };
+
+            // Push the expiry forward on activity (not on every request to avoid a write each time)
+            let expires_at = (now + chrono::Duration::days(SESSION_TTL_DAYS)).naive_utc();
+
+            let session = if expires_at - session.expires_at
+                > chrono::Duration::hours(SESSION_EXTEND_INTERVAL_HOURS)
+            {
+                let mut session: sessions::ActiveModel = session.into();
+                session.expires_at = ActiveValue::Set(expires_at);
+
+                session.update(db_client).await.map_err(|err| {
+                    warn!("Failed to extend session: {:?}", err);
+                })?
+            } else {
+                session
+            };
+
             Ok(Some(session))
         }
+        AccessToken::Refresh(_) => {
+            debug!("Refresh tokens can't be used as access tokens");
+            Ok(None)
+        }
     }
 }
@@ -10,7 +10,7 @@ pub async fn get(db_client: &DatabaseConnection, id: i32) -> Result<Option<users
             debug!("Failed to get user: {:?}", err);
         })? {
         Some(user) => Ok(Some(user)),
-        None => return Ok(None),
+        None => Ok(None),
     }
 }
 
@@ -0,0 +1,49 @@
+//! /docs/api/v1/calendar/create_feed
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::get_db_client,
+    utils,
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError},
+    v1_post,
+};
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Request {}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    token: String,
+    /// The path of the feed (the client knows the host)
+    path: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+enum Error {
+    DatabaseError,
+}
+
+async fn post(data: RequestData<Request>) -> Result<Response, ResponseError<Error>> {
+    let id = match data.user {
+        Some(user) => user.id,
+        None => {
+            return Err(ResponseError::RequestError(
+                ErrorResponseStatus::Unauthorized,
+            ))
+        }
+    };
+
+    // Replaces the old feed, so a leaked URL can be rotated
+    let token = utils::calendar_feeds::create(get_db_client(), id)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    Ok(Response {
+        path: format!("/api/v1/calendar/{}.ics", token),
+        token,
+    })
+}
+
+v1_post!(post_handler, post, UserAuth, Request, Response, Error);
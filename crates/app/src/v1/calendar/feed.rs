@@ -0,0 +1,49 @@
+//! /docs/api/v1/calendar/feed
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{
+    database::get_db_client,
+    utils,
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError},
+    v1_get,
+};
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    enabled: bool,
+    created_at: Option<chrono::DateTime<chrono::Utc>>,
+    last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+enum Error {
+    DatabaseError,
+}
+
+async fn get(data: RequestData<()>) -> Result<Response, ResponseError<Error>> {
+    let id = match data.user {
+        Some(user) => user.id,
+        None => {
+            return Err(ResponseError::RequestError(
+                ErrorResponseStatus::Unauthorized,
+            ))
+        }
+    };
+
+    let feed = utils::calendar_feeds::get_by_user_id(get_db_client(), id)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    Ok(Response {
+        enabled: feed.is_some(),
+        created_at: feed.as_ref().map(|feed| feed.created_at.and_utc()),
+        last_used_at: feed
+            .as_ref()
+            .and_then(|feed| feed.last_used_at)
+            .map(|last_used_at| last_used_at.and_utc()),
+    })
+}
+
+v1_get!(get_handler, get, UserAuth, Response, Error);
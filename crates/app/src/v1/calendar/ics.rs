@@ -0,0 +1,232 @@
+//! /docs/api/v1/calendar/ics
+//! Calendar apps can't send headers or read the json responses, so this route is authenticated
+//! by the token in the path and returns the calendar (or a plain status) directly.
+
+use std::collections::HashMap;
+
+use actix_web::{http::header, web, HttpResponse};
+use chrono::{Duration, NaiveDate, Utc};
+use schoology::SchoologyError;
+use sea_orm::DatabaseConnection;
+
+use crate::{
+    database::get_db_client,
+    schoology::get_schoology_client,
+    utils::{
+        self,
+        ics::{Event, EventTime},
+        schoology_link::LinkedAccount,
+    },
+};
+
+/// The name calendar apps show for the feed
+const CALENDAR_NAME: &str = "Tuwa";
+/// How many past days are in the feed
+const PAST_DAYS: i64 = 7;
+/// How many days ahead are in the feed
+const FUTURE_DAYS: i64 = 28;
+/// When calendar apps should try again after Schoology failed (in seconds)
+const RETRY_AFTER_SECONDS: u64 = 300;
+
+fn not_found() -> HttpResponse {
+    HttpResponse::NotFound()
+        .content_type(header::ContentType::plaintext())
+        .body("Calendar feed not found")
+}
+
+fn server_error() -> HttpResponse {
+    HttpResponse::InternalServerError()
+        .content_type(header::ContentType::plaintext())
+        .body("Internal server error")
+}
+
+/// Calendar apps keep the events they have instead of removing the Schoology ones
+fn unavailable() -> HttpResponse {
+    HttpResponse::ServiceUnavailable()
+        .content_type(header::ContentType::plaintext())
+        .insert_header((header::RETRY_AFTER, RETRY_AFTER_SECONDS.to_string()))
+        .body("Schoology is unavailable")
+}
+
+/// The periods (and special days) of the bell schedule
+async fn schedule_events(
+    db_client: &DatabaseConnection,
+    user_id: i32,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<Event>, ()> {
+    let user_periods: HashMap<String, Option<String>> =
+        utils::schedule::get_user_periods(db_client, user_id)
+            .await?
+            .into_iter()
+            .map(|period| (period.period, period.name))
+            .collect();
+
+    let mut events = Vec::new();
+
+    for day in utils::schedule::for_range(db_client, from, to).await? {
+        let date = day.date;
+
+        // Minimum days, holidays, ...
+        if let Some(name) = day.name {
+            events.push(Event {
+                uid: format!("day-{}@tuwa", date.format("%Y%m%d")),
+                time: EventTime::AllDay(date),
+                categories: vec![match day.schedule {
+                    Some(_) => "Special Schedule".to_string(),
+                    None => "No School".to_string(),
+                }],
+                summary: name,
+                description: None,
+            });
+        }
+
+        for period in day.periods {
+            let name = user_periods
+                .get(&period.period)
+                .cloned()
+                .flatten()
+                .unwrap_or(period.name);
+
+            events.push(Event {
+                // The start keeps it unique if a period is on the schedule twice
+                uid: format!(
+                    "period-{}-{}@tuwa",
+                    period.start.format("%Y%m%dT%H%M%SZ"),
+                    period.period
+                ),
+                time: EventTime::Timed {
+                    start: period.start,
+                    end: period.end,
+                },
+                summary: name,
+                description: None,
+                categories: vec!["Class".to_string()],
+            });
+        }
+    }
+
+    Ok(events)
+}
+
+/// The assignments and events on the user's Schoology calendar
+async fn schoology_events(
+    account: &LinkedAccount,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<Event>, SchoologyError> {
+    let schoology_client = get_schoology_client();
+
+    let assignments = schoology::assignments::get_assignments(
+        schoology_client,
+        &account.tokens,
+        account.schoology_id,
+        from,
+        to,
+    )
+    .await?;
+
+    let calendar = schoology::events::get_all_events(
+        schoology_client,
+        &account.tokens,
+        account.schoology_id,
+        from,
+        to,
+    )
+    .await?;
+
+    let mut events = Vec::new();
+
+    for assignment in assignments {
+        let due = match assignment.due {
+            Some(due) => utils::schedule::to_utc(due.date(), due.time()),
+            None => continue,
+        };
+
+        events.push(Event {
+            uid: format!("assignment-{}@tuwa", assignment.id),
+            time: EventTime::Timed {
+                start: due,
+                end: due,
+            },
+            summary: format!("Due: {}", assignment.title),
+            description: Some(assignment.description),
+            categories: vec!["Assignment".to_string()],
+        });
+    }
+
+    // Due dates are already there as assignments
+    for event in calendar.into_iter().filter(|event| !event.is_due_date()) {
+        let time = match event.time {
+            schoology::events::EventTime::AllDay(date) => EventTime::AllDay(date),
+            schoology::events::EventTime::Timed { start, end } => {
+                let start = utils::schedule::to_utc(start.date(), start.time());
+
+                EventTime::Timed {
+                    start,
+                    end: end
+                        .map(|end| utils::schedule::to_utc(end.date(), end.time()))
+                        .unwrap_or(start),
+                }
+            }
+        };
+
+        events.push(Event {
+            uid: format!("event-{}@tuwa", event.id),
+            time,
+            summary: event.title,
+            description: Some(event.description),
+            categories: vec!["Event".to_string()],
+        });
+    }
+
+    Ok(events)
+}
+
+async fn get(token: String) -> Result<String, HttpResponse> {
+    let db_client = get_db_client();
+
+    let feed = utils::calendar_feeds::verify(db_client, &token)
+        .await
+        .map_err(|_| server_error())?
+        .ok_or_else(not_found)?;
+
+    let today = utils::schedule::today();
+    let from = today - Duration::days(PAST_DAYS);
+    let to = today + Duration::days(FUTURE_DAYS);
+
+    let mut events = schedule_events(db_client, feed.user_id, from, to)
+        .await
+        .map_err(|_| server_error())?;
+
+    let account = utils::schoology_link::get_account(db_client, feed.user_id)
+        .await
+        .map_err(|_| server_error())?;
+
+    if let Some(account) = account {
+        match schoology_events(&account, from, to).await {
+            Ok(mut schoology_events) => events.append(&mut schoology_events),
+            // The user removed the app from Schoology, there is only the schedule left
+            Err(err) if err.is_unauthorized() => {
+                debug!("Schoology access of user {} was revoked", feed.user_id);
+            }
+            Err(err) => {
+                warn!("Failed to get Schoology calendar: {:?}", err);
+                return Err(unavailable());
+            }
+        }
+    }
+
+    Ok(utils::ics::render(CALENDAR_NAME, &events, Utc::now()))
+}
+
+pub async fn get_handler(path: web::Path<String>) -> HttpResponse {
+    match get(path.into_inner()).await {
+        Ok(calendar) => HttpResponse::Ok()
+            .content_type("text/calendar; charset=utf-8")
+            // The URL is a secret
+            .insert_header((header::CACHE_CONTROL, "private, no-store"))
+            .body(calendar),
+        Err(response) => response,
+    }
+}
@@ -0,0 +1,36 @@
+use std::borrow::Cow;
+
+use actix_web::web;
+
+use crate::v1::V1Scope;
+
+mod create_feed;
+mod feed;
+mod ics;
+mod revoke_feed;
+#[cfg(test)]
+mod tests;
+
+/// The path of the calendar feeds, the token in it is a secret
+const FEED_PREFIX: &str = "/api/v1/calendar/";
+
+/// Masks the token of a calendar feed path so it can be logged
+pub fn redact_feed_token(path: &str) -> Cow<'_, str> {
+    match path
+        .strip_prefix(FEED_PREFIX)
+        .and_then(|rest| rest.strip_suffix(".ics"))
+    {
+        Some(token) if !token.is_empty() && !token.contains('/') => {
+            Cow::Owned(format!("{}<redacted>.ics", FEED_PREFIX))
+        }
+        _ => Cow::Borrowed(path),
+    }
+}
+
+pub fn create_calendar_service() -> V1Scope {
+    V1Scope::new("/calendar")
+        .route("/feed", feed::route())
+        .route("/feed", create_feed::route())
+        .route("/feed/revoke", revoke_feed::route())
+        .raw_route("/{token}.ics", web::get().to(ics::get_handler))
+}
@@ -0,0 +1,45 @@
+//! /docs/api/v1/calendar/revoke_feed
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::get_db_client,
+    utils,
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError},
+    v1_post,
+};
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Request {}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {}
+
+#[derive(Debug, Serialize, JsonSchema)]
+enum Error {
+    FeedNotFound,
+    DatabaseError,
+}
+
+async fn post(data: RequestData<Request>) -> Result<Response, ResponseError<Error>> {
+    let id = match data.user {
+        Some(user) => user.id,
+        None => {
+            return Err(ResponseError::RequestError(
+                ErrorResponseStatus::Unauthorized,
+            ))
+        }
+    };
+
+    if !utils::calendar_feeds::revoke(get_db_client(), id)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?
+    {
+        return Err(ResponseError::ClientError(Error::FeedNotFound));
+    }
+
+    Ok(Response {})
+}
+
+v1_post!(post_handler, post, UserAuth, Request, Response, Error);
@@ -0,0 +1,151 @@
+use actix_web::{
+    http::{header, StatusCode},
+    test::{call_and_read_body_json, call_service, init_service, read_body, TestRequest},
+    web, App,
+};
+use chrono::Duration;
+use schoology_mock::{MockAssignment, MockEvent, MockSection};
+use serde_json::{json, Value};
+
+use crate::{
+    testing, utils,
+    v1::{calendar::redact_feed_token, create_v1_service},
+};
+
+#[test]
+fn calendar_feed() {
+    testing::run(|mock| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let (token, schoology_id) = testing::linked_user(mock).await;
+
+        let section = testing::new_schoology_id();
+        mock.add_section(MockSection::new(section, section + 100));
+        mock.enroll(section, schoology_id, false);
+
+        let date = utils::schedule::today() + Duration::days(2);
+        let due = format!("{} 23:59:00", date);
+        mock.add_assignment(MockAssignment::new(section, section, &due));
+        mock.add_event(MockEvent::new(
+            section,
+            Some(section),
+            &format!("{} 18:00:00", date),
+        ));
+
+        let req = TestRequest::get()
+            .uri("/api/v1/calendar/feed")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+        assert_eq!(res["data"]["enabled"], false);
+
+        let req = TestRequest::post()
+            .uri("/api/v1/calendar/feed")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({}))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        let path = res["data"]["path"].as_str().unwrap().to_string();
+
+        // Calendar apps don't send the session token
+        let res = call_service(&app, TestRequest::get().uri(&path).to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/calendar; charset=utf-8"
+        );
+
+        let body = String::from_utf8(read_body(res).await.to_vec()).unwrap();
+        assert!(body.starts_with("BEGIN:VCALENDAR\r\n"), "{}", body);
+        assert!(body.ends_with("END:VCALENDAR\r\n"), "{}", body);
+        assert!(body
+            .split("\r\n")
+            .all(|line| line.len() <= 75 && !line.contains('\n')));
+        assert!(
+            body.contains(&format!("UID:assignment-{}@tuwa", section)),
+            "{}",
+            body
+        );
+        assert!(
+            body.contains(&format!("SUMMARY:Due: Assignment {}", section)),
+            "{}",
+            body
+        );
+        assert!(
+            body.contains(&format!("UID:event-{}@tuwa", section)),
+            "{}",
+            body
+        );
+        // The due date on the calendar is already there as the assignment
+        assert!(
+            !body.contains(&format!("UID:event-{}@tuwa", section + 1_000_000)),
+            "{}",
+            body
+        );
+
+        let req = TestRequest::get()
+            .uri("/api/v1/calendar/feed")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"]["enabled"], true);
+        assert!(res["data"]["last_used_at"].is_string(), "{}", res);
+
+        // A new URL replaces the old one
+        let req = TestRequest::post()
+            .uri("/api/v1/calendar/feed")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({}))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        let new_path = res["data"]["path"].as_str().unwrap().to_string();
+        assert_ne!(path, new_path);
+
+        let res = call_service(&app, TestRequest::get().uri(&path).to_request()).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let res = call_service(&app, TestRequest::get().uri(&new_path).to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::post()
+            .uri("/api/v1/calendar/feed/revoke")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({}))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        let res = call_service(&app, TestRequest::get().uri(&new_path).to_request()).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let req = TestRequest::post()
+            .uri("/api/v1/calendar/feed/revoke")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({}))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"], "FeedNotFound", "{}", res);
+    });
+}
+
+#[test]
+fn redacts_feed_tokens() {
+    assert_eq!(
+        redact_feed_token("/api/v1/calendar/c2VjcmV0.ics"),
+        "/api/v1/calendar/<redacted>.ics"
+    );
+
+    // Other routes are logged as they are
+    assert_eq!(
+        redact_feed_token("/api/v1/calendar/feed"),
+        "/api/v1/calendar/feed"
+    );
+    assert_eq!(
+        redact_feed_token("/api/v1/schoology/user"),
+        "/api/v1/schoology/user"
+    );
+}
@@ -0,0 +1,153 @@
+//! /docs/api/v1/clubs/list
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use futures::future::try_join_all;
+use schemars::JsonSchema;
+use schoology::events::EventTime;
+use serde::Serialize;
+
+use crate::{
+    database::get_db_client,
+    schoology::get_schoology_client,
+    utils,
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError, SchoologyRouteError},
+    v1_get,
+};
+
+/// How far ahead meetings are looked for (in days)
+const UPCOMING_DAYS: i64 = 28;
+/// The most meetings returned for every club
+const MAX_MEETINGS: usize = 3;
+
+#[derive(Serialize, JsonSchema)]
+struct Meeting {
+    id: usize,
+    title: String,
+    description: String,
+    all_day: bool,
+    start: NaiveDateTime,
+    end: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Club {
+    id: usize,
+    title: String,
+    description: String,
+    category: String,
+    picture_url: String,
+    website: Option<String>,
+    /// The next meetings, the soonest first
+    meetings: Vec<Meeting>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    clubs: Vec<Club>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[allow(clippy::enum_variant_names)]
+enum Error {
+    SchoologyNotLinked,
+    SchoologyError,
+    SchoologyRateLimited,
+    DatabaseError,
+}
+
+impl SchoologyRouteError for Error {
+    const NOT_LINKED: Option<Self> = Some(Error::SchoologyNotLinked);
+    const RATE_LIMITED: Self = Error::SchoologyRateLimited;
+    const FAILED: Self = Error::SchoologyError;
+}
+
+async fn get(data: RequestData<()>) -> Result<Response, ResponseError<Error>> {
+    let id = match data.user {
+        Some(user) => user.id,
+        None => {
+            return Err(ResponseError::RequestError(
+                ErrorResponseStatus::Unauthorized,
+            ))
+        }
+    };
+
+    let db_client = get_db_client();
+
+    // Get the user's Schoology account
+    let account = utils::schoology_link::get_account(db_client, id)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?
+        .ok_or(ResponseError::ClientError(Error::SchoologyNotLinked))?;
+
+    let schoology_client = get_schoology_client();
+
+    // Clubs are groups on Schoology
+    let groups =
+        schoology::groups::get_user_groups(schoology_client, &account.tokens, account.schoology_id)
+            .await?;
+
+    // Schoology's times are in the school's time zone
+    let now = Utc::now()
+        .with_timezone(&utils::schedule::timezone())
+        .naive_local();
+    let today = now.date();
+
+    // Every club at once
+    let events = try_join_all(groups.iter().map(|group| {
+        schoology::events::get_group_events(
+            schoology_client,
+            &account.tokens,
+            group.id,
+            today,
+            today + Duration::days(UPCOMING_DAYS - 1),
+        )
+    }))
+    .await?;
+
+    let clubs = groups
+        .into_iter()
+        .zip(events)
+        .map(|(group, events)| {
+            let mut meetings = events
+                .into_iter()
+                // Meetings that already ended are not upcoming
+                .filter(|event| match event.time {
+                    EventTime::AllDay(date) => date >= today,
+                    EventTime::Timed { start, end } => end.unwrap_or(start) >= now,
+                })
+                .map(|event| {
+                    let (all_day, end) = match event.time {
+                        EventTime::AllDay(_) => (true, None),
+                        EventTime::Timed { end, .. } => (false, end),
+                    };
+
+                    Meeting {
+                        id: event.id,
+                        start: event.start(),
+                        title: event.title,
+                        description: event.description,
+                        all_day,
+                        end,
+                    }
+                })
+                .collect::<Vec<Meeting>>();
+
+            meetings.sort_by(|a, b| a.start.cmp(&b.start).then(a.id.cmp(&b.id)));
+            meetings.truncate(MAX_MEETINGS);
+
+            Club {
+                id: group.id,
+                title: group.title,
+                description: group.description,
+                category: group.category,
+                picture_url: group.picture_url,
+                website: Some(group.website).filter(|website| !website.is_empty()),
+                meetings,
+            }
+        })
+        .collect();
+
+    Ok(Response { clubs })
+}
+
+v1_get!(get_handler, get, UserAuth, Response, Error);
@@ -0,0 +1,9 @@
+use crate::v1::V1Scope;
+
+mod list;
+#[cfg(test)]
+mod tests;
+
+pub fn create_clubs_service() -> V1Scope {
+    V1Scope::new("/clubs").route("", list::route())
+}
@@ -0,0 +1,74 @@
+use actix_web::{
+    test::{call_and_read_body_json, init_service, TestRequest},
+    web, App,
+};
+use chrono::Duration;
+use schoology_mock::{MockEvent, MockGroup};
+use serde_json::Value;
+
+use crate::{testing, utils, v1::create_v1_service};
+
+#[test]
+fn clubs() {
+    testing::run(|mock| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let (token, schoology_id) = testing::linked_user(mock).await;
+
+        let group = testing::new_schoology_id();
+        let mut robotics = MockGroup::new(group);
+        robotics.title = "Robotics Club".to_string();
+        mock.add_group(robotics);
+        mock.add_group(MockGroup::new(group + 1));
+        mock.join_group(group, schoology_id, false);
+
+        let today = utils::schedule::today();
+        let meeting = |id: usize, date: chrono::NaiveDate| {
+            let mut event = MockEvent::new(id, None, &format!("{} 15:30:00", date));
+            event.group_id = Some(group);
+            event
+        };
+
+        // Already over
+        mock.add_event(meeting(group, today - Duration::days(1)));
+        mock.add_event(meeting(group + 1, today + Duration::days(7)));
+        mock.add_event(meeting(group + 2, today + Duration::days(1)));
+        mock.add_event(meeting(group + 3, today + Duration::days(14)));
+        mock.add_event(meeting(group + 4, today + Duration::days(21)));
+        // Too far ahead
+        mock.add_event(meeting(group + 5, today + Duration::days(40)));
+
+        let req = TestRequest::get().uri("/api/v1/clubs").to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["status"], "Unauthorized", "{}", res);
+
+        let req = TestRequest::get()
+            .uri("/api/v1/clubs")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        // Not a member of the other group
+        let clubs = res["data"]["clubs"].as_array().unwrap();
+        assert_eq!(clubs.len(), 1, "{}", res);
+        assert_eq!(clubs[0]["id"], group);
+        assert_eq!(clubs[0]["title"], "Robotics Club");
+        assert!(clubs[0]["website"].is_null());
+
+        let meetings = clubs[0]["meetings"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|meeting| meeting["id"].as_u64().unwrap() as usize)
+            .collect::<Vec<usize>>();
+        assert_eq!(meetings, vec![group + 2, group + 1, group + 3]);
+
+        assert_eq!(
+            clubs[0]["meetings"][0]["start"],
+            format!("{}T15:30:00", today + Duration::days(1))
+        );
+        assert_eq!(clubs[0]["meetings"][0]["all_day"], false);
+    });
+}
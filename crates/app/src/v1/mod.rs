@@ -1,13 +1,97 @@
-use actix_web::{body, http::header, web};
+use ::schoology::SchoologyError;
+use actix_web::{
+    http::{header, Method},
+    web, HttpMessage,
+};
+use once_cell::sync::OnceCell;
 use serde::{de, Serialize};
 
 use crate::{database, utils};
 
 use self::types::{ErrorFault, ErrorResponseStatus, ResponseData};
 
+pub mod calendar;
+pub mod clubs;
+pub mod notifications;
+pub mod openapi;
+pub mod push;
+pub mod rate_limit;
+pub mod schedule;
 pub mod schoology;
+pub mod sessions;
+pub mod status;
 pub mod types;
 
+/// A route defined with `v1_get!` / `v1_post!`
+pub struct V1Route {
+    pub method: Method,
+    pub auth: Authentication,
+    /// Creates the actix route
+    pub route: fn() -> actix_web::Route,
+    /// Describes the route for the OpenAPI document
+    pub describe: fn(&mut openapi::Generators) -> openapi::Operation,
+}
+
+/// An actix scope that also keeps the routes for the OpenAPI document
+pub struct V1Scope {
+    path: String,
+    scope: actix_web::Scope,
+    routes: Vec<openapi::DescribedRoute>,
+}
+
+impl V1Scope {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            scope: web::scope(path),
+            routes: Vec::new(),
+        }
+    }
+
+    /// Adds a route defined with `v1_get!` / `v1_post!`
+    pub fn route(mut self, path: &str, route: V1Route) -> Self {
+        self.scope = self.scope.route(path, (route.route)());
+        self.routes.push(openapi::DescribedRoute {
+            path: format!("{}{}", self.path, path),
+            method: route.method,
+            auth: route.auth,
+            describe: route.describe,
+        });
+        self
+    }
+
+    /// Adds a route that doesn't return JSON, it is left out of the OpenAPI document
+    pub fn raw_route(mut self, path: &str, route: actix_web::Route) -> Self {
+        self.scope = self.scope.route(path, route);
+        self
+    }
+
+    /// Nests a scope
+    pub fn service(mut self, scope: V1Scope) -> Self {
+        let path = self.path.clone();
+
+        self.routes.extend(
+            scope
+                .routes
+                .into_iter()
+                .map(|route| openapi::DescribedRoute {
+                    path: format!("{}{}", path, route.path),
+                    ..route
+                }),
+        );
+        self.scope = self.scope.service(scope.scope);
+        self
+    }
+
+    pub fn routes(&self) -> &[openapi::DescribedRoute] {
+        &self.routes
+    }
+
+    pub fn into_scope(self) -> actix_web::Scope {
+        self.scope
+    }
+}
+
 async fn not_found() -> actix_web::HttpResponse {
     let response: ResponseData<(), ()> = ResponseData::route_error(ErrorResponseStatus::NotFound);
 
@@ -15,13 +99,31 @@ async fn not_found() -> actix_web::HttpResponse {
 }
 
 #[derive(Clone, Copy, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 pub enum Authentication {
+    /// Anyone can access the route (a session is still resolved if one is sent)
     NoAuth,
+    /// Requires a valid session
     UserAuth,
+    /// Requires a valid session of an admin (or root) user
     AdminAuth,
+    /// Requires a valid session of a root user (no route needs it yet)
+    #[allow(dead_code)]
     RootAuth,
 }
 
+impl Authentication {
+    /// Checks if the user has the privileges required by the authentication level
+    pub fn is_satisfied_by(&self, user: &orm::users::Model) -> bool {
+        match self {
+            Authentication::NoAuth | Authentication::UserAuth => true,
+            Authentication::AdminAuth => user.is_admin || user.is_root,
+            Authentication::RootAuth => user.is_root,
+        }
+    }
+}
+
+#[allow(clippy::enum_variant_names)]
 pub enum ResponseError<T>
 where
     T: Serialize,
@@ -31,6 +133,33 @@ where
     RequestError(ErrorResponseStatus),
 }
 
+/// The errors of a route that calls Schoology, so `?` can be used on Schoology requests
+pub trait SchoologyRouteError: Sized {
+    /// The user revoked access or the token expired (`None` for routes without a linked account)
+    const NOT_LINKED: Option<Self> = None;
+    /// The requested resource doesn't exist (`None` if the route doesn't request one by id)
+    const NOT_FOUND: Option<Self> = None;
+    const RATE_LIMITED: Self;
+    /// Any other error
+    const FAILED: Self;
+}
+
+impl<T> From<SchoologyError> for ResponseError<T>
+where
+    T: Serialize + SchoologyRouteError,
+{
+    fn from(err: SchoologyError) -> Self {
+        match (T::NOT_LINKED, T::NOT_FOUND) {
+            (Some(not_linked), _) if err.is_unauthorized() => {
+                ResponseError::ClientError(not_linked)
+            }
+            (_, Some(not_found)) if err.is_not_found() => ResponseError::ClientError(not_found),
+            _ if err.is_rate_limited() => ResponseError::ServerError(T::RATE_LIMITED),
+            _ => ResponseError::ServerError(T::FAILED),
+        }
+    }
+}
+
 pub struct RequestData<T>
 where
     T: de::DeserializeOwned,
@@ -42,12 +171,57 @@ where
     pub http_request: actix_web::HttpRequest,
 }
 
-/// The GET wrapper (because get has no body)
-/// Returns a async function that returns a actix_web::HttpResponse
-pub async fn get_util(
-    http_request: actix_web::HttpRequest,
-    auth: Authentication,
-) -> Result<RequestData<()>, ErrorResponseStatus> {
+/// How many `X-Forwarded-For` entries the proxies in front of the server append
+/// GCP's load balancer appends `<client ip>, <load balancer ip>`, everything before them is sent by the client.
+pub const DEFAULT_PROXY_HOPS: usize = 2;
+
+static PROXY_HOPS: OnceCell<usize> = OnceCell::new();
+
+/// Sets how many `X-Forwarded-For` entries are appended by trusted proxies (`0` uses the peer address)
+pub fn set_proxy_hops(hops: usize) -> Result<(), String> {
+    PROXY_HOPS
+        .set(hops)
+        .map_err(|_| "Failed to set proxy hops".to_string())
+}
+
+/// Gets the IP of the client, as the first trusted proxy saw it
+/// The leftmost `X-Forwarded-For` entries can be anything, so only the one the proxies appended is used.
+pub fn client_ip(http_request: &actix_web::HttpRequest) -> String {
+    let hops = PROXY_HOPS.get().copied().unwrap_or(DEFAULT_PROXY_HOPS);
+
+    let forwarded_for = http_request
+        .headers()
+        .get_all(header::X_FORWARDED_FOR)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .collect::<Vec<&str>>();
+
+    // Fewer entries means the request didn't come through the proxies
+    if hops > 0 && forwarded_for.len() >= hops {
+        return forwarded_for[forwarded_for.len() - hops].to_string();
+    }
+
+    http_request
+        .peer_addr()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or("unknown".to_string())
+}
+
+/// The session of a request, once it was looked up
+#[derive(Clone)]
+struct ResolvedSession(Option<orm::sessions::Model>);
+
+/// Gets the session of the bearer token (`None` without a token or if it's invalid)
+/// The session is only looked up once per request, the rate limiter needs it before the route.
+pub async fn get_session(
+    http_request: &actix_web::HttpRequest,
+) -> Result<Option<orm::sessions::Model>, ErrorResponseStatus> {
+    if let Some(ResolvedSession(session)) = http_request.extensions().get::<ResolvedSession>() {
+        return Ok(session.clone());
+    }
+
     // Get the session
     let session = http_request.headers().get(header::AUTHORIZATION);
 
@@ -57,10 +231,7 @@ pub async fn get_util(
             Ok(session) => {
                 let session = session.strip_prefix("Bearer ");
 
-                match session {
-                    Some(session) => Some(session.to_string()),
-                    None => None,
-                }
+                session.map(|session| session.to_string())
             }
             Err(_) => {
                 debug!("Failed to convert session to string");
@@ -78,17 +249,30 @@ pub async fn get_util(
         Some(session) => match utils::sessions::verify(db_client, &session).await {
             Ok(session) => session,
             Err(_) => {
-                debug!("Failed to decode session");
-                return Err(ErrorResponseStatus::BadRequest);
+                debug!("Failed to verify session");
+                return Err(ErrorResponseStatus::InternalServerError);
             }
         },
         None => None,
     };
 
-    // Check required authentication
-    if auth != Authentication::NoAuth {
-        return Err(ErrorResponseStatus::Forbidden);
-    }
+    http_request
+        .extensions_mut()
+        .insert(ResolvedSession(session.clone()));
+
+    Ok(session)
+}
+
+/// The GET wrapper (because get has no body)
+/// Returns a async function that returns a actix_web::HttpResponse
+pub async fn get_util(
+    http_request: actix_web::HttpRequest,
+    auth: Authentication,
+) -> Result<RequestData<()>, ErrorResponseStatus> {
+    let session = get_session(&http_request).await?;
+
+    // Get the database client
+    let db_client = database::get_db_client();
 
     // Get the user
     let user = match session {
@@ -96,12 +280,28 @@ pub async fn get_util(
             Ok(user) => user,
             Err(_) => {
                 debug!("Failed to get user");
-                return Err(ErrorResponseStatus::BadRequest);
+                return Err(ErrorResponseStatus::InternalServerError);
             }
         },
         None => None,
     };
 
+    // Check required authentication
+    if auth != Authentication::NoAuth {
+        let user = match user {
+            Some(ref user) => user,
+            None => {
+                debug!("Missing or invalid session");
+                return Err(ErrorResponseStatus::Unauthorized);
+            }
+        };
+
+        if !auth.is_satisfied_by(user) {
+            debug!("User {} does not have the required privileges", user.id);
+            return Err(ErrorResponseStatus::Forbidden);
+        }
+    }
+
     Ok(RequestData {
         session,
         user,
@@ -138,6 +338,31 @@ where
     })
 }
 
+/// The GET wrapper for routes with query parameters
+pub async fn query_util<T>(
+    http_request: actix_web::HttpRequest,
+    auth: Authentication,
+) -> Result<RequestData<T>, ErrorResponseStatus>
+where
+    T: de::DeserializeOwned,
+{
+    let base = get_util(http_request, auth).await?;
+
+    // Get the query parameters
+    let query = web::Query::<T>::from_query(base.http_request.query_string()).map_err(|_| {
+        debug!("Failed to parse query parameters");
+        ErrorResponseStatus::BadRequest
+    })?;
+
+    Ok(RequestData {
+        session: base.session,
+        user: base.user,
+        auth: base.auth,
+        data: query.into_inner(),
+        http_request: base.http_request,
+    })
+}
+
 pub async fn encode_response<T, E>(response: Result<T, ResponseError<E>>) -> actix_web::HttpResponse
 where
     T: Serialize,
@@ -169,8 +394,19 @@ where
 #[macro_export]
 macro_rules! v1_get {
     ($name: ident, $fn_name: ident, $auth: ident, $res: ty, $err: ty) => {
+        pub fn route() -> $crate::v1::V1Route {
+            $crate::v1::V1Route {
+                method: actix_web::http::Method::GET,
+                auth: $crate::v1::Authentication::$auth,
+                route: || actix_web::web::get().to($name),
+                describe: |generators| {
+                    $crate::v1::openapi::Operation::new::<$res, $err>(generators)
+                },
+            }
+        }
+
         pub async fn $name(req: actix_web::HttpRequest) -> actix_web::HttpResponse {
-            use crate::v1::{encode_response, get_util, Authentication, ResponseError};
+            use $crate::v1::{encode_response, get_util, Authentication, ResponseError};
             // Get the request data
             let request_data = match get_util(req, Authentication::$auth).await {
                 Ok(request_data) => request_data,
@@ -187,6 +423,40 @@ macro_rules! v1_get {
             // Encode the response
             let response = encode_response(response).await;
 
+            response
+        }
+    };
+    ($name: ident, $fn_name: ident, $auth: ident, $query: ty, $res: ty, $err: ty) => {
+        pub fn route() -> $crate::v1::V1Route {
+            $crate::v1::V1Route {
+                method: actix_web::http::Method::GET,
+                auth: $crate::v1::Authentication::$auth,
+                route: || actix_web::web::get().to($name),
+                describe: |generators| {
+                    $crate::v1::openapi::Operation::new::<$res, $err>(generators)
+                        .with_query::<$query>(generators)
+                },
+            }
+        }
+
+        pub async fn $name(req: actix_web::HttpRequest) -> actix_web::HttpResponse {
+            use $crate::v1::{encode_response, query_util, Authentication, ResponseError};
+            // Get the request data
+            let request_data = match query_util::<$query>(req, Authentication::$auth).await {
+                Ok(request_data) => request_data,
+                Err(err) => {
+                    let err: Result<$res, ResponseError<$err>> =
+                        Err(ResponseError::RequestError(err));
+                    return encode_response(err).await;
+                }
+            };
+
+            // Pass the request data to the handler
+            let response: Result<$res, ResponseError<$err>> = $fn_name(request_data).await;
+
+            // Encode the response
+            let response = encode_response(response).await;
+
             response
         }
     };
@@ -195,11 +465,23 @@ macro_rules! v1_get {
 #[macro_export]
 macro_rules! v1_post {
     ($name: ident, $fn_name: ident, $auth: ident, $req: ty, $res: ty, $err: ty) => {
+        pub fn route() -> $crate::v1::V1Route {
+            $crate::v1::V1Route {
+                method: actix_web::http::Method::POST,
+                auth: $crate::v1::Authentication::$auth,
+                route: || actix_web::web::post().to($name),
+                describe: |generators| {
+                    $crate::v1::openapi::Operation::new::<$res, $err>(generators)
+                        .with_body::<$req>(generators)
+                },
+            }
+        }
+
         pub async fn $name(
             bytes: actix_web::web::Bytes,
             req: actix_web::HttpRequest,
         ) -> actix_web::HttpResponse {
-            use crate::v1::{encode_response, post_util, Authentication, ResponseError};
+            use $crate::v1::{encode_response, post_util, Authentication, ResponseError};
             // Get the request data
             let request_data = match post_util::<$req>(bytes, req, Authentication::$auth).await {
                 Ok(request_data) => request_data,
@@ -221,8 +503,22 @@ macro_rules! v1_post {
     };
 }
 
-pub fn create_v1_service() -> actix_web::Scope {
-    web::scope("/v1")
+/// Every route of the v1 API
+pub fn create_v1_scope() -> V1Scope {
+    V1Scope::new("/v1")
+        .service(calendar::create_calendar_service())
+        .service(clubs::create_clubs_service())
+        .service(notifications::create_notifications_service())
+        .service(push::create_push_service())
+        .service(schedule::create_schedule_service())
         .service(schoology::create_schoology_service())
+        .service(sessions::create_sessions_service())
+        .service(status::create_status_service())
+        .raw_route("/openapi.json", web::get().to(openapi::get_handler))
+}
+
+pub fn create_v1_service() -> actix_web::Scope {
+    create_v1_scope()
+        .into_scope()
         .default_service(web::route().to(not_found))
 }
@@ -0,0 +1,116 @@
+//! /docs/api/v1/notifications/list
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    database::get_db_client,
+    utils,
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError},
+    v1_get,
+};
+
+/// The page size when `limit` is not given
+const DEFAULT_LIMIT: u64 = 20;
+/// The largest page that can be requested at once
+const MAX_LIMIT: u64 = 100;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Query {
+    pub before: Option<Uuid>,
+    pub limit: Option<u64>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Notification {
+    id: Uuid,
+    #[serde(rename = "type")]
+    kind: String,
+    title: String,
+    body: String,
+    data: Value,
+    created_at: chrono::DateTime<chrono::Utc>,
+    read: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    notifications: Vec<Notification>,
+    unread: u64,
+    /// The unread threads in the Schoology inbox as of the last check (`None` before the first one)
+    unread_messages: Option<i32>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[allow(clippy::enum_variant_names)]
+enum Error {
+    InvalidLimit,
+    NotificationNotFound,
+    DatabaseError,
+}
+
+async fn get(data: RequestData<Query>) -> Result<Response, ResponseError<Error>> {
+    let id = match data.user {
+        Some(user) => user.id,
+        None => {
+            return Err(ResponseError::RequestError(
+                ErrorResponseStatus::Unauthorized,
+            ))
+        }
+    };
+
+    let limit = data.data.limit.unwrap_or(DEFAULT_LIMIT);
+
+    if limit == 0 || limit > MAX_LIMIT {
+        return Err(ResponseError::ClientError(Error::InvalidLimit));
+    }
+
+    let db_client = get_db_client();
+
+    // The cursor has to be one of the user's own notifications
+    let before = match data.data.before {
+        Some(before) => Some(
+            utils::notifications::get(db_client, before)
+                .await
+                .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?
+                .filter(|notification| notification.user_id == id)
+                .ok_or(ResponseError::ClientError(Error::NotificationNotFound))?,
+        ),
+        None => None,
+    };
+
+    let notifications = utils::notifications::list_by_user(db_client, id, before.as_ref(), limit)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    let unread = utils::notifications::count_unread(db_client, id)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    let unread_messages = utils::message_poll::get(db_client, id)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?
+        .filter(|state| state.initialized)
+        .map(|state| state.unread_count);
+
+    Ok(Response {
+        notifications: notifications
+            .into_iter()
+            .map(|n| Notification {
+                id: n.id,
+                kind: n.kind,
+                title: n.title,
+                body: n.body,
+                data: n.data,
+                created_at: n.created_at.and_utc(),
+                read: n.read_at.is_some(),
+            })
+            .collect(),
+        unread,
+        unread_messages,
+    })
+}
+
+v1_get!(get_handler, get, UserAuth, Query, Response, Error);
@@ -0,0 +1,16 @@
+use crate::v1::V1Scope;
+
+mod list;
+mod read;
+mod settings;
+#[cfg(test)]
+mod tests;
+mod update_settings;
+
+pub fn create_notifications_service() -> V1Scope {
+    V1Scope::new("/notifications")
+        .route("", list::route())
+        .route("/read", read::route())
+        .route("/settings", settings::route())
+        .route("/settings", update_settings::route())
+}
@@ -0,0 +1,49 @@
+//! /docs/api/v1/notifications/read
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    database::get_db_client,
+    utils,
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError},
+    v1_post,
+};
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Request {
+    /// The notifications to mark as read (all of them if not given)
+    pub ids: Option<Vec<Uuid>>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    marked: u64,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+enum Error {
+    DatabaseError,
+}
+
+async fn post(data: RequestData<Request>) -> Result<Response, ResponseError<Error>> {
+    let id = match data.user {
+        Some(user) => user.id,
+        None => {
+            return Err(ResponseError::RequestError(
+                ErrorResponseStatus::Unauthorized,
+            ))
+        }
+    };
+
+    let db_client = get_db_client();
+
+    let marked = utils::notifications::mark_read(db_client, id, data.data.ids)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    Ok(Response { marked })
+}
+
+v1_post!(post_handler, post, UserAuth, Request, Response, Error);
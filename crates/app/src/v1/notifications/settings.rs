@@ -0,0 +1,58 @@
+//! /docs/api/v1/notifications/settings
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{
+    database::get_db_client,
+    utils,
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError},
+    v1_get,
+};
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    poll_interval: i32,
+    next_poll_at: chrono::DateTime<chrono::Utc>,
+    last_polled_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// How many polls in a row failed (the interval is backed off while this is not 0)
+    failures: i32,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+enum Error {
+    SchoologyNotLinked,
+    DatabaseError,
+}
+
+async fn get(data: RequestData<()>) -> Result<Response, ResponseError<Error>> {
+    let id = match data.user {
+        Some(user) => user.id,
+        None => {
+            return Err(ResponseError::RequestError(
+                ErrorResponseStatus::Unauthorized,
+            ))
+        }
+    };
+
+    let db_client = get_db_client();
+
+    // Only linked users are polled
+    utils::schoology_link::get_by_user_id(db_client, id)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?
+        .ok_or(ResponseError::ClientError(Error::SchoologyNotLinked))?;
+
+    let state = utils::grade_poll::ensure(db_client, id)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    Ok(Response {
+        poll_interval: state.interval_minutes,
+        next_poll_at: state.next_poll_at.and_utc(),
+        last_polled_at: state.last_polled_at.map(|polled_at| polled_at.and_utc()),
+        failures: state.failures,
+    })
+}
+
+v1_get!(get_handler, get, UserAuth, Response, Error);
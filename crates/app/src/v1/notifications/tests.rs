@@ -0,0 +1,328 @@
+use actix_web::{
+    test::{call_and_read_body_json, init_service, TestRequest},
+    web, App,
+};
+use schoology_mock::{MockAssignment, MockGrade, MockSection, MockThread};
+use serde_json::{json, Value};
+
+use crate::{
+    database::get_db_client, schoology::get_schoology_client, testing, utils, v1::create_v1_service,
+};
+
+/// The user id of a linked test user
+async fn user_id(schoology_id: usize) -> i32 {
+    utils::schoology_link::get(get_db_client(), schoology_id as i32)
+        .await
+        .unwrap()
+        .unwrap()
+        .user_id
+}
+
+/// Polls the grades of a user right away
+async fn poll(user_id: i32) -> Result<usize, ()> {
+    let state = utils::grade_poll::ensure(get_db_client(), user_id)
+        .await
+        .unwrap();
+
+    utils::grade_poll::poll(get_db_client(), get_schoology_client(), state).await
+}
+
+/// Checks the inbox of a user right away
+async fn check_messages(user_id: i32) -> Result<usize, ()> {
+    let state = utils::message_poll::ensure(get_db_client(), user_id)
+        .await
+        .unwrap();
+
+    utils::message_poll::check(get_db_client(), get_schoology_client(), state).await
+}
+
+#[test]
+fn grade_notifications() {
+    testing::run(|mock| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let (token, schoology_id) = testing::linked_user(mock).await;
+        let user_id = user_id(schoology_id).await;
+
+        let section = testing::new_schoology_id();
+        mock.add_section(MockSection::new(section, section + 100));
+        mock.enroll(section, schoology_id, false);
+
+        let (first, second) = (testing::new_schoology_id(), testing::new_schoology_id());
+        mock.add_assignment(MockAssignment::new(first, section, "2023-10-10 08:00:00"));
+        mock.add_assignment(MockAssignment::new(second, section, "2023-10-11 08:00:00"));
+
+        mock.add_grade(MockGrade::new(schoology_id, section, first, 1, 9.0, 10.0));
+
+        // The first poll only records the grades
+        assert_eq!(poll(user_id).await, Ok(0));
+
+        // A changed grade and a new one
+        mock.add_grade(MockGrade::new(schoology_id, section, first, 1, 10.0, 10.0));
+        mock.add_grade(MockGrade::new(schoology_id, section, second, 1, 45.0, 50.0));
+
+        assert_eq!(poll(user_id).await, Ok(2));
+
+        // Nothing changed since
+        assert_eq!(poll(user_id).await, Ok(0));
+
+        let req = TestRequest::get()
+            .uri("/api/v1/notifications")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+        assert_eq!(res["data"]["unread"], 2);
+
+        let notifications = res["data"]["notifications"].as_array().unwrap();
+        assert_eq!(notifications.len(), 2);
+
+        let changed = notifications
+            .iter()
+            .find(|n| n["type"] == "GradeChanged")
+            .unwrap();
+        assert_eq!(
+            changed["title"],
+            format!("Grade changed in Course {}", section + 100)
+        );
+        assert_eq!(
+            changed["body"],
+            format!("Assignment {}: 9/10 → 10/10", first)
+        );
+        assert_eq!(changed["data"]["previous_grade"], 9.0);
+
+        let posted = notifications
+            .iter()
+            .find(|n| n["type"] == "GradePosted")
+            .unwrap();
+        assert_eq!(posted["body"], format!("Assignment {}: 45/50", second));
+        assert_eq!(posted["read"], false);
+
+        // Pages
+        let req = TestRequest::get()
+            .uri("/api/v1/notifications?limit=1")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        let page = res["data"]["notifications"].as_array().unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0]["id"], notifications[0]["id"]);
+
+        let req = TestRequest::get()
+            .uri(&format!(
+                "/api/v1/notifications?limit=1&before={}",
+                page[0]["id"].as_str().unwrap()
+            ))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        let page = res["data"]["notifications"].as_array().unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0]["id"], notifications[1]["id"]);
+
+        let req = TestRequest::get()
+            .uri("/api/v1/notifications?limit=1000")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"], "InvalidLimit", "{}", res);
+
+        // Read one, then the rest
+        let req = TestRequest::post()
+            .uri("/api/v1/notifications/read")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "ids": [notifications[0]["id"]] }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"]["marked"], 1, "{}", res);
+
+        let req = TestRequest::post()
+            .uri("/api/v1/notifications/read")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({}))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"]["marked"], 1, "{}", res);
+
+        let req = TestRequest::get()
+            .uri("/api/v1/notifications")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"]["unread"], 0);
+    });
+}
+
+#[test]
+fn grade_poll_backoff() {
+    testing::run(|mock| async move {
+        let (_, schoology_id) = testing::linked_user(mock).await;
+        let user_id = user_id(schoology_id).await;
+        let db_client = get_db_client();
+
+        // The user removed the app from Schoology
+        mock.revoke(schoology_id);
+
+        assert_eq!(poll(user_id).await, Err(()));
+        assert_eq!(poll(user_id).await, Err(()));
+
+        let state = utils::grade_poll::get(db_client, user_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(state.failures, 2);
+        assert!(state.last_polled_at.is_none());
+
+        // 30 minutes doubled twice
+        let wait = state.next_poll_at - chrono::Utc::now().naive_utc();
+        assert!(wait > chrono::Duration::minutes(119), "{}", wait);
+        assert!(wait <= chrono::Duration::minutes(120), "{}", wait);
+
+        // Logging in again clears the backoff
+        utils::grade_poll::reset(db_client, user_id).await.unwrap();
+
+        let state = utils::grade_poll::get(db_client, user_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(state.failures, 0);
+        assert!(state.next_poll_at <= chrono::Utc::now().naive_utc());
+    });
+}
+
+#[test]
+fn notification_settings() {
+    testing::run(|mock| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let (token, _) = testing::linked_user(mock).await;
+
+        let req = TestRequest::get()
+            .uri("/api/v1/notifications/settings")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+        assert_eq!(res["data"]["poll_interval"], 30);
+        assert_eq!(res["data"]["failures"], 0);
+
+        let req = TestRequest::post()
+            .uri("/api/v1/notifications/settings")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "poll_interval": 5 }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"], "InvalidPollInterval", "{}", res);
+
+        let req = TestRequest::post()
+            .uri("/api/v1/notifications/settings")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "poll_interval": 60 }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+        assert_eq!(res["data"]["poll_interval"], 60);
+    });
+}
+
+#[test]
+fn message_notifications() {
+    testing::run(|mock| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let (token, schoology_id) = testing::linked_user(mock).await;
+        let user_id = user_id(schoology_id).await;
+        let teacher = testing::new_schoology_id();
+
+        // Messages from before the first check are not notified
+        let old = testing::new_schoology_id();
+        mock.add_thread(MockThread::new(
+            old,
+            teacher,
+            vec![schoology_id],
+            chrono::Utc::now().timestamp() - 60,
+        ));
+
+        assert_eq!(check_messages(user_id).await, Ok(0));
+
+        let mut thread = MockThread::new(
+            testing::new_schoology_id(),
+            teacher,
+            vec![schoology_id],
+            chrono::Utc::now().timestamp() + 1,
+        );
+        thread.subject = "Missing lab report".to_string();
+        let thread_id = thread.id;
+        mock.add_thread(thread);
+
+        // The grades are polled on their own
+        assert_eq!(poll(user_id).await, Ok(0));
+
+        assert_eq!(check_messages(user_id).await, Ok(1));
+
+        // Already notified
+        assert_eq!(check_messages(user_id).await, Ok(0));
+
+        let req = TestRequest::get()
+            .uri("/api/v1/notifications")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+        assert_eq!(res["data"]["unread"], 1);
+        assert_eq!(res["data"]["unread_messages"], 2);
+
+        let notification = &res["data"]["notifications"][0];
+        assert_eq!(notification["type"], "NewMessage");
+        assert_eq!(notification["body"], "Missing lab report");
+        assert_eq!(notification["data"]["thread_id"], thread_id);
+        assert_eq!(notification["data"]["author_id"], teacher);
+    });
+}
+
+#[test]
+fn message_poll_backoff() {
+    testing::run(|mock| async move {
+        let (_, schoology_id) = testing::linked_user(mock).await;
+        let user_id = user_id(schoology_id).await;
+        let db_client = get_db_client();
+
+        assert_eq!(check_messages(user_id).await, Ok(0));
+
+        mock.revoke(schoology_id);
+
+        assert_eq!(check_messages(user_id).await, Err(()));
+        assert_eq!(check_messages(user_id).await, Err(()));
+
+        let state = utils::message_poll::get(db_client, user_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(state.failures, 2);
+        assert!(state.initialized);
+
+        // 15 minutes doubled twice
+        let wait = state.next_check_at - chrono::Utc::now().naive_utc();
+        assert!(wait > chrono::Duration::minutes(59), "{}", wait);
+        assert!(wait <= chrono::Duration::minutes(60), "{}", wait);
+
+        // The grades are backed off separately
+        let grades = utils::grade_poll::ensure(db_client, user_id).await.unwrap();
+        assert_eq!(grades.failures, 0);
+
+        // Logging in again clears the backoff
+        utils::message_poll::reset(db_client, user_id)
+            .await
+            .unwrap();
+
+        let state = utils::message_poll::get(db_client, user_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(state.failures, 0);
+        assert!(state.next_check_at <= chrono::Utc::now().naive_utc());
+    });
+}
@@ -0,0 +1,68 @@
+//! /docs/api/v1/notifications/update_settings
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::get_db_client,
+    utils::{
+        self,
+        grade_poll::{MAX_INTERVAL_MINUTES, MIN_INTERVAL_MINUTES},
+    },
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError},
+    v1_post,
+};
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Request {
+    /// How often the grades are checked (in minutes)
+    pub poll_interval: i32,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    poll_interval: i32,
+    next_poll_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[allow(clippy::enum_variant_names)]
+enum Error {
+    InvalidPollInterval,
+    SchoologyNotLinked,
+    DatabaseError,
+}
+
+async fn post(data: RequestData<Request>) -> Result<Response, ResponseError<Error>> {
+    let id = match data.user {
+        Some(user) => user.id,
+        None => {
+            return Err(ResponseError::RequestError(
+                ErrorResponseStatus::Unauthorized,
+            ))
+        }
+    };
+
+    if !(MIN_INTERVAL_MINUTES..=MAX_INTERVAL_MINUTES).contains(&data.data.poll_interval) {
+        return Err(ResponseError::ClientError(Error::InvalidPollInterval));
+    }
+
+    let db_client = get_db_client();
+
+    // Only linked users are polled
+    utils::schoology_link::get_by_user_id(db_client, id)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?
+        .ok_or(ResponseError::ClientError(Error::SchoologyNotLinked))?;
+
+    let state = utils::grade_poll::set_interval(db_client, id, data.data.poll_interval)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    Ok(Response {
+        poll_interval: state.interval_minutes,
+        next_poll_at: state.next_poll_at.and_utc(),
+    })
+}
+
+v1_post!(post_handler, post, UserAuth, Request, Response, Error);
@@ -0,0 +1,300 @@
+//! /docs/api/v1/openapi
+//! The OpenAPI 3.1 document of the v1 API, generated from the routes defined with `v1_get!` / `v1_post!`
+
+use actix_web::{http::Method, HttpResponse};
+use once_cell::sync::Lazy;
+use schemars::{generate::SchemaSettings, JsonSchema, Schema, SchemaGenerator};
+use serde_json::{json, Map, Value};
+
+use crate::v1::{create_v1_scope, types::ErrorResponseStatus, Authentication, V1Scope};
+
+#[cfg(test)]
+mod tests;
+
+/// Where the API is mounted (see `main.rs`)
+const BASE_PATH: &str = "/api";
+
+/// Generated on the first request, the routes can't change while running
+static DOCUMENT: Lazy<String> = Lazy::new(|| document(&create_v1_scope()).to_string());
+
+/// Generates the schemas of the routes
+/// Responses are described as they are serialized and requests as they are deserialized, nested
+/// response types are shared in `components/schemas` while requests are inlined.
+pub struct Generators {
+    responses: SchemaGenerator,
+    requests: SchemaGenerator,
+}
+
+impl Generators {
+    fn new() -> Self {
+        Self {
+            responses: SchemaSettings::draft2020_12()
+                .for_serialize()
+                .with(|settings| settings.definitions_path = "/components/schemas".into())
+                .into_generator(),
+            requests: SchemaSettings::draft2020_12()
+                .for_deserialize()
+                .with(|settings| settings.inline_subschemas = true)
+                .into_generator(),
+        }
+    }
+}
+
+/// What a route takes and returns
+pub struct Operation {
+    query: Option<Schema>,
+    body: Option<Schema>,
+    response: Schema,
+    error: Schema,
+}
+
+impl Operation {
+    /// A route returning `Res` on success and `Err` as it's `RouteError`
+    pub fn new<Res, Err>(generators: &mut Generators) -> Self
+    where
+        Res: JsonSchema,
+        Err: JsonSchema,
+    {
+        // Inlined, every route module has it's own `Response` and `Error`
+        Self {
+            query: None,
+            body: None,
+            response: Res::json_schema(&mut generators.responses),
+            error: Err::json_schema(&mut generators.responses),
+        }
+    }
+
+    /// Takes `Query` as the query parameters
+    pub fn with_query<Query>(mut self, generators: &mut Generators) -> Self
+    where
+        Query: JsonSchema,
+    {
+        self.query = Some(Query::json_schema(&mut generators.requests));
+        self
+    }
+
+    /// Takes `Request` as the JSON body
+    pub fn with_body<Request>(mut self, generators: &mut Generators) -> Self
+    where
+        Request: JsonSchema,
+    {
+        self.body = Some(Request::json_schema(&mut generators.requests));
+        self
+    }
+}
+
+/// A route as it is described in the document
+pub struct DescribedRoute {
+    pub path: String,
+    pub method: Method,
+    pub auth: Authentication,
+    pub describe: fn(&mut Generators) -> Operation,
+}
+
+/// The `ResponseData` variants (see `types.rs`), `RequestError` is the same for every route
+fn envelope(kind: &str, field: &str, schema: Value) -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "type": { "const": kind },
+            field: schema,
+        },
+        "required": ["type", field],
+    })
+}
+
+fn json_content(description: &str, schema: Value) -> Value {
+    json!({
+        "description": description,
+        "content": { "application/json": { "schema": schema } },
+    })
+}
+
+/// The query string parameters, one per property of the query type
+fn parameters(query: &Schema) -> Vec<Value> {
+    let required = query
+        .get("required")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+
+    query
+        .get("properties")
+        .and_then(Value::as_object)
+        .map(|properties| {
+            properties
+                .iter()
+                .map(|(name, schema)| {
+                    let mut parameter = json!({
+                        "name": name,
+                        "in": "query",
+                        "required": required.contains(&json!(name)),
+                        "schema": schema,
+                    });
+
+                    if let Some(description) = schema.get("description") {
+                        parameter["description"] = description.clone();
+                    }
+
+                    parameter
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// `get /api/v1/schoology/messages/thread` is `getSchoologyMessagesThread`
+fn operation_id(method: &Method, path: &str) -> String {
+    let mut id = method.as_str().to_lowercase();
+
+    for segment in path
+        .split(['/', '_', '.'])
+        .skip_while(|segment| *segment != "v1")
+        .skip(1)
+    {
+        let mut chars = segment.chars();
+
+        if let Some(first) = chars.next() {
+            id.extend(first.to_uppercase());
+            id.push_str(chars.as_str());
+        }
+    }
+
+    id
+}
+
+fn describe(route: &DescribedRoute, generators: &mut Generators) -> Value {
+    let operation = (route.describe)(generators);
+    let path = format!("{}{}", BASE_PATH, route.path);
+
+    let route_error = envelope("RouteError", "data", operation.error.to_value());
+    let request_error = json!({ "$ref": "#/components/schemas/RequestError" });
+
+    let mut responses = Map::new();
+    responses.insert(
+        "200".to_string(),
+        json_content(
+            "Success",
+            envelope("Success", "data", operation.response.to_value()),
+        ),
+    );
+    responses.insert(
+        "400".to_string(),
+        json_content(
+            "A `RouteError` that is the client's fault, or a malformed request",
+            json!({ "oneOf": [route_error, request_error] }),
+        ),
+    );
+
+    if route.auth != Authentication::NoAuth {
+        responses.insert(
+            "401".to_string(),
+            json_content("The session is missing or invalid", request_error.clone()),
+        );
+    }
+
+    if matches!(
+        route.auth,
+        Authentication::AdminAuth | Authentication::RootAuth
+    ) {
+        responses.insert(
+            "403".to_string(),
+            json_content(
+                "The user does not have the required permissions",
+                request_error.clone(),
+            ),
+        );
+    }
+
+    responses.insert(
+        "429".to_string(),
+        json_content(
+            "Too many requests, see `Retry-After`",
+            request_error.clone(),
+        ),
+    );
+    responses.insert(
+        "500".to_string(),
+        json_content(
+            "A `RouteError` that is the server's fault, or an internal error",
+            json!({ "oneOf": [route_error, request_error] }),
+        ),
+    );
+
+    let (permission, security) = match route.auth {
+        // A session is still used if one is sent
+        Authentication::NoAuth => ("none", json!([{}, { "bearer": [] }])),
+        Authentication::UserAuth => ("user", json!([{ "bearer": [] }])),
+        Authentication::AdminAuth => ("admin", json!([{ "bearer": [] }])),
+        Authentication::RootAuth => ("root", json!([{ "bearer": [] }])),
+    };
+
+    let mut value = json!({
+        "operationId": operation_id(&route.method, &path),
+        "tags": [route.path.split('/').nth(2).unwrap_or_default()],
+        "security": security,
+        "x-permission": permission,
+        "responses": responses,
+    });
+
+    if let Some(query) = &operation.query {
+        value["parameters"] = Value::Array(parameters(query));
+    }
+
+    if let Some(body) = operation.body {
+        value["requestBody"] = json!({
+            "required": true,
+            "content": { "application/json": { "schema": body.to_value() } },
+        });
+    }
+
+    value
+}
+
+/// Generates the document of every route in the scope
+pub fn document(scope: &V1Scope) -> Value {
+    let mut generators = Generators::new();
+    let mut paths = Map::new();
+
+    for route in scope.routes() {
+        let operation = describe(route, &mut generators);
+        let path = format!("{}{}", BASE_PATH, route.path);
+
+        let item = paths
+            .entry(path)
+            .or_insert_with(|| Value::Object(Map::new()));
+        item[route.method.as_str().to_lowercase()] = operation;
+    }
+
+    let status = generators
+        .responses
+        .subschema_for::<ErrorResponseStatus>()
+        .to_value();
+
+    let mut schemas = generators.responses.take_definitions(true);
+    schemas.insert(
+        "RequestError".to_string(),
+        envelope("RequestError", "status", status),
+    );
+
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "Tuwa API",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": {
+            "schemas": schemas,
+            "securitySchemes": {
+                "bearer": { "type": "http", "scheme": "bearer" },
+            },
+        },
+    })
+}
+
+pub async fn get_handler() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .body(DOCUMENT.as_str())
+}
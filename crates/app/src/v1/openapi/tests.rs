@@ -0,0 +1,90 @@
+use actix_web::{
+    test::{call_and_read_body_json, call_service, init_service, TestRequest},
+    web, App,
+};
+use serde_json::Value;
+
+use super::document;
+use crate::{
+    testing,
+    v1::{create_v1_scope, create_v1_service},
+};
+
+#[test]
+fn serves_document() {
+    testing::run(|_| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let req = TestRequest::get().uri("/api/v1/openapi.json").to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(
+            res.headers().get("content-type").unwrap(),
+            "application/json"
+        );
+
+        let req = TestRequest::get().uri("/api/v1/openapi.json").to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["openapi"], "3.1.0", "{}", res);
+        assert_eq!(res, document(&create_v1_scope()));
+
+        let user = &res["paths"]["/api/v1/schoology/user"]["get"];
+        assert_eq!(user["operationId"], "getSchoologyUser", "{}", user);
+        assert_eq!(user["tags"][0], "schoology", "{}", user);
+        assert_eq!(user["x-permission"], "user", "{}", user);
+        assert!(user["responses"]["401"].is_object(), "{}", user);
+        assert!(user["responses"]["403"].is_null(), "{}", user);
+
+        // Query parameters, with their defaults
+        let messages = &res["paths"]["/api/v1/schoology/messages"]["get"];
+        let folder = &messages["parameters"][0];
+        assert_eq!(folder["name"], "folder", "{}", messages);
+        assert_eq!(folder["in"], "query", "{}", messages);
+        assert_eq!(folder["required"], false, "{}", messages);
+
+        // Request bodies
+        let subscribe = &res["paths"]["/api/v1/push/subscribe"]["post"];
+        let body = &subscribe["requestBody"]["content"]["application/json"]["schema"];
+        assert!(body["properties"]["endpoint"].is_object(), "{}", subscribe);
+
+        // The envelope, as `ResponseData` serializes it
+        let success = &user["responses"]["200"]["content"]["application/json"]["schema"];
+        assert_eq!(success["properties"]["type"]["const"], "Success");
+        assert!(success["properties"]["data"]["properties"]["first_name"].is_object());
+
+        let request_error = &res["components"]["schemas"]["RequestError"];
+        assert_eq!(request_error["properties"]["type"]["const"], "RequestError");
+        assert!(res["components"]["schemas"]["ErrorResponseStatus"].is_object());
+
+        // Admin routes
+        let update = &res["paths"]["/api/v1/schedule/template"]["post"];
+        assert_eq!(update["x-permission"], "admin", "{}", update);
+        assert!(update["responses"]["403"].is_object(), "{}", update);
+    });
+}
+
+#[test]
+fn documents_every_route() {
+    testing::run(|_| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let document = document(&create_v1_scope());
+        let paths = document["paths"].as_object().unwrap();
+        assert!(paths.len() > 30, "{}", paths.len());
+
+        // Every documented route exists (an unknown route is `NotFound`)
+        for (path, item) in paths {
+            for method in item.as_object().unwrap().keys() {
+                let req = match method.as_str() {
+                    "get" => TestRequest::get(),
+                    "post" => TestRequest::post(),
+                    _ => panic!("Unexpected method {} on {}", method, path),
+                };
+                let res = call_service(&app, req.uri(path).to_request()).await;
+                assert_ne!(res.status(), 404, "{} {}", method, path);
+            }
+        }
+    });
+}
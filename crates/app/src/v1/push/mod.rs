@@ -0,0 +1,14 @@
+use crate::v1::V1Scope;
+
+mod subscribe;
+#[cfg(test)]
+mod tests;
+mod unsubscribe;
+mod vapid_key;
+
+pub fn create_push_service() -> V1Scope {
+    V1Scope::new("/push")
+        .route("/vapid_key", vapid_key::route())
+        .route("/subscribe", subscribe::route())
+        .route("/unsubscribe", unsubscribe::route())
+}
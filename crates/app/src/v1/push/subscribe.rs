@@ -0,0 +1,75 @@
+//! /docs/api/v1/push/subscribe
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    database::get_db_client,
+    push::{get_push_client, is_valid_keys},
+    utils,
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError},
+    v1_post,
+};
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Keys {
+    pub p256dh: String,
+    pub auth: String,
+}
+
+/// The `PushSubscription` of the browser (as returned by `toJSON()`)
+#[derive(Deserialize, JsonSchema)]
+pub struct Request {
+    pub endpoint: String,
+    pub keys: Keys,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    id: Uuid,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[allow(clippy::enum_variant_names)]
+enum Error {
+    PushNotConfigured,
+    InvalidSubscription,
+    DatabaseError,
+}
+
+async fn post(data: RequestData<Request>) -> Result<Response, ResponseError<Error>> {
+    let id = match data.user {
+        Some(user) => user.id,
+        None => {
+            return Err(ResponseError::RequestError(
+                ErrorResponseStatus::Unauthorized,
+            ))
+        }
+    };
+
+    let push_client =
+        get_push_client().ok_or(ResponseError::ServerError(Error::PushNotConfigured))?;
+
+    let req = data.data;
+
+    // The server will send requests to the endpoint, so it has to be a push service
+    if !push_client.is_valid_endpoint(&req.endpoint)
+        || !is_valid_keys(&req.keys.p256dh, &req.keys.auth)
+    {
+        return Err(ResponseError::ClientError(Error::InvalidSubscription));
+    }
+
+    let db_client = get_db_client();
+
+    let subscription =
+        utils::push::subscribe(db_client, id, req.endpoint, req.keys.p256dh, req.keys.auth)
+            .await
+            .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    Ok(Response {
+        id: subscription.id,
+    })
+}
+
+v1_post!(post_handler, post, UserAuth, Request, Response, Error);
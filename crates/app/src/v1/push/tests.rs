@@ -0,0 +1,346 @@
+use actix_web::{
+    test::{call_and_read_body_json, init_service, TestRequest},
+    web, App,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use orm::{push_deliveries, push_subscriptions};
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_128_GCM},
+    agreement::{self, EphemeralPrivateKey, UnparsedPublicKey, ECDH_P256},
+    hkdf::{self, HKDF_SHA256},
+    rand::{SecureRandom, SystemRandom},
+    signature::{self, ECDSA_P256_SHA256_FIXED},
+};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::{
+    database::get_db_client,
+    push::get_push_client,
+    testing::{self, PushSink},
+    utils::{self, notifications::NotificationKind},
+    v1::create_v1_service,
+};
+
+/// The keys of a browser
+struct UserAgent {
+    private_key: EphemeralPrivateKey,
+    public_key: Vec<u8>,
+    auth: [u8; 16],
+}
+
+impl UserAgent {
+    fn new() -> Self {
+        let rng = SystemRandom::new();
+
+        let private_key = EphemeralPrivateKey::generate(&ECDH_P256, &rng).unwrap();
+        let public_key = private_key.compute_public_key().unwrap().as_ref().to_vec();
+
+        let mut auth = [0u8; 16];
+        rng.fill(&mut auth).unwrap();
+
+        Self {
+            private_key,
+            public_key,
+            auth,
+        }
+    }
+
+    fn subscription(&self, endpoint: &str) -> Value {
+        json!({
+            "endpoint": endpoint,
+            "expirationTime": null,
+            "keys": {
+                "p256dh": URL_SAFE_NO_PAD.encode(&self.public_key),
+                "auth": URL_SAFE_NO_PAD.encode(self.auth),
+            }
+        })
+    }
+
+    /// Decrypts a `aes128gcm` message (the key can only be used once)
+    fn decrypt(self, body: &[u8]) -> Vec<u8> {
+        struct Len(usize);
+
+        impl hkdf::KeyType for Len {
+            fn len(&self) -> usize {
+                self.0
+            }
+        }
+
+        fn expand(salt: &[u8], ikm: &[u8], info: &[u8], len: usize) -> Vec<u8> {
+            let mut out = vec![0u8; len];
+            hkdf::Salt::new(HKDF_SHA256, salt)
+                .extract(ikm)
+                .expand(&[info], Len(len))
+                .unwrap()
+                .fill(&mut out)
+                .unwrap();
+            out
+        }
+
+        let (salt, rest) = body.split_at(16);
+        let (record_size, rest) = rest.split_at(4);
+        let (id_len, rest) = rest.split_at(1);
+        let (as_public, ciphertext) = rest.split_at(id_len[0] as usize);
+
+        assert_eq!(u32::from_be_bytes(record_size.try_into().unwrap()), 4096);
+
+        let ecdh_secret = agreement::agree_ephemeral(
+            self.private_key,
+            &UnparsedPublicKey::new(&ECDH_P256, as_public),
+            |secret| secret.to_vec(),
+        )
+        .unwrap();
+
+        let key_info = [b"WebPush: info\0", self.public_key.as_slice(), as_public].concat();
+        let ikm = expand(&self.auth, &ecdh_secret, &key_info, 32);
+
+        let cek = expand(salt, &ikm, b"Content-Encoding: aes128gcm\0", 16);
+        let nonce = expand(salt, &ikm, b"Content-Encoding: nonce\0", 12);
+
+        let key = LessSafeKey::new(UnboundKey::new(&AES_128_GCM, &cek).unwrap());
+        let mut record = ciphertext.to_vec();
+        let plaintext = key
+            .open_in_place(
+                Nonce::try_assume_unique_for_key(&nonce).unwrap(),
+                Aad::empty(),
+                &mut record,
+            )
+            .unwrap();
+
+        // The last record ends with 0x02
+        assert_eq!(plaintext.last(), Some(&0x02));
+        plaintext[..plaintext.len() - 1].to_vec()
+    }
+}
+
+/// Checks the VAPID `Authorization` header of a message
+fn verify_vapid(authorization: &str, audience: &str) {
+    let (token, key) = authorization
+        .strip_prefix("vapid t=")
+        .unwrap()
+        .split_once(", k=")
+        .unwrap();
+
+    assert_eq!(key, get_push_client().unwrap().public_key());
+
+    let (signing_input, signature) = token.rsplit_once('.').unwrap();
+    signature::UnparsedPublicKey::new(
+        &ECDSA_P256_SHA256_FIXED,
+        URL_SAFE_NO_PAD.decode(key).unwrap(),
+    )
+    .verify(
+        signing_input.as_bytes(),
+        &URL_SAFE_NO_PAD.decode(signature).unwrap(),
+    )
+    .unwrap();
+
+    let claims = signing_input.split('.').nth(1).unwrap();
+    let claims: Value = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(claims).unwrap()).unwrap();
+    assert_eq!(claims["aud"], audience);
+    assert_eq!(claims["sub"], "mailto:test@example.com");
+    assert!(claims["exp"].as_i64().unwrap() > chrono::Utc::now().timestamp());
+}
+
+async fn deliveries(subscription_id: Uuid) -> Vec<push_deliveries::Model> {
+    push_deliveries::Entity::find()
+        .filter(push_deliveries::Column::SubscriptionId.eq(subscription_id))
+        .all(get_db_client())
+        .await
+        .unwrap()
+}
+
+#[test]
+fn push_subscriptions() {
+    testing::run(|mock| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let req = TestRequest::get()
+            .uri("/api/v1/push/vapid_key")
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+        assert_eq!(
+            URL_SAFE_NO_PAD
+                .decode(res["data"]["public_key"].as_str().unwrap())
+                .unwrap()
+                .len(),
+            65
+        );
+
+        let (token, _) = testing::linked_user(mock).await;
+        let user_agent = UserAgent::new();
+        let endpoint = format!("http://127.0.0.1:1/push/{}", Uuid::new_v4());
+
+        let req = TestRequest::post()
+            .uri("/api/v1/push/subscribe")
+            .set_json(user_agent.subscription(&endpoint))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["status"], "Unauthorized", "{}", res);
+
+        // Not a push service
+        let req = TestRequest::post()
+            .uri("/api/v1/push/subscribe")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(user_agent.subscription("http://169.254.169.254/latest/meta-data"))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"], "InvalidSubscription", "{}", res);
+
+        // Bad keys
+        let req = TestRequest::post()
+            .uri("/api/v1/push/subscribe")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "endpoint": endpoint, "keys": { "p256dh": "AAAA", "auth": "AAAA" } }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"], "InvalidSubscription", "{}", res);
+
+        let req = TestRequest::post()
+            .uri("/api/v1/push/subscribe")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(user_agent.subscription(&endpoint))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        let req = TestRequest::post()
+            .uri("/api/v1/push/unsubscribe")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "endpoint": "http://127.0.0.1:1/push/other" }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"], "SubscriptionNotFound", "{}", res);
+
+        let req = TestRequest::post()
+            .uri("/api/v1/push/unsubscribe")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "endpoint": endpoint }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+    });
+}
+
+#[test]
+fn push_delivery() {
+    testing::run(|mock| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let db_client = get_db_client();
+        let push_client = get_push_client().unwrap();
+        let sink = PushSink::start().await;
+
+        let (token, schoology_id) = testing::linked_user(mock).await;
+        let user_id = utils::schoology_link::get(db_client, schoology_id as i32)
+            .await
+            .unwrap()
+            .unwrap()
+            .user_id;
+
+        let user_agent = UserAgent::new();
+        let name = Uuid::new_v4().to_string();
+
+        let req = TestRequest::post()
+            .uri("/api/v1/push/subscribe")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(user_agent.subscription(&sink.endpoint(&name)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        let subscription_id: Uuid = res["data"]["id"].as_str().unwrap().parse().unwrap();
+
+        // A new notification is queued for every subscription
+        let notification = utils::notifications::create(
+            db_client,
+            user_id,
+            NotificationKind::GradePosted,
+            "New grade in Math".to_string(),
+            "Quiz: 9/10".to_string(),
+            json!({ "grade": 9.0 }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(deliveries(subscription_id).await.len(), 1);
+
+        utils::push::deliver_due(db_client, push_client)
+            .await
+            .unwrap();
+
+        let requests = sink.take_requests();
+        assert_eq!(requests.len(), 1);
+
+        let request = &requests[0];
+        assert_eq!(request.path, format!("/push/{}", name));
+        assert_eq!(request.headers["content-encoding"], "aes128gcm");
+        assert_eq!(request.headers["ttl"], "86400");
+        verify_vapid(
+            &request.headers["authorization"],
+            sink.endpoint("").trim_end_matches("/push/"),
+        );
+
+        let payload: Value = serde_json::from_slice(&user_agent.decrypt(&request.body)).unwrap();
+        assert_eq!(payload["id"], notification.id.to_string());
+        assert_eq!(payload["type"], "GradePosted");
+        assert_eq!(payload["title"], "New grade in Math");
+        assert_eq!(payload["data"]["grade"], 9.0);
+
+        // Delivered
+        assert!(deliveries(subscription_id).await.is_empty());
+
+        // The push service is down, retried later
+        sink.set_status(503);
+
+        utils::notifications::create(
+            db_client,
+            user_id,
+            NotificationKind::GradeChanged,
+            "Grade changed in Math".to_string(),
+            "Quiz: 9/10 → 10/10".to_string(),
+            json!({}),
+        )
+        .await
+        .unwrap();
+
+        utils::push::deliver_due(db_client, push_client)
+            .await
+            .unwrap();
+
+        let pending = deliveries(subscription_id).await;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].attempts, 1);
+        assert!(pending[0].next_attempt_at > chrono::Utc::now().naive_utc());
+        assert_eq!(sink.take_requests().len(), 1);
+
+        // The browser unsubscribed, the subscription is removed
+        sink.set_status(410);
+
+        push_deliveries::Entity::update_many()
+            .col_expr(
+                push_deliveries::Column::NextAttemptAt,
+                sea_orm::sea_query::Expr::value(chrono::Utc::now().naive_utc()),
+            )
+            .filter(push_deliveries::Column::Id.eq(pending[0].id))
+            .exec(db_client)
+            .await
+            .unwrap();
+
+        utils::push::deliver_due(db_client, push_client)
+            .await
+            .unwrap();
+
+        assert_eq!(sink.take_requests().len(), 1);
+        assert!(push_subscriptions::Entity::find_by_id(subscription_id)
+            .one(db_client)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(deliveries(subscription_id).await.is_empty());
+    });
+}
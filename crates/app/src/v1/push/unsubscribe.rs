@@ -0,0 +1,51 @@
+//! /docs/api/v1/push/unsubscribe
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::get_db_client,
+    utils,
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError},
+    v1_post,
+};
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Request {
+    pub endpoint: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {}
+
+#[derive(Debug, Serialize, JsonSchema)]
+enum Error {
+    SubscriptionNotFound,
+    DatabaseError,
+}
+
+async fn post(data: RequestData<Request>) -> Result<Response, ResponseError<Error>> {
+    let id = match data.user {
+        Some(user) => user.id,
+        None => {
+            return Err(ResponseError::RequestError(
+                ErrorResponseStatus::Unauthorized,
+            ))
+        }
+    };
+
+    let db_client = get_db_client();
+
+    // Only the user's own subscriptions
+    let removed = utils::push::unsubscribe(db_client, id, &data.data.endpoint)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    if !removed {
+        return Err(ResponseError::ClientError(Error::SubscriptionNotFound));
+    }
+
+    Ok(Response {})
+}
+
+v1_post!(post_handler, post, UserAuth, Request, Response, Error);
@@ -0,0 +1,31 @@
+//! /docs/api/v1/push/vapid_key
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{
+    push::get_push_client,
+    v1::{RequestData, ResponseError},
+    v1_get,
+};
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    public_key: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+enum Error {
+    PushNotConfigured,
+}
+
+async fn get(_data: RequestData<()>) -> Result<Response, ResponseError<Error>> {
+    let push_client =
+        get_push_client().ok_or(ResponseError::ServerError(Error::PushNotConfigured))?;
+
+    Ok(Response {
+        public_key: push_client.public_key().to_string(),
+    })
+}
+
+v1_get!(get_handler, get, NoAuth, Response, Error);
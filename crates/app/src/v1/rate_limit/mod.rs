@@ -0,0 +1,251 @@
+//! Limits how many requests a client can send, by IP (see `client_ip`) and by session user
+
+use std::{
+    collections::HashMap,
+    future::{ready, Ready},
+    rc::Rc,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use actix_web::{
+    body::EitherBody,
+    dev::{ResourceDef, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{HeaderValue, RETRY_AFTER},
+};
+use futures::future::LocalBoxFuture;
+
+use crate::v1::{
+    calendar::redact_feed_token,
+    client_ip, get_session,
+    types::{ErrorResponseStatus, ResponseData},
+};
+
+#[cfg(test)]
+mod tests;
+
+/// The most clients that are tracked, the ones that are back to a full budget are dropped once it's reached
+const MAX_BUCKETS: usize = 100_000;
+
+/// How many requests a client can send in a period
+#[derive(Debug, Clone, Copy)]
+pub struct Budget {
+    pub requests: u32,
+    pub period: Duration,
+}
+
+impl Budget {
+    pub const fn per_minute(requests: u32) -> Self {
+        Self {
+            requests,
+            period: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Every route without it's own budget shares this one
+pub const DEFAULT_BUDGET: Budget = Budget::per_minute(120);
+
+/// The routes that are expensive (a Schoology request, a new row) or can be abused
+pub const ROUTE_BUDGETS: &[(&str, Budget)] = &[
+    ("/api/v1/schoology/request_token", Budget::per_minute(10)),
+    ("/api/v1/schoology/login", Budget::per_minute(10)),
+    ("/api/v1/schoology/messages/reply", Budget::per_minute(10)),
+    ("/api/v1/sessions/refresh", Budget::per_minute(30)),
+    ("/api/v1/calendar/feed", Budget::per_minute(30)),
+    // Public and a lot of queries, calendar apps only poll it every few minutes
+    ("/api/v1/calendar/{token}.ics", Budget::per_minute(10)),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Client {
+    Ip(String),
+    User(i32),
+}
+
+/// A token bucket
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, budget: &Budget) {
+        let now = Instant::now();
+        let rate = budget.requests as f64 / budget.period.as_secs_f64();
+
+        self.tokens = (self.tokens + now.duration_since(self.updated).as_secs_f64() * rate)
+            .min(budget.requests as f64);
+        self.updated = now;
+    }
+}
+
+struct Limits {
+    default: Budget,
+    routes: Vec<(ResourceDef, Budget)>,
+    /// By client and route (`None` for the routes sharing the default budget)
+    buckets: Mutex<HashMap<(Client, Option<usize>), Bucket>>,
+}
+
+impl Limits {
+    fn lock(&self) -> MutexGuard<'_, HashMap<(Client, Option<usize>), Bucket>> {
+        self.buckets.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// The route's index and budget
+    fn route(&self, path: &str) -> (Option<usize>, Budget) {
+        let path = path.trim_end_matches('/');
+
+        self.routes
+            .iter()
+            .position(|(route, _)| route.is_match(path))
+            .map(|index| (Some(index), self.routes[index].1))
+            .unwrap_or((None, self.default))
+    }
+
+    /// Takes a request from the client's budget, returns how long to wait if there is none left
+    fn check(&self, client: Client, path: &str) -> Result<(), Duration> {
+        let (route, budget) = self.route(path);
+        let mut buckets = self.lock();
+
+        if buckets.len() >= MAX_BUCKETS {
+            let default = self.default;
+            let routes = &self.routes;
+
+            // A full bucket is the same as no bucket
+            buckets.retain(|(_, route), bucket| {
+                let budget = route.map(|index| routes[index].1).unwrap_or(default);
+                bucket.refill(&budget);
+                bucket.tokens < budget.requests as f64
+            });
+
+            if buckets.len() >= MAX_BUCKETS {
+                warn!("Too many clients are rate limited, starting over");
+                buckets.clear();
+            }
+        }
+
+        let bucket = buckets.entry((client, route)).or_insert(Bucket {
+            tokens: budget.requests as f64,
+            updated: Instant::now(),
+        });
+        bucket.refill(&budget);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        let rate = budget.requests as f64 / budget.period.as_secs_f64();
+        Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+    }
+}
+
+/// The middleware, clones share the budgets (create it once, outside of `HttpServer::new`)
+#[derive(Clone)]
+pub struct RateLimit {
+    limits: Arc<Limits>,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self::new(DEFAULT_BUDGET, ROUTE_BUDGETS)
+    }
+}
+
+impl RateLimit {
+    /// Creates a rate limiter, `routes` are full path patterns (e.g. `/api/v1/schoology/login` or `/api/v1/calendar/{token}.ics`)
+    pub fn new(default: Budget, routes: &[(&str, Budget)]) -> Self {
+        Self {
+            limits: Arc::new(Limits {
+                default,
+                routes: routes
+                    .iter()
+                    .map(|(route, budget)| (ResourceDef::new(*route), *budget))
+                    .collect(),
+                buckets: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service: Rc::new(service),
+            limits: self.limits.clone(),
+        }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    limits: Arc<Limits>,
+}
+
+/// The response of a request over the limit
+fn too_many_requests(req: ServiceRequest, retry_after: Duration) -> ServiceResponse {
+    let response: ResponseData<(), ()> =
+        ResponseData::route_error(ErrorResponseStatus::TooManyRequests);
+    let mut response = response.into_response();
+
+    // Whole seconds, rounded up so the client doesn't come back too early
+    let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(seconds.max(1)));
+
+    req.into_response(response)
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_web::dev::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let limits = self.limits.clone();
+
+        Box::pin(async move {
+            let path = req.path().to_string();
+
+            // Checked first, it doesn't need the database
+            let ip = client_ip(req.request());
+
+            if let Err(retry_after) = limits.check(Client::Ip(ip.clone()), &path) {
+                debug!("Rate limited {} on {}", ip, redact_feed_token(&path));
+                return Ok(too_many_requests(req, retry_after).map_into_right_body());
+            }
+
+            // Invalid sessions are left to the route, which rejects them
+            if let Ok(Some(session)) = get_session(req.request()).await {
+                if let Err(retry_after) = limits.check(Client::User(session.user_id), &path) {
+                    debug!("Rate limited user {} on {}", session.user_id, path);
+                    return Ok(too_many_requests(req, retry_after).map_into_right_body());
+                }
+            }
+
+            service
+                .call(req)
+                .await
+                .map(ServiceResponse::map_into_left_body)
+        })
+    }
+}
@@ -0,0 +1,201 @@
+use std::time::Duration;
+
+use actix_web::{
+    http::header,
+    test::{call_service, init_service, read_body_json, TestRequest},
+    web, App,
+};
+use serde_json::Value;
+
+use super::{Budget, RateLimit};
+use crate::{
+    testing,
+    v1::{client_ip, create_v1_service},
+};
+
+/// `X-Forwarded-For` as the load balancer sends it, the client ip and the load balancer's own
+fn forwarded_for(ip: &str) -> String {
+    format!("{}, 35.191.0.1", ip)
+}
+
+#[test]
+fn limits_routes_by_ip() {
+    testing::run(|_| async move {
+        let rate_limit = RateLimit::new(
+            Budget::per_minute(100),
+            &[("/api/v1/schoology/request_token", Budget::per_minute(2))],
+        );
+        let app = init_service(
+            App::new().service(
+                web::scope("/api")
+                    .wrap(rate_limit)
+                    .service(create_v1_service()),
+            ),
+        )
+        .await;
+
+        let ip = format!("203.0.113.{}", testing::new_schoology_id() % 250);
+        let request_token = |ip: &str| {
+            TestRequest::get()
+                .uri("/api/v1/schoology/request_token")
+                .insert_header(("X-Forwarded-For", forwarded_for(ip)))
+                .to_request()
+        };
+
+        for _ in 0..2 {
+            let res = call_service(&app, request_token(&ip)).await;
+            assert_eq!(res.status(), 200);
+        }
+
+        let res = call_service(&app, request_token(&ip)).await;
+        assert_eq!(res.status(), 429);
+
+        // A token comes back every 30 seconds
+        let retry_after = res.headers().get(header::RETRY_AFTER).unwrap();
+        let retry_after = retry_after.to_str().unwrap().parse::<u64>().unwrap();
+        assert!(retry_after > 0 && retry_after <= 30, "{}", retry_after);
+
+        let res: Value = read_body_json(res).await;
+        assert_eq!(res["type"], "RequestError", "{}", res);
+        assert_eq!(res["status"], "TooManyRequests", "{}", res);
+
+        // Other routes and other clients have their own budget
+        let req = TestRequest::get()
+            .uri("/api/v1/schedule/template")
+            .insert_header(("X-Forwarded-For", forwarded_for(&ip)))
+            .to_request();
+        assert_ne!(call_service(&app, req).await.status(), 429);
+
+        let res = call_service(&app, request_token("198.51.100.7")).await;
+        assert_eq!(res.status(), 200);
+    });
+}
+
+#[test]
+fn limits_users_across_ips() {
+    testing::run(|mock| async move {
+        let rate_limit = RateLimit::new(
+            Budget {
+                requests: 3,
+                period: Duration::from_secs(60),
+            },
+            &[],
+        );
+        let app = init_service(
+            App::new().service(
+                web::scope("/api")
+                    .wrap(rate_limit)
+                    .service(create_v1_service()),
+            ),
+        )
+        .await;
+
+        let (token, _) = testing::linked_user(mock).await;
+        let (other_token, _) = testing::linked_user(mock).await;
+
+        let sessions = |token: &str, ip: usize| {
+            TestRequest::get()
+                .uri("/api/v1/sessions")
+                .insert_header(("Authorization", format!("Bearer {}", token)))
+                .insert_header(("X-Forwarded-For", forwarded_for(&format!("192.0.2.{}", ip))))
+                .to_request()
+        };
+
+        // Switching IPs doesn't help
+        for ip in 0..3 {
+            let res = call_service(&app, sessions(&token, ip)).await;
+            assert_eq!(res.status(), 200);
+        }
+
+        let res = call_service(&app, sessions(&token, 3)).await;
+        assert_eq!(res.status(), 429);
+        assert!(res.headers().contains_key(header::RETRY_AFTER));
+
+        let res = call_service(&app, sessions(&other_token, 4)).await;
+        assert_eq!(res.status(), 200);
+    });
+}
+
+#[test]
+fn ignores_spoofed_ips() {
+    testing::run(|_| async move {
+        let rate_limit = RateLimit::new(Budget::per_minute(2), &[]);
+        let app = init_service(
+            App::new().service(
+                web::scope("/api")
+                    .wrap(rate_limit)
+                    .service(create_v1_service()),
+            ),
+        )
+        .await;
+
+        let ip = format!("198.51.100.{}", testing::new_schoology_id() % 250);
+        let template = |spoofed: usize| {
+            TestRequest::get()
+                .uri("/api/v1/schedule/template")
+                .insert_header((
+                    "X-Forwarded-For",
+                    format!("10.0.0.{}, {}", spoofed, forwarded_for(&ip)),
+                ))
+                .to_request()
+        };
+
+        // The client can prepend anything, the budget is still the one of the ip the load balancer saw
+        for spoofed in 0..2 {
+            let res = call_service(&app, template(spoofed)).await;
+            assert_ne!(res.status(), 429);
+        }
+
+        let res = call_service(&app, template(2)).await;
+        assert_eq!(res.status(), 429);
+
+        let req = TestRequest::get()
+            .insert_header((
+                "X-Forwarded-For",
+                format!("203.0.113.9, {}", forwarded_for(&ip)),
+            ))
+            .to_http_request();
+        assert_eq!(client_ip(&req), ip);
+
+        // Without the load balancer's entries the header is not trusted at all
+        let req = TestRequest::get()
+            .insert_header(("X-Forwarded-For", "203.0.113.9"))
+            .to_http_request();
+        assert_eq!(client_ip(&req), "unknown");
+    });
+}
+
+#[test]
+fn limits_routes_by_pattern() {
+    testing::run(|_| async move {
+        let rate_limit = RateLimit::new(
+            Budget::per_minute(100),
+            &[("/api/v1/calendar/{token}.ics", Budget::per_minute(2))],
+        );
+        let app = init_service(
+            App::new().service(
+                web::scope("/api")
+                    .wrap(rate_limit)
+                    .service(create_v1_service()),
+            ),
+        )
+        .await;
+
+        let ip = format!("192.0.2.{}", testing::new_schoology_id() % 250);
+        let feed = |token: &str| {
+            TestRequest::get()
+                .uri(&format!("/api/v1/calendar/{}.ics", token))
+                .insert_header(("X-Forwarded-For", forwarded_for(&ip)))
+                .to_request()
+        };
+
+        // Every feed shares the budget, guessing tokens doesn't get more requests
+        for token in ["first", "second"] {
+            let res = call_service(&app, feed(token)).await;
+            assert_ne!(res.status(), 429);
+        }
+
+        let res = call_service(&app, feed("third")).await;
+        assert_eq!(res.status(), 429);
+    });
+}
@@ -0,0 +1,65 @@
+//! /docs/api/v1/schedule/day
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    database::get_db_client,
+    utils,
+    v1::{RequestData, ResponseError},
+    v1_get,
+};
+
+use super::{get_user_periods, Period};
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Query {
+    /// The date at the school (defaults to today)
+    pub date: Option<chrono::NaiveDate>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    date: chrono::NaiveDate,
+    name: Option<String>,
+    schedule_id: Option<Uuid>,
+    schedule_name: Option<String>,
+    periods: Vec<Period>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+enum Error {
+    DatabaseError,
+}
+
+async fn get(data: RequestData<Query>) -> Result<Response, ResponseError<Error>> {
+    let date = data.data.date.unwrap_or_else(utils::schedule::today);
+
+    let day = utils::schedule::for_date(get_db_client(), date)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    let user_periods = get_user_periods(&data.user)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    let (schedule_id, schedule_name) = match day.schedule {
+        Some(schedule) => (Some(schedule.id), Some(schedule.name)),
+        None => (None, None),
+    };
+
+    Ok(Response {
+        date: day.date,
+        name: day.name,
+        schedule_id,
+        schedule_name,
+        periods: day
+            .periods
+            .into_iter()
+            .map(|period| Period::new(period, &user_periods))
+            .collect(),
+    })
+}
+
+v1_get!(get_handler, get, NoAuth, Query, Response, Error);
@@ -0,0 +1,46 @@
+//! /docs/api/v1/schedule/delete_overrides
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::get_db_client,
+    utils::{self, schedule::MAX_RANGE_DAYS},
+    v1::{RequestData, ResponseError},
+    v1_post,
+};
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Request {
+    pub from: chrono::NaiveDate,
+    /// The last date (defaults to `from`)
+    pub to: Option<chrono::NaiveDate>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    deleted: u64,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+enum Error {
+    InvalidDateRange,
+    DatabaseError,
+}
+
+async fn post(data: RequestData<Request>) -> Result<Response, ResponseError<Error>> {
+    let from = data.data.from;
+    let to = data.data.to.unwrap_or(from);
+
+    if from > to || (to - from).num_days() >= MAX_RANGE_DAYS {
+        return Err(ResponseError::ClientError(Error::InvalidDateRange));
+    }
+
+    let deleted = utils::schedule::delete_overrides(get_db_client(), from, to)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    Ok(Response { deleted })
+}
+
+v1_post!(post_handler, post, AdminAuth, Request, Response, Error);
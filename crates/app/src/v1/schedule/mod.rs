@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use orm::user_periods;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{database::get_db_client, utils, utils::schedule::DayPeriod, v1::V1Scope};
+
+mod day;
+mod delete_overrides;
+mod now;
+mod overrides;
+mod periods;
+mod template;
+#[cfg(test)]
+mod tests;
+mod update_bells;
+mod update_overrides;
+mod update_periods;
+mod update_template;
+
+pub fn create_schedule_service() -> V1Scope {
+    V1Scope::new("/schedule")
+        .route("/now", now::route())
+        .route("/day", day::route())
+        .route("/template", template::route())
+        .route("/template", update_template::route())
+        .route("/bells", update_bells::route())
+        .route("/overrides", overrides::route())
+        .route("/overrides", update_overrides::route())
+        .route("/overrides/delete", delete_overrides::route())
+        .route("/periods", periods::route())
+        .route("/periods", update_periods::route())
+}
+
+/// A period on a date with the name and color the user gave it
+#[derive(Serialize, JsonSchema)]
+struct Period {
+    period: String,
+    name: String,
+    color: Option<String>,
+    start: chrono::DateTime<chrono::Utc>,
+    end: chrono::DateTime<chrono::Utc>,
+}
+
+impl Period {
+    fn new(period: DayPeriod, user_periods: &HashMap<String, user_periods::Model>) -> Self {
+        let user_period = user_periods.get(&period.period);
+
+        Period {
+            name: user_period
+                .and_then(|user_period| user_period.name.clone())
+                .unwrap_or(period.name),
+            color: user_period.and_then(|user_period| user_period.color.clone()),
+            period: period.period,
+            start: period.start,
+            end: period.end,
+        }
+    }
+}
+
+/// Gets the periods of the user by their id (none without a user)
+async fn get_user_periods(
+    user: &Option<orm::users::Model>,
+) -> Result<HashMap<String, user_periods::Model>, ()> {
+    let user = match user {
+        Some(user) => user,
+        None => return Ok(HashMap::new()),
+    };
+
+    let periods = utils::schedule::get_user_periods(get_db_client(), user.id).await?;
+
+    Ok(periods
+        .into_iter()
+        .map(|period| (period.period.clone(), period))
+        .collect())
+}
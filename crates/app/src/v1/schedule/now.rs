@@ -0,0 +1,56 @@
+//! /docs/api/v1/schedule/now
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    database::get_db_client,
+    utils,
+    v1::{RequestData, ResponseError},
+    v1_get,
+};
+
+use super::{get_user_periods, Period};
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Query {
+    /// The time to check (defaults to now)
+    pub at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    date: chrono::NaiveDate,
+    name: Option<String>,
+    schedule_id: Option<Uuid>,
+    current: Option<Period>,
+    next: Option<Period>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+enum Error {
+    DatabaseError,
+}
+
+async fn get(data: RequestData<Query>) -> Result<Response, ResponseError<Error>> {
+    let time = data.data.at.unwrap_or_else(chrono::Utc::now);
+
+    let now = utils::schedule::at(get_db_client(), time)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    let user_periods = get_user_periods(&data.user)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    Ok(Response {
+        date: now.day.date,
+        name: now.day.name,
+        schedule_id: now.day.schedule.map(|schedule| schedule.id),
+        current: now.current.map(|period| Period::new(period, &user_periods)),
+        next: now.next.map(|period| Period::new(period, &user_periods)),
+    })
+}
+
+v1_get!(get_handler, get, NoAuth, Query, Response, Error);
@@ -0,0 +1,61 @@
+//! /docs/api/v1/schedule/overrides
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    database::get_db_client,
+    utils::{self, schedule::MAX_RANGE_DAYS},
+    v1::{RequestData, ResponseError},
+    v1_get,
+};
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Query {
+    pub from: chrono::NaiveDate,
+    pub to: chrono::NaiveDate,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Override {
+    date: chrono::NaiveDate,
+    name: String,
+    schedule_id: Option<Uuid>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    overrides: Vec<Override>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+enum Error {
+    InvalidDateRange,
+    DatabaseError,
+}
+
+async fn get(data: RequestData<Query>) -> Result<Response, ResponseError<Error>> {
+    let Query { from, to } = data.data;
+
+    if from > to || (to - from).num_days() >= MAX_RANGE_DAYS {
+        return Err(ResponseError::ClientError(Error::InvalidDateRange));
+    }
+
+    let overrides = utils::schedule::list_overrides(get_db_client(), from, to)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    Ok(Response {
+        overrides: overrides
+            .into_iter()
+            .map(|day_override| Override {
+                date: day_override.date,
+                name: day_override.name,
+                schedule_id: day_override.schedule_id,
+            })
+            .collect(),
+    })
+}
+
+v1_get!(get_handler, get, NoAuth, Query, Response, Error);
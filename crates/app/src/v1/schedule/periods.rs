@@ -0,0 +1,56 @@
+//! /docs/api/v1/schedule/periods
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{
+    database::get_db_client,
+    utils,
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError},
+    v1_get,
+};
+
+#[derive(Serialize, JsonSchema)]
+struct UserPeriod {
+    period: String,
+    name: Option<String>,
+    color: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    periods: Vec<UserPeriod>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+enum Error {
+    DatabaseError,
+}
+
+async fn get(data: RequestData<()>) -> Result<Response, ResponseError<Error>> {
+    let id = match data.user {
+        Some(user) => user.id,
+        None => {
+            return Err(ResponseError::RequestError(
+                ErrorResponseStatus::Unauthorized,
+            ))
+        }
+    };
+
+    let periods = utils::schedule::get_user_periods(get_db_client(), id)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    Ok(Response {
+        periods: periods
+            .into_iter()
+            .map(|period| UserPeriod {
+                period: period.period,
+                name: period.name,
+                color: period.color,
+            })
+            .collect(),
+    })
+}
+
+v1_get!(get_handler, get, UserAuth, Response, Error);
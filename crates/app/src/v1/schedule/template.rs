@@ -0,0 +1,74 @@
+//! /docs/api/v1/schedule/template
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    database::get_db_client,
+    utils,
+    v1::{RequestData, ResponseError},
+    v1_get,
+};
+
+#[derive(Serialize, JsonSchema)]
+struct BellPeriod {
+    period: String,
+    name: String,
+    start: chrono::NaiveTime,
+    end: chrono::NaiveTime,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct BellSchedule {
+    id: Uuid,
+    name: String,
+    periods: Vec<BellPeriod>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    timezone: String,
+    schedules: Vec<BellSchedule>,
+    weekdays: [Option<Uuid>; 7],
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+enum Error {
+    DatabaseError,
+}
+
+async fn get(_data: RequestData<()>) -> Result<Response, ResponseError<Error>> {
+    let db_client = get_db_client();
+
+    let schedules = utils::schedule::list_schedules(db_client)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    let weekdays = utils::schedule::get_weekdays(db_client)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    Ok(Response {
+        timezone: utils::schedule::timezone().name().to_string(),
+        schedules: schedules
+            .into_iter()
+            .map(|(schedule, periods)| BellSchedule {
+                id: schedule.id,
+                name: schedule.name,
+                periods: periods
+                    .into_iter()
+                    .map(|period| BellPeriod {
+                        period: period.period,
+                        name: period.name,
+                        start: period.start_time,
+                        end: period.end_time,
+                    })
+                    .collect(),
+            })
+            .collect(),
+        weekdays,
+    })
+}
+
+v1_get!(get_handler, get, NoAuth, Response, Error);
@@ -0,0 +1,383 @@
+use actix_web::{
+    test::{call_and_read_body_json, init_service, TestRequest},
+    web, App,
+};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::{testing, v1::create_v1_service};
+
+/// A GET request with an optional session
+fn get(uri: &str, token: Option<&str>) -> TestRequest {
+    authorize(TestRequest::get().uri(uri), token)
+}
+
+/// A POST request with an optional session
+fn post(uri: &str, token: Option<&str>, body: Value) -> TestRequest {
+    authorize(TestRequest::post().uri(uri).set_json(body), token)
+}
+
+fn authorize(req: TestRequest, token: Option<&str>) -> TestRequest {
+    match token {
+        Some(token) => req.insert_header(("Authorization", format!("Bearer {}", token))),
+        None => req,
+    }
+}
+
+/// The only test that changes the weekly template, so the other tests can't race with it
+#[test]
+fn schedule() {
+    testing::run(|mock| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let admin = testing::admin_user().await;
+        let (user, _) = testing::linked_user(mock).await;
+
+        let regular = json!({
+            "name": "Regular",
+            "periods": [
+                { "period": "1", "name": "Period 1", "start": "08:00", "end": "08:50" },
+                { "period": "2", "name": "Period 2", "start": "09:00", "end": "09:50" },
+                { "period": "lunch", "name": "Lunch", "start": "12:00", "end": "12:30" }
+            ]
+        });
+
+        // Only admins can change the schedule
+        let res: Value = call_and_read_body_json(
+            &app,
+            post("/api/v1/schedule/bells", None, regular.clone()).to_request(),
+        )
+        .await;
+        assert_eq!(res["status"], "Unauthorized", "{}", res);
+
+        let res: Value = call_and_read_body_json(
+            &app,
+            post("/api/v1/schedule/bells", Some(&user), regular.clone()).to_request(),
+        )
+        .await;
+        assert_eq!(res["status"], "Forbidden", "{}", res);
+
+        // Overlapping periods
+        let res: Value = call_and_read_body_json(
+            &app,
+            post(
+                "/api/v1/schedule/bells",
+                Some(&admin),
+                json!({
+                    "name": "Broken",
+                    "periods": [
+                        { "period": "1", "name": "Period 1", "start": "08:00", "end": "09:00" },
+                        { "period": "2", "name": "Period 2", "start": "08:30", "end": "09:30" }
+                    ]
+                }),
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(res["data"], "InvalidSchedule", "{}", res);
+
+        let res: Value = call_and_read_body_json(
+            &app,
+            post(
+                "/api/v1/schedule/bells",
+                Some(&admin),
+                json!({ "id": Uuid::new_v4(), "name": "Missing", "periods": [] }),
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(res["data"], "ScheduleNotFound", "{}", res);
+
+        let res: Value = call_and_read_body_json(
+            &app,
+            post("/api/v1/schedule/bells", Some(&admin), regular).to_request(),
+        )
+        .await;
+        assert_eq!(res["type"], "Success", "{}", res);
+        let regular_id = res["data"]["id"].as_str().unwrap().to_string();
+
+        let res: Value = call_and_read_body_json(
+            &app,
+            post(
+                "/api/v1/schedule/bells",
+                Some(&admin),
+                json!({
+                    "name": "Minimum Day",
+                    "periods": [
+                        { "period": "1", "name": "Period 1", "start": "08:00", "end": "08:30" }
+                    ]
+                }),
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(res["type"], "Success", "{}", res);
+        let minimum_id = res["data"]["id"].as_str().unwrap().to_string();
+
+        let res: Value = call_and_read_body_json(
+            &app,
+            post(
+                "/api/v1/schedule/template",
+                Some(&admin),
+                json!({ "weekdays": [Uuid::new_v4(), null, null, null, null, null, null] }),
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(res["data"], "ScheduleNotFound", "{}", res);
+
+        let res: Value = call_and_read_body_json(&app, post("/api/v1/schedule/template",
+            Some(&admin),
+            json!({ "weekdays": [regular_id, regular_id, regular_id, regular_id, regular_id, null, null] }),
+        ).to_request())
+        .await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        let res: Value =
+            call_and_read_body_json(&app, get("/api/v1/schedule/template", None).to_request())
+                .await;
+        assert_eq!(res["data"]["timezone"], "America/Los_Angeles", "{}", res);
+        assert_eq!(res["data"]["weekdays"][0], regular_id);
+        assert_eq!(res["data"]["weekdays"][5], Value::Null);
+        let schedules = res["data"]["schedules"].as_array().unwrap();
+        let listed = schedules.iter().find(|s| s["id"] == regular_id).unwrap();
+        assert_eq!(listed["periods"][2]["start"], "12:00:00");
+
+        // Start from a clean month (from previous runs)
+        let res: Value = call_and_read_body_json(
+            &app,
+            post(
+                "/api/v1/schedule/overrides/delete",
+                Some(&admin),
+                json!({ "from": "2031-03-01", "to": "2031-03-31" }),
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        let res: Value = call_and_read_body_json(
+            &app,
+            post(
+                "/api/v1/schedule/overrides",
+                Some(&admin),
+                json!({ "from": "2031-03-04", "name": "Minimum Day", "schedule_id": minimum_id }),
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(res["data"]["dates"], 1, "{}", res);
+
+        let res: Value = call_and_read_body_json(
+            &app,
+            post(
+                "/api/v1/schedule/overrides",
+                Some(&admin),
+                json!({ "from": "2031-03-05", "to": "2031-03-06", "name": "Spring Break" }),
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(res["data"]["dates"], 2, "{}", res);
+
+        let res: Value = call_and_read_body_json(
+            &app,
+            post(
+                "/api/v1/schedule/overrides",
+                Some(&admin),
+                json!({ "from": "2031-03-06", "to": "2031-03-05", "name": "Backwards" }),
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(res["data"], "InvalidDateRange", "{}", res);
+
+        let res: Value = call_and_read_body_json(
+            &app,
+            get(
+                "/api/v1/schedule/overrides?from=2031-03-01&to=2031-03-31",
+                None,
+            )
+            .to_request(),
+        )
+        .await;
+        let overrides = res["data"]["overrides"].as_array().unwrap();
+        assert_eq!(overrides.len(), 3, "{}", res);
+        assert_eq!(overrides[0]["schedule_id"], minimum_id);
+        assert_eq!(overrides[1]["schedule_id"], Value::Null);
+
+        // A regular Monday (PST, UTC-8)
+        let res: Value = call_and_read_body_json(
+            &app,
+            get("/api/v1/schedule/day?date=2031-03-03", None).to_request(),
+        )
+        .await;
+        assert_eq!(res["data"]["schedule_id"], regular_id, "{}", res);
+        assert_eq!(res["data"]["name"], Value::Null);
+        assert_eq!(res["data"]["periods"][0]["start"], "2031-03-03T16:00:00Z");
+        assert_eq!(res["data"]["periods"][2]["name"], "Lunch");
+
+        let res: Value = call_and_read_body_json(
+            &app,
+            get("/api/v1/schedule/day?date=2031-03-05", None).to_request(),
+        )
+        .await;
+        assert_eq!(res["data"]["name"], "Spring Break", "{}", res);
+        assert_eq!(res["data"]["schedule_id"], Value::Null);
+        assert_eq!(res["data"]["periods"], json!([]));
+
+        let res: Value = call_and_read_body_json(
+            &app,
+            get("/api/v1/schedule/day?date=2031-03-08", None).to_request(),
+        )
+        .await;
+        assert_eq!(res["data"]["schedule_id"], Value::Null, "{}", res);
+
+        // The user's names and colors replace the defaults
+        let res: Value = call_and_read_body_json(
+            &app,
+            post(
+                "/api/v1/schedule/periods",
+                Some(&admin),
+                json!({ "periods": [{ "period": "1", "name": "Chemistry", "color": "#00AA00" }] }),
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        let res: Value = call_and_read_body_json(
+            &app,
+            get("/api/v1/schedule/now?at=2031-03-03T16:30:00Z", Some(&admin)).to_request(),
+        )
+        .await;
+        assert_eq!(res["data"]["current"]["period"], "1", "{}", res);
+        assert_eq!(res["data"]["current"]["name"], "Chemistry");
+        assert_eq!(res["data"]["current"]["color"], "#00aa00");
+        assert_eq!(res["data"]["next"]["name"], "Period 2");
+        assert_eq!(res["data"]["next"]["start"], "2031-03-03T17:00:00Z");
+
+        let res: Value = call_and_read_body_json(
+            &app,
+            get("/api/v1/schedule/now?at=2031-03-03T16:30:00Z", None).to_request(),
+        )
+        .await;
+        assert_eq!(res["data"]["current"]["name"], "Period 1", "{}", res);
+
+        // After the last period the next one is on the minimum day
+        let res: Value = call_and_read_body_json(
+            &app,
+            get("/api/v1/schedule/now?at=2031-03-03T21:00:00Z", None).to_request(),
+        )
+        .await;
+        assert_eq!(res["data"]["current"], Value::Null, "{}", res);
+        assert_eq!(res["data"]["next"]["start"], "2031-03-04T16:00:00Z");
+        assert_eq!(res["data"]["next"]["end"], "2031-03-04T16:30:00Z");
+
+        // Spring break is skipped
+        let res: Value = call_and_read_body_json(
+            &app,
+            get("/api/v1/schedule/now?at=2031-03-04T17:00:00Z", None).to_request(),
+        )
+        .await;
+        assert_eq!(res["data"]["name"], "Minimum Day", "{}", res);
+        assert_eq!(res["data"]["schedule_id"], minimum_id);
+        assert_eq!(res["data"]["next"]["start"], "2031-03-07T16:00:00Z");
+
+        let res: Value = call_and_read_body_json(
+            &app,
+            post(
+                "/api/v1/schedule/overrides/delete",
+                Some(&admin),
+                json!({ "from": "2031-03-01", "to": "2031-03-31" }),
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(res["data"]["deleted"], 3, "{}", res);
+    });
+}
+
+#[test]
+fn user_periods() {
+    testing::run(|mock| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let (token, _) = testing::linked_user(mock).await;
+
+        let res: Value =
+            call_and_read_body_json(&app, get("/api/v1/schedule/periods", None).to_request()).await;
+        assert_eq!(res["status"], "Unauthorized", "{}", res);
+
+        let res: Value = call_and_read_body_json(
+            &app,
+            post(
+                "/api/v1/schedule/periods",
+                Some(&token),
+                json!({ "periods": [{ "period": "1", "color": "red" }] }),
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(res["data"], "InvalidPeriods", "{}", res);
+
+        let res: Value = call_and_read_body_json(&app, post("/api/v1/schedule/periods",
+            Some(&token),
+            json!({ "periods": [{ "period": "1", "name": "A" }, { "period": "1", "name": "B" }] }),
+        ).to_request())
+        .await;
+        assert_eq!(res["data"], "InvalidPeriods", "{}", res);
+
+        let res: Value = call_and_read_body_json(
+            &app,
+            post(
+                "/api/v1/schedule/periods",
+                Some(&token),
+                json!({ "periods": [
+                { "period": "2", "name": "History" },
+                { "period": "1", "color": "#FFCC00" }
+            ] }),
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        let res: Value = call_and_read_body_json(
+            &app,
+            get("/api/v1/schedule/periods", Some(&token)).to_request(),
+        )
+        .await;
+        assert_eq!(
+            res["data"]["periods"],
+            json!([
+                { "period": "1", "name": null, "color": "#ffcc00" },
+                { "period": "2", "name": "History", "color": null }
+            ]),
+            "{}",
+            res
+        );
+
+        // Replaces all of them
+        let res: Value = call_and_read_body_json(
+            &app,
+            post(
+                "/api/v1/schedule/periods",
+                Some(&token),
+                json!({ "periods": [] }),
+            )
+            .to_request(),
+        )
+        .await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        let res: Value = call_and_read_body_json(
+            &app,
+            get("/api/v1/schedule/periods", Some(&token)).to_request(),
+        )
+        .await;
+        assert_eq!(res["data"]["periods"], json!([]), "{}", res);
+    });
+}
@@ -0,0 +1,72 @@
+//! /docs/api/v1/schedule/update_bells
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    database::get_db_client,
+    utils::{
+        self,
+        schedule::{is_valid_name, is_valid_periods, NewPeriod},
+    },
+    v1::{RequestData, ResponseError},
+    v1_post,
+};
+
+#[derive(Deserialize, JsonSchema)]
+pub struct BellPeriod {
+    pub period: String,
+    pub name: String,
+    pub start: chrono::NaiveTime,
+    pub end: chrono::NaiveTime,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Request {
+    /// The bell schedule to replace (a new one is created without it)
+    pub id: Option<Uuid>,
+    pub name: String,
+    pub periods: Vec<BellPeriod>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    id: Uuid,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[allow(clippy::enum_variant_names)]
+enum Error {
+    InvalidSchedule,
+    ScheduleNotFound,
+    DatabaseError,
+}
+
+async fn post(data: RequestData<Request>) -> Result<Response, ResponseError<Error>> {
+    let req = data.data;
+
+    let periods: Vec<NewPeriod> = req
+        .periods
+        .into_iter()
+        .map(|period| NewPeriod {
+            period: period.period,
+            name: period.name,
+            start: period.start,
+            end: period.end,
+        })
+        .collect();
+
+    if !is_valid_name(&req.name) || !is_valid_periods(&periods) {
+        return Err(ResponseError::ClientError(Error::InvalidSchedule));
+    }
+
+    let schedule = utils::schedule::save_schedule(get_db_client(), req.id, req.name, periods)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?
+        .ok_or(ResponseError::ClientError(Error::ScheduleNotFound))?;
+
+    Ok(Response { id: schedule.id })
+}
+
+v1_post!(post_handler, post, AdminAuth, Request, Response, Error);
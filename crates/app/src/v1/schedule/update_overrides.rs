@@ -0,0 +1,72 @@
+//! /docs/api/v1/schedule/update_overrides
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    database::get_db_client,
+    utils::{
+        self,
+        schedule::{is_valid_name, MAX_RANGE_DAYS},
+    },
+    v1::{RequestData, ResponseError},
+    v1_post,
+};
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Request {
+    pub from: chrono::NaiveDate,
+    /// The last date (defaults to `from`)
+    pub to: Option<chrono::NaiveDate>,
+    pub name: String,
+    /// The bell schedule of the dates (no school without it)
+    pub schedule_id: Option<Uuid>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    dates: u64,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[allow(clippy::enum_variant_names)]
+enum Error {
+    InvalidDateRange,
+    InvalidName,
+    ScheduleNotFound,
+    DatabaseError,
+}
+
+async fn post(data: RequestData<Request>) -> Result<Response, ResponseError<Error>> {
+    let req = data.data;
+    let from = req.from;
+    let to = req.to.unwrap_or(from);
+
+    if from > to || (to - from).num_days() >= MAX_RANGE_DAYS {
+        return Err(ResponseError::ClientError(Error::InvalidDateRange));
+    }
+
+    if !is_valid_name(&req.name) {
+        return Err(ResponseError::ClientError(Error::InvalidName));
+    }
+
+    let db_client = get_db_client();
+
+    if let Some(schedule_id) = req.schedule_id {
+        if !utils::schedule::schedules_exist(db_client, &[schedule_id])
+            .await
+            .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?
+        {
+            return Err(ResponseError::ClientError(Error::ScheduleNotFound));
+        }
+    }
+
+    let dates = utils::schedule::set_overrides(db_client, from, to, req.schedule_id, req.name)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    Ok(Response { dates })
+}
+
+v1_post!(post_handler, post, AdminAuth, Request, Response, Error);
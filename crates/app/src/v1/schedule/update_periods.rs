@@ -0,0 +1,83 @@
+//! /docs/api/v1/schedule/update_periods
+
+use std::collections::HashSet;
+
+use orm::user_periods;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::get_db_client,
+    utils::{
+        self,
+        schedule::{is_valid_color, is_valid_name, MAX_USER_PERIODS},
+    },
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError},
+    v1_post,
+};
+
+#[derive(Deserialize, JsonSchema)]
+pub struct UserPeriod {
+    pub period: String,
+    pub name: Option<String>,
+    pub color: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Request {
+    /// Replaces all of the user's periods
+    pub periods: Vec<UserPeriod>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {}
+
+#[derive(Debug, Serialize, JsonSchema)]
+enum Error {
+    InvalidPeriods,
+    DatabaseError,
+}
+
+async fn post(data: RequestData<Request>) -> Result<Response, ResponseError<Error>> {
+    let id = match data.user {
+        Some(user) => user.id,
+        None => {
+            return Err(ResponseError::RequestError(
+                ErrorResponseStatus::Unauthorized,
+            ))
+        }
+    };
+
+    let periods = data.data.periods;
+
+    let mut seen = HashSet::new();
+    let valid = periods.len() <= MAX_USER_PERIODS
+        && periods.iter().all(|period| {
+            is_valid_name(&period.period)
+                && seen.insert(period.period.as_str())
+                && period.name.as_deref().is_none_or(is_valid_name)
+                && period.color.as_deref().is_none_or(is_valid_color)
+        });
+
+    if !valid {
+        return Err(ResponseError::ClientError(Error::InvalidPeriods));
+    }
+
+    let periods = periods
+        .into_iter()
+        .map(|period| user_periods::Model {
+            user_id: id,
+            period: period.period,
+            name: period.name,
+            color: period.color.map(|color| color.to_ascii_lowercase()),
+        })
+        .collect();
+
+    utils::schedule::set_user_periods(get_db_client(), id, periods)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    Ok(Response {})
+}
+
+v1_post!(post_handler, post, UserAuth, Request, Response, Error);
@@ -0,0 +1,49 @@
+//! /docs/api/v1/schedule/update_template
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    database::get_db_client,
+    utils,
+    v1::{RequestData, ResponseError},
+    v1_post,
+};
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Request {
+    /// The bell schedule of every weekday (Monday first)
+    pub weekdays: [Option<Uuid>; 7],
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {}
+
+#[derive(Debug, Serialize, JsonSchema)]
+enum Error {
+    ScheduleNotFound,
+    DatabaseError,
+}
+
+async fn post(data: RequestData<Request>) -> Result<Response, ResponseError<Error>> {
+    let db_client = get_db_client();
+    let weekdays = data.data.weekdays;
+
+    let ids: Vec<Uuid> = weekdays.iter().flatten().copied().collect();
+
+    if !utils::schedule::schedules_exist(db_client, &ids)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?
+    {
+        return Err(ResponseError::ClientError(Error::ScheduleNotFound));
+    }
+
+    utils::schedule::set_weekdays(db_client, weekdays)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    Ok(Response {})
+}
+
+v1_post!(post_handler, post, AdminAuth, Request, Response, Error);
@@ -0,0 +1,140 @@
+//! /docs/api/v1/schoology/assignments
+
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::get_db_client,
+    schoology::get_schoology_client,
+    utils,
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError, SchoologyRouteError},
+    v1_get,
+};
+
+/// The longest range that can be requested at once (in days)
+const MAX_RANGE_DAYS: i64 = 62;
+/// The range when `to` is not given (in days)
+const DEFAULT_RANGE_DAYS: i64 = 7;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Query {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Serialize, JsonSchema)]
+enum AssignmentType {
+    Assignment,
+    Assessment,
+    Discussion,
+    Other,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Assignment {
+    id: usize,
+    section_id: usize,
+    title: String,
+    description: String,
+    due: Option<NaiveDateTime>,
+    #[serde(rename = "type")]
+    kind: AssignmentType,
+    max_points: Option<f64>,
+    completed: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    assignments: Vec<Assignment>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[allow(clippy::enum_variant_names)]
+enum Error {
+    InvalidDateRange,
+    SchoologyNotLinked,
+    SchoologyError,
+    SchoologyRateLimited,
+    DatabaseError,
+}
+
+impl SchoologyRouteError for Error {
+    const NOT_LINKED: Option<Self> = Some(Error::SchoologyNotLinked);
+    const RATE_LIMITED: Self = Error::SchoologyRateLimited;
+    const FAILED: Self = Error::SchoologyError;
+}
+
+async fn get(data: RequestData<Query>) -> Result<Response, ResponseError<Error>> {
+    let id = match data.user {
+        Some(user) => user.id,
+        None => {
+            return Err(ResponseError::RequestError(
+                ErrorResponseStatus::Unauthorized,
+            ))
+        }
+    };
+
+    // Default to the next week
+    let from = data
+        .data
+        .from
+        .unwrap_or_else(|| chrono::Utc::now().date_naive());
+    let to = data
+        .data
+        .to
+        .unwrap_or(from + Duration::days(DEFAULT_RANGE_DAYS - 1));
+
+    if to < from || to - from >= Duration::days(MAX_RANGE_DAYS) {
+        return Err(ResponseError::ClientError(Error::InvalidDateRange));
+    }
+
+    let db_client = get_db_client();
+
+    // Get the user's Schoology account
+    let account = utils::schoology_link::get_account(db_client, id)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?
+        .ok_or(ResponseError::ClientError(Error::SchoologyNotLinked))?;
+
+    // Fetch the assignments from Schoology
+    let schoology_client = get_schoology_client();
+
+    let assignments = schoology::assignments::get_assignments(
+        schoology_client,
+        &account.tokens,
+        account.schoology_id,
+        from,
+        to,
+    )
+    .await?;
+
+    Ok(Response {
+        assignments: assignments
+            .into_iter()
+            .map(|assignment| Assignment {
+                id: assignment.id,
+                section_id: assignment.section_id,
+                title: assignment.title,
+                description: assignment.description,
+                due: assignment.due,
+                kind: match assignment.kind {
+                    schoology::assignments::AssignmentType::Assignment => {
+                        AssignmentType::Assignment
+                    }
+                    schoology::assignments::AssignmentType::Assessment => {
+                        AssignmentType::Assessment
+                    }
+                    schoology::assignments::AssignmentType::Discussion => {
+                        AssignmentType::Discussion
+                    }
+                    schoology::assignments::AssignmentType::Other => AssignmentType::Other,
+                },
+                max_points: assignment.max_points,
+                completed: assignment.completed,
+            })
+            .collect(),
+    })
+}
+
+v1_get!(get_handler, get, UserAuth, Query, Response, Error);
@@ -0,0 +1,164 @@
+//! /docs/api/v1/schoology/events
+
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use schemars::JsonSchema;
+use schoology::events::{EventRealm, EventTime, RsvpStatus};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::get_db_client,
+    schoology::get_schoology_client,
+    utils,
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError, SchoologyRouteError},
+    v1_get,
+};
+
+/// The longest range that can be requested at once (in days)
+const MAX_RANGE_DAYS: i64 = 62;
+/// The range when `to` is not given (in days)
+const DEFAULT_RANGE_DAYS: i64 = 7;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Query {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Serialize, JsonSchema)]
+enum Realm {
+    Section,
+    Group,
+    School,
+    District,
+    User,
+    Other,
+}
+
+#[derive(Serialize, JsonSchema)]
+enum Rsvp {
+    NotRequested,
+    Pending,
+    Attending,
+    Maybe,
+    NotAttending,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Event {
+    id: usize,
+    title: String,
+    description: String,
+    all_day: bool,
+    start: NaiveDateTime,
+    end: Option<NaiveDateTime>,
+    realm: Realm,
+    realm_id: Option<usize>,
+    rsvp: Rsvp,
+}
+
+impl From<schoology::events::Event> for Event {
+    fn from(event: schoology::events::Event) -> Self {
+        let (realm, realm_id) = match event.realm {
+            EventRealm::Section(id) => (Realm::Section, Some(id)),
+            EventRealm::Group(id) => (Realm::Group, Some(id)),
+            EventRealm::School(id) => (Realm::School, Some(id)),
+            EventRealm::District(id) => (Realm::District, Some(id)),
+            EventRealm::User(id) => (Realm::User, Some(id)),
+            EventRealm::Other => (Realm::Other, None),
+        };
+
+        let (all_day, end) = match event.time {
+            EventTime::AllDay(_) => (true, None),
+            EventTime::Timed { end, .. } => (false, end),
+        };
+
+        Event {
+            id: event.id,
+            start: event.start(),
+            title: event.title,
+            description: event.description,
+            all_day,
+            end,
+            realm,
+            realm_id,
+            rsvp: match event.rsvp {
+                RsvpStatus::NotRequested => Rsvp::NotRequested,
+                RsvpStatus::Pending => Rsvp::Pending,
+                RsvpStatus::Attending => Rsvp::Attending,
+                RsvpStatus::Maybe => Rsvp::Maybe,
+                RsvpStatus::NotAttending => Rsvp::NotAttending,
+            },
+        }
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    events: Vec<Event>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[allow(clippy::enum_variant_names)]
+enum Error {
+    InvalidDateRange,
+    SchoologyNotLinked,
+    SchoologyError,
+    SchoologyRateLimited,
+    DatabaseError,
+}
+
+impl SchoologyRouteError for Error {
+    const NOT_LINKED: Option<Self> = Some(Error::SchoologyNotLinked);
+    const RATE_LIMITED: Self = Error::SchoologyRateLimited;
+    const FAILED: Self = Error::SchoologyError;
+}
+
+async fn get(data: RequestData<Query>) -> Result<Response, ResponseError<Error>> {
+    let id = match data.user {
+        Some(user) => user.id,
+        None => {
+            return Err(ResponseError::RequestError(
+                ErrorResponseStatus::Unauthorized,
+            ))
+        }
+    };
+
+    // Default to the next week
+    let from = data.data.from.unwrap_or_else(utils::schedule::today);
+    let to = data
+        .data
+        .to
+        .unwrap_or(from + Duration::days(DEFAULT_RANGE_DAYS - 1));
+
+    if to < from || to - from >= Duration::days(MAX_RANGE_DAYS) {
+        return Err(ResponseError::ClientError(Error::InvalidDateRange));
+    }
+
+    let db_client = get_db_client();
+
+    // Get the user's Schoology account
+    let account = utils::schoology_link::get_account(db_client, id)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?
+        .ok_or(ResponseError::ClientError(Error::SchoologyNotLinked))?;
+
+    let events = schoology::events::get_all_events(
+        get_schoology_client(),
+        &account.tokens,
+        account.schoology_id,
+        from,
+        to,
+    )
+    .await?;
+
+    Ok(Response {
+        // Due dates are in `/assignments`
+        events: events
+            .into_iter()
+            .filter(|event| !event.is_due_date())
+            .map(Event::from)
+            .collect(),
+    })
+}
+
+v1_get!(get_handler, get, UserAuth, Query, Response, Error);
@@ -0,0 +1,199 @@
+//! /docs/api/v1/schoology/grades
+
+use schemars::JsonSchema;
+use schoology::grades::{GradeException, SectionGrades};
+use serde::Serialize;
+
+use crate::{
+    database::get_db_client,
+    schoology::get_schoology_client,
+    utils,
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError, SchoologyRouteError},
+    v1_get,
+};
+
+#[derive(Serialize, JsonSchema)]
+enum Exception {
+    Excused,
+    Incomplete,
+    Missing,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct AssignmentGrade {
+    assignment_id: usize,
+    category_id: Option<usize>,
+    grade: Option<f64>,
+    max_points: Option<f64>,
+    exception: Option<Exception>,
+    comment: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct CategoryGrade {
+    id: usize,
+    title: String,
+    weight: Option<f64>,
+    grade: Option<f64>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Period {
+    id: String,
+    title: String,
+    grade: Option<f64>,
+    categories: Vec<CategoryGrade>,
+    assignments: Vec<AssignmentGrade>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Section {
+    id: usize,
+    course_title: String,
+    section_title: String,
+    periods: Vec<Period>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    sections: Vec<Section>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[allow(clippy::enum_variant_names)]
+enum Error {
+    SchoologyNotLinked,
+    SchoologyError,
+    SchoologyRateLimited,
+    DatabaseError,
+}
+
+impl SchoologyRouteError for Error {
+    const NOT_LINKED: Option<Self> = Some(Error::SchoologyNotLinked);
+    const RATE_LIMITED: Self = Error::SchoologyRateLimited;
+    const FAILED: Self = Error::SchoologyError;
+}
+
+/// Combines the final grades, the category breakdown and the assignment grades of each period
+fn periods(section: SectionGrades) -> Vec<Period> {
+    let mut periods = section
+        .final_grades
+        .iter()
+        .map(|final_grade| Period {
+            id: final_grade.period_id.clone(),
+            title: String::new(),
+            grade: final_grade.grade,
+            categories: section
+                .categories
+                .iter()
+                .map(|category| CategoryGrade {
+                    id: category.id,
+                    title: category.title.clone(),
+                    weight: category.weight,
+                    grade: final_grade
+                        .categories
+                        .iter()
+                        .find(|grade| grade.category_id == category.id)
+                        .and_then(|grade| grade.grade),
+                })
+                .collect(),
+            assignments: Vec::new(),
+        })
+        .collect::<Vec<Period>>();
+
+    for period_grades in section.periods {
+        let index = match periods.iter().position(|p| p.id == period_grades.period_id) {
+            Some(index) => index,
+            None => {
+                // A period without a final grade (yet)
+                periods.push(Period {
+                    id: period_grades.period_id.clone(),
+                    title: String::new(),
+                    grade: None,
+                    categories: Vec::new(),
+                    assignments: Vec::new(),
+                });
+                periods.len() - 1
+            }
+        };
+
+        let period = &mut periods[index];
+        period.title = period_grades.period_title;
+        period.assignments = period_grades
+            .assignments
+            .into_iter()
+            .map(|grade| AssignmentGrade {
+                assignment_id: grade.assignment_id,
+                category_id: grade.category_id,
+                grade: grade.grade,
+                max_points: grade.max_points,
+                exception: match grade.exception {
+                    GradeException::None => None,
+                    GradeException::Excused => Some(Exception::Excused),
+                    GradeException::Incomplete => Some(Exception::Incomplete),
+                    GradeException::Missing => Some(Exception::Missing),
+                },
+                comment: grade.comment,
+            })
+            .collect();
+    }
+
+    periods
+}
+
+async fn get(data: RequestData<()>) -> Result<Response, ResponseError<Error>> {
+    let id = match data.user {
+        Some(user) => user.id,
+        None => {
+            return Err(ResponseError::RequestError(
+                ErrorResponseStatus::Unauthorized,
+            ))
+        }
+    };
+
+    let db_client = get_db_client();
+
+    // Get the user's Schoology account
+    let account = utils::schoology_link::get_account(db_client, id)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?
+        .ok_or(ResponseError::ClientError(Error::SchoologyNotLinked))?;
+
+    // Fetch the grades and the section names from Schoology
+    let schoology_client = get_schoology_client();
+
+    let (grades, sections) = tokio::try_join!(
+        schoology::grades::get_user_grades(schoology_client, &account.tokens, account.schoology_id),
+        schoology::sections::get_user_sections(
+            schoology_client,
+            &account.tokens,
+            account.schoology_id
+        ),
+    )?;
+
+    let mut grades = grades
+        .into_iter()
+        .map(|grades| {
+            let section = sections
+                .iter()
+                .find(|section| section.id == grades.section_id);
+
+            Section {
+                id: grades.section_id,
+                course_title: section
+                    .map(|section| section.course_title.clone())
+                    .unwrap_or_default(),
+                section_title: section
+                    .map(|section| section.section_title.clone())
+                    .unwrap_or_default(),
+                periods: periods(grades),
+            }
+        })
+        .collect::<Vec<Section>>();
+
+    grades.sort_by(|a, b| a.course_title.cmp(&b.course_title));
+
+    Ok(Response { sections: grades })
+}
+
+v1_get!(get_handler, get, UserAuth, Response, Error);
@@ -1,48 +1,60 @@
 //! /docs/api/v1/schoology/login
 
+use schemars::JsonSchema;
 use schoology::{oauth, users, SchoologyTokenPair};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
     database::get_db_client,
-    schoology::get_schoology_client,
+    schoology::{cache::get_cache, get_schoology_client},
     utils,
-    v1::{RequestData, ResponseError},
+    v1::{client_ip, RequestData, ResponseError, SchoologyRouteError},
     v1_post,
 };
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct Request {
     pub id: Uuid,
     pub signature: String,
     pub login: bool, // The user may need to reauthorize the app but they are already logged in
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct Response {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub session_token: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub session_expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_expires_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
+#[allow(clippy::enum_variant_names)]
 enum Error {
     SchoologyError,
+    SchoologyRateLimited,
     DatabaseError,
     InvalidFlowId,
     InvalidSignature,
     SchoologyApplicationNotAuthorized,
 }
 
+impl SchoologyRouteError for Error {
+    const RATE_LIMITED: Self = Error::SchoologyRateLimited;
+    const FAILED: Self = Error::SchoologyError;
+}
+
 async fn post(req: RequestData<Request>) -> Result<Response, ResponseError<Error>> {
     let schoology_client = get_schoology_client();
 
     let db_client = get_db_client();
 
     // Find the uuid in the database (if not expired)
-    let request_token = utils::schoology_request_tokens::get(&db_client, req.data.id)
+    let request_token = utils::schoology_request_tokens::get(db_client, req.data.id)
         .await
         .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?
         .ok_or(ResponseError::ClientError(Error::InvalidFlowId))?;
@@ -73,27 +85,26 @@ async fn post(req: RequestData<Request>) -> Result<Response, ResponseError<Error
     };
 
     // Get the access token
-    let token = oauth::get_oauth_access_token(&schoology_client, &token)
+    let token = oauth::get_oauth_access_token(schoology_client, &token)
         .await
-        .map_err(|_| ResponseError::ClientError(Error::SchoologyApplicationNotAuthorized))?;
+        .map_err(|err| match err.is_unauthorized() {
+            true => ResponseError::ClientError(Error::SchoologyApplicationNotAuthorized),
+            false => ResponseError::from(err),
+        })?;
 
     // Delete the request token from the database
-    utils::schoology_request_tokens::delete(&db_client, request_token.id)
+    utils::schoology_request_tokens::delete(db_client, request_token.id)
         .await
         .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
 
     // Get the user id and user info
-    let schoology_id = users::get_user_id(&schoology_client, &token)
-        .await
-        .map_err(|_| ResponseError::ClientError(Error::SchoologyError))?;
+    let schoology_id = users::get_user_id(schoology_client, &token).await?;
 
     // Get user info
-    let user_info = users::get_schoology_user(schoology_client, &token, schoology_id)
-        .await
-        .map_err(|_| ResponseError::ClientError(Error::SchoologyError))?;
+    let user_info = users::get_schoology_user(schoology_client, &token, schoology_id).await?;
 
     // Check if there is a user with the same schoology id
-    let link = utils::schoology_link::get(&db_client, schoology_id as i32)
+    let link = utils::schoology_link::get(db_client, schoology_id as i32)
         .await
         .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
 
@@ -104,8 +115,8 @@ async fn post(req: RequestData<Request>) -> Result<Response, ResponseError<Error
 
             // Update the user
             utils::schoology_link::update(
-                &db_client,
-                user.user_id as i32,
+                db_client,
+                user.user_id,
                 Some(user_info.name_first),
                 Some(user_info.name_last),
                 Some(user_info.primary_email),
@@ -124,7 +135,7 @@ async fn post(req: RequestData<Request>) -> Result<Response, ResponseError<Error
 
             // Create a new link
             utils::schoology_link::create(
-                &db_client,
+                db_client,
                 user.id,
                 schoology_id as i32,
                 Some(user_info.name_first),
@@ -139,37 +150,45 @@ async fn post(req: RequestData<Request>) -> Result<Response, ResponseError<Error
     }
     .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
 
+    // The tokens are new, so a user that was backed off can be polled again right away
+    utils::grade_poll::reset(db_client, link.user_id).await.ok();
+    utils::message_poll::reset(db_client, link.user_id)
+        .await
+        .ok();
+
+    // The account may be a different one, or access was granted again after it was removed
+    get_cache().invalidate(link.user_id).await;
+
     let session = match req.data.login {
         true => {
             // User ip
-            let ip = req.http_request.connection_info();
+            let ip = client_ip(&req.http_request);
 
             // Create a new session
-            let session =
-                utils::sessions::create(&db_client, link.user_id as i32, ip.host().to_string())
-                    .await
-                    .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+            let session = utils::sessions::create(db_client, link.user_id, ip)
+                .await
+                .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
 
             Some(session)
         }
         false => None,
     };
 
-    Ok(Response {
-        session_token: match session {
-            Some(ref session) => Some(
-                utils::sessions::encode(utils::sessions::AccessToken::user(
-                    session.id,
-                    session.token.clone(),
-                ))
-                .await
-                .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?,
-            ),
-            None => None,
+    Ok(match session {
+        Some(session) => Response {
+            session_token: Some(session.access_token),
+            session_expires_at: session
+                .session
+                .access_expires_at
+                .map(|expires_at| expires_at.and_utc()),
+            refresh_token: Some(session.refresh_token),
+            refresh_expires_at: Some(session.session.expires_at.and_utc()),
         },
-        session_expires_at: match session {
-            Some(ref session) => Some(session.expires_at.and_utc()),
-            None => None,
+        None => Response {
+            session_token: None,
+            session_expires_at: None,
+            refresh_token: None,
+            refresh_expires_at: None,
         },
     })
 }
@@ -0,0 +1,117 @@
+//! /docs/api/v1/schoology/messages
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use schoology::messages::MessageThread;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::get_db_client,
+    schoology::get_schoology_client,
+    utils,
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError, SchoologyRouteError},
+    v1_get,
+};
+
+#[derive(Deserialize, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Folder {
+    #[default]
+    Inbox,
+    Sent,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Query {
+    #[serde(default)]
+    pub folder: Folder,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Thread {
+    id: usize,
+    subject: String,
+    author_id: usize,
+    recipient_ids: Vec<usize>,
+    last_updated: Option<DateTime<Utc>>,
+    unread: bool,
+}
+
+impl From<MessageThread> for Thread {
+    fn from(thread: MessageThread) -> Self {
+        Thread {
+            id: thread.id,
+            subject: thread.subject,
+            author_id: thread.author_id,
+            recipient_ids: thread.recipient_ids,
+            last_updated: thread.last_updated,
+            unread: thread.unread,
+        }
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    threads: Vec<Thread>,
+    /// Only for the inbox
+    unread_count: Option<usize>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[allow(clippy::enum_variant_names)]
+enum Error {
+    SchoologyNotLinked,
+    SchoologyError,
+    SchoologyRateLimited,
+    DatabaseError,
+}
+
+impl SchoologyRouteError for Error {
+    const NOT_LINKED: Option<Self> = Some(Error::SchoologyNotLinked);
+    const RATE_LIMITED: Self = Error::SchoologyRateLimited;
+    const FAILED: Self = Error::SchoologyError;
+}
+
+async fn get(data: RequestData<Query>) -> Result<Response, ResponseError<Error>> {
+    let id = match data.user {
+        Some(user) => user.id,
+        None => {
+            return Err(ResponseError::RequestError(
+                ErrorResponseStatus::Unauthorized,
+            ))
+        }
+    };
+
+    let db_client = get_db_client();
+
+    // Get the user's Schoology account
+    let account = utils::schoology_link::get_account(db_client, id)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?
+        .ok_or(ResponseError::ClientError(Error::SchoologyNotLinked))?;
+
+    let schoology_client = get_schoology_client();
+
+    if data.data.folder == Folder::Sent {
+        let threads = schoology::messages::get_sent(schoology_client, &account.tokens).await?;
+
+        return Ok(Response {
+            threads: threads.into_iter().map(Thread::from).collect(),
+            unread_count: None,
+        });
+    }
+
+    let threads = schoology::messages::get_inbox(schoology_client, &account.tokens).await?;
+
+    // Keep the count of the notifications up to date, this is not worth failing the request for
+    let _ = utils::message_poll::update_unread_count(db_client, id, &threads).await;
+
+    let unread_count = threads.iter().filter(|thread| thread.unread).count();
+
+    Ok(Response {
+        threads: threads.into_iter().map(Thread::from).collect(),
+        unread_count: Some(unread_count),
+    })
+}
+
+v1_get!(get_handler, get, UserAuth, Query, Response, Error);
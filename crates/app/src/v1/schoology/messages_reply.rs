@@ -0,0 +1,120 @@
+//! /docs/api/v1/schoology/messages_reply
+
+use std::collections::BTreeSet;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::messages_thread::Message;
+use crate::{
+    database::get_db_client,
+    schoology::get_schoology_client,
+    utils,
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError, SchoologyRouteError},
+    v1_post,
+};
+
+/// The longest reply (in characters)
+const MAX_BODY_LENGTH: usize = 10000;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Request {
+    pub thread_id: usize,
+    pub body: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    message: Message,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[allow(clippy::enum_variant_names)]
+enum Error {
+    InvalidBody,
+    MessageNotFound,
+    SchoologyNotLinked,
+    SchoologyError,
+    SchoologyRateLimited,
+    DatabaseError,
+}
+
+impl SchoologyRouteError for Error {
+    const NOT_LINKED: Option<Self> = Some(Error::SchoologyNotLinked);
+    const NOT_FOUND: Option<Self> = Some(Error::MessageNotFound);
+    const RATE_LIMITED: Self = Error::SchoologyRateLimited;
+    const FAILED: Self = Error::SchoologyError;
+}
+
+/// `Re: <subject>`, without stacking them on long threads
+fn reply_subject(subject: &str) -> String {
+    let subject = subject.trim();
+
+    match subject.get(..3) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("re:") => subject.to_string(),
+        _ => format!("Re: {}", subject),
+    }
+}
+
+async fn post(req: RequestData<Request>) -> Result<Response, ResponseError<Error>> {
+    let id = match req.user {
+        Some(user) => user.id,
+        None => {
+            return Err(ResponseError::RequestError(
+                ErrorResponseStatus::Unauthorized,
+            ))
+        }
+    };
+
+    let body = req.data.body.trim();
+
+    if body.is_empty() || body.chars().count() > MAX_BODY_LENGTH {
+        return Err(ResponseError::ClientError(Error::InvalidBody));
+    }
+
+    let db_client = get_db_client();
+
+    // Get the user's Schoology account
+    let account = utils::schoology_link::get_account(db_client, id)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?
+        .ok_or(ResponseError::ClientError(Error::SchoologyNotLinked))?;
+
+    let schoology_client = get_schoology_client();
+
+    // The reply goes to everyone in the thread
+    let messages =
+        schoology::messages::get_thread(schoology_client, &account.tokens, req.data.thread_id)
+            .await?;
+
+    let subject = messages
+        .first()
+        .map(|message| reply_subject(&message.subject))
+        .ok_or(ResponseError::ClientError(Error::MessageNotFound))?;
+
+    let recipient_ids = messages
+        .iter()
+        .flat_map(|message| {
+            std::iter::once(message.author_id).chain(message.recipient_ids.iter().copied())
+        })
+        .filter(|recipient_id| *recipient_id != account.schoology_id)
+        .collect::<BTreeSet<usize>>()
+        .into_iter()
+        .collect::<Vec<usize>>();
+
+    let message = schoology::messages::reply(
+        schoology_client,
+        &account.tokens,
+        req.data.thread_id,
+        &subject,
+        body,
+        &recipient_ids,
+    )
+    .await?;
+
+    Ok(Response {
+        message: message.into(),
+    })
+}
+
+v1_post!(post_handler, post, UserAuth, Request, Response, Error);
@@ -0,0 +1,100 @@
+//! /docs/api/v1/schoology/messages_thread
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::get_db_client,
+    schoology::get_schoology_client,
+    utils,
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError, SchoologyRouteError},
+    v1_get,
+};
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Query {
+    pub id: usize,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub(super) struct Message {
+    pub id: usize,
+    pub author_id: usize,
+    pub recipient_ids: Vec<usize>,
+    pub body: String,
+    pub created: Option<DateTime<Utc>>,
+}
+
+impl From<schoology::messages::Message> for Message {
+    fn from(message: schoology::messages::Message) -> Self {
+        Message {
+            id: message.id,
+            author_id: message.author_id,
+            recipient_ids: message.recipient_ids,
+            body: message.body,
+            created: message.created,
+        }
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    id: usize,
+    subject: String,
+    messages: Vec<Message>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[allow(clippy::enum_variant_names)]
+enum Error {
+    MessageNotFound,
+    SchoologyNotLinked,
+    SchoologyError,
+    SchoologyRateLimited,
+    DatabaseError,
+}
+
+impl SchoologyRouteError for Error {
+    const NOT_LINKED: Option<Self> = Some(Error::SchoologyNotLinked);
+    const NOT_FOUND: Option<Self> = Some(Error::MessageNotFound);
+    const RATE_LIMITED: Self = Error::SchoologyRateLimited;
+    const FAILED: Self = Error::SchoologyError;
+}
+
+async fn get(data: RequestData<Query>) -> Result<Response, ResponseError<Error>> {
+    let id = match data.user {
+        Some(user) => user.id,
+        None => {
+            return Err(ResponseError::RequestError(
+                ErrorResponseStatus::Unauthorized,
+            ))
+        }
+    };
+
+    let db_client = get_db_client();
+
+    // Get the user's Schoology account
+    let account = utils::schoology_link::get_account(db_client, id)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?
+        .ok_or(ResponseError::ClientError(Error::SchoologyNotLinked))?;
+
+    let messages =
+        schoology::messages::get_thread(get_schoology_client(), &account.tokens, data.data.id)
+            .await?;
+
+    // The subject of a thread is the subject of it's first message
+    let subject = messages
+        .first()
+        .map(|message| message.subject.clone())
+        .ok_or(ResponseError::ClientError(Error::MessageNotFound))?;
+
+    Ok(Response {
+        id: data.data.id,
+        subject,
+        messages: messages.into_iter().map(Message::from).collect(),
+    })
+}
+
+v1_get!(get_handler, get, UserAuth, Query, Response, Error);
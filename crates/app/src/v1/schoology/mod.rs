@@ -1,12 +1,30 @@
-use actix_web::web;
+use crate::v1::V1Scope;
 
+mod assignments;
+mod events;
+mod grades;
 mod login;
+mod messages;
+mod messages_reply;
+mod messages_thread;
 mod request_token;
+mod sections;
+#[cfg(test)]
+mod tests;
+mod updates;
 mod user;
 
-pub fn create_schoology_service() -> actix_web::Scope {
-    web::scope("/schoology")
-        .route("/request_token", web::get().to(request_token::get_handler))
-        .route("/login", web::post().to(login::post_handler))
-        .route("/user", web::get().to(user::get_handler))
+pub fn create_schoology_service() -> V1Scope {
+    V1Scope::new("/schoology")
+        .route("/request_token", request_token::route())
+        .route("/login", login::route())
+        .route("/user", user::route())
+        .route("/sections", sections::route())
+        .route("/assignments", assignments::route())
+        .route("/events", events::route())
+        .route("/grades", grades::route())
+        .route("/updates", updates::route())
+        .route("/messages", messages::route())
+        .route("/messages/thread", messages_thread::route())
+        .route("/messages/reply", messages_reply::route())
 }
@@ -1,4 +1,5 @@
 //! /docs/api/v1/schoology/request_token
+use schemars::JsonSchema;
 use schoology::oauth::get_oauth_request_token;
 use serde::Serialize;
 use uuid::Uuid;
@@ -7,11 +8,11 @@ use crate::{
     database::get_db_client,
     schoology::get_schoology_client,
     utils,
-    v1::{RequestData, ResponseError},
+    v1::{RequestData, ResponseError, SchoologyRouteError},
     v1_get,
 };
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 struct Response {
     pub id: Uuid,
     pub signature: String,
@@ -19,24 +20,29 @@ struct Response {
     pub expires_at: chrono::DateTime<chrono::Utc>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
+#[allow(clippy::enum_variant_names)]
 enum Error {
     SchoologyError,
+    SchoologyRateLimited,
     DatabaseError,
 }
 
+impl SchoologyRouteError for Error {
+    const RATE_LIMITED: Self = Error::SchoologyRateLimited;
+    const FAILED: Self = Error::SchoologyError;
+}
+
 async fn get(_: RequestData<()>) -> Result<Response, ResponseError<Error>> {
     let schoology_client = get_schoology_client();
     let db_client = get_db_client();
 
     // Get the request token
-    let request_token = get_oauth_request_token(&schoology_client)
-        .await
-        .map_err(|_| ResponseError::ClientError(Error::SchoologyError))?;
+    let request_token = get_oauth_request_token(schoology_client).await?;
 
     // Create a new entry in the database
     let entry = utils::schoology_request_tokens::create(
-        &db_client,
+        db_client,
         request_token.access_token.clone(),
         request_token.token_secret.clone(),
         request_token.ttl as usize,
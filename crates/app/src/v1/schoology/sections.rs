@@ -0,0 +1,110 @@
+//! /docs/api/v1/schoology/sections
+
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::get_db_client,
+    schoology::{
+        cache::{get_cache, CachePolicy},
+        get_schoology_client,
+    },
+    utils,
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError, SchoologyRouteError},
+    v1_get,
+};
+
+/// Classes only change between semesters
+const CACHE_POLICY: CachePolicy = CachePolicy {
+    ttl: Duration::from_secs(15 * 60),
+    stale: Duration::from_secs(6 * 60 * 60),
+};
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+struct Section {
+    id: usize,
+    course_id: usize,
+    course_title: String,
+    course_code: String,
+    section_title: String,
+    location: String,
+    picture_url: String,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+struct Response {
+    sections: Vec<Section>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[allow(clippy::enum_variant_names)]
+enum Error {
+    SchoologyNotLinked,
+    SchoologyError,
+    SchoologyRateLimited,
+    DatabaseError,
+}
+
+impl SchoologyRouteError for Error {
+    const NOT_LINKED: Option<Self> = Some(Error::SchoologyNotLinked);
+    const RATE_LIMITED: Self = Error::SchoologyRateLimited;
+    const FAILED: Self = Error::SchoologyError;
+}
+
+async fn get(data: RequestData<()>) -> Result<Response, ResponseError<Error>> {
+    let id = match data.user {
+        Some(user) => user.id,
+        None => {
+            return Err(ResponseError::RequestError(
+                ErrorResponseStatus::Unauthorized,
+            ))
+        }
+    };
+
+    let db_client = get_db_client();
+
+    // Get the user's Schoology account
+    let account = utils::schoology_link::get_account(db_client, id)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?
+        .ok_or(ResponseError::ClientError(Error::SchoologyNotLinked))?;
+
+    // Fetch the sections from Schoology (or the cache)
+    let tokens = account.tokens;
+    let schoology_id = account.schoology_id;
+
+    get_cache()
+        .get(id, "sections", CACHE_POLICY, move || async move {
+            let sections = schoology::sections::get_user_sections(
+                get_schoology_client(),
+                &tokens,
+                schoology_id,
+            )
+            .await?;
+
+            // Only the current classes
+            let mut sections = sections
+                .into_iter()
+                .filter(|section| section.active)
+                .map(|section| Section {
+                    id: section.id,
+                    course_id: section.course_id,
+                    course_title: section.course_title,
+                    course_code: section.course_code,
+                    section_title: section.section_title,
+                    location: section.location,
+                    picture_url: section.profile_url,
+                })
+                .collect::<Vec<Section>>();
+
+            sections.sort_by(|a, b| a.course_title.cmp(&b.course_title));
+
+            Ok(Response { sections })
+        })
+        .await
+        .map_err(ResponseError::from)
+}
+
+v1_get!(get_handler, get, UserAuth, Response, Error);
@@ -0,0 +1,861 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use actix_web::{
+    test::{call_and_read_body_json, call_service, init_service, TestRequest},
+    web, App,
+};
+use orm::schoology_link;
+use schoology_mock::{
+    MockAssignment, MockAttachment, MockCategory, MockComment, MockEvent, MockGrade, MockSection,
+    MockThread, MockUpdate, MockUser,
+};
+use sea_orm::{ActiveModelTrait, ActiveValue, EntityTrait};
+use serde_json::{json, Value};
+
+use crate::{
+    database::get_db_client,
+    schoology::cache::{get_cache, CachePolicy},
+    testing, utils,
+    v1::create_v1_service,
+};
+
+#[test]
+fn login_flow() {
+    testing::run(|mock| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let schoology_id = testing::new_schoology_id();
+        mock.add_user(MockUser::new(schoology_id));
+
+        // Start the flow
+        let req = TestRequest::get()
+            .uri("/api/v1/schoology/request_token")
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        let flow = &res["data"];
+
+        // Login before the user authorized the app
+        let req = TestRequest::post()
+            .uri("/api/v1/schoology/login")
+            .set_json(json!({ "id": flow["id"], "signature": flow["signature"], "login": true }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"], "SchoologyApplicationNotAuthorized", "{}", res);
+
+        assert!(mock.authorize(flow["access_token"].as_str().unwrap(), schoology_id));
+
+        // A bad signature
+        let req = TestRequest::post()
+            .uri("/api/v1/schoology/login")
+            .set_json(json!({ "id": flow["id"], "signature": "invalid", "login": true }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"], "InvalidSignature", "{}", res);
+
+        // Login
+        let req = TestRequest::post()
+            .uri("/api/v1/schoology/login")
+            .set_json(json!({ "id": flow["id"], "signature": flow["signature"], "login": true }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        let token = res["data"]["session_token"].as_str().unwrap().to_string();
+
+        // The flow can only be used once
+        let req = TestRequest::post()
+            .uri("/api/v1/schoology/login")
+            .set_json(json!({ "id": flow["id"], "signature": flow["signature"], "login": true }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"], "InvalidFlowId", "{}", res);
+
+        // Get the user
+        let req = TestRequest::get()
+            .uri("/api/v1/schoology/user")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+        assert_eq!(res["data"]["last_name"], format!("User {}", schoology_id));
+
+        // The user removed the app from Schoology, the profile is cached for a while
+        mock.revoke(schoology_id);
+
+        let req = TestRequest::get()
+            .uri("/api/v1/schoology/user")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        let link = utils::schoology_link::get(get_db_client(), schoology_id as i32)
+            .await
+            .unwrap()
+            .unwrap();
+        get_cache().invalidate(link.user_id).await;
+
+        let req = TestRequest::get()
+            .uri("/api/v1/schoology/user")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"], "SchoologyNotLinked", "{}", res);
+    });
+}
+
+#[test]
+fn user_requires_session() {
+    testing::run(|mock| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let req = TestRequest::get()
+            .uri("/api/v1/schoology/user")
+            .to_request();
+        let res = call_service(&app, req).await;
+
+        assert_eq!(res.status(), 401);
+
+        // Sessions from before refresh tokens have no access token expiry
+        let (token, _) = testing::linked_user(mock).await;
+        let session = utils::sessions::verify(get_db_client(), &token)
+            .await
+            .unwrap()
+            .unwrap();
+
+        orm::sessions::ActiveModel {
+            id: ActiveValue::Set(session.id),
+            access_expires_at: ActiveValue::Set(None),
+            ..Default::default()
+        }
+        .update(get_db_client())
+        .await
+        .unwrap();
+
+        let req = TestRequest::get()
+            .uri("/api/v1/schoology/user")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res = call_service(&app, req).await;
+
+        assert_eq!(res.status(), 401);
+    });
+}
+
+#[test]
+fn cached_user() {
+    testing::run(|mock| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let (token, schoology_id) = testing::linked_user(mock).await;
+
+        let req = TestRequest::get()
+            .uri("/api/v1/schoology/user")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+        assert_eq!(res["data"]["first_name"], "Test");
+
+        // The change only shows up once the cached profile is dropped
+        let mut user = MockUser::new(schoology_id);
+        user.name_first = "Renamed".to_string();
+        mock.add_user(user);
+
+        let req = TestRequest::get()
+            .uri("/api/v1/schoology/user")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"]["first_name"], "Test", "{}", res);
+
+        let link = utils::schoology_link::get(get_db_client(), schoology_id as i32)
+            .await
+            .unwrap()
+            .unwrap();
+        get_cache().invalidate(link.user_id).await;
+
+        let req = TestRequest::get()
+            .uri("/api/v1/schoology/user")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"]["first_name"], "Renamed", "{}", res);
+    });
+}
+
+#[test]
+fn cache_coalesces_requests() {
+    testing::run(|_| async move {
+        let user_id = testing::new_schoology_id() as i32;
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let policy = CachePolicy {
+            ttl: Duration::from_secs(60),
+            stale: Duration::from_secs(60),
+        };
+
+        let get = || {
+            let fetches = fetches.clone();
+
+            get_cache().get(user_id, "test", policy, move || async move {
+                fetches.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(50)).await;
+
+                Ok(42)
+            })
+        };
+
+        let (a, b) = futures::join!(get(), get());
+        assert_eq!(a.unwrap(), 42);
+        assert_eq!(b.unwrap(), 42);
+        assert_eq!(get().await.unwrap(), 42);
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        // Stale entries are returned while they are refreshed
+        let stale = CachePolicy {
+            ttl: Duration::ZERO,
+            stale: Duration::from_secs(60),
+        };
+        let fetches_ = fetches.clone();
+        let value = get_cache()
+            .get(user_id, "test", stale, move || async move {
+                fetches_.fetch_add(1, Ordering::SeqCst);
+
+                Ok(43)
+            })
+            .await
+            .unwrap();
+        assert_eq!(value, 42);
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+        assert_eq!(get().await.unwrap(), 43);
+    });
+}
+
+#[test]
+fn sections() {
+    testing::run(|mock| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let (token, schoology_id) = testing::linked_user(mock).await;
+
+        let base = testing::new_schoology_id();
+        let mut past = MockSection::new(base + 2, base + 102);
+        past.active = false;
+
+        mock.add_section(MockSection::new(base, base + 100));
+        mock.add_section(MockSection::new(base + 1, base + 101));
+        mock.add_section(past);
+        mock.enroll(base, schoology_id, false);
+        mock.enroll(base + 2, schoology_id, false);
+
+        let req = TestRequest::get()
+            .uri("/api/v1/schoology/sections")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        let sections = res["data"]["sections"].as_array().unwrap();
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0]["id"], base);
+        assert_eq!(
+            sections[0]["course_title"],
+            format!("Course {}", base + 100)
+        );
+    });
+}
+
+#[test]
+fn assignments() {
+    testing::run(|mock| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let (token, schoology_id) = testing::linked_user(mock).await;
+
+        let section = testing::new_schoology_id();
+        mock.add_section(MockSection::new(section, section + 100));
+        mock.enroll(section, schoology_id, false);
+
+        mock.add_assignment(MockAssignment::new(section, section, "2023-10-20 23:59:00"));
+        mock.add_assignment(MockAssignment::new(
+            section + 1,
+            section,
+            "2023-11-20 23:59:00",
+        ));
+
+        let req = TestRequest::get()
+            .uri("/api/v1/schoology/assignments?from=2023-10-16&to=2023-10-22")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        let assignments = res["data"]["assignments"].as_array().unwrap();
+        assert_eq!(assignments.len(), 1);
+        assert_eq!(assignments[0]["id"], section);
+        assert_eq!(assignments[0]["due"], "2023-10-20T23:59:00");
+        assert_eq!(assignments[0]["type"], "Assignment");
+
+        // `to` before `from`
+        let req = TestRequest::get()
+            .uri("/api/v1/schoology/assignments?from=2023-10-16&to=2023-10-15")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"], "InvalidDateRange", "{}", res);
+
+        // Not a date
+        let req = TestRequest::get()
+            .uri("/api/v1/schoology/assignments?from=tomorrow")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["status"], "BadRequest", "{}", res);
+    });
+}
+
+#[test]
+fn events() {
+    testing::run(|mock| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let (token, schoology_id) = testing::linked_user(mock).await;
+
+        let section = testing::new_schoology_id();
+        mock.add_section(MockSection::new(section, section + 100));
+        mock.enroll(section, schoology_id, false);
+
+        // Far in the future, school wide events are on every user's calendar
+        let mut game = MockEvent::new(section, Some(section), "2031-05-07 18:00:00");
+        game.end = "2031-05-07 20:00:00".to_string();
+        game.rsvp = true;
+        game.rsvp_status = "attending".to_string();
+        mock.add_event(game);
+
+        let mut rally = MockEvent::new(section + 1, None, "2031-05-08 00:00:00");
+        rally.all_day = true;
+        mock.add_event(rally);
+
+        mock.add_assignment(MockAssignment::new(section, section, "2031-05-06 23:59:00"));
+
+        let req = TestRequest::get()
+            .uri("/api/v1/schoology/events?from=2031-05-05&to=2031-05-11")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        // The due date is not an event
+        let events = res["data"]["events"].as_array().unwrap();
+        let events = events
+            .iter()
+            .filter(|event| {
+                [section, section + 1].contains(&(event["id"].as_u64().unwrap() as usize))
+            })
+            .collect::<Vec<&Value>>();
+        assert_eq!(events.len(), 2, "{}", res);
+
+        assert_eq!(events[0]["start"], "2031-05-07T18:00:00");
+        assert_eq!(events[0]["end"], "2031-05-07T20:00:00");
+        assert_eq!(events[0]["all_day"], false);
+        assert_eq!(events[0]["realm"], "Section");
+        assert_eq!(events[0]["realm_id"], section);
+        assert_eq!(events[0]["rsvp"], "Attending");
+
+        assert_eq!(events[1]["all_day"], true);
+        assert_eq!(events[1]["start"], "2031-05-08T00:00:00");
+        assert_eq!(events[1]["realm"], "School");
+        assert_eq!(events[1]["rsvp"], "NotRequested");
+
+        let req = TestRequest::get()
+            .uri("/api/v1/schoology/events?from=2031-05-05&to=2031-08-05")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"], "InvalidDateRange", "{}", res);
+    });
+}
+
+#[test]
+fn grades() {
+    testing::run(|mock| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let (token, schoology_id) = testing::linked_user(mock).await;
+
+        let section = testing::new_schoology_id();
+        mock.add_section(MockSection::new(section, section + 100));
+        mock.enroll(section, schoology_id, false);
+
+        mock.add_category(MockCategory {
+            id: 1,
+            section_id: section,
+            title: "Homework".to_string(),
+            weight: Some(40.0),
+        });
+        mock.add_category(MockCategory {
+            id: 2,
+            section_id: section,
+            title: "Tests".to_string(),
+            weight: Some(60.0),
+        });
+
+        mock.add_grade(MockGrade::new(schoology_id, section, 1, 1, 9.0, 10.0));
+        mock.add_grade(MockGrade::new(schoology_id, section, 2, 2, 45.0, 50.0));
+
+        let req = TestRequest::get()
+            .uri("/api/v1/schoology/grades")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        let section = &res["data"]["sections"][0];
+        assert_eq!(
+            section["course_title"],
+            format!("Course {}", section["id"].as_u64().unwrap() + 100)
+        );
+
+        let period = &section["periods"][0];
+        assert_eq!(period["title"], "Quarter 1");
+        assert_eq!(period["grade"], 90.0);
+        assert_eq!(period["categories"][0]["title"], "Homework");
+        assert_eq!(period["categories"][0]["grade"], 90.0);
+        assert_eq!(period["categories"][1]["weight"], 60.0);
+        assert_eq!(period["assignments"].as_array().unwrap().len(), 2);
+    });
+}
+
+#[test]
+fn updates() {
+    testing::run(|mock| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let (token, schoology_id) = testing::linked_user(mock).await;
+
+        let section = testing::new_schoology_id();
+        mock.add_section(MockSection::new(section, section + 100));
+        mock.enroll(section, schoology_id, false);
+
+        let mut announcement = MockUpdate::new(section, Some(section), section + 1, 1_697_900_000);
+        announcement.attachments = vec![MockAttachment {
+            id: section,
+            kind: "link".to_string(),
+            title: "Study guide".to_string(),
+            url: "https://example.com/study-guide".to_string(),
+        }];
+        announcement.comments = vec![MockComment {
+            id: section,
+            author_id: schoology_id,
+            body: "Thank you!".to_string(),
+            created: 1_697_900_100,
+        }];
+        mock.add_update(announcement);
+        mock.add_update(MockUpdate::new(
+            section + 1,
+            Some(section),
+            section + 1,
+            1_697_800_000,
+        ));
+
+        let req = TestRequest::get()
+            .uri(&format!(
+                "/api/v1/schoology/updates?section_id={}&limit=1&comments=true",
+                section
+            ))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        let update = &res["data"]["updates"][0];
+        assert_eq!(update["id"], section);
+        assert_eq!(update["realm"], "Section");
+        assert_eq!(update["realm_id"], section);
+        assert_eq!(update["created"], "2023-10-21T14:53:20Z");
+        assert_eq!(update["attachments"][0]["type"], "Link");
+        assert_eq!(
+            update["attachments"][0]["url"],
+            "https://example.com/study-guide"
+        );
+        assert_eq!(update["comments"][0]["body"], "Thank you!");
+        assert_eq!(update["comments"][0]["author_id"], schoology_id);
+        assert_eq!(res["data"]["next_start"], 1);
+
+        let req = TestRequest::get()
+            .uri(&format!(
+                "/api/v1/schoology/updates?section_id={}&start=1&limit=1",
+                section
+            ))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"]["updates"][0]["id"], section + 1, "{}", res);
+        assert!(res["data"]["updates"][0]["comments"].is_null());
+        assert!(res["data"]["next_start"].is_null());
+
+        // The feed of every section
+        let req = TestRequest::get()
+            .uri("/api/v1/schoology/updates?limit=50")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+        assert!(res["data"]["updates"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|update| update["id"] == section + 1));
+
+        let req = TestRequest::get()
+            .uri("/api/v1/schoology/updates?limit=51")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"], "InvalidLimit", "{}", res);
+
+        let req = TestRequest::get()
+            .uri(&format!(
+                "/api/v1/schoology/updates?section_id={}",
+                section + 2
+            ))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"], "SectionNotFound", "{}", res);
+    });
+}
+
+#[test]
+fn messages() {
+    testing::run(|mock| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let (token, schoology_id) = testing::linked_user(mock).await;
+        let (teacher, classmate) = (testing::new_schoology_id(), testing::new_schoology_id());
+
+        let mut thread = MockThread::new(
+            testing::new_schoology_id(),
+            teacher,
+            vec![schoology_id, classmate],
+            1_697_900_000,
+        );
+        thread.subject = "Group project".to_string();
+        let thread_id = thread.id;
+        mock.add_thread(thread);
+
+        let req = TestRequest::get()
+            .uri("/api/v1/schoology/messages")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+        assert_eq!(res["data"]["unread_count"], 1);
+
+        let thread = &res["data"]["threads"][0];
+        assert_eq!(thread["id"], thread_id);
+        assert_eq!(thread["subject"], "Group project");
+        assert_eq!(thread["author_id"], teacher);
+        assert_eq!(thread["last_updated"], "2023-10-21T14:53:20Z");
+        assert_eq!(thread["unread"], true);
+
+        let req = TestRequest::get()
+            .uri(&format!(
+                "/api/v1/schoology/messages/thread?id={}",
+                thread_id
+            ))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+        assert_eq!(res["data"]["subject"], "Group project");
+        assert_eq!(res["data"]["messages"][0]["author_id"], teacher);
+        assert_eq!(
+            res["data"]["messages"][0]["body"],
+            format!("Body of message {}", thread_id)
+        );
+
+        // Reading the thread marked it as read
+        let req = TestRequest::get()
+            .uri("/api/v1/schoology/messages?folder=inbox")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"]["unread_count"], 0, "{}", res);
+
+        let req = TestRequest::post()
+            .uri("/api/v1/schoology/messages/reply")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "thread_id": thread_id, "body": "  " }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"], "InvalidBody", "{}", res);
+
+        let req = TestRequest::post()
+            .uri("/api/v1/schoology/messages/reply")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "thread_id": thread_id, "body": "I can do the slides." }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+        assert_eq!(res["data"]["message"]["author_id"], schoology_id);
+        assert_eq!(res["data"]["message"]["body"], "I can do the slides.");
+
+        // The reply is sent to everyone else in the thread
+        let thread = mock.thread(thread_id).unwrap();
+        assert_eq!(thread.messages.len(), 2);
+        assert!(thread.unread.contains(&teacher));
+        assert!(thread.unread.contains(&classmate));
+
+        let req = TestRequest::post()
+            .uri("/api/v1/schoology/messages/reply")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "thread_id": testing::new_schoology_id(), "body": "Hello?" }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"], "MessageNotFound", "{}", res);
+
+        let req = TestRequest::get()
+            .uri("/api/v1/schoology/messages?folder=sent")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+        assert!(res["data"]["unread_count"].is_null());
+        assert_eq!(res["data"]["threads"], json!([]));
+    });
+}
+
+/// Key rotation goes through every link, so the tests that change the stored links don't run at the same time
+static LINKS: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Creates a link with tokens for a new user, returns the user id
+async fn new_link(access_token: &str, token_secret: &str) -> i32 {
+    let db_client = get_db_client();
+
+    let user = utils::users::create(db_client).await.unwrap();
+
+    utils::schoology_link::create(
+        db_client,
+        user.id,
+        testing::new_schoology_id() as i32,
+        None,
+        None,
+        None,
+        None,
+        Some(access_token.to_string()),
+        Some(token_secret.to_string()),
+    )
+    .await
+    .unwrap();
+
+    user.id
+}
+
+/// Gets a link as it is stored
+async fn stored_link(user_id: i32) -> schoology_link::Model {
+    schoology_link::Entity::find_by_id(user_id)
+        .one(get_db_client())
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[test]
+fn encrypted_tokens() {
+    testing::run(|_| async move {
+        let _links = LINKS.lock().await;
+        let db_client = get_db_client();
+
+        let user_id = new_link("access", "secret").await;
+
+        let stored = stored_link(user_id).await;
+        assert!(stored.key_id.is_some());
+        assert!(stored.wrapped_key.is_some());
+        assert_ne!(stored.access_token.as_deref(), Some("access"));
+        assert_ne!(stored.token_secret.as_deref(), Some("secret"));
+
+        let link = utils::schoology_link::get_by_user_id(db_client, user_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(link.access_token.as_deref(), Some("access"));
+        assert_eq!(link.token_secret.as_deref(), Some("secret"));
+
+        // The token that isn't changed is kept
+        utils::schoology_link::update(
+            db_client,
+            user_id,
+            None,
+            None,
+            None,
+            None,
+            Some("new access".to_string()),
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_ne!(stored_link(user_id).await.wrapped_key, stored.wrapped_key);
+
+        let link = utils::schoology_link::get_by_user_id(db_client, user_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(link.access_token.as_deref(), Some("new access"));
+        assert_eq!(link.token_secret.as_deref(), Some("secret"));
+    });
+}
+
+#[test]
+fn encrypted_tokens_bound_to_row() {
+    testing::run(|_| async move {
+        let _links = LINKS.lock().await;
+        let db_client = get_db_client();
+
+        let first = new_link("first access", "first secret").await;
+        let second = new_link("second access", "second secret").await;
+        let stored = stored_link(first).await;
+
+        // Moved to another column
+        schoology_link::ActiveModel {
+            user_id: ActiveValue::Set(first),
+            token_secret: ActiveValue::Set(stored.access_token.clone()),
+            ..Default::default()
+        }
+        .update(db_client)
+        .await
+        .unwrap();
+
+        assert!(utils::schoology_link::get_by_user_id(db_client, first)
+            .await
+            .is_err());
+
+        // Moved to another row (with or without the data key)
+        schoology_link::ActiveModel {
+            user_id: ActiveValue::Set(second),
+            access_token: ActiveValue::Set(stored.access_token.clone()),
+            ..Default::default()
+        }
+        .update(db_client)
+        .await
+        .unwrap();
+
+        assert!(utils::schoology_link::get_by_user_id(db_client, second)
+            .await
+            .is_err());
+
+        schoology_link::ActiveModel {
+            user_id: ActiveValue::Set(second),
+            access_token: ActiveValue::Set(stored.access_token),
+            token_secret: ActiveValue::Set(stored.token_secret),
+            key_id: ActiveValue::Set(stored.key_id),
+            wrapped_key: ActiveValue::Set(stored.wrapped_key),
+            ..Default::default()
+        }
+        .update(db_client)
+        .await
+        .unwrap();
+
+        assert!(utils::schoology_link::get_by_user_id(db_client, second)
+            .await
+            .is_err());
+
+        // Key rotation must not trip over the broken links
+        for user_id in [first, second] {
+            schoology_link::Entity::delete_by_id(user_id)
+                .exec(db_client)
+                .await
+                .unwrap();
+        }
+    });
+}
+
+#[test]
+fn rotate_keys() {
+    testing::run(|_| async move {
+        let _links = LINKS.lock().await;
+        let db_client = get_db_client();
+
+        let user_id = new_link("access", "secret").await;
+        let stored = stored_link(user_id).await;
+
+        let count = utils::schoology_link::rotate_keys(db_client).await.unwrap();
+        assert!(count >= 1);
+
+        let rotated = stored_link(user_id).await;
+        assert_ne!(rotated.wrapped_key, stored.wrapped_key);
+        assert_ne!(rotated.access_token, stored.access_token);
+        assert_ne!(rotated.token_secret, stored.token_secret);
+
+        let link = utils::schoology_link::get_by_user_id(db_client, user_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(link.access_token.as_deref(), Some("access"));
+        assert_eq!(link.token_secret.as_deref(), Some("secret"));
+    });
+}
+
+#[test]
+fn encrypt_legacy_links() {
+    testing::run(|_| async move {
+        let _links = LINKS.lock().await;
+        let db_client = get_db_client();
+
+        let user = utils::users::create(db_client).await.unwrap();
+
+        // A link from before tokens were encrypted
+        schoology_link::ActiveModel {
+            user_id: ActiveValue::Set(user.id),
+            schoology_id: ActiveValue::Set(testing::new_schoology_id() as i32),
+            first_name: ActiveValue::Set(None),
+            last_name: ActiveValue::Set(None),
+            email: ActiveValue::Set(None),
+            picture_url: ActiveValue::Set(None),
+            access_token: ActiveValue::Set(Some("access".to_string())),
+            token_secret: ActiveValue::Set(Some("secret".to_string())),
+            key_id: ActiveValue::Set(None),
+            wrapped_key: ActiveValue::Set(None),
+        }
+        .insert(db_client)
+        .await
+        .unwrap();
+
+        let count = utils::schoology_link::encrypt_all_legacy(db_client)
+            .await
+            .unwrap();
+        assert!(count >= 1);
+
+        let stored = stored_link(user.id).await;
+        assert!(stored.key_id.is_some());
+        assert_ne!(stored.access_token.as_deref(), Some("access"));
+        assert_ne!(stored.token_secret.as_deref(), Some("secret"));
+
+        let link = utils::schoology_link::get_by_user_id(db_client, user.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(link.access_token.as_deref(), Some("access"));
+        assert_eq!(link.token_secret.as_deref(), Some("secret"));
+    });
+}
@@ -0,0 +1,257 @@
+//! /docs/api/v1/schoology/updates
+
+use chrono::{DateTime, Utc};
+use futures::future::try_join_all;
+use schemars::JsonSchema;
+use schoology::{
+    updates::{AttachmentKind, UpdateRealm},
+    SchoologyError,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::get_db_client,
+    schoology::get_schoology_client,
+    utils,
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError, SchoologyRouteError},
+    v1_get,
+};
+
+/// The page size when `limit` is not given
+const DEFAULT_LIMIT: usize = 20;
+/// The largest page that can be requested (comments are fetched for every update)
+const MAX_LIMIT: usize = 50;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Query {
+    /// Only the updates of a section
+    pub section_id: Option<usize>,
+    pub start: Option<usize>,
+    pub limit: Option<usize>,
+    /// Whether to include the comments of every update
+    #[serde(default)]
+    pub comments: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+enum Realm {
+    Section,
+    Group,
+    School,
+    User,
+    Other,
+}
+
+#[derive(Serialize, JsonSchema)]
+enum AttachmentType {
+    File,
+    Link,
+    Video,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Attachment {
+    id: usize,
+    #[serde(rename = "type")]
+    kind: AttachmentType,
+    title: String,
+    url: String,
+    filename: Option<String>,
+    size: Option<usize>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Comment {
+    id: usize,
+    author_id: usize,
+    body: String,
+    created: Option<DateTime<Utc>>,
+    likes: usize,
+    parent_id: Option<usize>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Update {
+    id: usize,
+    author_id: usize,
+    body: String,
+    created: Option<DateTime<Utc>>,
+    last_updated: Option<DateTime<Utc>>,
+    realm: Realm,
+    realm_id: Option<usize>,
+    likes: usize,
+    liked: bool,
+    comment_count: usize,
+    attachments: Vec<Attachment>,
+    /// Only when the comments were requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comments: Option<Vec<Comment>>,
+}
+
+impl Update {
+    fn new(update: schoology::updates::Update, comments: Option<Vec<Comment>>) -> Self {
+        let (realm, realm_id) = match update.realm {
+            UpdateRealm::Section(id) => (Realm::Section, Some(id)),
+            UpdateRealm::Group(id) => (Realm::Group, Some(id)),
+            UpdateRealm::School(id) => (Realm::School, Some(id)),
+            UpdateRealm::User(id) => (Realm::User, Some(id)),
+            UpdateRealm::Other => (Realm::Other, None),
+        };
+
+        Update {
+            id: update.id,
+            author_id: update.author_id,
+            body: update.body,
+            created: update.created,
+            last_updated: update.last_updated,
+            realm,
+            realm_id,
+            likes: update.likes,
+            liked: update.liked,
+            comment_count: update.comment_count,
+            attachments: update
+                .attachments
+                .into_iter()
+                .map(|attachment| Attachment {
+                    id: attachment.id,
+                    kind: match attachment.kind {
+                        AttachmentKind::File => AttachmentType::File,
+                        AttachmentKind::Link => AttachmentType::Link,
+                        AttachmentKind::Video => AttachmentType::Video,
+                    },
+                    title: attachment.title,
+                    url: attachment.url,
+                    filename: attachment.filename,
+                    size: attachment.size,
+                })
+                .collect(),
+            comments,
+        }
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    updates: Vec<Update>,
+    /// The `start` of the next page, `null` on the last page
+    next_start: Option<usize>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[allow(clippy::enum_variant_names)]
+enum Error {
+    InvalidLimit,
+    SectionNotFound,
+    SchoologyNotLinked,
+    SchoologyError,
+    SchoologyRateLimited,
+    DatabaseError,
+}
+
+impl SchoologyRouteError for Error {
+    const NOT_LINKED: Option<Self> = Some(Error::SchoologyNotLinked);
+    const NOT_FOUND: Option<Self> = Some(Error::SectionNotFound);
+    const RATE_LIMITED: Self = Error::SchoologyRateLimited;
+    const FAILED: Self = Error::SchoologyError;
+}
+
+async fn get(data: RequestData<Query>) -> Result<Response, ResponseError<Error>> {
+    let id = match data.user {
+        Some(user) => user.id,
+        None => {
+            return Err(ResponseError::RequestError(
+                ErrorResponseStatus::Unauthorized,
+            ))
+        }
+    };
+
+    let start = data.data.start.unwrap_or(0);
+    let limit = data.data.limit.unwrap_or(DEFAULT_LIMIT);
+
+    if limit == 0 || limit > MAX_LIMIT {
+        return Err(ResponseError::ClientError(Error::InvalidLimit));
+    }
+
+    let db_client = get_db_client();
+
+    // Get the user's Schoology account
+    let account = utils::schoology_link::get_account(db_client, id)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?
+        .ok_or(ResponseError::ClientError(Error::SchoologyNotLinked))?;
+
+    let schoology_client = get_schoology_client();
+
+    let page = match data.data.section_id {
+        Some(section_id) => {
+            schoology::updates::get_section_updates(
+                schoology_client,
+                &account.tokens,
+                section_id,
+                start,
+                limit,
+            )
+            .await
+        }
+        None => {
+            schoology::updates::get_recent_updates(schoology_client, &account.tokens, start, limit)
+                .await
+        }
+    }?;
+
+    let next_start = page.has_more.then_some(start + page.items.len());
+
+    // Every update at once, updates without comments don't need a request
+    let comments = if data.data.comments {
+        try_join_all(page.items.iter().map(|update| async {
+            if update.comment_count == 0 {
+                return Ok(Some(Vec::new()));
+            }
+
+            let comments = schoology::updates::get_update_comments(
+                schoology_client,
+                &account.tokens,
+                update.realm,
+                update.id,
+            )
+            .await?;
+
+            Ok::<_, SchoologyError>(Some(
+                comments
+                    .into_iter()
+                    .map(|comment| Comment {
+                        id: comment.id,
+                        author_id: comment.author_id,
+                        body: comment.body,
+                        created: comment.created,
+                        likes: comment.likes,
+                        parent_id: comment.parent_id,
+                    })
+                    .collect(),
+            ))
+        }))
+        .await
+        .map_err(|err| {
+            // The update was deleted since the page was fetched
+            if err.is_not_found() {
+                ResponseError::ServerError(Error::SchoologyError)
+            } else {
+                ResponseError::from(err)
+            }
+        })?
+    } else {
+        page.items.iter().map(|_| None).collect()
+    };
+
+    Ok(Response {
+        updates: page
+            .items
+            .into_iter()
+            .zip(comments)
+            .map(|(update, comments)| Update::new(update, comments))
+            .collect(),
+        next_start,
+    })
+}
+
+v1_get!(get_handler, get, UserAuth, Query, Response, Error);
@@ -1,28 +1,49 @@
 //! /docs/api/v1/schoology/user
 
-use serde::Serialize;
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     database::get_db_client,
-    schoology::get_schoology_client,
+    schoology::{
+        cache::{get_cache, CachePolicy},
+        get_schoology_client,
+    },
     utils,
-    v1::{types::ErrorResponseStatus, RequestData, ResponseError},
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError, SchoologyRouteError},
     v1_get,
 };
 
-#[derive(Serialize)]
+/// Names and pictures rarely change
+const CACHE_POLICY: CachePolicy = CachePolicy {
+    ttl: Duration::from_secs(60 * 60),
+    stale: Duration::from_secs(24 * 60 * 60),
+};
+
+#[derive(Serialize, Deserialize, JsonSchema)]
 struct Response {
     first_name: String,
     last_name: String,
     picture_url: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
+#[allow(clippy::enum_variant_names)]
 enum Error {
     SchoologyNotLinked,
+    SchoologyError,
+    SchoologyRateLimited,
     DatabaseError,
 }
 
+impl SchoologyRouteError for Error {
+    const NOT_LINKED: Option<Self> = Some(Error::SchoologyNotLinked);
+    const RATE_LIMITED: Self = Error::SchoologyRateLimited;
+    const FAILED: Self = Error::SchoologyError;
+}
+
 async fn get(data: RequestData<()>) -> Result<Response, ResponseError<Error>> {
     let id = match data.user {
         Some(user) => user.id,
@@ -35,35 +56,30 @@ async fn get(data: RequestData<()>) -> Result<Response, ResponseError<Error>> {
 
     let db_client = get_db_client();
 
-    // Fetch the user from the database
-    let user = utils::schoology_link::get_by_user_id(db_client, id)
+    // Get the user's Schoology account
+    let account = utils::schoology_link::get_account(db_client, id)
         .await
         .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?
-        .ok_or(ResponseError::ServerError(Error::SchoologyNotLinked))?;
+        .ok_or(ResponseError::ClientError(Error::SchoologyNotLinked))?;
 
-    // Fetch the user from Schoology
-    let schoology_client = get_schoology_client();
+    // Fetch the user from Schoology (or the cache)
+    let tokens = account.tokens;
+    let schoology_id = account.schoology_id;
 
-    let user = schoology::users::get_schoology_user(
-        &schoology_client,
-        &schoology::SchoologyTokenPair {
-            access_token: user
-                .access_token
-                .ok_or(ResponseError::ServerError(Error::SchoologyNotLinked))?,
-            token_secret: user
-                .token_secret
-                .ok_or(ResponseError::ServerError(Error::SchoologyNotLinked))?,
-        },
-        user.schoology_id as usize,
-    )
-    .await
-    .map_err(|_| ResponseError::ServerError(Error::SchoologyNotLinked))?;
+    get_cache()
+        .get(id, "user", CACHE_POLICY, move || async move {
+            let user =
+                schoology::users::get_schoology_user(get_schoology_client(), &tokens, schoology_id)
+                    .await?;
 
-    Ok(Response {
-        first_name: user.name_first,
-        last_name: user.name_last,
-        picture_url: user.picture_url,
-    })
+            Ok(Response {
+                first_name: user.name_first,
+                last_name: user.name_last,
+                picture_url: user.picture_url,
+            })
+        })
+        .await
+        .map_err(ResponseError::from)
 }
 
 v1_get!(get_handler, get, UserAuth, Response, Error);
@@ -0,0 +1,62 @@
+//! /docs/api/v1/sessions/list
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    database::get_db_client,
+    utils,
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError},
+    v1_get,
+};
+
+#[derive(Serialize, JsonSchema)]
+struct Session {
+    id: Uuid,
+    initial_ip: String,
+    expires_at: chrono::DateTime<chrono::Utc>,
+    /// Whether this is the session used to make the request
+    current: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    sessions: Vec<Session>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+enum Error {
+    DatabaseError,
+}
+
+async fn get(data: RequestData<()>) -> Result<Response, ResponseError<Error>> {
+    let (user, session) = match (data.user, data.session) {
+        (Some(user), Some(session)) => (user, session),
+        _ => {
+            return Err(ResponseError::RequestError(
+                ErrorResponseStatus::Unauthorized,
+            ))
+        }
+    };
+
+    let db_client = get_db_client();
+
+    let sessions = utils::sessions::list_by_user(db_client, user.id)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    Ok(Response {
+        sessions: sessions
+            .into_iter()
+            .map(|s| Session {
+                id: s.id,
+                initial_ip: s.initial_ip,
+                expires_at: s.expires_at.and_utc(),
+                current: s.id == session.id,
+            })
+            .collect(),
+    })
+}
+
+v1_get!(get_handler, get, UserAuth, Response, Error);
@@ -0,0 +1,18 @@
+use crate::v1::V1Scope;
+
+mod list;
+mod logout;
+mod refresh;
+mod revoke;
+mod revoke_all;
+#[cfg(test)]
+mod tests;
+
+pub fn create_sessions_service() -> V1Scope {
+    V1Scope::new("/sessions")
+        .route("", list::route())
+        .route("/logout", logout::route())
+        .route("/refresh", refresh::route())
+        .route("/revoke", revoke::route())
+        .route("/revoke_all", revoke_all::route())
+}
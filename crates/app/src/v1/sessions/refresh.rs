@@ -0,0 +1,52 @@
+//! /docs/api/v1/sessions/refresh
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::get_db_client,
+    utils,
+    v1::{RequestData, ResponseError},
+    v1_post,
+};
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Request {
+    pub refresh_token: String,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct Response {
+    pub session_token: String,
+    pub session_expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub refresh_token: String,
+    pub refresh_expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+enum Error {
+    DatabaseError,
+    InvalidRefreshToken,
+}
+
+async fn post(data: RequestData<Request>) -> Result<Response, ResponseError<Error>> {
+    let db_client = get_db_client();
+
+    // Rotate the tokens
+    let session = utils::sessions::refresh(db_client, &data.data.refresh_token)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?
+        .ok_or(ResponseError::ClientError(Error::InvalidRefreshToken))?;
+
+    Ok(Response {
+        session_token: session.access_token,
+        session_expires_at: session
+            .session
+            .access_expires_at
+            .map(|expires_at| expires_at.and_utc()),
+        refresh_token: session.refresh_token,
+        refresh_expires_at: session.session.expires_at.and_utc(),
+    })
+}
+
+v1_post!(post_handler, post, NoAuth, Request, Response, Error);
@@ -0,0 +1,54 @@
+//! /docs/api/v1/sessions/revoke
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    database::get_db_client,
+    utils,
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError},
+    v1_post,
+};
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Request {
+    pub id: Uuid,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct Response {}
+
+#[derive(Debug, Serialize, JsonSchema)]
+enum Error {
+    DatabaseError,
+    SessionNotFound,
+}
+
+async fn post(data: RequestData<Request>) -> Result<Response, ResponseError<Error>> {
+    let user = match data.user {
+        Some(user) => user,
+        None => {
+            return Err(ResponseError::RequestError(
+                ErrorResponseStatus::Unauthorized,
+            ))
+        }
+    };
+
+    let db_client = get_db_client();
+
+    // Only allow revoking the user's own sessions
+    let session = utils::sessions::get(db_client, data.data.id)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?
+        .filter(|session| session.user_id == user.id)
+        .ok_or(ResponseError::ClientError(Error::SessionNotFound))?;
+
+    utils::sessions::delete(db_client, session.id)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    Ok(Response {})
+}
+
+v1_post!(post_handler, post, UserAuth, Request, Response, Error);
@@ -0,0 +1,53 @@
+//! /docs/api/v1/sessions/revoke_all
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::get_db_client,
+    utils,
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError},
+    v1_post,
+};
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Request {
+    /// Keep the session used to make the request
+    pub keep_current: bool,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct Response {
+    revoked: u64,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+enum Error {
+    DatabaseError,
+}
+
+async fn post(data: RequestData<Request>) -> Result<Response, ResponseError<Error>> {
+    let (user, session) = match (data.user, data.session) {
+        (Some(user), Some(session)) => (user, session),
+        _ => {
+            return Err(ResponseError::RequestError(
+                ErrorResponseStatus::Unauthorized,
+            ))
+        }
+    };
+
+    let db_client = get_db_client();
+
+    let keep = match data.data.keep_current {
+        true => Some(session.id),
+        false => None,
+    };
+
+    let revoked = utils::sessions::delete_by_user(db_client, user.id, keep)
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    Ok(Response { revoked })
+}
+
+v1_post!(post_handler, post, UserAuth, Request, Response, Error);
@@ -0,0 +1,438 @@
+use actix_web::{
+    test::{call_and_read_body_json, init_service, TestRequest},
+    web, App,
+};
+use orm::sessions;
+use sea_orm::{ActiveModelTrait, ActiveValue};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::{
+    database::get_db_client,
+    testing,
+    utils::{self, sessions::NewSession},
+    v1::create_v1_service,
+};
+
+/// Creates a user and logs them in
+async fn login() -> NewSession {
+    let db_client = get_db_client();
+
+    let user = utils::users::create(db_client).await.unwrap();
+
+    utils::sessions::create(db_client, user.id, "127.0.0.1".to_string())
+        .await
+        .unwrap()
+}
+
+#[test]
+fn refresh_rotation() {
+    testing::run(|_| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let session = login().await;
+
+        let req = TestRequest::post()
+            .uri("/api/v1/sessions/refresh")
+            .set_json(json!({ "refresh_token": session.refresh_token }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        let token = res["data"]["session_token"].as_str().unwrap().to_string();
+        let refresh_token = res["data"]["refresh_token"].as_str().unwrap().to_string();
+        assert_ne!(token, session.access_token);
+        assert_ne!(refresh_token, session.refresh_token);
+
+        // The old session token is replaced
+        let req = TestRequest::get()
+            .uri("/api/v1/sessions")
+            .insert_header(("Authorization", format!("Bearer {}", session.access_token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["status"], "Unauthorized", "{}", res);
+
+        let req = TestRequest::get()
+            .uri("/api/v1/sessions")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        // Session tokens are not refresh tokens
+        let req = TestRequest::post()
+            .uri("/api/v1/sessions/refresh")
+            .set_json(json!({ "refresh_token": token }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"], "InvalidRefreshToken", "{}", res);
+
+        // The new refresh token can be used
+        let req = TestRequest::post()
+            .uri("/api/v1/sessions/refresh")
+            .set_json(json!({ "refresh_token": refresh_token }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+    });
+}
+
+#[test]
+fn refresh_token_reuse() {
+    testing::run(|_| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let session = login().await;
+
+        let refreshed = utils::sessions::refresh(get_db_client(), &session.refresh_token)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // Using the first refresh token again revokes the session
+        let req = TestRequest::post()
+            .uri("/api/v1/sessions/refresh")
+            .set_json(json!({ "refresh_token": session.refresh_token }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"], "InvalidRefreshToken", "{}", res);
+
+        assert!(utils::sessions::get(get_db_client(), session.session.id)
+            .await
+            .unwrap()
+            .is_none());
+
+        // Including the tokens from the refresh
+        let req = TestRequest::get()
+            .uri("/api/v1/sessions")
+            .insert_header((
+                "Authorization",
+                format!("Bearer {}", refreshed.access_token),
+            ))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["status"], "Unauthorized", "{}", res);
+
+        let req = TestRequest::post()
+            .uri("/api/v1/sessions/refresh")
+            .set_json(json!({ "refresh_token": refreshed.refresh_token }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"], "InvalidRefreshToken", "{}", res);
+    });
+}
+
+#[test]
+fn expired_session_token() {
+    testing::run(|_| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let session = login().await;
+
+        sessions::ActiveModel {
+            id: ActiveValue::Set(session.session.id),
+            access_expires_at: ActiveValue::Set(Some(
+                (chrono::Utc::now() - chrono::Duration::minutes(1)).naive_utc(),
+            )),
+            ..Default::default()
+        }
+        .update(get_db_client())
+        .await
+        .unwrap();
+
+        let req = TestRequest::get()
+            .uri("/api/v1/sessions")
+            .insert_header(("Authorization", format!("Bearer {}", session.access_token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["status"], "Unauthorized", "{}", res);
+
+        // The refresh token still works
+        let req = TestRequest::post()
+            .uri("/api/v1/sessions/refresh")
+            .set_json(json!({ "refresh_token": session.refresh_token }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        let token = res["data"]["session_token"].as_str().unwrap();
+        let req = TestRequest::get()
+            .uri("/api/v1/sessions")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+    });
+}
+
+#[test]
+fn refresh_legacy_session() {
+    testing::run(|_| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let db_client = get_db_client();
+
+        let user = utils::users::create(db_client).await.unwrap();
+
+        // A session from before tokens were hashed and refresh tokens existed
+        let id = Uuid::new_v4();
+        sessions::ActiveModel {
+            id: ActiveValue::Set(id),
+            user_id: ActiveValue::Set(user.id),
+            token: ActiveValue::Set(Some("legacy-secret".to_string())),
+            token_hash: ActiveValue::Set(None),
+            initial_ip: ActiveValue::Set("127.0.0.1".to_string()),
+            expires_at: ActiveValue::Set(
+                (chrono::Utc::now() + chrono::Duration::days(1)).naive_utc(),
+            ),
+            access_expires_at: ActiveValue::Set(None),
+            refresh_key: ActiveValue::Set(None),
+            refresh_generation: ActiveValue::Set(0),
+        }
+        .insert(db_client)
+        .await
+        .unwrap();
+
+        let legacy_token = utils::sessions::encode(utils::sessions::AccessToken::user(
+            id,
+            "legacy-secret".to_string(),
+        ))
+        .await
+        .unwrap();
+
+        let req = TestRequest::get()
+            .uri("/api/v1/sessions")
+            .insert_header(("Authorization", format!("Bearer {}", legacy_token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["status"], "Unauthorized", "{}", res);
+
+        // The old token is exchanged once for a session token and refresh token
+        let req = TestRequest::post()
+            .uri("/api/v1/sessions/refresh")
+            .set_json(json!({ "refresh_token": legacy_token }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        let token = res["data"]["session_token"].as_str().unwrap().to_string();
+        let refresh_token = res["data"]["refresh_token"].as_str().unwrap().to_string();
+
+        let session = utils::sessions::get(db_client, id).await.unwrap().unwrap();
+        assert!(session.token.is_none());
+        assert!(session.refresh_key.is_some());
+
+        let req = TestRequest::post()
+            .uri("/api/v1/sessions/refresh")
+            .set_json(json!({ "refresh_token": legacy_token }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"], "InvalidRefreshToken", "{}", res);
+
+        let req = TestRequest::get()
+            .uri("/api/v1/sessions")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        let req = TestRequest::post()
+            .uri("/api/v1/sessions/refresh")
+            .set_json(json!({ "refresh_token": refresh_token }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+    });
+}
+
+#[test]
+fn list_sessions() {
+    testing::run(|_| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let session = login().await;
+        let other = utils::sessions::create(
+            get_db_client(),
+            session.session.user_id,
+            "127.0.0.2".to_string(),
+        )
+        .await
+        .unwrap();
+        // Someone else's session
+        login().await;
+
+        let req = TestRequest::get().uri("/api/v1/sessions").to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["status"], "Unauthorized", "{}", res);
+
+        let req = TestRequest::get()
+            .uri("/api/v1/sessions")
+            .insert_header(("Authorization", format!("Bearer {}", session.access_token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        let sessions = res["data"]["sessions"].as_array().unwrap();
+        assert_eq!(sessions.len(), 2, "{}", res);
+
+        let current = sessions
+            .iter()
+            .find(|s| s["id"] == session.session.id.to_string())
+            .unwrap();
+        assert_eq!(current["current"], true);
+        assert_eq!(current["initial_ip"], "127.0.0.1");
+
+        let other = sessions
+            .iter()
+            .find(|s| s["id"] == other.session.id.to_string())
+            .unwrap();
+        assert_eq!(other["current"], false);
+        assert_eq!(other["initial_ip"], "127.0.0.2");
+    });
+}
+
+#[test]
+fn revoke_session() {
+    testing::run(|_| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let session = login().await;
+        let other = utils::sessions::create(
+            get_db_client(),
+            session.session.user_id,
+            "127.0.0.1".to_string(),
+        )
+        .await
+        .unwrap();
+        let someone_else = login().await;
+
+        // Only the user's own sessions can be revoked
+        let req = TestRequest::post()
+            .uri("/api/v1/sessions/revoke")
+            .insert_header(("Authorization", format!("Bearer {}", session.access_token)))
+            .set_json(json!({ "id": someone_else.session.id }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"], "SessionNotFound", "{}", res);
+
+        assert!(
+            utils::sessions::get(get_db_client(), someone_else.session.id)
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        let req = TestRequest::post()
+            .uri("/api/v1/sessions/revoke")
+            .insert_header(("Authorization", format!("Bearer {}", session.access_token)))
+            .set_json(json!({ "id": other.session.id }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        let req = TestRequest::get()
+            .uri("/api/v1/sessions")
+            .insert_header(("Authorization", format!("Bearer {}", other.access_token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["status"], "Unauthorized", "{}", res);
+    });
+}
+
+#[test]
+fn logout() {
+    testing::run(|_| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let session = login().await;
+
+        let req = TestRequest::post()
+            .uri("/api/v1/sessions/logout")
+            .insert_header(("Authorization", format!("Bearer {}", session.access_token)))
+            .set_json(json!({}))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        let req = TestRequest::get()
+            .uri("/api/v1/sessions")
+            .insert_header(("Authorization", format!("Bearer {}", session.access_token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["status"], "Unauthorized", "{}", res);
+
+        // The refresh token is gone with the session
+        let req = TestRequest::post()
+            .uri("/api/v1/sessions/refresh")
+            .set_json(json!({ "refresh_token": session.refresh_token }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["data"], "InvalidRefreshToken", "{}", res);
+    });
+}
+
+#[test]
+fn revoke_all_sessions() {
+    testing::run(|_| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let session = login().await;
+        let user_id = session.session.user_id;
+        let mut others = Vec::new();
+        for _ in 0..2 {
+            others.push(
+                utils::sessions::create(get_db_client(), user_id, "127.0.0.1".to_string())
+                    .await
+                    .unwrap(),
+            );
+        }
+        let someone_else = login().await;
+
+        let req = TestRequest::post()
+            .uri("/api/v1/sessions/revoke_all")
+            .insert_header(("Authorization", format!("Bearer {}", session.access_token)))
+            .set_json(json!({ "keep_current": true }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+        assert_eq!(res["data"]["revoked"], 2, "{}", res);
+
+        for other in &others {
+            assert!(utils::sessions::get(get_db_client(), other.session.id)
+                .await
+                .unwrap()
+                .is_none());
+        }
+        assert!(
+            utils::sessions::get(get_db_client(), someone_else.session.id)
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        // The current session was kept
+        let req = TestRequest::post()
+            .uri("/api/v1/sessions/revoke_all")
+            .insert_header(("Authorization", format!("Bearer {}", session.access_token)))
+            .set_json(json!({ "keep_current": false }))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+        assert_eq!(res["data"]["revoked"], 1, "{}", res);
+
+        let req = TestRequest::get()
+            .uri("/api/v1/sessions")
+            .insert_header(("Authorization", format!("Bearer {}", session.access_token)))
+            .to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["status"], "Unauthorized", "{}", res);
+    });
+}
@@ -0,0 +1,138 @@
+//! /docs/api/v1/status/details
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{
+    health, jobs,
+    v1::{types::ErrorResponseStatus, RequestData, ResponseError},
+    v1_get,
+};
+
+#[derive(Serialize, JsonSchema)]
+struct Check {
+    ok: bool,
+    /// How long the check took (in milliseconds)
+    latency_ms: Option<u64>,
+    error: Option<String>,
+}
+
+impl From<Result<std::time::Duration, String>> for Check {
+    fn from(result: Result<std::time::Duration, String>) -> Self {
+        match result {
+            Ok(latency) => Self {
+                ok: true,
+                latency_ms: Some(latency.as_millis() as u64),
+                error: None,
+            },
+            Err(error) => Self {
+                ok: false,
+                latency_ms: None,
+                error: Some(error),
+            },
+        }
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Pool {
+    /// Open connections (idle or in use)
+    size: u32,
+    idle: usize,
+    max: u32,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Database {
+    #[serde(flatten)]
+    check: Check,
+    pool: Pool,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Migrations {
+    /// Whether every migration of the server is applied
+    current: bool,
+    expected: Option<String>,
+    applied: Option<String>,
+    pending: Vec<String>,
+    /// Applied by a newer version of the server
+    unknown: Vec<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Job {
+    name: String,
+    interval_seconds: u64,
+    running: bool,
+    runs: u64,
+    last_started: Option<DateTime<Utc>>,
+    last_finished: Option<DateTime<Utc>>,
+    last_duration_ms: Option<u64>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Response {
+    version: String,
+    database: Database,
+    migrations: Migrations,
+    schoology: Check,
+    jobs: Vec<Job>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+enum Error {
+    DatabaseError,
+}
+
+async fn get(data: RequestData<()>) -> Result<Response, ResponseError<Error>> {
+    if data.user.is_none() {
+        return Err(ResponseError::RequestError(
+            ErrorResponseStatus::Unauthorized,
+        ));
+    }
+
+    let database = health::check_database().await;
+    let pool = health::pool_usage();
+
+    let migrations = health::check_migrations()
+        .await
+        .map_err(|_| ResponseError::ServerError(Error::DatabaseError))?;
+
+    let schoology = health::check_schoology().await;
+
+    Ok(Response {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        database: Database {
+            check: database.into(),
+            pool: Pool {
+                size: pool.size,
+                idle: pool.idle,
+                max: pool.max,
+            },
+        },
+        migrations: Migrations {
+            current: migrations.is_current(),
+            expected: migrations.expected,
+            applied: migrations.applied,
+            pending: migrations.pending,
+            unknown: migrations.unknown,
+        },
+        schoology: schoology.into(),
+        jobs: jobs::list()
+            .into_iter()
+            .map(|job| Job {
+                name: job.name.to_string(),
+                interval_seconds: job.interval.as_secs(),
+                running: job.running,
+                runs: job.runs,
+                last_started: job.last_started,
+                last_finished: job.last_finished,
+                last_duration_ms: job.last_duration.map(|d| d.as_millis() as u64),
+            })
+            .collect(),
+    })
+}
+
+v1_get!(get_handler, get, AdminAuth, Response, Error);
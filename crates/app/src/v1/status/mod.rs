@@ -0,0 +1,9 @@
+use crate::v1::V1Scope;
+
+mod details;
+#[cfg(test)]
+mod tests;
+
+pub fn create_status_service() -> V1Scope {
+    V1Scope::new("/status").route("", details::route())
+}
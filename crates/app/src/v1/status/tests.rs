@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use actix_web::{
+    test::{call_and_read_body_json, call_service, init_service, TestRequest},
+    web, App,
+};
+use serde_json::Value;
+
+use crate::{
+    health::{self, ReadinessChecks},
+    jobs, testing,
+    v1::create_v1_service,
+};
+
+#[test]
+fn health_checks() {
+    testing::run(|_| async move {
+        let app = init_service(
+            App::new().configure(|cfg| health::configure(cfg, ReadinessChecks { schoology: true })),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/healthz").to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["status"], "ok", "{}", res);
+
+        // The tests run every migration, and the mock Schoology server is up
+        let req = TestRequest::get().uri("/readyz").to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), 200);
+
+        let req = TestRequest::get().uri("/readyz").to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["status"], "ready", "{}", res);
+        assert_eq!(res["checks"]["database"]["ok"], true, "{}", res);
+        assert_eq!(res["checks"]["migrations"]["ok"], true, "{}", res);
+        assert_eq!(res["checks"]["migrations"]["pending"], Value::Array(vec![]));
+        assert_eq!(
+            res["checks"]["migrations"]["expected"],
+            res["checks"]["migrations"]["applied"]
+        );
+        assert_eq!(res["checks"]["schoology"]["ok"], true, "{}", res);
+
+        // Schoology is only checked if asked to
+        let app = init_service(
+            App::new().configure(|cfg| health::configure(cfg, ReadinessChecks::default())),
+        )
+        .await;
+
+        let req = TestRequest::get().uri("/readyz").to_request();
+        let res: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(res["status"], "ready", "{}", res);
+        assert!(res["checks"]["schoology"].is_null(), "{}", res);
+    });
+}
+
+#[test]
+fn status() {
+    testing::run(|mock| async move {
+        let app =
+            init_service(App::new().service(web::scope("/api").service(create_v1_service()))).await;
+
+        let admin = testing::admin_user().await;
+        let (user, _) = testing::linked_user(mock).await;
+
+        let get = |token: &str| {
+            TestRequest::get()
+                .uri("/api/v1/status")
+                .insert_header(("Authorization", format!("Bearer {}", token)))
+                .to_request()
+        };
+
+        // Only admins can see it
+        let res: Value = call_and_read_body_json(&app, get(&user)).await;
+        assert_eq!(res["status"], "Forbidden", "{}", res);
+
+        jobs::register("status_test", Duration::from_secs(60));
+        jobs::run("status_test", async {}).await;
+
+        let res: Value = call_and_read_body_json(&app, get(&admin)).await;
+        assert_eq!(res["type"], "Success", "{}", res);
+
+        let data = &res["data"];
+        assert_eq!(data["database"]["ok"], true, "{}", data);
+        assert!(data["database"]["latency_ms"].is_u64(), "{}", data);
+        assert_eq!(data["database"]["pool"]["max"], 5, "{}", data);
+        assert!(data["database"]["pool"]["size"].as_u64().unwrap() >= 1);
+        assert_eq!(data["migrations"]["current"], true, "{}", data);
+        assert_eq!(data["schoology"]["ok"], true, "{}", data);
+
+        let job = data["jobs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|job| job["name"] == "status_test")
+            .unwrap();
+        assert_eq!(job["interval_seconds"], 60, "{}", job);
+        assert_eq!(job["running"], false, "{}", job);
+        assert_eq!(job["runs"], 1, "{}", job);
+        assert!(job["last_finished"].is_string(), "{}", job);
+    });
+}
@@ -1,4 +1,5 @@
 use actix_web::http;
+use schemars::JsonSchema;
 use serde::Serialize;
 
 #[derive(Serialize)]
@@ -52,6 +53,7 @@ where
                 ErrorResponseStatus::InternalServerError => {
                     actix_web::HttpResponse::InternalServerError()
                 }
+                ErrorResponseStatus::TooManyRequests => actix_web::HttpResponse::TooManyRequests(),
             },
             ResponseData::RouteError(error) => match error.fault {
                 ErrorFault::Client => actix_web::HttpResponse::BadRequest(),
@@ -73,7 +75,7 @@ where
     pub data: Data,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub enum ErrorResponseStatus {
     /// Self-explanatory; the requested resource was not found. (Used only when a path parameter is used).
     NotFound,
@@ -85,6 +87,8 @@ pub enum ErrorResponseStatus {
     BadRequest,
     /// The server encountered an internal error.
     InternalServerError,
+    /// The client sent too many requests, the `Retry-After` header says when to try again.
+    TooManyRequests,
 }
 
 #[derive(Serialize)]
@@ -1,9 +1,19 @@
-pub use sea_orm_migration::prelude::*;
+pub use sea_orm_migration::{prelude::*, seaql_migrations};
 
 mod m20230930_000001_create_users;
 mod m20231008_000001_schoology_request_tokens;
 mod m20231009_000001_schoology_link;
 mod m20231010_000001_sessions;
+mod m20231016_000001_hash_session_tokens;
+mod m20231017_000001_session_refresh_tokens;
+mod m20231018_000001_encrypt_schoology_tokens;
+mod m20231019_000001_grade_notifications;
+mod m20231020_000001_push_subscriptions;
+mod m20231021_000001_schedules;
+mod m20231022_000001_calendar_feeds;
+mod m20231023_000001_message_notifications;
+mod m20231024_000001_schoology_cache;
+mod m20231025_000001_message_poll_schedule;
 
 pub struct Migrator;
 
@@ -15,6 +25,16 @@ impl MigratorTrait for Migrator {
             Box::new(m20231008_000001_schoology_request_tokens::Migration),
             Box::new(m20231009_000001_schoology_link::Migration),
             Box::new(m20231010_000001_sessions::Migration),
+            Box::new(m20231016_000001_hash_session_tokens::Migration),
+            Box::new(m20231017_000001_session_refresh_tokens::Migration),
+            Box::new(m20231018_000001_encrypt_schoology_tokens::Migration),
+            Box::new(m20231019_000001_grade_notifications::Migration),
+            Box::new(m20231020_000001_push_subscriptions::Migration),
+            Box::new(m20231021_000001_schedules::Migration),
+            Box::new(m20231022_000001_calendar_feeds::Migration),
+            Box::new(m20231023_000001_message_notifications::Migration),
+            Box::new(m20231024_000001_schoology_cache::Migration),
+            Box::new(m20231025_000001_message_poll_schedule::Migration),
         ]
     }
 }
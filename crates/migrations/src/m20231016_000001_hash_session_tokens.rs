@@ -0,0 +1,56 @@
+//! Adds the column `token_hash` to the `sessions` table and makes `token` nullable.
+//! New sessions only store a keyed hash of their secret in `token_hash`.
+//! Existing sessions keep their raw `token` until the API hashes it (on startup or on first use).
+
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Sessions::Table)
+                    .add_column(ColumnDef::new(Sessions::TokenHash).string())
+                    .modify_column(ColumnDef::new(Sessions::Token).string().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Hashed sessions can't be restored, so they are logged out
+        manager
+            .exec_stmt(
+                Query::delete()
+                    .from_table(Sessions::Table)
+                    .and_where(Expr::col(Sessions::Token).is_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Sessions::Table)
+                    .drop_column(Sessions::TokenHash)
+                    .modify_column(ColumnDef::new(Sessions::Token).string().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Sessions {
+    Table,
+    Token,
+    TokenHash,
+}
@@ -0,0 +1,55 @@
+//! Adds the columns needed for short-lived access tokens and rotating refresh tokens to the `sessions` table.
+//! `access_expires_at` is when the current access token expires, `refresh_key` is used to derive refresh tokens
+//! and `refresh_generation` is the generation of the only refresh token that may still be used.
+//! Existing sessions are left without either, their access token is exchanged once through `/api/v1/sessions/refresh`.
+
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Sessions::Table)
+                    .add_column(ColumnDef::new(Sessions::AccessExpiresAt).date_time())
+                    .add_column(ColumnDef::new(Sessions::RefreshKey).string())
+                    .add_column(
+                        ColumnDef::new(Sessions::RefreshGeneration)
+                            .integer()
+                            .default(Expr::value(0))
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Sessions::Table)
+                    .drop_column(Sessions::AccessExpiresAt)
+                    .drop_column(Sessions::RefreshKey)
+                    .drop_column(Sessions::RefreshGeneration)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Sessions {
+    Table,
+    AccessExpiresAt,
+    RefreshKey,
+    RefreshGeneration,
+}
@@ -0,0 +1,60 @@
+//! Adds the columns `key_id` and `wrapped_key` to the `schoology_link` table.
+//! `access_token` and `token_secret` are encrypted with a per-row data key, which is stored in `wrapped_key`
+//! encrypted with the master key `key_id`. Rows without a `key_id` are still in plaintext.
+
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SchoologyLink::Table)
+                    .add_column(ColumnDef::new(SchoologyLink::KeyId).text())
+                    .add_column(ColumnDef::new(SchoologyLink::WrappedKey).text())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Encrypted tokens can't be read without the key, so they are removed
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(SchoologyLink::Table)
+                    .value(SchoologyLink::AccessToken, Option::<String>::None)
+                    .value(SchoologyLink::TokenSecret, Option::<String>::None)
+                    .and_where(Expr::col(SchoologyLink::KeyId).is_not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SchoologyLink::Table)
+                    .drop_column(SchoologyLink::KeyId)
+                    .drop_column(SchoologyLink::WrappedKey)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum SchoologyLink {
+    Table,
+    AccessToken,
+    TokenSecret,
+    KeyId,
+    WrappedKey,
+}
@@ -0,0 +1,207 @@
+//! Adds the tables used by the grade poller.
+//! `grade_snapshots` is the last seen grade of every assignment, `notifications` is the feed of changes
+//! and `grade_poll_state` is when each user is polled next (and how often it failed).
+
+use sea_orm_migration::prelude::*;
+
+use crate::m20230930_000001_create_users::Users;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(GradeSnapshots::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(GradeSnapshots::UserId).integer().not_null())
+                    .col(
+                        ColumnDef::new(GradeSnapshots::SectionId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(GradeSnapshots::AssignmentId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(GradeSnapshots::Grade).double())
+                    .col(ColumnDef::new(GradeSnapshots::MaxPoints).double())
+                    .col(
+                        ColumnDef::new(GradeSnapshots::Exception)
+                            .integer()
+                            .default(Expr::value(0))
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(GradeSnapshots::UpdatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(GradeSnapshots::UserId)
+                            .col(GradeSnapshots::SectionId)
+                            .col(GradeSnapshots::AssignmentId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_grade_snapshots_user_id")
+                            .from(GradeSnapshots::Table, GradeSnapshots::UserId)
+                            .to(Users::Table, Users::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Notifications::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Notifications::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Notifications::UserId).integer().not_null())
+                    .col(ColumnDef::new(Notifications::Kind).string().not_null())
+                    .col(ColumnDef::new(Notifications::Title).text().not_null())
+                    .col(ColumnDef::new(Notifications::Body).text().not_null())
+                    .col(ColumnDef::new(Notifications::Data).json_binary().not_null())
+                    .col(
+                        ColumnDef::new(Notifications::CreatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Notifications::ReadAt).date_time())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_notifications_user_id")
+                            .from(Notifications::Table, Notifications::UserId)
+                            .to(Users::Table, Users::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // The feed is read newest first
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_notifications_user_id_created_at")
+                    .table(Notifications::Table)
+                    .col(Notifications::UserId)
+                    .col(Notifications::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(GradePollState::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(GradePollState::UserId)
+                            .integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(GradePollState::IntervalMinutes)
+                            .integer()
+                            .default(Expr::value(30))
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(GradePollState::Failures)
+                            .integer()
+                            .default(Expr::value(0))
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(GradePollState::NextPollAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(GradePollState::LastPolledAt).date_time())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_grade_poll_state_user_id")
+                            .from(GradePollState::Table, GradePollState::UserId)
+                            .to(Users::Table, Users::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // The poller looks for the users that are due
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_grade_poll_state_next_poll_at")
+                    .table(GradePollState::Table)
+                    .col(GradePollState::NextPollAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(GradePollState::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Notifications::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(GradeSnapshots::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum GradeSnapshots {
+    Table,
+    UserId,
+    SectionId,
+    AssignmentId,
+    Grade,
+    MaxPoints,
+    Exception,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Notifications {
+    Table,
+    Id,
+    UserId,
+    Kind,
+    Title,
+    Body,
+    Data,
+    CreatedAt,
+    ReadAt,
+}
+
+#[derive(DeriveIden)]
+enum GradePollState {
+    Table,
+    UserId,
+    IntervalMinutes,
+    Failures,
+    NextPollAt,
+    LastPolledAt,
+}
@@ -0,0 +1,174 @@
+//! Adds the tables used to deliver notifications with Web Push.
+//! `push_subscriptions` are the browsers of a user and `push_deliveries` is the queue of notifications
+//! that still have to be sent to them (a row is deleted once it was delivered or given up on).
+
+use sea_orm_migration::prelude::*;
+
+use crate::m20230930_000001_create_users::Users;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PushSubscriptions::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PushSubscriptions::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(PushSubscriptions::UserId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PushSubscriptions::Endpoint)
+                            .text()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(PushSubscriptions::P256dh).text().not_null())
+                    .col(ColumnDef::new(PushSubscriptions::Auth).text().not_null())
+                    .col(
+                        ColumnDef::new(PushSubscriptions::CreatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_push_subscriptions_user_id")
+                            .from(PushSubscriptions::Table, PushSubscriptions::UserId)
+                            .to(Users::Table, Users::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_push_subscriptions_user_id")
+                    .table(PushSubscriptions::Table)
+                    .col(PushSubscriptions::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(PushDeliveries::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PushDeliveries::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(PushDeliveries::SubscriptionId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PushDeliveries::NotificationId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PushDeliveries::Attempts)
+                            .integer()
+                            .default(Expr::value(0))
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PushDeliveries::NextAttemptAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PushDeliveries::CreatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    // Removing a subscription (or an old notification) drops it's pending deliveries
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_push_deliveries_subscription_id")
+                            .from(PushDeliveries::Table, PushDeliveries::SubscriptionId)
+                            .to(PushSubscriptions::Table, PushSubscriptions::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_push_deliveries_notification_id")
+                            .from(PushDeliveries::Table, PushDeliveries::NotificationId)
+                            .to(Notifications::Table, Notifications::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // The queue is read by the time of the next attempt
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_push_deliveries_next_attempt_at")
+                    .table(PushDeliveries::Table)
+                    .col(PushDeliveries::NextAttemptAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PushDeliveries::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(PushSubscriptions::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum PushSubscriptions {
+    Table,
+    Id,
+    UserId,
+    Endpoint,
+    #[sea_orm(iden = "p256dh")]
+    P256dh,
+    Auth,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum PushDeliveries {
+    Table,
+    Id,
+    SubscriptionId,
+    NotificationId,
+    Attempts,
+    NextAttemptAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Notifications {
+    Table,
+    Id,
+}
@@ -0,0 +1,220 @@
+//! Adds the tables of the bell schedule.
+//! `bell_schedules` (and their `bell_periods`) are the schedules of a day, `schedule_weekdays` is the
+//! weekly default, `schedule_overrides` replaces it on a date (a minimum day, finals, a holiday, ...)
+//! and `user_periods` are the names and colors users gave to their periods.
+
+use sea_orm_migration::prelude::*;
+
+use crate::m20230930_000001_create_users::Users;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(BellSchedules::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(BellSchedules::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(BellSchedules::Name).text().not_null())
+                    .col(
+                        ColumnDef::new(BellSchedules::CreatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BellSchedules::UpdatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(BellPeriods::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(BellPeriods::ScheduleId).uuid().not_null())
+                    .col(ColumnDef::new(BellPeriods::Position).integer().not_null())
+                    .col(ColumnDef::new(BellPeriods::Period).text().not_null())
+                    .col(ColumnDef::new(BellPeriods::Name).text().not_null())
+                    .col(ColumnDef::new(BellPeriods::StartTime).time().not_null())
+                    .col(ColumnDef::new(BellPeriods::EndTime).time().not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(BellPeriods::ScheduleId)
+                            .col(BellPeriods::Position),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_bell_periods_schedule_id")
+                            .from(BellPeriods::Table, BellPeriods::ScheduleId)
+                            .to(BellSchedules::Table, BellSchedules::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ScheduleWeekdays::Table)
+                    .if_not_exists()
+                    // 0 is Monday, 6 is Sunday
+                    .col(
+                        ColumnDef::new(ScheduleWeekdays::Weekday)
+                            .small_integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ScheduleWeekdays::ScheduleId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_schedule_weekdays_schedule_id")
+                            .from(ScheduleWeekdays::Table, ScheduleWeekdays::ScheduleId)
+                            .to(BellSchedules::Table, BellSchedules::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ScheduleOverrides::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ScheduleOverrides::Date)
+                            .date()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    // No schedule means there is no school
+                    .col(ColumnDef::new(ScheduleOverrides::ScheduleId).uuid())
+                    .col(ColumnDef::new(ScheduleOverrides::Name).text().not_null())
+                    .col(
+                        ColumnDef::new(ScheduleOverrides::CreatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_schedule_overrides_schedule_id")
+                            .from(ScheduleOverrides::Table, ScheduleOverrides::ScheduleId)
+                            .to(BellSchedules::Table, BellSchedules::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserPeriods::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(UserPeriods::UserId).integer().not_null())
+                    .col(ColumnDef::new(UserPeriods::Period).text().not_null())
+                    .col(ColumnDef::new(UserPeriods::Name).text())
+                    .col(ColumnDef::new(UserPeriods::Color).string())
+                    .primary_key(
+                        Index::create()
+                            .col(UserPeriods::UserId)
+                            .col(UserPeriods::Period),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_user_periods_user_id")
+                            .from(UserPeriods::Table, UserPeriods::UserId)
+                            .to(Users::Table, Users::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(UserPeriods::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(ScheduleOverrides::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(ScheduleWeekdays::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(BellPeriods::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(BellSchedules::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum BellSchedules {
+    Table,
+    Id,
+    Name,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum BellPeriods {
+    Table,
+    ScheduleId,
+    Position,
+    Period,
+    Name,
+    StartTime,
+    EndTime,
+}
+
+#[derive(DeriveIden)]
+enum ScheduleWeekdays {
+    Table,
+    Weekday,
+    ScheduleId,
+}
+
+#[derive(DeriveIden)]
+enum ScheduleOverrides {
+    Table,
+    Date,
+    ScheduleId,
+    Name,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum UserPeriods {
+    Table,
+    UserId,
+    Period,
+    Name,
+    Color,
+}
@@ -0,0 +1,63 @@
+//! Adds the calendar feeds of users.
+//! Calendar apps can't send an `Authorization` header, so a feed has it's own secret token in the URL.
+//! Like session tokens, only a hash of it is stored.
+
+use sea_orm_migration::prelude::*;
+
+use crate::m20230930_000001_create_users::Users;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CalendarFeeds::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CalendarFeeds::UserId)
+                            .integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(CalendarFeeds::TokenHash)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(CalendarFeeds::CreatedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(CalendarFeeds::LastUsedAt).date_time())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_calendar_feeds_user_id")
+                            .from(CalendarFeeds::Table, CalendarFeeds::UserId)
+                            .to(Users::Table, Users::Id),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CalendarFeeds::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum CalendarFeeds {
+    Table,
+    UserId,
+    TokenHash,
+    CreatedAt,
+    LastUsedAt,
+}
@@ -0,0 +1,63 @@
+//! Adds the state of the message notifications.
+//! The inbox is checked along with the grades, `last_message_at` is the newest message that was
+//! already seen so every message is only notified once.
+
+use sea_orm_migration::prelude::*;
+
+use crate::m20230930_000001_create_users::Users;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(MessagePollState::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(MessagePollState::UserId)
+                            .integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    // No messages were seen yet
+                    .col(ColumnDef::new(MessagePollState::LastMessageAt).date_time())
+                    .col(
+                        ColumnDef::new(MessagePollState::UnreadCount)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(MessagePollState::CheckedAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_message_poll_state_user_id")
+                            .from(MessagePollState::Table, MessagePollState::UserId)
+                            .to(Users::Table, Users::Id),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MessagePollState::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum MessagePollState {
+    Table,
+    UserId,
+    LastMessageAt,
+    UnreadCount,
+    CheckedAt,
+}
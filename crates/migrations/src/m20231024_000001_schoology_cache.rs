@@ -0,0 +1,79 @@
+//! Adds the shared cache of Schoology responses.
+//! Entries are per user and endpoint, `expires_at` is when they can't even be served stale anymore.
+
+use sea_orm_migration::prelude::*;
+
+use crate::m20230930_000001_create_users::Users;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SchoologyCache::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(SchoologyCache::UserId).integer().not_null())
+                    .col(ColumnDef::new(SchoologyCache::Key).text().not_null())
+                    .col(
+                        ColumnDef::new(SchoologyCache::Value)
+                            .json_binary()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SchoologyCache::StoredAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SchoologyCache::ExpiresAt)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(SchoologyCache::UserId)
+                            .col(SchoologyCache::Key),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_schoology_cache_user_id")
+                            .from(SchoologyCache::Table, SchoologyCache::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // For clearing the expired entries
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_schoology_cache_expires_at")
+                    .table(SchoologyCache::Table)
+                    .col(SchoologyCache::ExpiresAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SchoologyCache::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SchoologyCache {
+    Table,
+    UserId,
+    Key,
+    Value,
+    StoredAt,
+    ExpiresAt,
+}
@@ -0,0 +1,65 @@
+//! Adds the columns needed to check the inboxes in their own job to the `message_poll_state` table.
+//! `failures` and `next_check_at` back off a user independently of their grades, and `initialized`
+//! is false until the first check recorded the messages that were already there.
+
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MessagePollState::Table)
+                    .add_column(
+                        ColumnDef::new(MessagePollState::Failures)
+                            .integer()
+                            .default(Expr::value(0))
+                            .not_null(),
+                    )
+                    .add_column(
+                        ColumnDef::new(MessagePollState::NextCheckAt)
+                            .date_time()
+                            .default(Expr::current_timestamp())
+                            .not_null(),
+                    )
+                    // Every existing row was written by a check
+                    .add_column(
+                        ColumnDef::new(MessagePollState::Initialized)
+                            .boolean()
+                            .default(Expr::value(true))
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(MessagePollState::Table)
+                    .drop_column(MessagePollState::Failures)
+                    .drop_column(MessagePollState::NextCheckAt)
+                    .drop_column(MessagePollState::Initialized)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum MessagePollState {
+    Table,
+    Failures,
+    NextCheckAt,
+    Initialized,
+}
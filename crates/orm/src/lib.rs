@@ -2,7 +2,20 @@
 
 pub mod prelude;
 
+pub mod bell_periods;
+pub mod bell_schedules;
+pub mod calendar_feeds;
+pub mod grade_poll_state;
+pub mod grade_snapshots;
+pub mod message_poll_state;
+pub mod notifications;
+pub mod push_deliveries;
+pub mod push_subscriptions;
+pub mod schedule_overrides;
+pub mod schedule_weekdays;
+pub mod schoology_cache;
 pub mod schoology_link;
 pub mod schoology_request_tokens;
 pub mod sessions;
+pub mod user_periods;
 pub mod users;
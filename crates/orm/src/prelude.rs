@@ -1,7 +1,13 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.3
 
 pub use super::{
-    schoology_link::Entity as SchoologyLink,
+    bell_periods::Entity as BellPeriods, bell_schedules::Entity as BellSchedules,
+    calendar_feeds::Entity as CalendarFeeds, grade_poll_state::Entity as GradePollState,
+    grade_snapshots::Entity as GradeSnapshots, message_poll_state::Entity as MessagePollState,
+    notifications::Entity as Notifications, push_deliveries::Entity as PushDeliveries,
+    push_subscriptions::Entity as PushSubscriptions,
+    schedule_overrides::Entity as ScheduleOverrides, schedule_weekdays::Entity as ScheduleWeekdays,
+    schoology_cache::Entity as SchoologyCache, schoology_link::Entity as SchoologyLink,
     schoology_request_tokens::Entity as SchoologyRequestTokens, sessions::Entity as Sessions,
-    users::Entity as Users,
+    user_periods::Entity as UserPeriods, users::Entity as Users,
 };
@@ -21,6 +21,10 @@ pub struct Model {
     pub access_token: Option<String>,
     #[sea_orm(column_type = "Text", nullable)]
     pub token_secret: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub key_id: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub wrapped_key: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
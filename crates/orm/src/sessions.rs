@@ -8,9 +8,13 @@ pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub id: Uuid,
     pub user_id: i32,
-    pub token: String,
+    pub token: Option<String>,
     pub initial_ip: String,
     pub expires_at: DateTime,
+    pub token_hash: Option<String>,
+    pub access_expires_at: Option<DateTime>,
+    pub refresh_key: Option<String>,
+    pub refresh_generation: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -14,10 +14,68 @@ pub struct Model {
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_one = "super::calendar_feeds::Entity")]
+    CalendarFeeds,
+    #[sea_orm(has_one = "super::grade_poll_state::Entity")]
+    GradePollState,
+    #[sea_orm(has_many = "super::grade_snapshots::Entity")]
+    GradeSnapshots,
+    #[sea_orm(has_one = "super::message_poll_state::Entity")]
+    MessagePollState,
+    #[sea_orm(has_many = "super::notifications::Entity")]
+    Notifications,
+    #[sea_orm(has_many = "super::push_subscriptions::Entity")]
+    PushSubscriptions,
+    #[sea_orm(has_many = "super::schoology_cache::Entity")]
+    SchoologyCache,
     #[sea_orm(has_many = "super::schoology_link::Entity")]
     SchoologyLink,
     #[sea_orm(has_many = "super::sessions::Entity")]
     Sessions,
+    #[sea_orm(has_many = "super::user_periods::Entity")]
+    UserPeriods,
+}
+
+impl Related<super::calendar_feeds::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CalendarFeeds.def()
+    }
+}
+
+impl Related<super::grade_poll_state::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::GradePollState.def()
+    }
+}
+
+impl Related<super::grade_snapshots::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::GradeSnapshots.def()
+    }
+}
+
+impl Related<super::message_poll_state::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MessagePollState.def()
+    }
+}
+
+impl Related<super::notifications::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Notifications.def()
+    }
+}
+
+impl Related<super::push_subscriptions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PushSubscriptions.def()
+    }
+}
+
+impl Related<super::schoology_cache::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SchoologyCache.def()
+    }
 }
 
 impl Related<super::schoology_link::Entity> for Entity {
@@ -32,4 +90,10 @@ impl Related<super::sessions::Entity> for Entity {
     }
 }
 
+impl Related<super::user_periods::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserPeriods.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
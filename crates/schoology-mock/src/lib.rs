@@ -0,0 +1,643 @@
+//! An in-process fake of the Schoology API for tests
+//!
+//! Implements the OAuth 1.0a flow (`/oauth/request_token`, `/oauth/access_token`), `/users/me`
+//! (including it's `303 See Other` redirect), `/users/{id}`, the section / course endpoints and messages.
+//! Every request must be signed with HMAC-SHA256 and a nonce can only be used once, just like the
+//! real API. Lists are paginated with `start` / `limit`. Throttling and server errors can be simulated
+//! with `MockSchoology::fail_requests`.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    future::{ready, Future},
+    net::TcpListener,
+    pin::Pin,
+    sync::Mutex,
+};
+
+use actix_web::{
+    dev::{ServerHandle, Service},
+    web, App, HttpServer,
+};
+use serde::Serialize;
+use url::Url;
+use uuid::Uuid;
+
+#[macro_use]
+extern crate log;
+
+mod oauth;
+mod routes;
+
+type LocalBoxFuture<T> = Pin<Box<dyn Future<Output = T>>>;
+
+/// The consumer key the mock accepts by default
+pub const CONSUMER_KEY: &str = "mock-consumer-key";
+/// The consumer secret the mock accepts by default
+pub const CONSUMER_SECRET: &str = "mock-consumer-secret";
+
+/// A Schoology user served by the mock
+#[derive(Debug, Clone, Serialize)]
+pub struct MockUser {
+    pub id: usize,
+    pub school_id: usize,
+    pub name_first: String,
+    pub name_last: String,
+    pub primary_email: String,
+    pub picture_url: String,
+}
+
+impl MockUser {
+    /// Creates a user with placeholder details
+    pub fn new(id: usize) -> Self {
+        Self {
+            id,
+            school_id: 1,
+            name_first: "Test".to_string(),
+            name_last: format!("User {}", id),
+            primary_email: format!("user{}@example.com", id),
+            picture_url: format!("https://example.com/pictures/{}.png", id),
+        }
+    }
+}
+
+/// A section served by the mock (the course is derived from it's sections)
+#[derive(Debug, Clone)]
+pub struct MockSection {
+    pub id: usize,
+    pub course_id: usize,
+    pub course_title: String,
+    pub course_code: String,
+    pub section_title: String,
+    pub active: bool,
+}
+
+impl MockSection {
+    /// Creates an active section with placeholder details
+    pub fn new(id: usize, course_id: usize) -> Self {
+        Self {
+            id,
+            course_id,
+            course_title: format!("Course {}", course_id),
+            course_code: format!("C{}", course_id),
+            section_title: format!("Section {}", id),
+            active: true,
+        }
+    }
+}
+
+/// An assignment of a section, it is also shown on the calendar of the section's members
+#[derive(Debug, Clone)]
+pub struct MockAssignment {
+    pub id: usize,
+    pub section_id: usize,
+    pub title: String,
+    /// `YYYY-MM-DD HH:MM:SS` (or empty)
+    pub due: String,
+    /// `assignment`, `assessment` or `discussion`
+    pub kind: String,
+    pub max_points: Option<f64>,
+    pub completed: bool,
+}
+
+impl MockAssignment {
+    /// Creates an assignment due at the given time
+    pub fn new(id: usize, section_id: usize, due: &str) -> Self {
+        Self {
+            id,
+            section_id,
+            title: format!("Assignment {}", id),
+            due: due.to_string(),
+            kind: "assignment".to_string(),
+            max_points: Some(100.0),
+            completed: false,
+        }
+    }
+}
+
+/// A calendar event, events without a section (or group) are shown to everyone
+#[derive(Debug, Clone)]
+pub struct MockEvent {
+    pub id: usize,
+    pub section_id: Option<usize>,
+    pub group_id: Option<usize>,
+    pub title: String,
+    pub description: String,
+    /// `YYYY-MM-DD HH:MM:SS`
+    pub start: String,
+    /// `YYYY-MM-DD HH:MM:SS` (or empty)
+    pub end: String,
+    pub all_day: bool,
+    /// Whether the event asks for answers
+    pub rsvp: bool,
+    /// `attending`, `maybe`, `not_attending` (or empty)
+    pub rsvp_status: String,
+    /// `event`, `assignment`, `assessment` or `discussion`
+    pub kind: String,
+    pub assignment_id: Option<usize>,
+}
+
+impl MockEvent {
+    /// Creates an event of a section
+    pub fn new(id: usize, section_id: Option<usize>, start: &str) -> Self {
+        Self {
+            id,
+            section_id,
+            group_id: None,
+            title: format!("Event {}", id),
+            description: String::new(),
+            start: start.to_string(),
+            end: String::new(),
+            all_day: false,
+            rsvp: false,
+            rsvp_status: String::new(),
+            kind: "event".to_string(),
+            assignment_id: None,
+        }
+    }
+}
+
+/// A group (a club or team)
+#[derive(Debug, Clone)]
+pub struct MockGroup {
+    pub id: usize,
+    pub title: String,
+    pub description: String,
+    pub category: String,
+}
+
+impl MockGroup {
+    /// Creates a group with placeholder details
+    pub fn new(id: usize) -> Self {
+        Self {
+            id,
+            title: format!("Group {}", id),
+            description: String::new(),
+            category: "Clubs".to_string(),
+        }
+    }
+}
+
+/// A file, link or video attached to an update
+#[derive(Debug, Clone)]
+pub struct MockAttachment {
+    pub id: usize,
+    /// `file`, `link` or `video`
+    pub kind: String,
+    pub title: String,
+    pub url: String,
+}
+
+/// A comment on an update
+#[derive(Debug, Clone)]
+pub struct MockComment {
+    pub id: usize,
+    pub author_id: usize,
+    pub body: String,
+    /// A unix timestamp
+    pub created: i64,
+}
+
+/// A post on the feed of a section, updates without a section (or group) are posted to the school
+#[derive(Debug, Clone)]
+pub struct MockUpdate {
+    pub id: usize,
+    pub section_id: Option<usize>,
+    pub group_id: Option<usize>,
+    pub author_id: usize,
+    pub body: String,
+    /// A unix timestamp, the feeds are sorted by it
+    pub created: i64,
+    pub likes: usize,
+    pub attachments: Vec<MockAttachment>,
+    pub comments: Vec<MockComment>,
+}
+
+impl MockUpdate {
+    /// Creates an update without attachments or comments
+    pub fn new(id: usize, section_id: Option<usize>, author_id: usize, created: i64) -> Self {
+        Self {
+            id,
+            section_id,
+            group_id: None,
+            author_id,
+            body: format!("Update {}", id),
+            created,
+            likes: 0,
+            attachments: Vec::new(),
+            comments: Vec::new(),
+        }
+    }
+}
+
+/// A message of a thread
+#[derive(Debug, Clone)]
+pub struct MockMessage {
+    pub id: usize,
+    pub author_id: usize,
+    pub body: String,
+    /// A unix timestamp
+    pub created: i64,
+}
+
+/// A conversation between users, it is in the inbox of everyone in it
+#[derive(Debug, Clone)]
+pub struct MockThread {
+    pub id: usize,
+    pub subject: String,
+    /// Who started the thread
+    pub author_id: usize,
+    pub recipient_ids: Vec<usize>,
+    /// The oldest first
+    pub messages: Vec<MockMessage>,
+    /// The users that did not read the last message yet
+    pub unread: HashSet<usize>,
+}
+
+impl MockThread {
+    /// Creates a thread with a single (unread) message of the author
+    pub fn new(id: usize, author_id: usize, recipient_ids: Vec<usize>, created: i64) -> Self {
+        Self {
+            id,
+            subject: format!("Message {}", id),
+            author_id,
+            unread: recipient_ids.iter().copied().collect(),
+            recipient_ids,
+            messages: vec![MockMessage {
+                id,
+                author_id,
+                body: format!("Body of message {}", id),
+                created,
+            }],
+        }
+    }
+}
+
+/// A grading category of a section
+#[derive(Debug, Clone)]
+pub struct MockCategory {
+    pub id: usize,
+    pub section_id: usize,
+    pub title: String,
+    /// The weight in percent (`None` for sections that are not weighted)
+    pub weight: Option<f64>,
+}
+
+/// The grade of a user on an assignment
+#[derive(Debug, Clone)]
+pub struct MockGrade {
+    pub user_id: usize,
+    pub section_id: usize,
+    pub period_id: String,
+    pub period_title: String,
+    pub assignment_id: usize,
+    pub category_id: usize,
+    pub grade: Option<f64>,
+    pub max_points: f64,
+    /// `0` none, `1` excused, `2` incomplete, `3` missing
+    pub exception: u8,
+}
+
+impl MockGrade {
+    /// Creates a grade in the first grading period (`p1`)
+    pub fn new(
+        user_id: usize,
+        section_id: usize,
+        assignment_id: usize,
+        category_id: usize,
+        grade: f64,
+        max_points: f64,
+    ) -> Self {
+        Self {
+            user_id,
+            section_id,
+            period_id: "p1".to_string(),
+            period_title: "Quarter 1".to_string(),
+            assignment_id,
+            category_id,
+            grade: Some(grade),
+            max_points,
+            exception: 0,
+        }
+    }
+}
+
+/// An enrollment of a user in a section
+#[derive(Debug, Clone)]
+pub(crate) struct MockEnrollment {
+    pub id: usize,
+    pub section_id: usize,
+    pub user_id: usize,
+    pub admin: bool,
+}
+
+/// A membership of a user in a group
+#[derive(Debug, Clone)]
+pub(crate) struct MockMembership {
+    pub id: usize,
+    pub group_id: usize,
+    pub user_id: usize,
+    pub admin: bool,
+}
+
+/// A token pair issued by the mock
+#[derive(Debug, Clone)]
+pub(crate) struct MockToken {
+    pub secret: String,
+    /// The user that owns (or authorized) the token
+    pub user_id: Option<usize>,
+}
+
+pub(crate) struct State {
+    pub users: HashMap<usize, MockUser>,
+    pub sections: BTreeMap<usize, MockSection>,
+    pub enrollments: Vec<MockEnrollment>,
+    pub groups: BTreeMap<usize, MockGroup>,
+    pub memberships: Vec<MockMembership>,
+    pub assignments: BTreeMap<usize, MockAssignment>,
+    pub events: BTreeMap<usize, MockEvent>,
+    pub updates: BTreeMap<usize, MockUpdate>,
+    pub threads: BTreeMap<usize, MockThread>,
+    pub categories: Vec<MockCategory>,
+    pub grades: Vec<MockGrade>,
+    pub request_tokens: HashMap<String, MockToken>,
+    pub access_tokens: HashMap<String, MockToken>,
+    /// Every `(timestamp, nonce)` pair that was used
+    pub nonces: HashSet<(String, String)>,
+    /// The number of requests with a valid signature
+    pub requests: usize,
+    /// The largest `limit` a list returns (Schoology caps it at 200)
+    pub max_page_size: usize,
+    /// The statuses the next requests are answered with instead
+    pub failures: VecDeque<u16>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            users: HashMap::new(),
+            sections: BTreeMap::new(),
+            enrollments: Vec::new(),
+            groups: BTreeMap::new(),
+            memberships: Vec::new(),
+            assignments: BTreeMap::new(),
+            events: BTreeMap::new(),
+            updates: BTreeMap::new(),
+            threads: BTreeMap::new(),
+            categories: Vec::new(),
+            grades: Vec::new(),
+            request_tokens: HashMap::new(),
+            access_tokens: HashMap::new(),
+            nonces: HashSet::new(),
+            requests: 0,
+            max_page_size: 200,
+            failures: VecDeque::new(),
+        }
+    }
+}
+
+pub(crate) struct Inner {
+    pub consumer_key: String,
+    pub consumer_secret: String,
+    pub base_url: Url,
+    pub state: Mutex<State>,
+}
+
+impl Inner {
+    pub fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        // A panicking test should not take the other tests down with it
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// A running mock Schoology server, it is stopped when dropped
+pub struct MockSchoology {
+    inner: web::Data<Inner>,
+    handle: ServerHandle,
+}
+
+impl MockSchoology {
+    /// Starts a mock server with the default consumer key and secret on a random local port
+    /// Must be called from within a tokio runtime
+    pub async fn start() -> std::io::Result<Self> {
+        Self::start_with_consumer(CONSUMER_KEY, CONSUMER_SECRET).await
+    }
+
+    /// Starts a mock server that accepts the given consumer key and secret
+    pub async fn start_with_consumer(
+        consumer_key: &str,
+        consumer_secret: &str,
+    ) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0))?;
+        let port = listener.local_addr()?.port();
+
+        let base_url = Url::parse(&format!("http://127.0.0.1:{}/v1/", port)).unwrap();
+
+        let inner = web::Data::new(Inner {
+            consumer_key: consumer_key.to_string(),
+            consumer_secret: consumer_secret.to_string(),
+            base_url,
+            state: Mutex::new(State::default()),
+        });
+
+        let data = inner.clone();
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(data.clone())
+                .wrap_fn(|req, srv| {
+                    let failure = req
+                        .app_data::<web::Data<Inner>>()
+                        .and_then(|inner| routes::failure(inner, req.request()));
+
+                    let response: LocalBoxFuture<_> = match failure {
+                        Some(response) => Box::pin(ready(Ok(req.into_response(response)))),
+                        None => Box::pin(srv.call(req)),
+                    };
+
+                    response
+                })
+                .service(web::scope("/v1").configure(routes::configure))
+        })
+        .workers(1)
+        .disable_signals()
+        .listen(listener)?
+        .run();
+
+        let handle = server.handle();
+        tokio::spawn(server);
+
+        debug!("Mock Schoology listening on port {}", port);
+
+        Ok(Self { inner, handle })
+    }
+
+    /// The base URL to pass to `SchoologyClient::with_base_url`
+    pub fn base_url(&self) -> Url {
+        self.inner.base_url.clone()
+    }
+
+    /// The consumer key the mock accepts
+    pub fn consumer_key(&self) -> &str {
+        &self.inner.consumer_key
+    }
+
+    /// The consumer secret the mock accepts
+    pub fn consumer_secret(&self) -> &str {
+        &self.inner.consumer_secret
+    }
+
+    /// Adds (or replaces) a user
+    pub fn add_user(&self, user: MockUser) {
+        self.inner.state().users.insert(user.id, user);
+    }
+
+    /// Adds (or replaces) a section
+    pub fn add_section(&self, section: MockSection) {
+        self.inner.state().sections.insert(section.id, section);
+    }
+
+    /// Enrolls a user in a section, `admin` enrolls them as a teacher
+    pub fn enroll(&self, section_id: usize, user_id: usize, admin: bool) {
+        let mut state = self.inner.state();
+
+        let id = state.enrollments.len() + 1;
+        state.enrollments.push(MockEnrollment {
+            id,
+            section_id,
+            user_id,
+            admin,
+        });
+    }
+
+    /// Adds (or replaces) a group
+    pub fn add_group(&self, group: MockGroup) {
+        self.inner.state().groups.insert(group.id, group);
+    }
+
+    /// Makes a user a member of a group, `admin` makes them an admin (an officer of a club)
+    pub fn join_group(&self, group_id: usize, user_id: usize, admin: bool) {
+        let mut state = self.inner.state();
+
+        let id = state.memberships.len() + 1;
+        state.memberships.push(MockMembership {
+            id,
+            group_id,
+            user_id,
+            admin,
+        });
+    }
+
+    /// Adds (or replaces) an assignment
+    pub fn add_assignment(&self, assignment: MockAssignment) {
+        self.inner
+            .state()
+            .assignments
+            .insert(assignment.id, assignment);
+    }
+
+    /// Adds (or replaces) a calendar event
+    pub fn add_event(&self, event: MockEvent) {
+        self.inner.state().events.insert(event.id, event);
+    }
+
+    /// Adds (or replaces) an update
+    pub fn add_update(&self, update: MockUpdate) {
+        self.inner.state().updates.insert(update.id, update);
+    }
+
+    /// Adds (or replaces) a message thread
+    pub fn add_thread(&self, thread: MockThread) {
+        self.inner.state().threads.insert(thread.id, thread);
+    }
+
+    /// Gets a message thread (with the replies it received)
+    pub fn thread(&self, thread_id: usize) -> Option<MockThread> {
+        self.inner.state().threads.get(&thread_id).cloned()
+    }
+
+    /// Adds a grading category
+    pub fn add_category(&self, category: MockCategory) {
+        self.inner.state().categories.push(category);
+    }
+
+    /// Adds (or replaces) the grade of a user on an assignment
+    pub fn add_grade(&self, grade: MockGrade) {
+        let mut state = self.inner.state();
+
+        state.grades.retain(|g| {
+            g.user_id != grade.user_id
+                || g.section_id != grade.section_id
+                || g.assignment_id != grade.assignment_id
+        });
+        state.grades.push(grade);
+    }
+
+    /// Caps the page size of lists, to test pagination without hundreds of items
+    pub fn set_max_page_size(&self, max_page_size: usize) {
+        self.inner.state().max_page_size = max_page_size;
+    }
+
+    /// Authorizes a request token as the given user, what the user does on Schoology's authorize page
+    /// Returns false if the request token does not exist
+    pub fn authorize(&self, request_token: &str, user_id: usize) -> bool {
+        match self.inner.state().request_tokens.get_mut(request_token) {
+            Some(token) => {
+                token.user_id = Some(user_id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Revokes all the access tokens of a user, what the user does when removing the app
+    pub fn revoke(&self, user_id: usize) {
+        self.inner
+            .state()
+            .access_tokens
+            .retain(|_, token| token.user_id != Some(user_id));
+    }
+
+    /// Issues an access token for a user without going through the OAuth flow
+    /// Returns the `(access_token, token_secret)` pair
+    pub fn issue_access_token(&self, user_id: usize) -> (String, String) {
+        let (token, secret) = new_token_pair();
+
+        self.inner.state().access_tokens.insert(
+            token.clone(),
+            MockToken {
+                secret: secret.clone(),
+                user_id: Some(user_id),
+            },
+        );
+
+        (token, secret)
+    }
+
+    /// Answers the next `count` requests with `status` (`429` comes with a `Retry-After`)
+    /// The nonces of the failed requests are used up, like Schoology does.
+    pub fn fail_requests(&self, status: u16, count: usize) {
+        self.inner
+            .state()
+            .failures
+            .extend(std::iter::repeat_n(status, count));
+    }
+
+    /// The number of requests with a valid signature the mock received
+    pub fn requests(&self) -> usize {
+        self.inner.state().requests
+    }
+}
+
+impl Drop for MockSchoology {
+    fn drop(&mut self) {
+        // Stop accepting connections (the future doesn't need to be awaited)
+        drop(self.handle.stop(false));
+    }
+}
+
+/// Generates a random token and token secret
+pub(crate) fn new_token_pair() -> (String, String) {
+    (
+        Uuid::new_v4().simple().to_string(),
+        Uuid::new_v4().simple().to_string(),
+    )
+}
@@ -0,0 +1,221 @@
+//! OAuth 1.0a signature verification (RFC 5849), written independently of the client's signing code
+
+use actix_web::{HttpMessage, HttpRequest, HttpResponse};
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::Inner;
+
+/// How far a timestamp may be from the server time (in seconds)
+const MAX_CLOCK_SKEW: i64 = 300;
+
+/// Which kind of token a request must be signed with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenKind {
+    /// Only the consumer key and secret
+    Consumer,
+    /// A request token (`/oauth/access_token`)
+    Request,
+    /// An access token (everything else)
+    Access,
+}
+
+/// RFC 3986 percent encoding, everything but the unreserved characters is encoded
+fn encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    encoded
+}
+
+/// Parses an `Authorization: OAuth k="v", ...` header
+fn parse_header(header: &str) -> Option<Vec<(String, String)>> {
+    let params = header.strip_prefix("OAuth ")?;
+
+    params
+        .split(',')
+        .map(str::trim)
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let (key, value) = param.split_once('=')?;
+            let value = value.strip_prefix('"')?.strip_suffix('"')?;
+
+            Some((
+                urlencoding::decode(key).ok()?.into_owned(),
+                urlencoding::decode(value).ok()?.into_owned(),
+            ))
+        })
+        .collect()
+}
+
+/// The `(timestamp, nonce)` pair of a request, if it has an OAuth header
+pub fn nonce(req: &HttpRequest) -> Option<(String, String)> {
+    let header = req.headers().get("Authorization")?.to_str().ok()?;
+    let params = parse_header(header)?;
+
+    let param = |name: &str| {
+        params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+    };
+
+    Some((param("oauth_timestamp")?, param("oauth_nonce")?))
+}
+
+/// The response Schoology sends for a rejected request
+pub fn unauthorized(message: &str) -> HttpResponse {
+    debug!("Mock Schoology rejected a request: {}", message);
+
+    HttpResponse::Unauthorized()
+        .content_type("text/plain")
+        .body(message.to_string())
+}
+
+/// Verifies the signature of a request, returns the token it was signed with or why it was rejected
+/// `body` is the request body, it's parameters are signed if it is a form (RFC 5849 3.4.1.3.1).
+pub fn verify(
+    inner: &Inner,
+    req: &HttpRequest,
+    body: &[u8],
+    kind: TokenKind,
+) -> Result<String, &'static str> {
+    let header = req
+        .headers()
+        .get("Authorization")
+        .and_then(|header| header.to_str().ok())
+        .ok_or("Missing OAuth authorization header")?;
+
+    let oauth_params = parse_header(header).ok_or("Malformed OAuth authorization header")?;
+
+    let param = |name: &str| {
+        oauth_params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    if param("oauth_consumer_key") != Some(inner.consumer_key.as_str()) {
+        return Err("Invalid consumer key");
+    }
+
+    if param("oauth_signature_method") != Some("HMAC-SHA256") {
+        return Err("Unsupported signature method");
+    }
+
+    if !matches!(param("oauth_version"), None | Some("1.0")) {
+        return Err("Unsupported OAuth version");
+    }
+
+    let signature = param("oauth_signature").ok_or("Missing signature")?;
+    let nonce = param("oauth_nonce").ok_or("Missing nonce")?;
+    let timestamp = param("oauth_timestamp").ok_or("Missing timestamp")?;
+
+    let time = timestamp.parse::<i64>().map_err(|_| "Invalid timestamp")?;
+
+    if (chrono::Utc::now().timestamp() - time).abs() > MAX_CLOCK_SKEW {
+        return Err("Timestamp is too old or in the future");
+    }
+
+    let mut state = inner.state();
+
+    // Find the token secret
+    let token = param("oauth_token");
+    let token_secret = match (kind, token) {
+        (TokenKind::Consumer, None) => None,
+        (TokenKind::Request, Some(token)) => state
+            .request_tokens
+            .get(token)
+            .map(|token| token.secret.clone()),
+        (TokenKind::Access, Some(token)) => state
+            .access_tokens
+            .get(token)
+            .map(|token| token.secret.clone()),
+        _ => None,
+    };
+
+    if kind != TokenKind::Consumer && token_secret.is_none() {
+        return Err("Invalid or expired token");
+    }
+
+    // Only form bodies are signed, JSON bodies are not
+    let form_body = if req.content_type() == "application/x-www-form-urlencoded" {
+        body
+    } else {
+        &[]
+    };
+
+    // Collect the parameters (oauth parameters, the query string and the form body)
+    let mut params = oauth_params
+        .iter()
+        .filter(|(key, _)| key != "oauth_signature")
+        .map(|(key, value)| (encode(key), encode(value)))
+        .chain(
+            url::form_urlencoded::parse(req.query_string().as_bytes())
+                .chain(url::form_urlencoded::parse(form_body))
+                .map(|(key, value)| (encode(&key), encode(&value))),
+        )
+        .collect::<Vec<(String, String)>>();
+
+    params.sort();
+
+    let params = params
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<String>>()
+        .join("&");
+
+    // The base string URI is the scheme, host and path of the request
+    let uri = format!(
+        "{}{}",
+        inner.base_url.origin().ascii_serialization(),
+        req.path()
+    );
+
+    let base_string = format!(
+        "{}&{}&{}",
+        req.method().as_str(),
+        encode(&uri),
+        encode(&params)
+    );
+
+    let signing_key = format!(
+        "{}&{}",
+        encode(&inner.consumer_secret),
+        encode(token_secret.as_deref().unwrap_or(""))
+    );
+
+    let signature = STANDARD_NO_PAD
+        .decode(signature.trim_end_matches('='))
+        .map_err(|_| "Malformed signature")?;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(signing_key.as_bytes()).unwrap();
+    mac.update(base_string.as_bytes());
+
+    if mac.verify_slice(&signature).is_err() {
+        return Err("Invalid signature");
+    }
+
+    // Only checked once the signature is valid so nonces can't be burned by others
+    if !state
+        .nonces
+        .insert((timestamp.to_string(), nonce.to_string()))
+    {
+        return Err(
+            "Duplicate timestamp/nonce combination, possible replay attack.  Request rejected.",
+        );
+    }
+
+    state.requests += 1;
+
+    Ok(token.unwrap_or_default().to_string())
+}
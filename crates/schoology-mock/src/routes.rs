@@ -0,0 +1,1306 @@
+use actix_web::{
+    http::{header, StatusCode},
+    web, HttpRequest, HttpResponse,
+};
+use serde_json::{json, Value};
+
+use crate::{
+    new_token_pair,
+    oauth::{nonce, unauthorized, verify, TokenKind},
+    Inner, MockAssignment, MockEvent, MockGrade, MockGroup, MockMessage, MockSection, MockThread,
+    MockToken, MockUpdate, State,
+};
+
+/// How long request tokens are valid for (in seconds), reported as `xoauth_token_ttl`
+const REQUEST_TOKEN_TTL: usize = 3600;
+/// The page size when no `limit` is given
+const DEFAULT_PAGE_SIZE: usize = 20;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    // Like Schoology, the OAuth endpoints also take signed form POSTs
+    cfg.route("/oauth/request_token", web::get().to(request_token))
+        .route("/oauth/request_token", web::post().to(request_token))
+        .route("/oauth/access_token", web::get().to(access_token))
+        .route("/oauth/access_token", web::post().to(access_token))
+        .route("/recent", web::get().to(recent))
+        .route("/users/me", web::get().to(users_me))
+        .route("/users/{id}", web::get().to(user))
+        .route("/users/{id}/sections", web::get().to(user_sections))
+        .route("/users/{id}/events", web::get().to(user_events))
+        .route("/users/{id}/grades", web::get().to(user_grades))
+        .route("/users/{id}/groups", web::get().to(user_groups))
+        .route("/groups/{id}", web::get().to(group))
+        .route("/groups/{id}/enrollments", web::get().to(group_enrollments))
+        .route("/groups/{id}/events", web::get().to(group_events))
+        .route("/groups/{id}/updates", web::get().to(group_updates))
+        .route("/sections/{id}", web::get().to(section))
+        .route(
+            "/sections/{id}/enrollments",
+            web::get().to(section_enrollments),
+        )
+        .route(
+            "/sections/{id}/assignments",
+            web::get().to(section_assignments),
+        )
+        .route("/sections/{id}/events", web::get().to(section_events))
+        .route("/sections/{id}/updates", web::get().to(section_updates))
+        .route(
+            "/{realm}/{id}/updates/{update_id}/comments",
+            web::get().to(update_comments),
+        )
+        .route("/courses/{id}", web::get().to(course))
+        .route("/messages/inbox", web::get().to(messages_inbox))
+        .route("/messages/sent", web::get().to(messages_sent))
+        .route("/messages/inbox/{id}", web::get().to(message_thread))
+        .route("/messages/{id}", web::post().to(message_reply));
+}
+
+fn form_response(params: &[(&str, &str)]) -> HttpResponse {
+    let body = url::form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(params)
+        .finish();
+
+    HttpResponse::Ok()
+        .content_type("application/x-www-form-urlencoded")
+        .body(body)
+}
+
+fn not_found(message: &str) -> HttpResponse {
+    HttpResponse::NotFound().json(json!({
+        "response_code": 404,
+        "message": message
+    }))
+}
+
+/// Returns a page of a list like Schoology, `{ <key>: [...], "total": "<n>", "links": { ... } }`
+fn list(req: &HttpRequest, inner: &Inner, key: &str, items: Vec<Value>) -> HttpResponse {
+    let query =
+        web::Query::<std::collections::HashMap<String, String>>::from_query(req.query_string())
+            .map(|query| query.into_inner())
+            .unwrap_or_default();
+
+    let start = query
+        .get("start")
+        .and_then(|start| start.parse::<usize>().ok())
+        .unwrap_or(0);
+    let limit = query
+        .get("limit")
+        .and_then(|limit| limit.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .min(inner.state().max_page_size);
+
+    let total = items.len();
+    let page = items
+        .into_iter()
+        .skip(start)
+        .take(limit)
+        .collect::<Vec<Value>>();
+
+    let link = |start: usize| {
+        let mut url = inner
+            .base_url
+            .join(req.path().trim_start_matches("/v1/"))
+            .unwrap();
+        url.query_pairs_mut()
+            .append_pair("start", &start.to_string())
+            .append_pair("limit", &limit.to_string());
+        url.to_string()
+    };
+
+    let mut links = json!({ "self": link(start) });
+    if start + limit < total {
+        links["next"] = json!(link(start + limit));
+    }
+
+    HttpResponse::Ok().json(json!({
+        key: page,
+        "total": total.to_string(),
+        "links": links
+    }))
+}
+
+fn section_json(section: &MockSection) -> Value {
+    // Schoology sends most ids and flags as strings
+    json!({
+        "id": section.id.to_string(),
+        "course_id": section.course_id.to_string(),
+        "course_title": section.course_title,
+        "course_code": section.course_code,
+        "section_title": section.section_title,
+        "section_code": "",
+        "school_id": "1",
+        "description": "",
+        "location": null,
+        "profile_url": "",
+        "active": if section.active { 1 } else { 0 },
+        "grading_periods": ["1"]
+    })
+}
+
+fn assignment_json(assignment: &MockAssignment) -> Value {
+    json!({
+        "id": assignment.id.to_string(),
+        "title": assignment.title,
+        "description": "",
+        "due": assignment.due,
+        "type": assignment.kind,
+        "max_points": assignment.max_points.map(|points| points.to_string()).unwrap_or_default(),
+        "grading_period": "1",
+        "grading_category": "1",
+        "completed": if assignment.completed { 1 } else { 0 }
+    })
+}
+
+fn group_json(group: &MockGroup) -> Value {
+    json!({
+        "id": group.id.to_string(),
+        "title": group.title,
+        "description": group.description,
+        "picture_url": format!("https://example.com/groups/{}.png", group.id),
+        "website": "",
+        "access_code": null,
+        "category": group.category,
+        "group_code": "",
+        "privacy_level": "school",
+        "school_id": "1",
+        "building_id": "1"
+    })
+}
+
+/// The realm of an event or update, the school when it has no section or group
+fn realm(section_id: Option<usize>, group_id: Option<usize>) -> &'static str {
+    match (section_id, group_id) {
+        (Some(_), _) => "section",
+        (None, Some(_)) => "group",
+        (None, None) => "school",
+    }
+}
+
+fn school_id(section_id: Option<usize>, group_id: Option<usize>) -> Option<&'static str> {
+    match (section_id, group_id) {
+        (None, None) => Some("1"),
+        _ => None,
+    }
+}
+
+fn event_json(event: &MockEvent) -> Value {
+    json!({
+        "id": event.id.to_string(),
+        "title": event.title,
+        "description": event.description,
+        "start": event.start,
+        "has_end": if event.end.is_empty() { 0 } else { 1 },
+        "end": if event.end.is_empty() { None } else { Some(&event.end) },
+        "all_day": if event.all_day { 1 } else { 0 },
+        "rsvp": if event.rsvp { 1 } else { 0 },
+        "rsvp_status": if event.rsvp_status.is_empty() { None } else { Some(&event.rsvp_status) },
+        "type": event.kind,
+        "assignment_id": event.assignment_id.map(|id| id.to_string()),
+        "realm": realm(event.section_id, event.group_id),
+        "section_id": event.section_id.map(|id| id.to_string()),
+        "group_id": event.group_id.map(|id| id.to_string()),
+        "school_id": school_id(event.section_id, event.group_id)
+    })
+}
+
+/// The due date of an assignment on the calendar
+fn assignment_event(assignment: &MockAssignment) -> MockEvent {
+    MockEvent {
+        title: assignment.title.clone(),
+        kind: assignment.kind.clone(),
+        assignment_id: Some(assignment.id),
+        ..MockEvent::new(
+            assignment.id + 1_000_000,
+            Some(assignment.section_id),
+            &assignment.due,
+        )
+    }
+}
+
+/// Whether `start` (`YYYY-MM-DD ...`) is within the `start_date` and `end_date` of the query
+/// Dates are compared as `YYYY-MM-DD` strings
+fn in_date_range(req: &HttpRequest, start: &str) -> bool {
+    let query =
+        web::Query::<std::collections::HashMap<String, String>>::from_query(req.query_string())
+            .map(|query| query.into_inner())
+            .unwrap_or_default();
+
+    let date = start.get(..10).unwrap_or("");
+
+    query
+        .get("start_date")
+        .is_none_or(|start_date| date >= start_date.as_str())
+        && query
+            .get("end_date")
+            .is_none_or(|end_date| date <= end_date.as_str())
+}
+
+fn update_json(update: &MockUpdate) -> Value {
+    let attachments = |kind: &str| {
+        update
+            .attachments
+            .iter()
+            .filter(|attachment| attachment.kind == kind)
+            .map(|attachment| {
+                json!({
+                    "id": attachment.id.to_string(),
+                    "title": attachment.title,
+                    "url": attachment.url,
+                    "filename": format!("{}.pdf", attachment.title),
+                    "filesize": "1024",
+                    "download_path": attachment.url
+                })
+            })
+            .collect::<Vec<Value>>()
+    };
+
+    json!({
+        "id": update.id.to_string(),
+        "uid": update.author_id.to_string(),
+        "body": update.body,
+        "created": update.created.to_string(),
+        "last_updated": update.created.to_string(),
+        "likes": update.likes.to_string(),
+        "user_like_action": false,
+        "num_comments": update.comments.len().to_string(),
+        "realm": realm(update.section_id, update.group_id),
+        "section_id": update.section_id.map(|id| id.to_string()),
+        "group_id": update.group_id.map(|id| id.to_string()),
+        "school_id": school_id(update.section_id, update.group_id),
+        // Schoology sends an empty list when there are no attachments
+        "attachments": if update.attachments.is_empty() {
+            json!([])
+        } else {
+            json!({
+                "files": { "file": attachments("file") },
+                "links": { "link": attachments("link") },
+                "videos": { "video": attachments("video") }
+            })
+        }
+    })
+}
+
+/// Sorts updates like Schoology, the most recent first
+fn sort_updates(updates: &mut [MockUpdate]) {
+    updates.sort_by(|a, b| b.created.cmp(&a.created).then(b.id.cmp(&a.id)));
+}
+
+/// Whether a user started or received a thread
+fn in_thread(thread: &MockThread, user_id: usize) -> bool {
+    thread.author_id == user_id || thread.recipient_ids.contains(&user_id)
+}
+
+fn recipient_ids(ids: &[usize]) -> String {
+    ids.iter()
+        .map(usize::to_string)
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// A thread of a folder, `status` is `read`, `unread` or `sent`
+fn thread_json(thread: &MockThread, status: &str) -> Value {
+    let last_updated = thread
+        .messages
+        .last()
+        .map(|message| message.created)
+        .unwrap_or(0);
+
+    json!({
+        "id": thread.id.to_string(),
+        "subject": thread.subject,
+        "recipient_ids": recipient_ids(&thread.recipient_ids),
+        "last_updated": last_updated.to_string(),
+        "author_id": thread.author_id.to_string(),
+        "message_status": status
+    })
+}
+
+fn message_json(thread: &MockThread, message: &MockMessage, status: &str) -> Value {
+    // Everyone in the thread but the author
+    let recipients = std::iter::once(thread.author_id)
+        .chain(thread.recipient_ids.iter().copied())
+        .filter(|id| *id != message.author_id)
+        .collect::<Vec<usize>>();
+
+    json!({
+        "id": message.id.to_string(),
+        "subject": thread.subject,
+        "recipient_ids": recipient_ids(&recipients),
+        "last_updated": message.created.to_string(),
+        "author_id": message.author_id.to_string(),
+        "message_status": status,
+        "message": message.body
+    })
+}
+
+/// Sorts threads like Schoology, the most recently updated first
+fn sort_threads(threads: &mut [&MockThread]) {
+    let last_updated = |thread: &MockThread| thread.messages.last().map(|m| m.created);
+
+    threads.sort_by(|a, b| last_updated(b).cmp(&last_updated(a)).then(b.id.cmp(&a.id)));
+}
+
+/// Whether a user is a member of a group
+fn is_member(state: &State, group_id: usize, user_id: usize) -> bool {
+    state
+        .memberships
+        .iter()
+        .any(|m| m.group_id == group_id && m.user_id == user_id)
+}
+
+/// Whether a user can see the events and updates of a realm, everyone can see the school's
+fn can_see(
+    state: &State,
+    section_id: Option<usize>,
+    group_id: Option<usize>,
+    user_id: usize,
+) -> bool {
+    match (section_id, group_id) {
+        (Some(section_id), _) => is_enrolled(state, section_id, user_id),
+        (None, Some(group_id)) => is_member(state, group_id, user_id),
+        (None, None) => true,
+    }
+}
+
+/// Whether a user is enrolled in a section
+fn is_enrolled(state: &State, section_id: usize, user_id: usize) -> bool {
+    state
+        .enrollments
+        .iter()
+        .any(|e| e.section_id == section_id && e.user_id == user_id)
+}
+
+async fn request_token(
+    req: HttpRequest,
+    inner: web::Data<Inner>,
+    body: web::Bytes,
+) -> HttpResponse {
+    if let Err(message) = verify(&inner, &req, &body, TokenKind::Consumer) {
+        return unauthorized(message);
+    }
+
+    let (token, secret) = new_token_pair();
+
+    inner.state().request_tokens.insert(
+        token.clone(),
+        MockToken {
+            secret: secret.clone(),
+            user_id: None,
+        },
+    );
+
+    form_response(&[
+        ("oauth_token", &token),
+        ("oauth_token_secret", &secret),
+        ("xoauth_token_ttl", &REQUEST_TOKEN_TTL.to_string()),
+    ])
+}
+
+async fn access_token(req: HttpRequest, inner: web::Data<Inner>, body: web::Bytes) -> HttpResponse {
+    let request_token = match verify(&inner, &req, &body, TokenKind::Request) {
+        Ok(token) => token,
+        Err(message) => return unauthorized(message),
+    };
+
+    let mut state = inner.state();
+
+    // The user must have authorized the request token first
+    let user_id = match state
+        .request_tokens
+        .get(&request_token)
+        .and_then(|token| token.user_id)
+    {
+        Some(user_id) => user_id,
+        None => return unauthorized("Request token has not been authorized"),
+    };
+
+    // Request tokens can only be exchanged once
+    state.request_tokens.remove(&request_token);
+
+    let (token, secret) = new_token_pair();
+
+    state.access_tokens.insert(
+        token.clone(),
+        MockToken {
+            secret: secret.clone(),
+            user_id: Some(user_id),
+        },
+    );
+
+    form_response(&[("oauth_token", &token), ("oauth_token_secret", &secret)])
+}
+
+/// Returns the user that owns an access token
+/// The response of a request that should fail, see `MockSchoology::fail_requests`
+pub fn failure(inner: &Inner, req: &HttpRequest) -> Option<HttpResponse> {
+    let mut state = inner.state();
+    let status = state.failures.pop_front()?;
+
+    if let Some(nonce) = nonce(req) {
+        state.nonces.insert(nonce);
+    }
+
+    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let mut response = HttpResponse::build(status);
+
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        response.insert_header((header::RETRY_AFTER, "1"));
+    }
+
+    Some(response.json(json!({ "error": status.canonical_reason() })))
+}
+
+fn token_user(inner: &Inner, token: &str) -> Option<usize> {
+    inner
+        .state()
+        .access_tokens
+        .get(token)
+        .and_then(|token| token.user_id)
+}
+
+async fn users_me(req: HttpRequest, inner: web::Data<Inner>) -> HttpResponse {
+    let user_id = match verify(&inner, &req, &[], TokenKind::Access) {
+        Ok(token) => token_user(&inner, &token),
+        Err(message) => return unauthorized(message),
+    };
+
+    let Some(user_id) = user_id else {
+        return HttpResponse::Unauthorized().finish();
+    };
+
+    // Schoology redirects to the user instead of returning it
+    let location = inner.base_url.join(&format!("users/{}", user_id)).unwrap();
+
+    HttpResponse::SeeOther()
+        .insert_header((header::LOCATION, location.to_string()))
+        .finish()
+}
+
+async fn user(req: HttpRequest, inner: web::Data<Inner>, path: web::Path<String>) -> HttpResponse {
+    if let Err(message) = verify(&inner, &req, &[], TokenKind::Access) {
+        return unauthorized(message);
+    }
+
+    let user = path
+        .parse::<usize>()
+        .ok()
+        .and_then(|id| inner.state().users.get(&id).cloned());
+
+    match user {
+        Some(user) => HttpResponse::Ok().json(user),
+        None => not_found("User not found"),
+    }
+}
+
+async fn user_sections(
+    req: HttpRequest,
+    inner: web::Data<Inner>,
+    path: web::Path<usize>,
+) -> HttpResponse {
+    if let Err(message) = verify(&inner, &req, &[], TokenKind::Access) {
+        return unauthorized(message);
+    }
+
+    let user_id = path.into_inner();
+
+    let sections = {
+        let state = inner.state();
+
+        if !state.users.contains_key(&user_id) {
+            return not_found("User not found");
+        }
+
+        state
+            .sections
+            .values()
+            .filter(|section| is_enrolled(&state, section.id, user_id))
+            .map(section_json)
+            .collect()
+    };
+
+    list(&req, &inner, "section", sections)
+}
+
+async fn section(
+    req: HttpRequest,
+    inner: web::Data<Inner>,
+    path: web::Path<usize>,
+) -> HttpResponse {
+    if let Err(message) = verify(&inner, &req, &[], TokenKind::Access) {
+        return unauthorized(message);
+    }
+
+    let section = inner.state().sections.get(&path).map(section_json);
+
+    match section {
+        Some(section) => HttpResponse::Ok().json(section),
+        None => not_found("Section not found"),
+    }
+}
+
+async fn section_enrollments(
+    req: HttpRequest,
+    inner: web::Data<Inner>,
+    path: web::Path<usize>,
+) -> HttpResponse {
+    if let Err(message) = verify(&inner, &req, &[], TokenKind::Access) {
+        return unauthorized(message);
+    }
+
+    let section_id = path.into_inner();
+
+    let enrollments = {
+        let state = inner.state();
+
+        if !state.sections.contains_key(&section_id) {
+            return not_found("Section not found");
+        }
+
+        state
+            .enrollments
+            .iter()
+            .filter(|e| e.section_id == section_id)
+            .filter_map(|e| {
+                let user = state.users.get(&e.user_id)?;
+
+                Some(json!({
+                    "id": e.id.to_string(),
+                    "uid": user.id.to_string(),
+                    "name_first": user.name_first,
+                    "name_last": user.name_last,
+                    "picture_url": user.picture_url,
+                    "admin": if e.admin { 1 } else { 0 },
+                    "status": "1"
+                }))
+            })
+            .collect()
+    };
+
+    list(&req, &inner, "enrollment", enrollments)
+}
+
+async fn course(req: HttpRequest, inner: web::Data<Inner>, path: web::Path<usize>) -> HttpResponse {
+    if let Err(message) = verify(&inner, &req, &[], TokenKind::Access) {
+        return unauthorized(message);
+    }
+
+    let course = inner
+        .state()
+        .sections
+        .values()
+        .find(|section| section.course_id == *path)
+        .map(|section| {
+            json!({
+                "id": section.course_id.to_string(),
+                "title": section.course_title,
+                "course_code": section.course_code,
+                "department": "",
+                "description": "",
+                "subject_area": "0",
+                "building_id": "1"
+            })
+        });
+
+    match course {
+        Some(course) => HttpResponse::Ok().json(course),
+        None => not_found("Course not found"),
+    }
+}
+
+async fn section_assignments(
+    req: HttpRequest,
+    inner: web::Data<Inner>,
+    path: web::Path<usize>,
+) -> HttpResponse {
+    if let Err(message) = verify(&inner, &req, &[], TokenKind::Access) {
+        return unauthorized(message);
+    }
+
+    let section_id = path.into_inner();
+
+    let assignments = {
+        let state = inner.state();
+
+        if !state.sections.contains_key(&section_id) {
+            return not_found("Section not found");
+        }
+
+        state
+            .assignments
+            .values()
+            .filter(|assignment| assignment.section_id == section_id)
+            .map(assignment_json)
+            .collect()
+    };
+
+    list(&req, &inner, "assignment", assignments)
+}
+
+async fn user_events(
+    req: HttpRequest,
+    inner: web::Data<Inner>,
+    path: web::Path<usize>,
+) -> HttpResponse {
+    if let Err(message) = verify(&inner, &req, &[], TokenKind::Access) {
+        return unauthorized(message);
+    }
+
+    let user_id = path.into_inner();
+
+    let events = {
+        let state = inner.state();
+
+        if !state.users.contains_key(&user_id) {
+            return not_found("User not found");
+        }
+
+        // Assignments with a due date are on the calendar too
+        let assignment_events = state
+            .assignments
+            .values()
+            .filter(|assignment| !assignment.due.is_empty())
+            .map(assignment_event);
+
+        let mut events = state
+            .events
+            .values()
+            .cloned()
+            .chain(assignment_events)
+            .filter(|event| can_see(&state, event.section_id, event.group_id, user_id))
+            .filter(|event| in_date_range(&req, &event.start))
+            .collect::<Vec<MockEvent>>();
+
+        events.sort_by(|a, b| a.start.cmp(&b.start));
+
+        events.iter().map(event_json).collect()
+    };
+
+    list(&req, &inner, "event", events)
+}
+
+async fn section_events(
+    req: HttpRequest,
+    inner: web::Data<Inner>,
+    path: web::Path<usize>,
+) -> HttpResponse {
+    if let Err(message) = verify(&inner, &req, &[], TokenKind::Access) {
+        return unauthorized(message);
+    }
+
+    let section_id = path.into_inner();
+
+    let events = {
+        let state = inner.state();
+
+        if !state.sections.contains_key(&section_id) {
+            return not_found("Section not found");
+        }
+
+        let assignment_events = state
+            .assignments
+            .values()
+            .filter(|assignment| !assignment.due.is_empty())
+            .map(assignment_event);
+
+        let mut events = state
+            .events
+            .values()
+            .cloned()
+            .chain(assignment_events)
+            .filter(|event| event.section_id == Some(section_id))
+            .filter(|event| in_date_range(&req, &event.start))
+            .collect::<Vec<MockEvent>>();
+
+        events.sort_by(|a, b| a.start.cmp(&b.start));
+
+        events.iter().map(event_json).collect()
+    };
+
+    list(&req, &inner, "event", events)
+}
+
+/// `100 * points / max points` of the graded (and not excused) grades, rounded to 2 decimals
+fn percent<'a>(grades: impl Iterator<Item = &'a MockGrade>) -> Option<f64> {
+    let (points, max_points) = grades
+        .filter(|grade| grade.exception != 1)
+        .filter_map(|grade| Some((grade.grade?, grade.max_points)))
+        .fold((0.0, 0.0), |(points, max), (grade, max_points)| {
+            (points + grade, max + max_points)
+        });
+
+    if max_points > 0.0 {
+        Some((points / max_points * 10000.0).round() / 100.0)
+    } else {
+        None
+    }
+}
+
+async fn user_grades(
+    req: HttpRequest,
+    inner: web::Data<Inner>,
+    path: web::Path<usize>,
+) -> HttpResponse {
+    if let Err(message) = verify(&inner, &req, &[], TokenKind::Access) {
+        return unauthorized(message);
+    }
+
+    let user_id = path.into_inner();
+    let state = inner.state();
+
+    if !state.users.contains_key(&user_id) {
+        return not_found("User not found");
+    }
+
+    let grades = state
+        .grades
+        .iter()
+        .filter(|grade| grade.user_id == user_id)
+        .collect::<Vec<&MockGrade>>();
+
+    // Group by section and grading period
+    let mut sections = std::collections::BTreeMap::new();
+    for grade in &grades {
+        sections
+            .entry(grade.section_id)
+            .or_insert_with(std::collections::BTreeMap::new)
+            .entry(grade.period_id.clone())
+            .or_insert_with(Vec::new)
+            .push(*grade);
+    }
+
+    let sections = sections
+        .into_iter()
+        .map(|(section_id, periods)| {
+            let categories = state
+                .categories
+                .iter()
+                .filter(|category| category.section_id == section_id)
+                .collect::<Vec<_>>();
+
+            let final_grades = periods
+                .iter()
+                .map(|(period_id, grades)| {
+                    let category_grades = categories
+                        .iter()
+                        .filter_map(|category| {
+                            let grade = percent(
+                                grades
+                                    .iter()
+                                    .copied()
+                                    .filter(|grade| grade.category_id == category.id),
+                            )?;
+
+                            Some((category, grade))
+                        })
+                        .collect::<Vec<_>>();
+
+                    // Weighted by category if the section has weights
+                    let weight = category_grades
+                        .iter()
+                        .filter_map(|(category, _)| category.weight)
+                        .sum::<f64>();
+
+                    let grade = if weight > 0.0 {
+                        let points = category_grades
+                            .iter()
+                            .map(|(category, grade)| category.weight.unwrap_or(0.0) * grade)
+                            .sum::<f64>();
+
+                        Some((points / weight * 100.0).round() / 100.0)
+                    } else {
+                        percent(grades.iter().copied())
+                    };
+
+                    json!({
+                        "period_id": period_id,
+                        "grade": grade,
+                        "weight": null,
+                        "comment": "",
+                        "grading_category": category_grades
+                            .iter()
+                            .map(|(category, grade)| json!({
+                                "category_id": category.id.to_string(),
+                                "grade": grade
+                            }))
+                            .collect::<Vec<Value>>()
+                    })
+                })
+                .collect::<Vec<Value>>();
+
+            json!({
+                "section_id": section_id.to_string(),
+                "period": periods
+                    .iter()
+                    .map(|(period_id, grades)| json!({
+                        "period_id": period_id,
+                        "period_title": grades[0].period_title,
+                        "assignment": grades
+                            .iter()
+                            .map(|grade| json!({
+                                "enrollment_id": "1",
+                                "assignment_id": grade.assignment_id.to_string(),
+                                "grade": grade.grade,
+                                "max_points": grade.max_points.to_string(),
+                                "exception": grade.exception,
+                                "type": "assignment",
+                                "category_id": grade.category_id.to_string(),
+                                "comment": ""
+                            }))
+                            .collect::<Vec<Value>>()
+                    }))
+                    .collect::<Vec<Value>>(),
+                "final_grade": final_grades,
+                "grading_category": categories
+                    .iter()
+                    .map(|category| json!({
+                        "id": category.id.to_string(),
+                        "title": category.title,
+                        "weight": category.weight,
+                        "calculation_type": 1
+                    }))
+                    .collect::<Vec<Value>>()
+            })
+        })
+        .collect::<Vec<Value>>();
+
+    HttpResponse::Ok().json(json!({ "section": sections }))
+}
+
+async fn recent(req: HttpRequest, inner: web::Data<Inner>) -> HttpResponse {
+    let user_id = match verify(&inner, &req, &[], TokenKind::Access) {
+        Ok(token) => token_user(&inner, &token),
+        Err(message) => return unauthorized(message),
+    };
+
+    let Some(user_id) = user_id else {
+        return HttpResponse::Unauthorized().finish();
+    };
+
+    let updates = {
+        let state = inner.state();
+
+        let mut updates = state
+            .updates
+            .values()
+            .filter(|update| can_see(&state, update.section_id, update.group_id, user_id))
+            .cloned()
+            .collect::<Vec<MockUpdate>>();
+
+        sort_updates(&mut updates);
+
+        updates.iter().map(update_json).collect()
+    };
+
+    list(&req, &inner, "update", updates)
+}
+
+async fn section_updates(
+    req: HttpRequest,
+    inner: web::Data<Inner>,
+    path: web::Path<usize>,
+) -> HttpResponse {
+    if let Err(message) = verify(&inner, &req, &[], TokenKind::Access) {
+        return unauthorized(message);
+    }
+
+    let section_id = path.into_inner();
+
+    let updates = {
+        let state = inner.state();
+
+        if !state.sections.contains_key(&section_id) {
+            return not_found("Section not found");
+        }
+
+        let mut updates = state
+            .updates
+            .values()
+            .filter(|update| update.section_id == Some(section_id))
+            .cloned()
+            .collect::<Vec<MockUpdate>>();
+
+        sort_updates(&mut updates);
+
+        // The feed of a realm doesn't say where the updates were posted
+        updates
+            .iter()
+            .map(|update| {
+                let mut json = update_json(update);
+                let json_object = json.as_object_mut().unwrap();
+                for key in ["realm", "section_id", "group_id", "school_id"] {
+                    json_object.remove(key);
+                }
+                json
+            })
+            .collect()
+    };
+
+    list(&req, &inner, "update", updates)
+}
+
+async fn update_comments(
+    req: HttpRequest,
+    inner: web::Data<Inner>,
+    path: web::Path<(String, usize, usize)>,
+) -> HttpResponse {
+    if let Err(message) = verify(&inner, &req, &[], TokenKind::Access) {
+        return unauthorized(message);
+    }
+
+    let (realm, realm_id, update_id) = path.into_inner();
+
+    let comments = {
+        let state = inner.state();
+
+        let update = state.updates.get(&update_id).filter(|update| {
+            match (realm.as_str(), update.section_id) {
+                ("sections", Some(section_id)) => section_id == realm_id,
+                ("schools", None) => realm_id == 1,
+                _ => false,
+            }
+        });
+
+        let Some(update) = update else {
+            return not_found("Update not found");
+        };
+
+        update
+            .comments
+            .iter()
+            .map(|comment| {
+                json!({
+                    "id": comment.id.to_string(),
+                    "uid": comment.author_id.to_string(),
+                    "comment": comment.body,
+                    "created": comment.created.to_string(),
+                    "parent_id": "0",
+                    "likes": "0"
+                })
+            })
+            .collect()
+    };
+
+    list(&req, &inner, "comment", comments)
+}
+
+async fn user_groups(
+    req: HttpRequest,
+    inner: web::Data<Inner>,
+    path: web::Path<usize>,
+) -> HttpResponse {
+    if let Err(message) = verify(&inner, &req, &[], TokenKind::Access) {
+        return unauthorized(message);
+    }
+
+    let user_id = path.into_inner();
+
+    let groups = {
+        let state = inner.state();
+
+        if !state.users.contains_key(&user_id) {
+            return not_found("User not found");
+        }
+
+        state
+            .groups
+            .values()
+            .filter(|group| is_member(&state, group.id, user_id))
+            .map(group_json)
+            .collect()
+    };
+
+    list(&req, &inner, "group", groups)
+}
+
+async fn group(req: HttpRequest, inner: web::Data<Inner>, path: web::Path<usize>) -> HttpResponse {
+    if let Err(message) = verify(&inner, &req, &[], TokenKind::Access) {
+        return unauthorized(message);
+    }
+
+    let group = inner.state().groups.get(&path).map(group_json);
+
+    match group {
+        Some(group) => HttpResponse::Ok().json(group),
+        None => not_found("Group not found"),
+    }
+}
+
+async fn group_enrollments(
+    req: HttpRequest,
+    inner: web::Data<Inner>,
+    path: web::Path<usize>,
+) -> HttpResponse {
+    if let Err(message) = verify(&inner, &req, &[], TokenKind::Access) {
+        return unauthorized(message);
+    }
+
+    let group_id = path.into_inner();
+
+    let enrollments = {
+        let state = inner.state();
+
+        if !state.groups.contains_key(&group_id) {
+            return not_found("Group not found");
+        }
+
+        state
+            .memberships
+            .iter()
+            .filter(|m| m.group_id == group_id)
+            .filter_map(|m| {
+                let user = state.users.get(&m.user_id)?;
+
+                Some(json!({
+                    "id": m.id.to_string(),
+                    "uid": user.id.to_string(),
+                    "name_first": user.name_first,
+                    "name_last": user.name_last,
+                    "picture_url": user.picture_url,
+                    "admin": if m.admin { 1 } else { 0 },
+                    "status": "1"
+                }))
+            })
+            .collect()
+    };
+
+    list(&req, &inner, "enrollment", enrollments)
+}
+
+async fn group_events(
+    req: HttpRequest,
+    inner: web::Data<Inner>,
+    path: web::Path<usize>,
+) -> HttpResponse {
+    if let Err(message) = verify(&inner, &req, &[], TokenKind::Access) {
+        return unauthorized(message);
+    }
+
+    let group_id = path.into_inner();
+
+    let events = {
+        let state = inner.state();
+
+        if !state.groups.contains_key(&group_id) {
+            return not_found("Group not found");
+        }
+
+        let mut events = state
+            .events
+            .values()
+            .filter(|event| event.group_id == Some(group_id))
+            .filter(|event| in_date_range(&req, &event.start))
+            .cloned()
+            .collect::<Vec<MockEvent>>();
+
+        events.sort_by(|a, b| a.start.cmp(&b.start));
+
+        events.iter().map(event_json).collect()
+    };
+
+    list(&req, &inner, "event", events)
+}
+
+async fn group_updates(
+    req: HttpRequest,
+    inner: web::Data<Inner>,
+    path: web::Path<usize>,
+) -> HttpResponse {
+    if let Err(message) = verify(&inner, &req, &[], TokenKind::Access) {
+        return unauthorized(message);
+    }
+
+    let group_id = path.into_inner();
+
+    let updates = {
+        let state = inner.state();
+
+        if !state.groups.contains_key(&group_id) {
+            return not_found("Group not found");
+        }
+
+        let mut updates = state
+            .updates
+            .values()
+            .filter(|update| update.section_id.is_none() && update.group_id == Some(group_id))
+            .cloned()
+            .collect::<Vec<MockUpdate>>();
+
+        sort_updates(&mut updates);
+
+        updates.iter().map(update_json).collect()
+    };
+
+    list(&req, &inner, "update", updates)
+}
+
+async fn messages_inbox(req: HttpRequest, inner: web::Data<Inner>) -> HttpResponse {
+    let user_id = match verify(&inner, &req, &[], TokenKind::Access) {
+        Ok(token) => token_user(&inner, &token),
+        Err(message) => return unauthorized(message),
+    };
+
+    let Some(user_id) = user_id else {
+        return HttpResponse::Unauthorized().finish();
+    };
+
+    let threads = {
+        let state = inner.state();
+
+        // Threads are in the inbox once someone else wrote in them
+        let mut threads = state
+            .threads
+            .values()
+            .filter(|thread| in_thread(thread, user_id))
+            .filter(|thread| thread.messages.iter().any(|m| m.author_id != user_id))
+            .collect::<Vec<&MockThread>>();
+
+        sort_threads(&mut threads);
+
+        threads
+            .into_iter()
+            .map(|thread| {
+                let status = match thread.unread.contains(&user_id) {
+                    true => "unread",
+                    false => "read",
+                };
+                thread_json(thread, status)
+            })
+            .collect()
+    };
+
+    list(&req, &inner, "message", threads)
+}
+
+async fn messages_sent(req: HttpRequest, inner: web::Data<Inner>) -> HttpResponse {
+    let user_id = match verify(&inner, &req, &[], TokenKind::Access) {
+        Ok(token) => token_user(&inner, &token),
+        Err(message) => return unauthorized(message),
+    };
+
+    let Some(user_id) = user_id else {
+        return HttpResponse::Unauthorized().finish();
+    };
+
+    let threads = {
+        let state = inner.state();
+
+        let mut threads = state
+            .threads
+            .values()
+            .filter(|thread| thread.author_id == user_id)
+            .collect::<Vec<&MockThread>>();
+
+        sort_threads(&mut threads);
+
+        threads
+            .into_iter()
+            .map(|thread| thread_json(thread, "sent"))
+            .collect()
+    };
+
+    list(&req, &inner, "message", threads)
+}
+
+async fn message_thread(
+    req: HttpRequest,
+    inner: web::Data<Inner>,
+    path: web::Path<usize>,
+) -> HttpResponse {
+    let user_id = match verify(&inner, &req, &[], TokenKind::Access) {
+        Ok(token) => token_user(&inner, &token),
+        Err(message) => return unauthorized(message),
+    };
+
+    let Some(user_id) = user_id else {
+        return HttpResponse::Unauthorized().finish();
+    };
+
+    let messages = {
+        let mut state = inner.state();
+
+        let thread = state
+            .threads
+            .get_mut(&path)
+            .filter(|thread| in_thread(thread, user_id));
+
+        let Some(thread) = thread else {
+            return not_found("Message not found");
+        };
+
+        // Reading a thread marks it as read
+        thread.unread.remove(&user_id);
+
+        thread
+            .messages
+            .iter()
+            .map(|message| message_json(thread, message, "read"))
+            .collect()
+    };
+
+    list(&req, &inner, "message", messages)
+}
+
+async fn message_reply(
+    req: HttpRequest,
+    inner: web::Data<Inner>,
+    path: web::Path<usize>,
+    body: web::Bytes,
+) -> HttpResponse {
+    let user_id = match verify(&inner, &req, &body, TokenKind::Access) {
+        Ok(token) => token_user(&inner, &token),
+        Err(message) => return unauthorized(message),
+    };
+
+    let Some(user_id) = user_id else {
+        return HttpResponse::Unauthorized().finish();
+    };
+
+    let body = serde_json::from_slice::<Value>(&body)
+        .ok()
+        .and_then(|body| body.get("message")?.as_str().map(str::to_string))
+        .filter(|body| !body.trim().is_empty());
+
+    let Some(body) = body else {
+        return HttpResponse::BadRequest().json(json!({
+            "response_code": 400,
+            "message": "A message is required"
+        }));
+    };
+
+    let mut state = inner.state();
+
+    let id = state
+        .threads
+        .values()
+        .flat_map(|thread| thread.messages.iter().map(|message| message.id))
+        .chain(state.threads.keys().copied())
+        .max()
+        .unwrap_or(0)
+        + 1;
+
+    let thread = state
+        .threads
+        .get_mut(&path)
+        .filter(|thread| in_thread(thread, user_id));
+
+    let Some(thread) = thread else {
+        return not_found("Message not found");
+    };
+
+    let message = MockMessage {
+        id,
+        author_id: user_id,
+        body,
+        created: chrono::Utc::now().timestamp(),
+    };
+
+    thread.unread = std::iter::once(thread.author_id)
+        .chain(thread.recipient_ids.iter().copied())
+        .filter(|id| *id != user_id)
+        .collect();
+
+    let json = message_json(thread, &message, "sent");
+    thread.messages.push(message);
+
+    HttpResponse::Created().json(json)
+}
@@ -0,0 +1,178 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use futures::future::try_join_all;
+use serde::Deserialize;
+
+use crate::{
+    de,
+    error::SchoologyError,
+    events::{get_user_events, EventRealm},
+    pagination::get_all,
+    sections::{get_user_sections, SchoologySection},
+    SchoologyClient, SchoologyTokenPair,
+};
+
+/// The kind of graded item
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssignmentType {
+    Assignment,
+    Assessment,
+    Discussion,
+    Other,
+}
+
+impl AssignmentType {
+    fn parse(kind: &str) -> Self {
+        match kind {
+            "assignment" => AssignmentType::Assignment,
+            "assessment" | "assessment_v2" => AssignmentType::Assessment,
+            "discussion" => AssignmentType::Discussion,
+            _ => AssignmentType::Other,
+        }
+    }
+}
+
+/// An assignment (or other graded item) of a section
+#[derive(Debug, Clone)]
+pub struct Assignment {
+    pub id: usize,
+    pub section_id: usize,
+    pub title: String,
+    pub description: String,
+    /// When it is due (in the school's time zone)
+    pub due: Option<NaiveDateTime>,
+    pub kind: AssignmentType,
+    pub max_points: Option<f64>,
+    /// Whether the user marked it as completed (or submitted it)
+    pub completed: bool,
+}
+
+#[derive(Deserialize)]
+struct SchoologyAssignment {
+    #[serde(deserialize_with = "de::id")]
+    id: usize,
+    #[serde(default, deserialize_with = "de::string")]
+    title: String,
+    #[serde(default, deserialize_with = "de::string")]
+    description: String,
+    #[serde(default, deserialize_with = "de::option_datetime")]
+    due: Option<NaiveDateTime>,
+    #[serde(default, rename = "type", deserialize_with = "de::string")]
+    kind: String,
+    #[serde(default, deserialize_with = "de::option_float")]
+    max_points: Option<f64>,
+    #[serde(default, deserialize_with = "de::flag")]
+    completed: bool,
+}
+
+/// Gets the assignments of a section
+pub async fn get_section_assignments(
+    client: &SchoologyClient,
+    token: &SchoologyTokenPair,
+    section_id: usize,
+) -> Result<Vec<Assignment>, SchoologyError> {
+    debug!("Getting assignments of section {}", section_id);
+
+    let assignments: Vec<SchoologyAssignment> = get_all(
+        client,
+        &format!("sections/{}/assignments", section_id),
+        &[],
+        token,
+        "assignment",
+    )
+    .await?;
+
+    Ok(assignments
+        .into_iter()
+        .map(|assignment| Assignment {
+            id: assignment.id,
+            section_id,
+            title: assignment.title,
+            description: assignment.description,
+            due: assignment.due,
+            kind: AssignmentType::parse(&assignment.kind),
+            max_points: assignment.max_points,
+            completed: assignment.completed,
+        })
+        .collect())
+}
+
+/// Gets everything due between `from` and `to` (both inclusive) across the user's current sections
+/// This combines the assignments of every section with the due dates on the user's calendar,
+/// so items only on the calendar (e.g. tests without an assignment) are not missed.
+pub async fn get_assignments(
+    client: &SchoologyClient,
+    token: &SchoologyTokenPair,
+    user_id: usize,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<Assignment>, SchoologyError> {
+    debug!(
+        "Getting assignments of user {} from {} to {}",
+        user_id, from, to
+    );
+
+    let start = from.and_hms_opt(0, 0, 0).unwrap();
+    let end = (to + Duration::days(1)).and_hms_opt(0, 0, 0).unwrap();
+    let in_range =
+        |due: &Option<NaiveDateTime>| matches!(due, Some(due) if *due >= start && *due < end);
+
+    let sections = get_user_sections(client, token, user_id)
+        .await?
+        .into_iter()
+        .filter(|section| section.active)
+        .collect::<Vec<SchoologySection>>();
+
+    // Every section at once
+    let section_assignments = try_join_all(
+        sections
+            .iter()
+            .map(|section| get_section_assignments(client, token, section.id)),
+    )
+    .await?;
+
+    let mut assignments = section_assignments
+        .into_iter()
+        .flatten()
+        .filter(|assignment| in_range(&assignment.due))
+        .collect::<Vec<Assignment>>();
+
+    // Add the due dates that are only on the calendar
+    let events = get_user_events(client, token, user_id, from, to).await?;
+
+    for event in events {
+        let kind = AssignmentType::parse(&event.kind);
+        let section_id = match event.realm {
+            EventRealm::Section(section_id)
+                if sections.iter().any(|section| section.id == section_id) =>
+            {
+                section_id
+            }
+            _ => continue,
+        };
+
+        let due = Some(event.start());
+        if kind == AssignmentType::Other || !in_range(&due) {
+            continue;
+        }
+
+        let id = event.assignment_id.unwrap_or(event.id);
+        if assignments.iter().any(|assignment| assignment.id == id) {
+            continue;
+        }
+
+        assignments.push(Assignment {
+            id,
+            section_id,
+            title: event.title,
+            description: event.description,
+            due,
+            kind,
+            max_points: None,
+            completed: false,
+        });
+    }
+
+    assignments.sort_by(|a, b| a.due.cmp(&b.due).then(a.id.cmp(&b.id)));
+
+    Ok(assignments)
+}
@@ -0,0 +1,158 @@
+//! Deserializers for Schoology's inconsistent JSON (ids and flags are sometimes strings)
+
+use serde::{de, Deserialize, Deserializer};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StringOrNumber {
+    String(String),
+    Number(u64),
+}
+
+/// An id that can be a string or a number
+pub fn id<'de, D>(deserializer: D) -> Result<usize, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match StringOrNumber::deserialize(deserializer)? {
+        StringOrNumber::String(value) => value.trim().parse().map_err(de::Error::custom),
+        StringOrNumber::Number(value) => Ok(value as usize),
+    }
+}
+
+/// An optional id, empty strings and `0` are treated as no id
+pub fn option_id<'de, D>(deserializer: D) -> Result<Option<usize>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<StringOrNumber>::deserialize(deserializer)? {
+        Some(StringOrNumber::String(value)) if value.trim().is_empty() => Ok(None),
+        Some(StringOrNumber::String(value)) => value
+            .trim()
+            .parse()
+            .map(|id| if id == 0 { None } else { Some(id) })
+            .map_err(de::Error::custom),
+        Some(StringOrNumber::Number(0)) | None => Ok(None),
+        Some(StringOrNumber::Number(value)) => Ok(Some(value as usize)),
+    }
+}
+
+/// A list of ids that can be strings or numbers
+pub fn ids<'de, D>(deserializer: D) -> Result<Vec<usize>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Id(#[serde(deserialize_with = "id")] usize);
+
+    let ids = Option::<Vec<Id>>::deserialize(deserializer)?;
+
+    Ok(ids.unwrap_or_default().into_iter().map(|id| id.0).collect())
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Flag {
+    Bool(bool),
+    Number(u64),
+    String(String),
+}
+
+/// A flag that can be a bool, `1`/`0` or `"1"`/`"0"`
+pub fn flag<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Option::<Flag>::deserialize(deserializer)? {
+        Some(Flag::Bool(value)) => value,
+        Some(Flag::Number(value)) => value != 0,
+        Some(Flag::String(value)) => !matches!(value.trim(), "" | "0" | "false"),
+        None => false,
+    })
+}
+
+/// A string that may be null
+pub fn string<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Option::<String>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// An optional number that can be a string or a number, empty strings are treated as no number
+pub fn option_float<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrFloat {
+        String(String),
+        Float(f64),
+    }
+
+    match Option::<StringOrFloat>::deserialize(deserializer)? {
+        Some(StringOrFloat::String(value)) if value.trim().is_empty() => Ok(None),
+        Some(StringOrFloat::String(value)) => {
+            value.trim().parse().map(Some).map_err(de::Error::custom)
+        }
+        Some(StringOrFloat::Float(value)) => Ok(Some(value)),
+        None => Ok(None),
+    }
+}
+
+/// An optional `YYYY-MM-DD HH:MM:SS` date (in the school's time zone), empty strings are treated as no date
+pub fn option_datetime<'de, D>(deserializer: D) -> Result<Option<chrono::NaiveDateTime>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(value) if value.trim().is_empty() => Ok(None),
+        Some(value) => chrono::NaiveDateTime::parse_from_str(value.trim(), "%Y-%m-%d %H:%M:%S")
+            .map(Some)
+            .map_err(de::Error::custom),
+        None => Ok(None),
+    }
+}
+
+/// An optional unix timestamp (in seconds), empty strings and `0` are treated as no time
+pub fn option_timestamp<'de, D>(
+    deserializer: D,
+) -> Result<Option<chrono::DateTime<chrono::Utc>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match option_id(deserializer)? {
+        Some(seconds) => chrono::DateTime::from_timestamp(seconds as i64, 0)
+            .map(Some)
+            .ok_or_else(|| de::Error::custom("timestamp out of range")),
+        None => Ok(None),
+    }
+}
+
+/// A list of ids that can be a comma separated string (`"1,2"`) or a list of ids
+pub fn comma_ids<'de, D>(deserializer: D) -> Result<Vec<usize>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Id(#[serde(deserialize_with = "id")] usize);
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum CommaIds {
+        String(String),
+        List(Vec<Id>),
+    }
+
+    match Option::<CommaIds>::deserialize(deserializer)? {
+        Some(CommaIds::String(value)) => value
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| id.parse().map_err(de::Error::custom))
+            .collect(),
+        Some(CommaIds::List(ids)) => Ok(ids.into_iter().map(|id| id.0).collect()),
+        None => Ok(Vec::new()),
+    }
+}
@@ -0,0 +1,234 @@
+use std::{fmt, time::Duration};
+
+use reqwest::{header::HeaderMap, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize};
+
+/// The longest error body that is kept (Schoology sometimes returns whole HTML pages)
+const MAX_BODY_LENGTH: usize = 1024;
+
+/// The rate limit headers of a response
+#[derive(Debug, Clone, Default)]
+pub struct RateLimit {
+    /// `X-RateLimit-Limit`: the number of requests allowed in the window
+    pub limit: Option<u64>,
+    /// `X-RateLimit-Remaining`: the number of requests left in the window
+    pub remaining: Option<u64>,
+    /// `X-RateLimit-Reset`: when the window resets (unix timestamp)
+    pub reset: Option<u64>,
+    /// `Retry-After`: how long to wait before retrying
+    pub retry_after: Option<Duration>,
+}
+
+impl RateLimit {
+    /// Parses the rate limit headers of a response
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        fn header(headers: &HeaderMap, name: &str) -> Option<u64> {
+            headers.get(name)?.to_str().ok()?.trim().parse().ok()
+        }
+
+        Self {
+            limit: header(headers, "x-ratelimit-limit"),
+            remaining: header(headers, "x-ratelimit-remaining"),
+            reset: header(headers, "x-ratelimit-reset"),
+            retry_after: header(headers, "retry-after").map(Duration::from_secs),
+        }
+    }
+}
+
+/// The body of an error response from Schoology
+#[derive(Debug, Clone, Default)]
+pub struct SchoologyErrorBody {
+    /// The error message (if Schoology sent one)
+    pub message: Option<String>,
+    /// The raw body (truncated)
+    pub raw: String,
+}
+
+#[derive(Deserialize)]
+struct JsonErrorBody {
+    message: Option<String>,
+    error: Option<String>,
+}
+
+impl SchoologyErrorBody {
+    /// Parses an error body, Schoology uses JSON for API errors and plain text for OAuth errors
+    pub fn parse(body: &str) -> Self {
+        let mut raw = body.trim().to_string();
+
+        if raw.len() > MAX_BODY_LENGTH {
+            let mut end = MAX_BODY_LENGTH;
+            while !raw.is_char_boundary(end) {
+                end -= 1;
+            }
+            raw.truncate(end);
+        }
+
+        let message = match serde_json::from_str::<JsonErrorBody>(&raw) {
+            Ok(json) => json.message.or(json.error),
+            Err(_) if !raw.is_empty() && !raw.starts_with('<') => Some(raw.clone()),
+            Err(_) => None,
+        };
+
+        Self { message, raw }
+    }
+}
+
+/// An error from a request to the Schoology API
+#[derive(Debug, Clone)]
+pub enum SchoologyError {
+    /// No response was received (connection, TLS, timeout, ...)
+    Transport {
+        message: String,
+        /// Whether the request timed out
+        timeout: bool,
+    },
+    /// Schoology responded with an unexpected status code
+    Status {
+        status: StatusCode,
+        body: SchoologyErrorBody,
+        rate_limit: RateLimit,
+    },
+    /// The response was received but could not be understood
+    InvalidResponse { message: String },
+}
+
+impl SchoologyError {
+    /// Creates an error from an unexpected response
+    pub async fn from_response(response: reqwest::Response) -> Self {
+        let status = response.status();
+        let rate_limit = RateLimit::from_headers(response.headers());
+
+        let body = match response.text().await {
+            Ok(body) => SchoologyErrorBody::parse(&body),
+            Err(err) => {
+                debug!("Failed to read error body: {:?}", err);
+                SchoologyErrorBody::default()
+            }
+        };
+
+        debug!(
+            "Schoology returned {}: {}",
+            status,
+            body.message.as_deref().unwrap_or("(no message)")
+        );
+
+        SchoologyError::Status {
+            status,
+            body,
+            rate_limit,
+        }
+    }
+
+    /// Creates an error for a response that could not be understood
+    pub fn invalid_response(message: impl fmt::Display) -> Self {
+        warn!("Invalid response from Schoology: {}", message);
+
+        SchoologyError::InvalidResponse {
+            message: message.to_string(),
+        }
+    }
+
+    /// The HTTP status code (if a response was received)
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            SchoologyError::Status { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+
+    /// Whether the request failed before a response was received
+    pub fn is_transport(&self) -> bool {
+        matches!(self, SchoologyError::Transport { .. })
+    }
+
+    /// Whether the token (or the application) is not authorized, e.g. the user revoked access
+    pub fn is_unauthorized(&self) -> bool {
+        self.status() == Some(StatusCode::UNAUTHORIZED)
+    }
+
+    /// Whether the resource does not exist
+    pub fn is_not_found(&self) -> bool {
+        self.status() == Some(StatusCode::NOT_FOUND)
+    }
+
+    /// Whether Schoology throttled the request
+    pub fn is_rate_limited(&self) -> bool {
+        self.status() == Some(StatusCode::TOO_MANY_REQUESTS)
+    }
+
+    /// Whether Schoology is down or unreachable
+    pub fn is_unavailable(&self) -> bool {
+        match self {
+            SchoologyError::Transport { .. } => true,
+            SchoologyError::Status { status, .. } => status.is_server_error(),
+            SchoologyError::InvalidResponse { .. } => false,
+        }
+    }
+
+    /// How long Schoology asked to wait before retrying
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            SchoologyError::Status { rate_limit, .. } => rate_limit.retry_after,
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for SchoologyError {
+    fn from(err: reqwest::Error) -> Self {
+        warn!("Schoology request failed: {:?}", err);
+
+        SchoologyError::Transport {
+            message: err.to_string(),
+            timeout: err.is_timeout(),
+        }
+    }
+}
+
+impl fmt::Display for SchoologyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchoologyError::Transport { message, .. } => {
+                write!(f, "Schoology request failed: {}", message)
+            }
+            SchoologyError::Status { status, body, .. } => match &body.message {
+                Some(message) => write!(f, "Schoology returned {}: {}", status, message),
+                None => write!(f, "Schoology returned {}", status),
+            },
+            SchoologyError::InvalidResponse { message } => {
+                write!(f, "Invalid response from Schoology: {}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SchoologyError {}
+
+/// Checks that a response has the expected status code
+pub async fn expect_status(
+    response: reqwest::Response,
+    expected: StatusCode,
+) -> Result<reqwest::Response, SchoologyError> {
+    if response.status() == expected {
+        Ok(response)
+    } else {
+        Err(SchoologyError::from_response(response).await)
+    }
+}
+
+/// Reads a `200 OK` response as text
+pub async fn read_text(response: reqwest::Response) -> Result<String, SchoologyError> {
+    let response = expect_status(response, StatusCode::OK).await?;
+
+    Ok(response.text().await?)
+}
+
+/// Reads a `200 OK` JSON response
+pub async fn read_json<T>(response: reqwest::Response) -> Result<T, SchoologyError>
+where
+    T: DeserializeOwned,
+{
+    let text = read_text(response).await?;
+
+    serde_json::from_str(&text).map_err(SchoologyError::invalid_response)
+}
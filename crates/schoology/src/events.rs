@@ -0,0 +1,285 @@
+use std::collections::HashSet;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use futures::future::try_join_all;
+use serde::Deserialize;
+
+use crate::{
+    de, error::SchoologyError, pagination::get_all, sections::get_user_sections, SchoologyClient,
+    SchoologyTokenPair,
+};
+
+/// When an event happens (in the school's time zone)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventTime {
+    /// The whole day
+    AllDay(NaiveDate),
+    /// At a time, some events have no end
+    Timed {
+        start: NaiveDateTime,
+        end: Option<NaiveDateTime>,
+    },
+}
+
+/// What the event belongs to (and so who can see it)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventRealm {
+    Section(usize),
+    Group(usize),
+    School(usize),
+    District(usize),
+    /// A personal event
+    User(usize),
+    Other,
+}
+
+/// The user's answer to an event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RsvpStatus {
+    /// The event does not ask for answers
+    NotRequested,
+    /// The user did not answer yet
+    Pending,
+    Attending,
+    Maybe,
+    NotAttending,
+}
+
+/// An event on a calendar
+/// Assignments with a due date are on the calendar too, see [`Event::is_due_date`].
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub id: usize,
+    pub title: String,
+    pub description: String,
+    pub time: EventTime,
+    pub realm: EventRealm,
+    pub rsvp: RsvpStatus,
+    /// `event`, or the kind of assignment it is the due date of
+    pub kind: String,
+    pub assignment_id: Option<usize>,
+}
+
+impl Event {
+    /// When it starts, all day events start at midnight
+    pub fn start(&self) -> NaiveDateTime {
+        match self.time {
+            EventTime::AllDay(date) => date.and_hms_opt(0, 0, 0).unwrap(),
+            EventTime::Timed { start, .. } => start,
+        }
+    }
+
+    /// Whether it is the due date of an assignment instead of an actual event
+    pub fn is_due_date(&self) -> bool {
+        self.kind != "event" || self.assignment_id.is_some()
+    }
+}
+
+#[derive(Deserialize)]
+struct SchoologyEvent {
+    #[serde(deserialize_with = "de::id")]
+    id: usize,
+    #[serde(default, deserialize_with = "de::string")]
+    title: String,
+    #[serde(default, deserialize_with = "de::string")]
+    description: String,
+    #[serde(default, deserialize_with = "de::option_datetime")]
+    start: Option<NaiveDateTime>,
+    #[serde(default, deserialize_with = "de::flag")]
+    has_end: bool,
+    #[serde(default, deserialize_with = "de::option_datetime")]
+    end: Option<NaiveDateTime>,
+    #[serde(default, deserialize_with = "de::flag")]
+    all_day: bool,
+    /// Whether the event asks for answers
+    #[serde(default, deserialize_with = "de::flag")]
+    rsvp: bool,
+    #[serde(default, deserialize_with = "de::string")]
+    rsvp_status: String,
+    #[serde(default, rename = "type", deserialize_with = "de::string")]
+    kind: String,
+    #[serde(default, deserialize_with = "de::option_id")]
+    assignment_id: Option<usize>,
+    #[serde(default, deserialize_with = "de::string")]
+    realm: String,
+    #[serde(default, deserialize_with = "de::option_id")]
+    section_id: Option<usize>,
+    #[serde(default, deserialize_with = "de::option_id")]
+    group_id: Option<usize>,
+    #[serde(default, deserialize_with = "de::option_id")]
+    school_id: Option<usize>,
+    #[serde(default, deserialize_with = "de::option_id")]
+    district_id: Option<usize>,
+    #[serde(default, deserialize_with = "de::option_id")]
+    user_id: Option<usize>,
+}
+
+impl SchoologyEvent {
+    /// Events without a start can't be shown on a calendar, so they are dropped
+    fn into_event(self) -> Option<Event> {
+        let start = self.start?;
+
+        let time = if self.all_day {
+            EventTime::AllDay(start.date())
+        } else {
+            EventTime::Timed {
+                start,
+                end: self.end.filter(|end| self.has_end && *end > start),
+            }
+        };
+
+        let realm = match self.realm.as_str() {
+            "section" => self.section_id.map(EventRealm::Section),
+            "group" => self.group_id.map(EventRealm::Group),
+            "school" => self.school_id.map(EventRealm::School),
+            "district" => self.district_id.map(EventRealm::District),
+            "user" => self.user_id.map(EventRealm::User),
+            _ => None,
+        }
+        .unwrap_or(EventRealm::Other);
+
+        let rsvp = match (self.rsvp, self.rsvp_status.as_str()) {
+            (false, _) => RsvpStatus::NotRequested,
+            (true, "attending") => RsvpStatus::Attending,
+            (true, "maybe") => RsvpStatus::Maybe,
+            (true, "not_attending") => RsvpStatus::NotAttending,
+            (true, _) => RsvpStatus::Pending,
+        };
+
+        Some(Event {
+            id: self.id,
+            title: self.title,
+            description: self.description,
+            time,
+            realm,
+            rsvp,
+            kind: self.kind,
+            assignment_id: self.assignment_id,
+        })
+    }
+}
+
+async fn get_events(
+    client: &SchoologyClient,
+    token: &SchoologyTokenPair,
+    path: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<Event>, SchoologyError> {
+    let events: Vec<SchoologyEvent> = get_all(
+        client,
+        path,
+        &[
+            ("start_date", from.to_string()),
+            ("end_date", to.to_string()),
+        ],
+        token,
+        "event",
+    )
+    .await?;
+
+    Ok(events
+        .into_iter()
+        .filter_map(SchoologyEvent::into_event)
+        .collect())
+}
+
+/// Gets the events on the calendar of a user between `from` and `to` (both inclusive)
+pub async fn get_user_events(
+    client: &SchoologyClient,
+    token: &SchoologyTokenPair,
+    user_id: usize,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<Event>, SchoologyError> {
+    debug!("Getting events of user {} from {} to {}", user_id, from, to);
+
+    get_events(
+        client,
+        token,
+        &format!("users/{}/events", user_id),
+        from,
+        to,
+    )
+    .await
+}
+
+/// Gets the events of a section between `from` and `to` (both inclusive)
+pub async fn get_section_events(
+    client: &SchoologyClient,
+    token: &SchoologyTokenPair,
+    section_id: usize,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<Event>, SchoologyError> {
+    debug!(
+        "Getting events of section {} from {} to {}",
+        section_id, from, to
+    );
+
+    get_events(
+        client,
+        token,
+        &format!("sections/{}/events", section_id),
+        from,
+        to,
+    )
+    .await
+}
+
+/// Gets the events of a group between `from` and `to` (both inclusive)
+pub async fn get_group_events(
+    client: &SchoologyClient,
+    token: &SchoologyTokenPair,
+    group_id: usize,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<Event>, SchoologyError> {
+    debug!(
+        "Getting events of group {} from {} to {}",
+        group_id, from, to
+    );
+
+    get_events(
+        client,
+        token,
+        &format!("groups/{}/events", group_id),
+        from,
+        to,
+    )
+    .await
+}
+
+/// Gets every event the user can see between `from` and `to` (both inclusive), sorted by start
+/// This merges the user's calendar (school wide and personal events) with the calendars of the
+/// user's current sections, an event on both is only returned once.
+pub async fn get_all_events(
+    client: &SchoologyClient,
+    token: &SchoologyTokenPair,
+    user_id: usize,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<Event>, SchoologyError> {
+    let sections = get_user_sections(client, token, user_id).await?;
+
+    let (user_events, section_events) = futures::try_join!(
+        get_user_events(client, token, user_id, from, to),
+        try_join_all(
+            sections
+                .iter()
+                .filter(|section| section.active)
+                .map(|section| get_section_events(client, token, section.id, from, to)),
+        )
+    )?;
+
+    let mut ids = HashSet::new();
+    let mut events = user_events
+        .into_iter()
+        .chain(section_events.into_iter().flatten())
+        .filter(|event| ids.insert(event.id))
+        .collect::<Vec<Event>>();
+
+    events.sort_by(|a, b| a.start().cmp(&b.start()).then(a.id.cmp(&b.id)));
+
+    Ok(events)
+}
@@ -0,0 +1,129 @@
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    de,
+    error::{read_json, SchoologyError},
+    SchoologyClient, SchoologyRequest, SchoologyTokenPair,
+};
+
+/// Why an assignment does not count (or counts as zero)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GradeException {
+    #[default]
+    None,
+    Excused,
+    Incomplete,
+    Missing,
+}
+
+impl<'de> Deserialize<'de> for GradeException {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(match de::option_id(deserializer)? {
+            Some(1) => GradeException::Excused,
+            Some(2) => GradeException::Incomplete,
+            Some(3) => GradeException::Missing,
+            _ => GradeException::None,
+        })
+    }
+}
+
+/// The grade of a single assignment
+#[derive(Deserialize, Debug, Clone)]
+pub struct AssignmentGrade {
+    #[serde(deserialize_with = "de::id")]
+    pub assignment_id: usize,
+    #[serde(default, deserialize_with = "de::option_float")]
+    pub grade: Option<f64>,
+    #[serde(default, deserialize_with = "de::option_float")]
+    pub max_points: Option<f64>,
+    #[serde(default, deserialize_with = "de::option_id")]
+    pub category_id: Option<usize>,
+    #[serde(default)]
+    pub exception: GradeException,
+    #[serde(default, deserialize_with = "de::string")]
+    pub comment: String,
+}
+
+/// The assignment grades of a grading period
+#[derive(Deserialize, Debug, Clone)]
+pub struct GradingPeriodGrades {
+    /// Grading period ids are strings (e.g. `p123456`)
+    pub period_id: String,
+    #[serde(default, deserialize_with = "de::string")]
+    pub period_title: String,
+    #[serde(default, rename = "assignment")]
+    pub assignments: Vec<AssignmentGrade>,
+}
+
+/// The grade of a grading category in a grading period
+#[derive(Deserialize, Debug, Clone)]
+pub struct CategoryGrade {
+    #[serde(deserialize_with = "de::id")]
+    pub category_id: usize,
+    #[serde(default, deserialize_with = "de::option_float")]
+    pub grade: Option<f64>,
+}
+
+/// The final grade of a grading period
+#[derive(Deserialize, Debug, Clone)]
+pub struct FinalGrade {
+    pub period_id: String,
+    #[serde(default, deserialize_with = "de::option_float")]
+    pub grade: Option<f64>,
+    #[serde(default, rename = "grading_category")]
+    pub categories: Vec<CategoryGrade>,
+}
+
+/// A grading category (e.g. Homework) and it's weight
+#[derive(Deserialize, Debug, Clone)]
+pub struct GradingCategory {
+    #[serde(deserialize_with = "de::id")]
+    pub id: usize,
+    #[serde(default, deserialize_with = "de::string")]
+    pub title: String,
+    /// The weight in percent (`None` if the section is not weighted)
+    #[serde(default, deserialize_with = "de::option_float")]
+    pub weight: Option<f64>,
+}
+
+/// The grades of a section
+#[derive(Deserialize, Debug, Clone)]
+pub struct SectionGrades {
+    #[serde(deserialize_with = "de::id")]
+    pub section_id: usize,
+    #[serde(default, rename = "period")]
+    pub periods: Vec<GradingPeriodGrades>,
+    #[serde(default, rename = "final_grade")]
+    pub final_grades: Vec<FinalGrade>,
+    #[serde(default, rename = "grading_category")]
+    pub categories: Vec<GradingCategory>,
+}
+
+#[derive(Deserialize)]
+struct GradesResponse {
+    #[serde(default, rename = "section")]
+    sections: Vec<SectionGrades>,
+}
+
+/// Gets the grades of a user in all of their sections
+pub async fn get_user_grades(
+    client: &SchoologyClient,
+    token: &SchoologyTokenPair,
+    user_id: usize,
+) -> Result<Vec<SectionGrades>, SchoologyError> {
+    debug!("Getting grades of user {}", user_id);
+
+    let response = client
+        .get(
+            &format!("users/{}/grades", user_id),
+            SchoologyRequest::new().with_access_tokens(token),
+        )
+        .await?;
+
+    let response: GradesResponse = read_json(response).await?;
+
+    Ok(response.sections)
+}
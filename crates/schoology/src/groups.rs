@@ -0,0 +1,85 @@
+use serde::Deserialize;
+
+use crate::{
+    de,
+    error::{read_json, SchoologyError},
+    pagination::get_all,
+    sections::SchoologyEnrollment,
+    SchoologyClient, SchoologyRequest, SchoologyTokenPair,
+};
+
+/// A group, clubs and teams are groups on Schoology
+#[derive(Deserialize, Debug, Clone)]
+pub struct SchoologyGroup {
+    #[serde(deserialize_with = "de::id")]
+    pub id: usize,
+    #[serde(default, deserialize_with = "de::string")]
+    pub title: String,
+    #[serde(default, deserialize_with = "de::string")]
+    pub description: String,
+    #[serde(default, deserialize_with = "de::string")]
+    pub picture_url: String,
+    #[serde(default, deserialize_with = "de::string")]
+    pub website: String,
+    #[serde(default, deserialize_with = "de::string")]
+    pub category: String,
+    /// `everyone`, `school`, `building`, `group` or `custom`
+    #[serde(default, deserialize_with = "de::string")]
+    pub privacy_level: String,
+    #[serde(default, deserialize_with = "de::option_id")]
+    pub school_id: Option<usize>,
+}
+
+/// Gets the groups a user is a member of
+pub async fn get_user_groups(
+    client: &SchoologyClient,
+    token: &SchoologyTokenPair,
+    user_id: usize,
+) -> Result<Vec<SchoologyGroup>, SchoologyError> {
+    debug!("Getting groups of user {}", user_id);
+
+    get_all(
+        client,
+        &format!("users/{}/groups", user_id),
+        &[],
+        token,
+        "group",
+    )
+    .await
+}
+
+/// Gets a group
+pub async fn get_group(
+    client: &SchoologyClient,
+    token: &SchoologyTokenPair,
+    group_id: usize,
+) -> Result<SchoologyGroup, SchoologyError> {
+    debug!("Getting group {}", group_id);
+
+    let response = client
+        .get(
+            &format!("groups/{}", group_id),
+            SchoologyRequest::new().with_access_tokens(token),
+        )
+        .await?;
+
+    read_json(response).await
+}
+
+/// Gets the members (and admins) of a group
+pub async fn get_group_enrollments(
+    client: &SchoologyClient,
+    token: &SchoologyTokenPair,
+    group_id: usize,
+) -> Result<Vec<SchoologyEnrollment>, SchoologyError> {
+    debug!("Getting enrollments of group {}", group_id);
+
+    get_all(
+        client,
+        &format!("groups/{}/enrollments", group_id),
+        &[],
+        token,
+        "enrollment",
+    )
+    .await
+}
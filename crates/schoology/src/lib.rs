@@ -1,18 +1,60 @@
-use once_cell::sync::Lazy;
+use std::{sync::Arc, time::Duration};
+
+use reqwest::{header, Method, StatusCode};
 use url::Url;
 
-pub static BASE_URL: Lazy<Url> = Lazy::new(|| Url::parse("https://api.schoology.com/v1/").unwrap());
+pub use error::SchoologyError;
+pub use limiter::RateLimiter;
+pub use pagination::Page;
+pub use retry::RetryPolicy;
+
+/// The default Schoology API base URL
+pub const DEFAULT_BASE_URL: &str = "https://api.schoology.com/v1/";
+/// The most redirects followed for a single request
+const MAX_REDIRECTS: usize = 5;
 
 #[macro_use]
 extern crate log;
 
+pub mod assignments;
+mod de;
+pub mod error;
+pub mod events;
+pub mod grades;
+pub mod groups;
+pub mod limiter;
+pub mod messages;
 pub mod oauth;
+mod pagination;
 pub mod proto;
+pub mod retry;
+pub mod sections;
+pub mod updates;
 pub mod users;
 
 pub struct SchoologyClient {
     pub consumer_key: String,
     pub consumer_secret: String,
+    /// The base URL of the API, request paths are relative to it
+    pub base_url: Url,
+    /// The HTTP client used for every request (shared for connection pooling)
+    pub http_client: reqwest::Client,
+    /// Every request (including redirects and retries) waits for it
+    pub rate_limiter: Arc<RateLimiter>,
+    /// When throttled or failed requests are sent again
+    pub retry_policy: RetryPolicy,
+}
+
+/// A `reqwest::ClientBuilder` with the settings the SchoologyClient expects
+/// Redirects are disabled because the SchoologyClient follows them itself (re-signing every hop).
+pub fn http_client_builder() -> reqwest::ClientBuilder {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .user_agent(concat!("tuwa-api/", env!("CARGO_PKG_VERSION")))
+        .connect_timeout(Duration::from_secs(10))
+        .timeout(Duration::from_secs(30))
+        .pool_idle_timeout(Duration::from_secs(90))
+        .pool_max_idle_per_host(32)
 }
 
 /// A token pair representing a user's oauth token and token secret
@@ -27,7 +69,7 @@ pub struct SchoologyRequest {
     pub query: Option<Vec<(String, String)>>,
     /// For JSON requests (ignored in GET requests)
     pub body: Option<String>,
-    /// For url encoded `application/x-www-form-urlencoded` requests
+    /// For url encoded `application/x-www-form-urlencoded` requests, these are signed (ignored in GET requests)
     pub oauth_body: Option<Vec<(String, String)>>,
     /// User token
     pub access_token: Option<String>,
@@ -38,6 +80,12 @@ pub struct SchoologyRequest {
     pub redirects: bool,
 }
 
+impl Default for SchoologyRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SchoologyRequest {
     /// Create a new blank SchoologyRequest
     pub fn new() -> Self {
@@ -92,65 +140,306 @@ impl SchoologyClient {
     /// Creates a new SchoologyClient
     pub fn new(consumer_key: String, consumer_secret: String) -> Self {
         debug!("Creating new SchoologyClient");
+
+        let http_client = http_client_builder().build().unwrap_or_else(|err| {
+            warn!(
+                "Failed to create HTTP client, using the default one: {:?}",
+                err
+            );
+            reqwest::Client::new()
+        });
+
         Self {
+            rate_limiter: RateLimiter::shared(&consumer_key),
             consumer_key,
             consumer_secret,
+            base_url: Url::parse(DEFAULT_BASE_URL).unwrap(),
+            http_client,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Use a different API base URL (e.g. a district specific host or a mock server)
+    pub fn with_base_url(mut self, mut base_url: Url) -> Self {
+        // Make sure paths are joined to the base instead of replacing it's last segment
+        if !base_url.path().ends_with('/') {
+            base_url.set_path(&format!("{}/", base_url.path()));
         }
+
+        self.base_url = base_url;
+        self
+    }
+
+    /// Use a shared HTTP client, see `http_client_builder` for the expected settings
+    /// The client should not follow redirects, otherwise redirects are sent with a reused nonce.
+    pub fn with_http_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
+    /// Use a different rate limiter, by default clients with the same consumer key share one
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    /// Use a different retry policy
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
     }
 
-    /// Generates a GET request to the Schoology API (withouth sending it)
+    /// Checks that the API is reachable, returns how long it took to respond
+    /// The request is not signed, rate limited or retried. Any response that isn't a server error counts.
+    pub async fn ping(&self) -> Result<Duration, SchoologyError> {
+        let start = std::time::Instant::now();
+
+        let response = self
+            .http_client
+            .get(self.base_url.clone())
+            .header("Accept", "application/json")
+            .send()
+            .await?;
+
+        if response.status().is_server_error() {
+            return Err(SchoologyError::from_response(response).await);
+        }
+
+        Ok(start.elapsed())
+    }
+
+    /// Sends a GET request to the Schoology API
     /// Note: Do NOT allow arbitrary paths to be passed in. This allows for path traversal attacks.
     pub async fn get(
         &self,
         path: &str,
         request: SchoologyRequest,
-    ) -> Result<reqwest::Response, reqwest::Error> {
-        debug!("Schoology API v1 GET request to {}", path);
+    ) -> Result<reqwest::Response, SchoologyError> {
+        self.request(Method::GET, path, request).await
+    }
+
+    /// Sends a POST request to the Schoology API
+    /// Note: Do NOT allow arbitrary paths to be passed in. This allows for path traversal attacks.
+    pub async fn post(
+        &self,
+        path: &str,
+        request: SchoologyRequest,
+    ) -> Result<reqwest::Response, SchoologyError> {
+        self.request(Method::POST, path, request).await
+    }
+
+    /// Sends a PUT request to the Schoology API
+    /// Note: Do NOT allow arbitrary paths to be passed in. This allows for path traversal attacks.
+    pub async fn put(
+        &self,
+        path: &str,
+        request: SchoologyRequest,
+    ) -> Result<reqwest::Response, SchoologyError> {
+        self.request(Method::PUT, path, request).await
+    }
+
+    /// Sends a DELETE request to the Schoology API
+    /// Note: Do NOT allow arbitrary paths to be passed in. This allows for path traversal attacks.
+    pub async fn delete(
+        &self,
+        path: &str,
+        request: SchoologyRequest,
+    ) -> Result<reqwest::Response, SchoologyError> {
+        self.request(Method::DELETE, path, request).await
+    }
+
+    /// Signs and sends a request to the Schoology API
+    /// Form bodies are part of the OAuth 1.0a signature, JSON bodies are sent as-is.
+    /// Note: Do NOT allow arbitrary paths to be passed in. This allows for path traversal attacks.
+    pub async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        request: SchoologyRequest,
+    ) -> Result<reqwest::Response, SchoologyError> {
+        debug!("Schoology API v1 {} request to {}", method, path);
 
         // Create the request URL
-        let mut url = BASE_URL.join(path).unwrap();
+        let mut url = self.base_url.join(path.trim_start_matches('/')).unwrap();
 
         // Add the query parameters
-        let url = if let Some(query) = request.query {
+        if let Some(query) = request.query {
             url.query_pairs_mut().extend_pairs(query);
-            url
-        } else {
-            url
+        }
+
+        // GET requests don't have a body
+        let (mut form_body, mut json_body) = match method {
+            Method::GET => (None, None),
+            _ => (request.oauth_body, request.body),
         };
 
+        if form_body.is_some() && json_body.is_some() {
+            warn!("Both a form and a JSON body were given, only the form body is sent");
+        }
+
+        let mut method = method;
+        let mut redirects = 0;
+
+        loop {
+            let response = self
+                .send_with_retries(
+                    &method,
+                    &url,
+                    request.access_token.as_deref(),
+                    request.token_secret.as_deref(),
+                    form_body.as_ref(),
+                    json_body.as_ref(),
+                )
+                .await?;
+
+            if !request.redirects
+                || !response.status().is_redirection()
+                || redirects >= MAX_REDIRECTS
+            {
+                return Ok(response);
+            }
+
+            // Get the redirect url
+            let next = match response
+                .headers()
+                .get(header::LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| url.join(location).ok())
+            {
+                Some(next) => next,
+                None => return Ok(response),
+            };
+
+            // Never send signed requests to other hosts
+            if next.origin() != self.base_url.origin() {
+                warn!("Not following redirect to another host: {}", next);
+                return Ok(response);
+            }
+
+            debug!("Following redirect to {}", next);
+
+            // Like browsers, only 307 and 308 keep the method and body
+            if !matches!(
+                response.status(),
+                StatusCode::TEMPORARY_REDIRECT | StatusCode::PERMANENT_REDIRECT
+            ) {
+                method = Method::GET;
+                form_body = None;
+                json_body = None;
+            }
+
+            url = next;
+            redirects += 1;
+        }
+    }
+
+    /// Sends a request, retrying it if Schoology is throttling or failed
+    /// Every attempt is signed again so the retries don't reuse the nonce.
+    async fn send_with_retries(
+        &self,
+        method: &Method,
+        url: &Url,
+        access_token: Option<&str>,
+        token_secret: Option<&str>,
+        form_body: Option<&Vec<(String, String)>>,
+        json_body: Option<&String>,
+    ) -> Result<reqwest::Response, SchoologyError> {
+        let mut retries = 0;
+
+        loop {
+            self.rate_limiter.acquire().await;
+
+            let response = self
+                .send(
+                    method,
+                    url,
+                    access_token,
+                    token_secret,
+                    form_body,
+                    json_body,
+                )
+                .await?;
+
+            let status = response.status();
+
+            if !self.retry_policy.should_retry(method, status, retries) {
+                return Ok(response);
+            }
+
+            let retry_after = error::RateLimit::from_headers(response.headers()).retry_after;
+
+            let Some(delay) = self.retry_policy.delay(retries, retry_after) else {
+                warn!("Schoology asked to wait {:?}, not retrying", retry_after);
+                return Ok(response);
+            };
+
+            warn!(
+                "Schoology returned {} for {}, retrying in {:?}",
+                status,
+                url.path(),
+                delay
+            );
+
+            // Every other request would be throttled too, so they all wait (the retry included)
+            if status == StatusCode::TOO_MANY_REQUESTS {
+                self.rate_limiter.pause(delay);
+            } else {
+                tokio::time::sleep(delay).await;
+            }
+
+            retries += 1;
+        }
+    }
+
+    /// Signs and sends a single request (a new nonce and timestamp is generated every time)
+    async fn send(
+        &self,
+        method: &Method,
+        url: &Url,
+        access_token: Option<&str>,
+        token_secret: Option<&str>,
+        form_body: Option<&Vec<(String, String)>>,
+        json_body: Option<&String>,
+    ) -> Result<reqwest::Response, SchoologyError> {
         // Oauth 1.0a header
         let oauth_header = proto::OAuth1AHeader::new(
-            "GET".to_string(),
+            method.to_string(),
             url.to_string(),
-            request.access_token,
-            request.token_secret,
+            access_token.map(str::to_string),
+            token_secret.map(str::to_string),
         );
 
-        // Generate the signature
-        let signature =
-            oauth_header.get_header("GET", &url, None, &self.consumer_key, &self.consumer_secret);
+        // Generate the signature (form parameters are part of the signature base string)
+        let body_params = form_body.map(|body| {
+            body.iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect::<Vec<(&str, &str)>>()
+        });
+
+        let signature = oauth_header.get_header(
+            method.as_str(),
+            url,
+            body_params,
+            &self.consumer_key,
+            &self.consumer_secret,
+        );
 
         debug!("Generated signature: {}", signature);
 
-        let client = match request.redirects {
-            true => reqwest::Client::new(),
-            false => match reqwest::Client::builder()
-                .redirect(reqwest::redirect::Policy::none())
-                .build()
-            {
-                Ok(client) => client,
-                Err(err) => {
-                    warn!("Failed to create client: {:?}", err);
-                    return Err(err);
-                }
-            },
+        let builder = self
+            .http_client
+            .request(method.clone(), url.clone())
+            .header("Accept", "application/json")
+            .header("Authorization", signature);
+
+        let builder = match (form_body, json_body) {
+            (Some(form_body), _) => builder.form(form_body),
+            (None, Some(json_body)) => builder
+                .header("Content-Type", "application/json")
+                .body(json_body.clone()),
+            (None, None) => builder,
         };
 
-        client
-            .get(url)
-            .header("Accept", "application/json")
-            .header("Authorization", signature)
-            .send()
-            .await
+        builder.send().await.map_err(SchoologyError::from)
     }
 }
@@ -0,0 +1,101 @@
+//! A client side rate limiter, so the API is not hammered until Schoology starts throttling
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard, OnceLock},
+    time::{Duration, Instant},
+};
+
+/// How many requests Schoology allows per consumer key in `DEFAULT_PERIOD`
+pub const DEFAULT_REQUESTS: u32 = 50;
+/// The window of `DEFAULT_REQUESTS`
+pub const DEFAULT_PERIOD: Duration = Duration::from_secs(5);
+
+/// The limiters of every consumer key, so clients with the same key share a budget
+static SHARED: OnceLock<Mutex<HashMap<String, Arc<RateLimiter>>>> = OnceLock::new();
+
+struct Bucket {
+    /// Negative when requests are waiting for a token
+    tokens: f64,
+    updated: Instant,
+}
+
+/// A token bucket, up to `requests` can be sent at once and it refills evenly over `period`
+pub struct RateLimiter {
+    capacity: f64,
+    /// Tokens added per second
+    rate: f64,
+    bucket: Mutex<Bucket>,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // The bucket is always valid, even if someone panicked while holding it
+    mutex.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+impl RateLimiter {
+    /// Creates a limiter allowing `requests` per `period`
+    pub fn new(requests: u32, period: Duration) -> Self {
+        let capacity = requests.max(1) as f64;
+
+        Self {
+            capacity,
+            rate: capacity / period.as_secs_f64().max(0.001),
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    /// Gets the limiter used by every client with this consumer key (Schoology's default limit)
+    pub fn shared(consumer_key: &str) -> Arc<Self> {
+        let mut limiters = lock(SHARED.get_or_init(|| Mutex::new(HashMap::new())));
+
+        limiters
+            .entry(consumer_key.to_string())
+            .or_insert_with(|| Arc::new(Self::new(DEFAULT_REQUESTS, DEFAULT_PERIOD)))
+            .clone()
+    }
+
+    fn refill(&self, bucket: &mut Bucket) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.capacity);
+        bucket.updated = now;
+    }
+
+    /// Takes a token, returns how long to wait before it can be used
+    /// Tokens are handed out in order, so a busy process can't starve a request.
+    pub fn reserve(&self) -> Duration {
+        let mut bucket = lock(&self.bucket);
+        self.refill(&mut bucket);
+
+        bucket.tokens -= 1.0;
+
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / self.rate)
+        }
+    }
+
+    /// Waits until a request can be sent
+    pub async fn acquire(&self) {
+        let wait = self.reserve();
+
+        if !wait.is_zero() {
+            debug!("Waiting {:?} for the Schoology rate limit", wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Stops handing out tokens for a while, e.g. when Schoology is throttling anyway
+    pub fn pause(&self, duration: Duration) {
+        let mut bucket = lock(&self.bucket);
+        self.refill(&mut bucket);
+
+        bucket.tokens = bucket.tokens.min(-duration.as_secs_f64() * self.rate);
+    }
+}
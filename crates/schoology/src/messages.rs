@@ -0,0 +1,182 @@
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+use crate::{
+    de,
+    error::{expect_status, SchoologyError},
+    pagination::get_all,
+    SchoologyClient, SchoologyRequest, SchoologyTokenPair,
+};
+
+/// A conversation in the inbox (or the sent folder), the subject and author are those of its first message
+#[derive(Debug, Clone)]
+pub struct MessageThread {
+    pub id: usize,
+    pub subject: String,
+    pub author_id: usize,
+    pub recipient_ids: Vec<usize>,
+    /// When the last message was sent
+    pub last_updated: Option<DateTime<Utc>>,
+    /// Whether the thread has messages the user did not read yet
+    pub unread: bool,
+}
+
+/// A message of a thread
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub id: usize,
+    pub subject: String,
+    pub author_id: usize,
+    pub recipient_ids: Vec<usize>,
+    /// The text of the message (can contain HTML)
+    pub body: String,
+    pub created: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+struct SchoologyMessage {
+    #[serde(deserialize_with = "de::id")]
+    id: usize,
+    #[serde(default, deserialize_with = "de::string")]
+    subject: String,
+    #[serde(deserialize_with = "de::id")]
+    author_id: usize,
+    #[serde(default, deserialize_with = "de::comma_ids")]
+    recipient_ids: Vec<usize>,
+    #[serde(default, deserialize_with = "de::option_timestamp")]
+    last_updated: Option<DateTime<Utc>>,
+    /// `read` or `unread` (`sent` in the sent folder)
+    #[serde(default, deserialize_with = "de::string")]
+    message_status: String,
+    /// Only sent for the messages of a thread
+    #[serde(default, deserialize_with = "de::string")]
+    message: String,
+}
+
+impl SchoologyMessage {
+    fn into_thread(self) -> MessageThread {
+        MessageThread {
+            id: self.id,
+            subject: self.subject,
+            author_id: self.author_id,
+            recipient_ids: self.recipient_ids,
+            last_updated: self.last_updated,
+            unread: self.message_status == "unread",
+        }
+    }
+
+    fn into_message(self) -> Message {
+        Message {
+            id: self.id,
+            subject: self.subject,
+            author_id: self.author_id,
+            recipient_ids: self.recipient_ids,
+            body: self.message,
+            created: self.last_updated,
+        }
+    }
+}
+
+async fn get_folder(
+    client: &SchoologyClient,
+    token: &SchoologyTokenPair,
+    path: &str,
+) -> Result<Vec<MessageThread>, SchoologyError> {
+    let threads: Vec<SchoologyMessage> = get_all(client, path, &[], token, "message").await?;
+
+    Ok(threads
+        .into_iter()
+        .map(SchoologyMessage::into_thread)
+        .collect())
+}
+
+/// Gets the threads in the user's inbox, the most recent first
+pub async fn get_inbox(
+    client: &SchoologyClient,
+    token: &SchoologyTokenPair,
+) -> Result<Vec<MessageThread>, SchoologyError> {
+    debug!("Getting inbox");
+
+    get_folder(client, token, "messages/inbox").await
+}
+
+/// Gets the threads the user started, the most recent first
+pub async fn get_sent(
+    client: &SchoologyClient,
+    token: &SchoologyTokenPair,
+) -> Result<Vec<MessageThread>, SchoologyError> {
+    debug!("Getting sent messages");
+
+    get_folder(client, token, "messages/sent").await
+}
+
+/// Gets the messages of a thread in the inbox, the oldest first
+/// Note: Schoology marks the thread as read.
+pub async fn get_thread(
+    client: &SchoologyClient,
+    token: &SchoologyTokenPair,
+    thread_id: usize,
+) -> Result<Vec<Message>, SchoologyError> {
+    debug!("Getting message thread {}", thread_id);
+
+    let messages: Vec<SchoologyMessage> = get_all(
+        client,
+        &format!("messages/inbox/{}", thread_id),
+        &[],
+        token,
+        "message",
+    )
+    .await?;
+
+    let mut messages = messages
+        .into_iter()
+        .map(SchoologyMessage::into_message)
+        .collect::<Vec<Message>>();
+
+    messages.sort_by(|a, b| a.created.cmp(&b.created).then(a.id.cmp(&b.id)));
+
+    Ok(messages)
+}
+
+/// Replies to a thread, returns the new message
+pub async fn reply(
+    client: &SchoologyClient,
+    token: &SchoologyTokenPair,
+    thread_id: usize,
+    subject: &str,
+    body: &str,
+    recipient_ids: &[usize],
+) -> Result<Message, SchoologyError> {
+    debug!("Replying to message thread {}", thread_id);
+
+    // Schoology wants the recipients as a comma separated list
+    let body = serde_json::json!({
+        "subject": subject,
+        "message": body,
+        "recipient_ids": recipient_ids
+            .iter()
+            .map(usize::to_string)
+            .collect::<Vec<String>>()
+            .join(","),
+    })
+    .to_string();
+
+    let response = client
+        .post(
+            &format!("messages/{}", thread_id),
+            SchoologyRequest::new()
+                .with_access_tokens(token)
+                .with_body(body),
+        )
+        .await?;
+
+    // Schoology answers `201 Created` with the message
+    let response = expect_status(response, StatusCode::CREATED).await?;
+    let text = response.text().await?;
+
+    let message: SchoologyMessage =
+        serde_json::from_str(&text).map_err(SchoologyError::invalid_response)?;
+
+    Ok(message.into_message())
+}
@@ -1,8 +1,10 @@
-use reqwest::StatusCode;
 use serde::Deserialize;
 use serde_urlencoded;
 
-use crate::{SchoologyClient, SchoologyRequest, SchoologyTokenPair};
+use crate::{
+    error::{read_text, SchoologyError},
+    SchoologyClient, SchoologyRequest, SchoologyTokenPair,
+};
 
 #[derive(Deserialize, Debug)]
 pub struct OauthRequestTokenResponse {
@@ -23,48 +25,21 @@ pub struct OauthRequestToken {
     pub ttl: usize,
 }
 
-pub enum OauthRequestTokenError {
-    /// Application not authorized
-    Unauthorized,
-    /// Other error
-    Other,
-}
-
 /// Sends a request to the Schoology API to get an oauth request token
+/// A `401 Unauthorized` error means the application is not authorized
 pub async fn get_oauth_request_token(
     client: &SchoologyClient,
-) -> Result<OauthRequestToken, OauthRequestTokenError> {
+) -> Result<OauthRequestToken, SchoologyError> {
     debug!("Getting oauth request token");
     let response = client
-        .get("/v1/oauth/request_token", SchoologyRequest::new())
-        .await;
+        .get("oauth/request_token", SchoologyRequest::new())
+        .await?;
 
-    let data = match response {
-        Ok(response) => match response.status() {
-            StatusCode::OK => response.text().await.map_err(|err| {
-                warn!("Failed to get oauth request token: {:?}", err);
-                OauthRequestTokenError::Other
-            }),
-            StatusCode::UNAUTHORIZED => {
-                warn!("Application not authorized");
-                Err(OauthRequestTokenError::Unauthorized)
-            }
-            _ => {
-                debug!("Unknown status code...");
-                Err(OauthRequestTokenError::Other)
-            }
-        },
-        Err(err) => {
-            warn!("Failed to get oauth request token: {:?}", err);
-            Err(OauthRequestTokenError::Other)
-        }
-    }?;
+    let data = read_text(response).await?;
 
     // Parse the response
-    let response: OauthRequestTokenResponse = serde_urlencoded::from_str(&data).map_err(|err| {
-        warn!("Failed to parse oauth request token response: {:?}", err);
-        OauthRequestTokenError::Other
-    })?;
+    let response: OauthRequestTokenResponse =
+        serde_urlencoded::from_str(&data).map_err(SchoologyError::invalid_response)?;
 
     Ok(OauthRequestToken {
         access_token: response.oauth_token,
@@ -81,54 +56,26 @@ pub struct OauthAccessTokenResponse {
     pub oauth_token_secret: String,
 }
 
-pub enum AccessTokenError {
-    /// Expired / non-existent request token or application not authorized
-    Unauthorized,
-    /// Other error
-    Other,
-}
-
 /// Sends a request to the Schoology API to get an oauth access token
 /// This uses the request_token's access_token and access_token_secret
+/// A `401 Unauthorized` error means the request token is invalid / expired or the user did not authorize the application
 pub async fn get_oauth_access_token(
     client: &SchoologyClient,
     tokens: &SchoologyTokenPair,
-) -> Result<SchoologyTokenPair, AccessTokenError> {
+) -> Result<SchoologyTokenPair, SchoologyError> {
     debug!("Getting oauth access token");
     let response = client
         .get(
-            "/v1/oauth/access_token",
+            "oauth/access_token",
             SchoologyRequest::new().with_access_tokens(tokens),
         )
-        .await;
-
-    let data = match response {
-        Ok(response) => match response.status() {
-            StatusCode::OK => response.text().await.map_err(|err| {
-                warn!("Failed to get oauth access token: {:?}", err);
-                AccessTokenError::Other
-            }),
-            StatusCode::UNAUTHORIZED => {
-                debug!("Token is invalid / expired or application not authorized");
-                Err(AccessTokenError::Unauthorized)
-            }
-            _ => {
-                debug!("Unknown status code: {}", response.status());
-                Err(AccessTokenError::Other)
-            }
-        },
+        .await?;
 
-        Err(err) => {
-            warn!("Failed to get oauth access token: {:?}", err);
-            Err(AccessTokenError::Other)
-        }
-    }?;
+    let data = read_text(response).await?;
 
     // Parse the response
-    let response: OauthAccessTokenResponse = serde_urlencoded::from_str(&data).map_err(|err| {
-        warn!("Failed to parse oauth access token response: {:?}", err);
-        AccessTokenError::Other
-    })?;
+    let response: OauthAccessTokenResponse =
+        serde_urlencoded::from_str(&data).map_err(SchoologyError::invalid_response)?;
 
     Ok(SchoologyTokenPair {
         access_token: response.oauth_token,
@@ -0,0 +1,118 @@
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{
+    de,
+    error::{read_json, SchoologyError},
+    SchoologyClient, SchoologyRequest, SchoologyTokenPair,
+};
+
+/// The largest page Schoology allows
+const PAGE_SIZE: usize = 200;
+/// A limit on the number of pages so a misbehaving API can't keep us looping
+const MAX_PAGES: usize = 50;
+
+#[derive(Deserialize)]
+struct Links {
+    #[serde(default)]
+    next: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawPage {
+    #[serde(default, deserialize_with = "de::option_id")]
+    total: Option<usize>,
+    #[serde(default)]
+    links: Option<Links>,
+    #[serde(flatten)]
+    items: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A page of a paginated list
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// The number of items in the whole list (Schoology leaves it out for some lists)
+    pub total: Option<usize>,
+    /// Whether there is a next page
+    pub has_more: bool,
+}
+
+/// Gets a page of a paginated list (`start` / `limit`), `key` is the field that holds the items
+pub(crate) async fn get_page<T>(
+    client: &SchoologyClient,
+    path: &str,
+    query: &[(&str, String)],
+    token: &SchoologyTokenPair,
+    key: &str,
+    start: usize,
+    limit: usize,
+) -> Result<Page<T>, SchoologyError>
+where
+    T: DeserializeOwned,
+{
+    let request = query.iter().fold(
+        SchoologyRequest::new().with_access_tokens(token),
+        |request, (key, value)| request.with_query_param(key.to_string(), value.clone()),
+    );
+
+    let response = client
+        .get(
+            path,
+            request
+                .with_query_param("start".to_string(), start.to_string())
+                .with_query_param("limit".to_string(), limit.to_string()),
+        )
+        .await?;
+
+    let mut page: RawPage = read_json(response).await?;
+
+    // Schoology leaves out the key (or sends `null`) for empty lists
+    let items: Vec<T> = match page.items.remove(key) {
+        Some(serde_json::Value::Null) | None => Vec::new(),
+        Some(value) => serde_json::from_value(value).map_err(SchoologyError::invalid_response)?,
+    };
+
+    // Schoology may return less than the limit, so the total (or the next link) decides
+    let has_more = !items.is_empty()
+        && match page.total {
+            Some(total) => start + items.len() < total,
+            None => match page.links {
+                Some(links) => links.next.is_some(),
+                None => items.len() >= limit,
+            },
+        };
+
+    Ok(Page {
+        items,
+        total: page.total,
+        has_more,
+    })
+}
+
+/// Gets every item of a paginated list (`start` / `limit`), `key` is the field that holds the items
+pub(crate) async fn get_all<T>(
+    client: &SchoologyClient,
+    path: &str,
+    query: &[(&str, String)],
+    token: &SchoologyTokenPair,
+    key: &str,
+) -> Result<Vec<T>, SchoologyError>
+where
+    T: DeserializeOwned,
+{
+    let mut items: Vec<T> = Vec::new();
+
+    for _ in 0..MAX_PAGES {
+        let page = get_page(client, path, query, token, key, items.len(), PAGE_SIZE).await?;
+
+        items.extend(page.items);
+
+        if !page.has_more {
+            return Ok(items);
+        }
+    }
+
+    warn!("Stopped paginating {} after {} pages", path, MAX_PAGES);
+
+    Ok(items)
+}
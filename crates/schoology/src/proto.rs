@@ -102,13 +102,7 @@ impl OAuth1AHeader {
         // URL encode the parameters and join them with "&"
         let params = params
             .iter()
-            .map(|(k, v)| {
-                format!(
-                    "{}={}",
-                    urlencoding::encode(k).to_string(),
-                    urlencoding::encode(v).to_string()
-                )
-            })
+            .map(|(k, v)| format!("{}={}", urlencoding::encode(k), urlencoding::encode(v)))
             .collect::<Vec<String>>()
             .join("&");
 
@@ -118,15 +112,15 @@ impl OAuth1AHeader {
         // Strip the url of any query parameters, fragment, etc.
         url.set_query(None);
         url.set_fragment(None);
-        let url = urlencoding::encode(&url.to_string()).to_string();
+        let url = urlencoding::encode(url.as_ref()).to_string();
 
         // `http method + "&" + url + "&" + params`
         let mut param_string =
             String::with_capacity(request_method.len() + url.len() + params.len() + 2);
-        param_string.push_str(&request_method);
-        param_string.push_str("&");
+        param_string.push_str(request_method);
+        param_string.push('&');
         param_string.push_str(&url);
-        param_string.push_str("&");
+        param_string.push('&');
         param_string.push_str(&params);
 
         // Generate the signing key `consumer_secret + "&" + access_token_secret`
@@ -139,8 +133,8 @@ impl OAuth1AHeader {
                     .len()
                 + 1,
         );
-        signing_key.push_str(&consumer_secret);
-        signing_key.push_str("&");
+        signing_key.push_str(consumer_secret);
+        signing_key.push('&');
         signing_key.push_str(&self.access_token_secret.clone().unwrap_or("".to_string()));
 
         debug!("Signing key: {}", param_string);
@@ -156,7 +150,7 @@ impl OAuth1AHeader {
         mac.update(param_string.as_bytes());
 
         // Base64 encode the signature
-        STANDARD_NO_PAD.encode(&mac.finalize().into_bytes())
+        STANDARD_NO_PAD.encode(mac.finalize().into_bytes())
     }
 
     /// Gets the OAuth 1.0a header for the request
@@ -195,13 +189,7 @@ impl OAuth1AHeader {
         // URL encode the parameters and join them with ","
         let header_str = header
             .iter()
-            .map(|(k, v)| {
-                format!(
-                    "{}=\"{}\"",
-                    urlencoding::encode(k).to_string(),
-                    urlencoding::encode(v).to_string()
-                )
-            })
+            .map(|(k, v)| format!("{}=\"{}\"", urlencoding::encode(k), urlencoding::encode(v)))
             .collect::<Vec<String>>()
             .join(",");
 
@@ -0,0 +1,76 @@
+//! Retrying requests Schoology failed to answer (throttled or a server error)
+
+use std::time::Duration;
+
+use rand::Rng;
+use reqwest::{Method, StatusCode};
+
+/// When and how often a request is sent again
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// How many times a request is retried (`0` disables retries)
+    pub max_retries: u32,
+    /// The delay before the first retry, it doubles with every retry
+    pub base_delay: Duration,
+    /// The longest delay, a `Retry-After` above it is not waited for
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retries
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Whether a response should be retried
+    /// Throttled requests were not handled so they are always retried, server errors only for
+    /// idempotent methods (a POST may have gone through).
+    pub fn should_retry(&self, method: &Method, status: StatusCode, retries: u32) -> bool {
+        if retries >= self.max_retries {
+            return false;
+        }
+
+        match status {
+            StatusCode::TOO_MANY_REQUESTS => true,
+            status if status.is_server_error() => {
+                matches!(
+                    *method,
+                    Method::GET | Method::HEAD | Method::PUT | Method::DELETE
+                )
+            }
+            _ => false,
+        }
+    }
+
+    /// How long to wait before retry number `retries` (starting at 0)
+    /// Returns `None` if Schoology asked to wait longer than `max_delay`.
+    pub fn delay(&self, retries: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retries))
+            .min(self.max_delay);
+
+        // Somewhere between half and all of the backoff, so clients that failed together don't retry together
+        let jitter = rand::thread_rng().gen_range(0..1000);
+        let delay = backoff / 2 + backoff / 2 * jitter / 1000;
+
+        match retry_after {
+            Some(retry_after) if retry_after > self.max_delay => None,
+            Some(retry_after) => Some(delay.max(retry_after)),
+            None => Some(delay),
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use serde::Deserialize;
+
+use crate::{
+    de,
+    error::{read_json, SchoologyError},
+    pagination::get_all,
+    SchoologyClient, SchoologyRequest, SchoologyTokenPair,
+};
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SchoologySection {
+    #[serde(deserialize_with = "de::id")]
+    pub id: usize,
+    #[serde(deserialize_with = "de::id")]
+    pub course_id: usize,
+    #[serde(default, deserialize_with = "de::string")]
+    pub course_title: String,
+    #[serde(default, deserialize_with = "de::string")]
+    pub course_code: String,
+    #[serde(default, deserialize_with = "de::string")]
+    pub section_title: String,
+    #[serde(default, deserialize_with = "de::string")]
+    pub section_code: String,
+    #[serde(default, deserialize_with = "de::option_id")]
+    pub school_id: Option<usize>,
+    #[serde(default, deserialize_with = "de::string")]
+    pub description: String,
+    #[serde(default, deserialize_with = "de::string")]
+    pub location: String,
+    #[serde(default, deserialize_with = "de::string")]
+    pub profile_url: String,
+    /// Whether the section is in a current grading period
+    #[serde(default, deserialize_with = "de::flag")]
+    pub active: bool,
+    #[serde(default, deserialize_with = "de::ids")]
+    pub grading_periods: Vec<usize>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SchoologyCourse {
+    #[serde(deserialize_with = "de::id")]
+    pub id: usize,
+    #[serde(default, deserialize_with = "de::string")]
+    pub title: String,
+    #[serde(default, deserialize_with = "de::string")]
+    pub course_code: String,
+    #[serde(default, deserialize_with = "de::string")]
+    pub department: String,
+    #[serde(default, deserialize_with = "de::string")]
+    pub description: String,
+    #[serde(default, deserialize_with = "de::string")]
+    pub subject_area: String,
+    #[serde(default, deserialize_with = "de::option_id")]
+    pub building_id: Option<usize>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SchoologyEnrollment {
+    #[serde(deserialize_with = "de::id")]
+    pub id: usize,
+    /// The user id
+    #[serde(deserialize_with = "de::id")]
+    pub uid: usize,
+    #[serde(default, deserialize_with = "de::string")]
+    pub name_first: String,
+    #[serde(default, deserialize_with = "de::string")]
+    pub name_last: String,
+    #[serde(default, deserialize_with = "de::string")]
+    pub picture_url: String,
+    /// Whether the user is a teacher (admin) of the section (or an admin of the group)
+    #[serde(default, deserialize_with = "de::flag")]
+    pub admin: bool,
+    /// `1` active, `2` expired, `3` invite pending, `4` request pending, `5` archived
+    #[serde(default, deserialize_with = "de::option_id")]
+    pub status: Option<usize>,
+}
+
+/// Gets the sections a user is enrolled in
+pub async fn get_user_sections(
+    client: &SchoologyClient,
+    token: &SchoologyTokenPair,
+    user_id: usize,
+) -> Result<Vec<SchoologySection>, SchoologyError> {
+    debug!("Getting sections of user {}", user_id);
+
+    get_all(
+        client,
+        &format!("users/{}/sections", user_id),
+        &[],
+        token,
+        "section",
+    )
+    .await
+}
+
+/// Gets a section
+pub async fn get_section(
+    client: &SchoologyClient,
+    token: &SchoologyTokenPair,
+    section_id: usize,
+) -> Result<SchoologySection, SchoologyError> {
+    debug!("Getting section {}", section_id);
+
+    let response = client
+        .get(
+            &format!("sections/{}", section_id),
+            SchoologyRequest::new().with_access_tokens(token),
+        )
+        .await?;
+
+    read_json(response).await
+}
+
+/// Gets a course
+pub async fn get_course(
+    client: &SchoologyClient,
+    token: &SchoologyTokenPair,
+    course_id: usize,
+) -> Result<SchoologyCourse, SchoologyError> {
+    debug!("Getting course {}", course_id);
+
+    let response = client
+        .get(
+            &format!("courses/{}", course_id),
+            SchoologyRequest::new().with_access_tokens(token),
+        )
+        .await?;
+
+    read_json(response).await
+}
+
+/// Gets the enrollments (students and teachers) of a section
+pub async fn get_section_enrollments(
+    client: &SchoologyClient,
+    token: &SchoologyTokenPair,
+    section_id: usize,
+) -> Result<Vec<SchoologyEnrollment>, SchoologyError> {
+    debug!("Getting enrollments of section {}", section_id);
+
+    get_all(
+        client,
+        &format!("sections/{}/enrollments", section_id),
+        &[],
+        token,
+        "enrollment",
+    )
+    .await
+}
@@ -0,0 +1,409 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::{
+    de,
+    error::SchoologyError,
+    pagination::{get_all, get_page, Page},
+    SchoologyClient, SchoologyTokenPair,
+};
+
+/// Where an update was posted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateRealm {
+    /// Usually an announcement of the teacher
+    Section(usize),
+    Group(usize),
+    School(usize),
+    /// A post on the profile of a user
+    User(usize),
+    Other,
+}
+
+impl UpdateRealm {
+    /// The path of the realm in the API
+    fn path(&self) -> Option<String> {
+        match self {
+            UpdateRealm::Section(id) => Some(format!("sections/{}", id)),
+            UpdateRealm::Group(id) => Some(format!("groups/{}", id)),
+            UpdateRealm::School(id) => Some(format!("schools/{}", id)),
+            UpdateRealm::User(id) => Some(format!("users/{}", id)),
+            UpdateRealm::Other => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachmentKind {
+    File,
+    Link,
+    Video,
+}
+
+/// A file, link or video attached to an update
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub id: usize,
+    pub kind: AttachmentKind,
+    pub title: String,
+    /// Where it can be opened (files need to be downloaded with the user's token)
+    pub url: String,
+    pub filename: Option<String>,
+    /// The size of files (in bytes)
+    pub size: Option<usize>,
+}
+
+/// A post on a feed
+#[derive(Debug, Clone)]
+pub struct Update {
+    pub id: usize,
+    pub author_id: usize,
+    /// The text of the update (can contain HTML)
+    pub body: String,
+    pub created: Option<DateTime<Utc>>,
+    pub last_updated: Option<DateTime<Utc>>,
+    pub realm: UpdateRealm,
+    pub likes: usize,
+    /// Whether the user liked the update
+    pub liked: bool,
+    pub comment_count: usize,
+    pub attachments: Vec<Attachment>,
+}
+
+/// A comment on an update
+#[derive(Debug, Clone)]
+pub struct Comment {
+    pub id: usize,
+    pub author_id: usize,
+    pub body: String,
+    pub created: Option<DateTime<Utc>>,
+    pub likes: usize,
+    /// The comment this is a reply to
+    pub parent_id: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct SchoologyFile {
+    #[serde(deserialize_with = "de::id")]
+    id: usize,
+    #[serde(default, deserialize_with = "de::string")]
+    title: String,
+    #[serde(default, deserialize_with = "de::string")]
+    filename: String,
+    #[serde(default, deserialize_with = "de::option_id")]
+    filesize: Option<usize>,
+    #[serde(default, deserialize_with = "de::string")]
+    download_path: String,
+}
+
+#[derive(Deserialize)]
+struct SchoologyLink {
+    #[serde(deserialize_with = "de::id")]
+    id: usize,
+    #[serde(default, deserialize_with = "de::string")]
+    title: String,
+    #[serde(default, deserialize_with = "de::string")]
+    url: String,
+}
+
+#[derive(Deserialize, Default)]
+struct SchoologyFiles {
+    #[serde(default)]
+    file: Vec<SchoologyFile>,
+}
+
+#[derive(Deserialize, Default)]
+struct SchoologyLinks {
+    #[serde(default)]
+    link: Vec<SchoologyLink>,
+}
+
+#[derive(Deserialize, Default)]
+struct SchoologyVideos {
+    #[serde(default)]
+    video: Vec<SchoologyLink>,
+}
+
+/// `{ "files": { "file": [...] }, "links": { "link": [...] }, "videos": { "video": [...] } }`
+#[derive(Deserialize, Default)]
+struct SchoologyAttachments {
+    #[serde(default)]
+    files: Option<SchoologyFiles>,
+    #[serde(default)]
+    links: Option<SchoologyLinks>,
+    #[serde(default)]
+    videos: Option<SchoologyVideos>,
+}
+
+impl SchoologyAttachments {
+    fn into_attachments(self) -> Vec<Attachment> {
+        let files = self
+            .files
+            .unwrap_or_default()
+            .file
+            .into_iter()
+            .map(|file| Attachment {
+                id: file.id,
+                kind: AttachmentKind::File,
+                title: file.title,
+                url: file.download_path,
+                filename: Some(file.filename).filter(|filename| !filename.is_empty()),
+                size: file.filesize,
+            });
+
+        let links = self
+            .links
+            .unwrap_or_default()
+            .link
+            .into_iter()
+            .map(|link| Attachment {
+                id: link.id,
+                kind: AttachmentKind::Link,
+                title: link.title,
+                url: link.url,
+                filename: None,
+                size: None,
+            });
+
+        let videos = self
+            .videos
+            .unwrap_or_default()
+            .video
+            .into_iter()
+            .map(|video| Attachment {
+                id: video.id,
+                kind: AttachmentKind::Video,
+                title: video.title,
+                url: video.url,
+                filename: None,
+                size: None,
+            });
+
+        files.chain(links).chain(videos).collect()
+    }
+}
+
+#[derive(Deserialize)]
+struct SchoologyUpdate {
+    #[serde(deserialize_with = "de::id")]
+    id: usize,
+    #[serde(deserialize_with = "de::id")]
+    uid: usize,
+    #[serde(default, deserialize_with = "de::string")]
+    body: String,
+    #[serde(default, deserialize_with = "de::option_timestamp")]
+    created: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "de::option_timestamp")]
+    last_updated: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "de::option_id")]
+    likes: Option<usize>,
+    #[serde(default, deserialize_with = "de::flag")]
+    user_like_action: bool,
+    #[serde(default, deserialize_with = "de::option_id")]
+    num_comments: Option<usize>,
+    #[serde(default, deserialize_with = "de::string")]
+    realm: String,
+    #[serde(default, deserialize_with = "de::option_id")]
+    section_id: Option<usize>,
+    #[serde(default, deserialize_with = "de::option_id")]
+    group_id: Option<usize>,
+    #[serde(default, deserialize_with = "de::option_id")]
+    school_id: Option<usize>,
+    #[serde(default, deserialize_with = "de::option_id")]
+    user_id: Option<usize>,
+    /// Schoology sends an empty list instead of an object when there are no attachments
+    #[serde(default)]
+    attachments: Option<serde_json::Value>,
+}
+
+impl SchoologyUpdate {
+    /// `realm` is used for updates of a realm's feed, they don't say where they were posted
+    fn into_update(self, realm: Option<UpdateRealm>) -> Update {
+        let realm = match self.realm.as_str() {
+            "section" => self.section_id.map(UpdateRealm::Section),
+            "group" => self.group_id.map(UpdateRealm::Group),
+            "school" => self.school_id.map(UpdateRealm::School),
+            "user" => self.user_id.map(UpdateRealm::User),
+            _ => None,
+        }
+        .or(realm)
+        .unwrap_or(UpdateRealm::Other);
+
+        let attachments = match self.attachments {
+            Some(value @ serde_json::Value::Object(_)) => {
+                serde_json::from_value::<SchoologyAttachments>(value)
+                    .map(SchoologyAttachments::into_attachments)
+                    .unwrap_or_else(|err| {
+                        warn!("Failed to parse attachments of update {}: {}", self.id, err);
+                        Vec::new()
+                    })
+            }
+            _ => Vec::new(),
+        };
+
+        Update {
+            id: self.id,
+            author_id: self.uid,
+            body: self.body,
+            created: self.created,
+            last_updated: self.last_updated,
+            realm,
+            likes: self.likes.unwrap_or(0),
+            liked: self.user_like_action,
+            comment_count: self.num_comments.unwrap_or(0),
+            attachments,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SchoologyComment {
+    #[serde(deserialize_with = "de::id")]
+    id: usize,
+    #[serde(deserialize_with = "de::id")]
+    uid: usize,
+    #[serde(default, deserialize_with = "de::string")]
+    comment: String,
+    #[serde(default, deserialize_with = "de::option_timestamp")]
+    created: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "de::option_id")]
+    likes: Option<usize>,
+    #[serde(default, deserialize_with = "de::option_id")]
+    parent_id: Option<usize>,
+}
+
+/// Gets a page of the user's feed, the most recent updates of all their sections, groups and schools
+pub async fn get_recent_updates(
+    client: &SchoologyClient,
+    token: &SchoologyTokenPair,
+    start: usize,
+    limit: usize,
+) -> Result<Page<Update>, SchoologyError> {
+    debug!("Getting recent updates from {} ({})", start, limit);
+
+    let page: Page<SchoologyUpdate> = get_page(
+        client,
+        "recent",
+        &[("with_attachments", "1".to_string())],
+        token,
+        "update",
+        start,
+        limit,
+    )
+    .await?;
+
+    Ok(Page {
+        items: page
+            .items
+            .into_iter()
+            .map(|update| update.into_update(None))
+            .collect(),
+        total: page.total,
+        has_more: page.has_more,
+    })
+}
+
+/// Gets a page of the updates of a section, the most recent first
+pub async fn get_section_updates(
+    client: &SchoologyClient,
+    token: &SchoologyTokenPair,
+    section_id: usize,
+    start: usize,
+    limit: usize,
+) -> Result<Page<Update>, SchoologyError> {
+    debug!(
+        "Getting updates of section {} from {} ({})",
+        section_id, start, limit
+    );
+
+    let page: Page<SchoologyUpdate> = get_page(
+        client,
+        &format!("sections/{}/updates", section_id),
+        &[("with_attachments", "1".to_string())],
+        token,
+        "update",
+        start,
+        limit,
+    )
+    .await?;
+
+    Ok(Page {
+        items: page
+            .items
+            .into_iter()
+            .map(|update| update.into_update(Some(UpdateRealm::Section(section_id))))
+            .collect(),
+        total: page.total,
+        has_more: page.has_more,
+    })
+}
+
+/// Gets a page of the updates of a group, the most recent first
+pub async fn get_group_updates(
+    client: &SchoologyClient,
+    token: &SchoologyTokenPair,
+    group_id: usize,
+    start: usize,
+    limit: usize,
+) -> Result<Page<Update>, SchoologyError> {
+    debug!(
+        "Getting updates of group {} from {} ({})",
+        group_id, start, limit
+    );
+
+    let page: Page<SchoologyUpdate> = get_page(
+        client,
+        &format!("groups/{}/updates", group_id),
+        &[("with_attachments", "1".to_string())],
+        token,
+        "update",
+        start,
+        limit,
+    )
+    .await?;
+
+    Ok(Page {
+        items: page
+            .items
+            .into_iter()
+            .map(|update| update.into_update(Some(UpdateRealm::Group(group_id))))
+            .collect(),
+        total: page.total,
+        has_more: page.has_more,
+    })
+}
+
+/// Gets the comments of an update, the oldest first
+pub async fn get_update_comments(
+    client: &SchoologyClient,
+    token: &SchoologyTokenPair,
+    realm: UpdateRealm,
+    update_id: usize,
+) -> Result<Vec<Comment>, SchoologyError> {
+    debug!("Getting comments of update {}", update_id);
+
+    let Some(realm_path) = realm.path() else {
+        return Ok(Vec::new());
+    };
+
+    let comments: Vec<SchoologyComment> = get_all(
+        client,
+        &format!("{}/updates/{}/comments", realm_path, update_id),
+        &[],
+        token,
+        "comment",
+    )
+    .await?;
+
+    Ok(comments
+        .into_iter()
+        .map(|comment| Comment {
+            id: comment.id,
+            author_id: comment.uid,
+            body: comment.comment,
+            created: comment.created,
+            likes: comment.likes.unwrap_or(0),
+            parent_id: comment.parent_id,
+        })
+        .collect())
+}
@@ -0,0 +1,93 @@
+//! Tests of the assignment endpoints against the mock Schoology server
+
+use chrono::NaiveDate;
+use schoology::assignments::{get_assignments, get_section_assignments, AssignmentType};
+use schoology_mock::{MockAssignment, MockEvent, MockSection, MockUser};
+
+mod common;
+
+use common::{setup, token};
+
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2023, 10, day).unwrap()
+}
+
+#[tokio::test]
+async fn section_assignments() {
+    let (mock, client) = setup().await;
+    mock.add_section(MockSection::new(10, 100));
+
+    let mut quiz = MockAssignment::new(2, 10, "");
+    quiz.kind = "assessment".to_string();
+    quiz.max_points = None;
+    quiz.completed = true;
+
+    mock.add_assignment(MockAssignment::new(1, 10, "2023-10-20 23:59:00"));
+    mock.add_assignment(quiz);
+
+    let assignments = get_section_assignments(&client, &token(&mock, 1), 10)
+        .await
+        .unwrap();
+
+    assert_eq!(assignments.len(), 2);
+
+    assert_eq!(assignments[0].section_id, 10);
+    assert_eq!(assignments[0].kind, AssignmentType::Assignment);
+    assert_eq!(assignments[0].max_points, Some(100.0));
+    assert_eq!(
+        assignments[0].due,
+        date(20).and_hms_opt(23, 59, 0),
+        "due dates are parsed"
+    );
+
+    assert_eq!(assignments[1].kind, AssignmentType::Assessment);
+    assert_eq!(assignments[1].due, None);
+    assert_eq!(assignments[1].max_points, None);
+    assert!(assignments[1].completed);
+}
+
+#[tokio::test]
+async fn due_in_range() {
+    let (mock, client) = setup().await;
+    mock.add_user(MockUser::new(1));
+
+    let mut past = MockSection::new(12, 102);
+    past.active = false;
+
+    mock.add_section(MockSection::new(10, 100));
+    mock.add_section(MockSection::new(11, 101));
+    mock.add_section(past);
+    mock.enroll(10, 1, false);
+    mock.enroll(11, 1, false);
+    mock.enroll(12, 1, false);
+
+    mock.add_assignment(MockAssignment::new(1, 10, "2023-10-16 08:00:00"));
+    mock.add_assignment(MockAssignment::new(2, 11, "2023-10-22 23:59:00"));
+    // Outside of the range
+    mock.add_assignment(MockAssignment::new(3, 10, "2023-10-23 00:00:00"));
+    mock.add_assignment(MockAssignment::new(4, 10, "2023-10-15 23:59:59"));
+    // No due date
+    mock.add_assignment(MockAssignment::new(5, 11, ""));
+    // A section of a past grading period
+    mock.add_assignment(MockAssignment::new(6, 12, "2023-10-18 12:00:00"));
+
+    // A test that is only on the calendar
+    let mut test = MockEvent::new(7, Some(11), "2023-10-18 10:00:00");
+    test.kind = "assessment".to_string();
+    mock.add_event(test);
+
+    // Not due dates
+    mock.add_event(MockEvent::new(8, Some(10), "2023-10-17 10:00:00"));
+    mock.add_event(MockEvent::new(9, None, "2023-10-17 10:00:00"));
+
+    let assignments = get_assignments(&client, &token(&mock, 1), 1, date(16), date(22))
+        .await
+        .unwrap();
+
+    let ids = assignments.iter().map(|a| a.id).collect::<Vec<usize>>();
+    assert_eq!(ids, vec![1, 7, 2]);
+
+    assert_eq!(assignments[1].section_id, 11);
+    assert_eq!(assignments[1].kind, AssignmentType::Assessment);
+    assert_eq!(assignments[1].max_points, None);
+}
@@ -0,0 +1,26 @@
+use schoology::{SchoologyClient, SchoologyTokenPair};
+use schoology_mock::MockSchoology;
+
+/// Starts a mock server and a client pointed at it
+pub async fn setup() -> (MockSchoology, SchoologyClient) {
+    let mock = MockSchoology::start().await.unwrap();
+
+    let client = SchoologyClient::new(
+        mock.consumer_key().to_string(),
+        mock.consumer_secret().to_string(),
+    )
+    .with_base_url(mock.base_url());
+
+    (mock, client)
+}
+
+/// Issues an access token for a user
+#[allow(dead_code)]
+pub fn token(mock: &MockSchoology, user_id: usize) -> SchoologyTokenPair {
+    let (access_token, token_secret) = mock.issue_access_token(user_id);
+
+    SchoologyTokenPair {
+        access_token,
+        token_secret,
+    }
+}
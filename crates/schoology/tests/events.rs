@@ -0,0 +1,93 @@
+//! Tests of the event endpoints against the mock Schoology server
+
+use chrono::NaiveDate;
+use schoology::events::{get_all_events, get_section_events, EventRealm, EventTime, RsvpStatus};
+use schoology_mock::{MockAssignment, MockEvent, MockSection, MockUser};
+
+mod common;
+
+use common::{setup, token};
+
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2023, 10, day).unwrap()
+}
+
+#[tokio::test]
+async fn section_events() {
+    let (mock, client) = setup().await;
+    mock.add_section(MockSection::new(10, 100));
+
+    let mut game = MockEvent::new(1, Some(10), "2023-10-20 18:00:00");
+    game.end = "2023-10-20 20:30:00".to_string();
+    game.rsvp = true;
+    game.rsvp_status = "maybe".to_string();
+
+    let mut field_trip = MockEvent::new(2, Some(10), "2023-10-18 00:00:00");
+    field_trip.all_day = true;
+    field_trip.rsvp = true;
+
+    mock.add_event(game);
+    mock.add_event(field_trip);
+    // Another section
+    mock.add_event(MockEvent::new(3, Some(11), "2023-10-18 10:00:00"));
+    // Outside of the range
+    mock.add_event(MockEvent::new(4, Some(10), "2023-10-23 10:00:00"));
+
+    let events = get_section_events(&client, &token(&mock, 1), 10, date(16), date(22))
+        .await
+        .unwrap();
+
+    assert_eq!(events.len(), 2);
+
+    assert_eq!(events[0].id, 2);
+    assert_eq!(events[0].time, EventTime::AllDay(date(18)));
+    assert_eq!(events[0].realm, EventRealm::Section(10));
+    assert_eq!(events[0].rsvp, RsvpStatus::Pending);
+
+    assert_eq!(
+        events[1].time,
+        EventTime::Timed {
+            start: date(20).and_hms_opt(18, 0, 0).unwrap(),
+            end: date(20).and_hms_opt(20, 30, 0),
+        }
+    );
+    assert_eq!(events[1].rsvp, RsvpStatus::Maybe);
+    assert!(!events[1].is_due_date());
+}
+
+#[tokio::test]
+async fn all_events() {
+    let (mock, client) = setup().await;
+    mock.add_user(MockUser::new(1));
+
+    let mut past = MockSection::new(12, 102);
+    past.active = false;
+
+    mock.add_section(MockSection::new(10, 100));
+    mock.add_section(past);
+    mock.enroll(10, 1, false);
+    mock.enroll(12, 1, false);
+
+    // A rally, on the calendar of every user
+    let mut rally = MockEvent::new(1, None, "2023-10-19 00:00:00");
+    rally.all_day = true;
+    mock.add_event(rally);
+
+    mock.add_event(MockEvent::new(2, Some(10), "2023-10-17 10:00:00"));
+    // A section of a past grading period is still on the user's calendar
+    mock.add_event(MockEvent::new(3, Some(12), "2023-10-16 10:00:00"));
+    mock.add_assignment(MockAssignment::new(4, 10, "2023-10-18 23:59:00"));
+
+    let events = get_all_events(&client, &token(&mock, 1), 1, date(16), date(22))
+        .await
+        .unwrap();
+
+    // Events on both calendars are only returned once
+    let ids = events.iter().map(|e| e.id).collect::<Vec<usize>>();
+    assert_eq!(ids, vec![3, 2, 1_000_004, 1]);
+
+    assert!(events[2].is_due_date());
+    assert_eq!(events[2].assignment_id, Some(4));
+    assert_eq!(events[3].realm, EventRealm::School(1));
+    assert_eq!(events[3].rsvp, RsvpStatus::NotRequested);
+}
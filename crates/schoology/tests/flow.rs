@@ -0,0 +1,124 @@
+//! End to end tests of the OAuth flow against the mock Schoology server
+
+use schoology::{
+    oauth::{get_oauth_access_token, get_oauth_request_token},
+    users::{get_schoology_user, get_user_id},
+    SchoologyClient, SchoologyRequest, SchoologyTokenPair,
+};
+use schoology_mock::MockUser;
+
+mod common;
+
+use common::{setup, token};
+
+#[tokio::test]
+async fn login_flow() {
+    let (mock, client) = setup().await;
+    mock.add_user(MockUser::new(42));
+
+    // Get a request token and authorize it as the user
+    let request_token = get_oauth_request_token(&client).await.unwrap();
+    assert!(request_token.ttl > 0);
+    assert!(mock.authorize(&request_token.access_token, 42));
+
+    // Exchange it for an access token
+    let token = get_oauth_access_token(
+        &client,
+        &SchoologyTokenPair {
+            access_token: request_token.access_token,
+            token_secret: request_token.token_secret,
+        },
+    )
+    .await
+    .unwrap();
+
+    // Get the user
+    let user_id = get_user_id(&client, &token).await.unwrap();
+    assert_eq!(user_id, 42);
+
+    let user = get_schoology_user(&client, &token, user_id).await.unwrap();
+    assert_eq!(user.id, 42);
+    assert_eq!(user.primary_email, "user42@example.com");
+}
+
+#[tokio::test]
+async fn unauthorized_request_token() {
+    let (_mock, client) = setup().await;
+
+    let request_token = get_oauth_request_token(&client).await.unwrap();
+
+    // The user never authorized the app
+    let err = get_oauth_access_token(
+        &client,
+        &SchoologyTokenPair {
+            access_token: request_token.access_token,
+            token_secret: request_token.token_secret,
+        },
+    )
+    .await
+    .err()
+    .expect("the request token was not authorized");
+
+    assert!(err.is_unauthorized());
+}
+
+#[tokio::test]
+async fn invalid_consumer_secret() {
+    let (mock, _client) = setup().await;
+
+    let client = SchoologyClient::new(mock.consumer_key().to_string(), "wrong".to_string())
+        .with_base_url(mock.base_url());
+
+    let err = get_oauth_request_token(&client)
+        .await
+        .err()
+        .expect("the consumer secret is wrong");
+
+    assert!(err.is_unauthorized());
+    assert_eq!(mock.requests(), 0);
+}
+
+#[tokio::test]
+async fn revoked_access_token() {
+    let (mock, client) = setup().await;
+    mock.add_user(MockUser::new(7));
+
+    let token = token(&mock, 7);
+
+    assert_eq!(get_user_id(&client, &token).await.unwrap(), 7);
+
+    mock.revoke(7);
+
+    let err = get_user_id(&client, &token).await.unwrap_err();
+    assert!(err.is_unauthorized());
+}
+
+#[tokio::test]
+async fn unknown_user() {
+    let (mock, client) = setup().await;
+
+    let token = token(&mock, 1);
+
+    let err = get_schoology_user(&client, &token, 1).await.unwrap_err();
+    assert!(err.is_not_found());
+}
+
+#[tokio::test]
+async fn follows_redirects_with_new_nonces() {
+    let (mock, client) = setup().await;
+    mock.add_user(MockUser::new(3));
+
+    let token = token(&mock, 3);
+
+    // Following the `/users/me` redirect must re-sign the request, otherwise the nonce is reused
+    let response = client
+        .get(
+            "users/me",
+            SchoologyRequest::new().with_access_tokens(&token),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    assert_eq!(mock.requests(), 2);
+}
@@ -0,0 +1,73 @@
+//! Tests of the grades endpoint against the mock Schoology server
+
+use schoology::grades::{get_user_grades, GradeException};
+use schoology_mock::{MockCategory, MockGrade, MockUser};
+
+mod common;
+
+use common::{setup, token};
+
+#[tokio::test]
+async fn user_grades() {
+    let (mock, client) = setup().await;
+    mock.add_user(MockUser::new(1));
+
+    mock.add_category(MockCategory {
+        id: 1,
+        section_id: 10,
+        title: "Homework".to_string(),
+        weight: Some(40.0),
+    });
+    mock.add_category(MockCategory {
+        id: 2,
+        section_id: 10,
+        title: "Tests".to_string(),
+        weight: Some(60.0),
+    });
+
+    mock.add_grade(MockGrade::new(1, 10, 100, 1, 10.0, 10.0));
+    mock.add_grade(MockGrade::new(1, 10, 101, 2, 40.0, 50.0));
+
+    let mut excused = MockGrade::new(1, 10, 102, 2, 0.0, 50.0);
+    excused.exception = 1;
+    mock.add_grade(excused);
+
+    // Another user
+    mock.add_grade(MockGrade::new(2, 10, 100, 1, 0.0, 10.0));
+
+    let sections = get_user_grades(&client, &token(&mock, 1), 1).await.unwrap();
+
+    assert_eq!(sections.len(), 1);
+
+    let section = &sections[0];
+    assert_eq!(section.section_id, 10);
+    assert_eq!(section.categories.len(), 2);
+    assert_eq!(section.categories[1].title, "Tests");
+    assert_eq!(section.categories[1].weight, Some(60.0));
+
+    let period = &section.periods[0];
+    assert_eq!(period.period_id, "p1");
+    assert_eq!(period.period_title, "Quarter 1");
+    assert_eq!(period.assignments.len(), 3);
+    assert_eq!(period.assignments[0].grade, Some(10.0));
+    assert_eq!(period.assignments[0].max_points, Some(10.0));
+    assert_eq!(period.assignments[0].category_id, Some(1));
+    assert_eq!(period.assignments[2].exception, GradeException::Excused);
+
+    // 40% * 100 + 60% * 80
+    let final_grade = &section.final_grades[0];
+    assert_eq!(final_grade.period_id, "p1");
+    assert_eq!(final_grade.grade, Some(88.0));
+    assert_eq!(final_grade.categories[0].grade, Some(100.0));
+    assert_eq!(final_grade.categories[1].grade, Some(80.0));
+}
+
+#[tokio::test]
+async fn no_grades() {
+    let (mock, client) = setup().await;
+    mock.add_user(MockUser::new(1));
+
+    let sections = get_user_grades(&client, &token(&mock, 1), 1).await.unwrap();
+
+    assert!(sections.is_empty());
+}
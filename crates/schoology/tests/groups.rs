@@ -0,0 +1,98 @@
+//! Tests of the group endpoints against the mock Schoology server
+
+use chrono::NaiveDate;
+use schoology::{
+    events::{get_group_events, EventRealm},
+    groups::{get_group, get_group_enrollments, get_user_groups},
+    updates::{get_group_updates, get_recent_updates, UpdateRealm},
+};
+use schoology_mock::{MockEvent, MockGroup, MockUpdate, MockUser};
+
+mod common;
+
+use common::{setup, token};
+
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2023, 10, day).unwrap()
+}
+
+#[tokio::test]
+async fn groups() {
+    let (mock, client) = setup().await;
+    mock.add_user(MockUser::new(1));
+    mock.add_user(MockUser::new(2));
+
+    let mut robotics = MockGroup::new(20);
+    robotics.title = "Robotics Club".to_string();
+
+    mock.add_group(robotics);
+    mock.add_group(MockGroup::new(21));
+    mock.join_group(20, 1, false);
+    mock.join_group(20, 2, true);
+    mock.join_group(21, 2, false);
+
+    let token = token(&mock, 1);
+
+    let groups = get_user_groups(&client, &token, 1).await.unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].id, 20);
+    assert_eq!(groups[0].title, "Robotics Club");
+    assert_eq!(groups[0].category, "Clubs");
+    assert_eq!(groups[0].school_id, Some(1));
+
+    let group = get_group(&client, &token, 21).await.unwrap();
+    assert_eq!(group.title, "Group 21");
+    assert!(get_group(&client, &token, 22)
+        .await
+        .unwrap_err()
+        .is_not_found());
+
+    let members = get_group_enrollments(&client, &token, 20).await.unwrap();
+    assert_eq!(members.len(), 2);
+    assert!(!members[0].admin);
+    assert_eq!(members[1].uid, 2);
+    assert!(members[1].admin, "club officers are admins");
+}
+
+#[tokio::test]
+async fn group_events_and_updates() {
+    let (mock, client) = setup().await;
+    mock.add_user(MockUser::new(1));
+    mock.add_group(MockGroup::new(20));
+    mock.add_group(MockGroup::new(21));
+    mock.join_group(20, 1, false);
+
+    let mut meeting = MockEvent::new(1, None, "2023-10-18 15:30:00");
+    meeting.group_id = Some(20);
+    mock.add_event(meeting);
+
+    let mut other_meeting = MockEvent::new(2, None, "2023-10-18 15:30:00");
+    other_meeting.group_id = Some(21);
+    mock.add_event(other_meeting);
+
+    let mut update = MockUpdate::new(3, None, 2, 1_697_900_000);
+    update.group_id = Some(20);
+    mock.add_update(update);
+
+    let mut other_update = MockUpdate::new(4, None, 2, 1_697_900_000);
+    other_update.group_id = Some(21);
+    mock.add_update(other_update);
+
+    let token = token(&mock, 1);
+
+    let events = get_group_events(&client, &token, 20, date(16), date(22))
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id, 1);
+    assert_eq!(events[0].realm, EventRealm::Group(20));
+
+    let page = get_group_updates(&client, &token, 20, 0, 20).await.unwrap();
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items[0].realm, UpdateRealm::Group(20));
+
+    // Only the groups of the user are on their feed
+    let page = get_recent_updates(&client, &token, 0, 20).await.unwrap();
+    let ids = page.items.iter().map(|u| u.id).collect::<Vec<usize>>();
+    assert_eq!(ids, vec![3]);
+}
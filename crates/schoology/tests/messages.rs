@@ -0,0 +1,96 @@
+//! Tests of the message endpoints against the mock Schoology server
+
+use schoology::messages::{get_inbox, get_sent, get_thread, reply};
+use schoology_mock::{MockMessage, MockThread, MockUser};
+
+mod common;
+
+use common::{setup, token};
+
+#[tokio::test]
+async fn inbox_and_threads() {
+    let (mock, client) = setup().await;
+    mock.add_user(MockUser::new(1));
+    mock.add_user(MockUser::new(2));
+    mock.add_user(MockUser::new(3));
+    mock.set_max_page_size(1);
+
+    let mut read = MockThread::new(10, 2, vec![1], 1_697_800_000);
+    read.unread.clear();
+    read.subject = "Field trip".to_string();
+    read.messages.push(MockMessage {
+        id: 12,
+        author_id: 1,
+        body: "See you there".to_string(),
+        created: 1_697_850_000,
+    });
+    mock.add_thread(read);
+    mock.add_thread(MockThread::new(11, 2, vec![1, 3], 1_697_900_000));
+    // Someone else's conversation
+    mock.add_thread(MockThread::new(13, 2, vec![3], 1_697_950_000));
+
+    let token = token(&mock, 1);
+
+    let inbox = get_inbox(&client, &token).await.unwrap();
+    let ids = inbox.iter().map(|t| t.id).collect::<Vec<usize>>();
+    assert_eq!(ids, vec![11, 10]);
+    assert!(inbox[0].unread);
+    assert!(!inbox[1].unread);
+    assert_eq!(inbox[0].author_id, 2);
+    assert_eq!(inbox[0].recipient_ids, vec![1, 3]);
+    assert_eq!(
+        inbox[0].last_updated.map(|time| time.timestamp()),
+        Some(1_697_900_000)
+    );
+
+    let messages = get_thread(&client, &token, 10).await.unwrap();
+    let ids = messages.iter().map(|m| m.id).collect::<Vec<usize>>();
+    assert_eq!(ids, vec![10, 12]);
+    assert_eq!(messages[0].subject, "Field trip");
+    assert_eq!(messages[1].body, "See you there");
+    assert_eq!(messages[1].recipient_ids, vec![2]);
+
+    // Reading a thread marks it as read
+    get_thread(&client, &token, 11).await.unwrap();
+    let inbox = get_inbox(&client, &token).await.unwrap();
+    assert!(inbox.iter().all(|thread| !thread.unread));
+
+    let err = get_thread(&client, &token, 13).await.unwrap_err();
+    assert!(err.is_not_found());
+
+    assert!(get_sent(&client, &token).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn reply_to_thread() {
+    let (mock, client) = setup().await;
+    mock.add_user(MockUser::new(1));
+    mock.add_user(MockUser::new(2));
+    mock.add_thread(MockThread::new(20, 2, vec![1], 1_697_800_000));
+
+    let student = token(&mock, 1);
+
+    let message = reply(&client, &student, 20, "Re: Message 20", "Thank you!", &[2])
+        .await
+        .unwrap();
+    assert_eq!(message.author_id, 1);
+    assert_eq!(message.body, "Thank you!");
+    assert_eq!(message.recipient_ids, vec![2]);
+
+    let thread = mock.thread(20).unwrap();
+    assert_eq!(thread.messages.len(), 2);
+    assert!(thread.unread.contains(&2));
+    assert!(!thread.unread.contains(&1));
+
+    // The reply shows up for the teacher
+    let teacher = token(&mock, 2);
+    let inbox = get_inbox(&client, &teacher).await.unwrap();
+    assert_eq!(inbox.len(), 1);
+    assert!(inbox[0].unread);
+    assert_eq!(get_sent(&client, &teacher).await.unwrap().len(), 1);
+
+    let err = reply(&client, &student, 21, "Re: Nothing", "Hello?", &[2])
+        .await
+        .unwrap_err();
+    assert!(err.is_not_found());
+}
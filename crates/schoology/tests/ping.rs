@@ -0,0 +1,30 @@
+//! Tests of the reachability check against the mock Schoology server
+
+use schoology::SchoologyClient;
+
+mod common;
+
+use common::setup;
+
+#[tokio::test]
+async fn ping() {
+    let (mock, client) = setup().await;
+
+    // Not signed, so it's answered with an error, but it was answered
+    client.ping().await.unwrap();
+
+    // Not retried
+    mock.fail_requests(503, 1);
+    let err = client.ping().await.unwrap_err();
+    assert!(err.is_unavailable());
+    client.ping().await.unwrap();
+}
+
+#[tokio::test]
+async fn ping_unreachable() {
+    let client = SchoologyClient::new("key".to_string(), "secret".to_string())
+        .with_base_url("http://127.0.0.1:9/".parse().unwrap());
+
+    let err = client.ping().await.unwrap_err();
+    assert!(err.is_transport());
+}
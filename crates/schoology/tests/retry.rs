@@ -0,0 +1,143 @@
+//! Tests of the rate limiter and retries against the mock Schoology server
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use schoology::{
+    messages::reply, users::get_schoology_user, RateLimiter, RetryPolicy, SchoologyClient,
+};
+use schoology_mock::{MockSchoology, MockThread, MockUser};
+
+mod common;
+
+use common::{setup, token};
+
+/// A client with it's own limiter and short delays
+async fn retrying_setup(max_delay: Duration) -> (MockSchoology, SchoologyClient) {
+    let (mock, client) = setup().await;
+
+    let client = client
+        .with_rate_limiter(Arc::new(RateLimiter::new(50, Duration::from_secs(5))))
+        .with_retry_policy(RetryPolicy {
+            max_retries: 2,
+            base_delay: Duration::from_millis(10),
+            max_delay,
+        });
+
+    (mock, client)
+}
+
+#[tokio::test]
+async fn retries_throttled_requests() {
+    let (mock, client) = retrying_setup(Duration::from_secs(2)).await;
+    mock.add_user(MockUser::new(1));
+
+    let token = token(&mock, 1);
+
+    // The mock asks to wait a second, the retry is signed again (the failed nonce is used up)
+    mock.fail_requests(429, 1);
+
+    let start = Instant::now();
+    let user = get_schoology_user(&client, &token, 1).await.unwrap();
+    assert_eq!(user.id, 1);
+    assert!(start.elapsed() >= Duration::from_secs(1));
+    assert_eq!(mock.requests(), 1);
+}
+
+#[tokio::test]
+async fn gives_up_when_throttled_for_too_long() {
+    let (mock, client) = retrying_setup(Duration::from_millis(100)).await;
+    mock.add_user(MockUser::new(1));
+
+    let token = token(&mock, 1);
+
+    mock.fail_requests(429, 1);
+
+    let err = get_schoology_user(&client, &token, 1).await.unwrap_err();
+    assert!(err.is_rate_limited());
+    assert_eq!(mock.requests(), 0);
+}
+
+#[tokio::test]
+async fn retries_server_errors() {
+    let (mock, client) = retrying_setup(Duration::from_secs(2)).await;
+    mock.add_user(MockUser::new(1));
+
+    let token = token(&mock, 1);
+
+    mock.fail_requests(503, 2);
+    assert_eq!(get_schoology_user(&client, &token, 1).await.unwrap().id, 1);
+
+    // Out of retries
+    mock.fail_requests(500, 3);
+    let err = get_schoology_user(&client, &token, 1).await.unwrap_err();
+    assert!(err.is_unavailable());
+
+    assert_eq!(mock.requests(), 1);
+}
+
+#[tokio::test]
+async fn does_not_retry_failed_posts() {
+    let (mock, client) = retrying_setup(Duration::from_secs(2)).await;
+    mock.add_user(MockUser::new(1));
+    mock.add_user(MockUser::new(2));
+    mock.add_thread(MockThread::new(30, 2, vec![1], 1_697_800_000));
+
+    let token = token(&mock, 1);
+
+    // The message may have been sent, so it's not sent again
+    mock.fail_requests(500, 1);
+    let err = reply(&client, &token, 30, "Re: Message 30", "Hi", &[2])
+        .await
+        .unwrap_err();
+    assert!(err.is_unavailable());
+
+    // It was throttled, so it was never sent
+    mock.fail_requests(429, 1);
+    reply(&client, &token, 30, "Re: Message 30", "Hi", &[2])
+        .await
+        .unwrap();
+
+    assert_eq!(mock.thread(30).unwrap().messages.len(), 2);
+}
+
+#[test]
+fn rate_limiter() {
+    let limiter = RateLimiter::new(2, Duration::from_secs(1));
+
+    assert_eq!(limiter.reserve(), Duration::ZERO);
+    assert_eq!(limiter.reserve(), Duration::ZERO);
+
+    // The bucket is empty, tokens come back every half a second
+    let wait = limiter.reserve();
+    assert!(wait > Duration::from_millis(400) && wait <= Duration::from_millis(500));
+    let wait = limiter.reserve();
+    assert!(wait > Duration::from_millis(900) && wait <= Duration::from_secs(1));
+
+    limiter.pause(Duration::from_secs(3));
+    assert!(limiter.reserve() > Duration::from_secs(3));
+}
+
+#[test]
+fn retry_delays() {
+    let policy = RetryPolicy {
+        max_retries: 5,
+        base_delay: Duration::from_millis(100),
+        max_delay: Duration::from_secs(1),
+    };
+
+    for retries in 0..5 {
+        let backoff = Duration::from_millis(100 * 2u64.pow(retries)).min(Duration::from_secs(1));
+        let delay = policy.delay(retries, None).unwrap();
+
+        assert!(delay >= backoff / 2 && delay <= backoff, "{:?}", delay);
+    }
+
+    assert_eq!(
+        policy.delay(0, Some(Duration::from_millis(500))),
+        Some(Duration::from_millis(500))
+    );
+    assert_eq!(policy.delay(0, Some(Duration::from_secs(2))), None);
+}
@@ -0,0 +1,105 @@
+//! Tests of the section and course endpoints against the mock Schoology server
+
+use schoology::sections::{get_course, get_section, get_section_enrollments, get_user_sections};
+use schoology_mock::{MockSection, MockUser};
+
+mod common;
+
+use common::{setup, token};
+
+#[tokio::test]
+async fn user_sections() {
+    let (mock, client) = setup().await;
+    mock.add_user(MockUser::new(1));
+    mock.add_user(MockUser::new(2));
+
+    mock.add_section(MockSection::new(10, 100));
+    mock.add_section(MockSection::new(11, 101));
+    mock.add_section(MockSection::new(12, 102));
+    mock.enroll(10, 1, false);
+    mock.enroll(11, 1, false);
+    mock.enroll(12, 2, false);
+
+    let sections = get_user_sections(&client, &token(&mock, 1), 1)
+        .await
+        .unwrap();
+
+    let ids = sections.iter().map(|s| s.id).collect::<Vec<usize>>();
+    assert_eq!(ids, vec![10, 11]);
+    assert_eq!(sections[0].course_id, 100);
+    assert_eq!(sections[0].course_title, "Course 100");
+    assert!(sections[0].active);
+    assert_eq!(sections[0].grading_periods, vec![1]);
+}
+
+#[tokio::test]
+async fn paginates() {
+    let (mock, client) = setup().await;
+    mock.add_user(MockUser::new(1));
+    mock.set_max_page_size(2);
+
+    for id in 0..5 {
+        mock.add_section(MockSection::new(id + 10, id + 100));
+        mock.enroll(id + 10, 1, false);
+    }
+
+    let sections = get_user_sections(&client, &token(&mock, 1), 1)
+        .await
+        .unwrap();
+
+    assert_eq!(sections.len(), 5);
+    // 2 + 2 + 1
+    assert_eq!(mock.requests(), 3);
+}
+
+#[tokio::test]
+async fn empty_list() {
+    let (mock, client) = setup().await;
+    mock.add_user(MockUser::new(1));
+
+    let sections = get_user_sections(&client, &token(&mock, 1), 1)
+        .await
+        .unwrap();
+
+    assert!(sections.is_empty());
+}
+
+#[tokio::test]
+async fn section_and_course() {
+    let (mock, client) = setup().await;
+    mock.add_section(MockSection::new(10, 100));
+
+    let token = token(&mock, 1);
+
+    let section = get_section(&client, &token, 10).await.unwrap();
+    assert_eq!(section.section_title, "Section 10");
+
+    let course = get_course(&client, &token, section.course_id)
+        .await
+        .unwrap();
+    assert_eq!(course.id, 100);
+    assert_eq!(course.course_code, "C100");
+
+    let err = get_section(&client, &token, 11).await.unwrap_err();
+    assert!(err.is_not_found());
+}
+
+#[tokio::test]
+async fn enrollments() {
+    let (mock, client) = setup().await;
+    mock.add_user(MockUser::new(1));
+    mock.add_user(MockUser::new(2));
+    mock.add_section(MockSection::new(10, 100));
+    mock.enroll(10, 1, true);
+    mock.enroll(10, 2, false);
+
+    let enrollments = get_section_enrollments(&client, &token(&mock, 2), 10)
+        .await
+        .unwrap();
+
+    assert_eq!(enrollments.len(), 2);
+    assert_eq!(enrollments[0].uid, 1);
+    assert!(enrollments[0].admin);
+    assert!(!enrollments[1].admin);
+    assert_eq!(enrollments[1].status, Some(1));
+}
@@ -0,0 +1,76 @@
+//! Tests of the form body signatures against the mock Schoology server
+
+use reqwest::StatusCode;
+use schoology::{proto::OAuth1AHeader, SchoologyRequest};
+use schoology_mock::MockSchoology;
+
+mod common;
+
+use common::setup;
+
+/// Sends a form POST to `/oauth/request_token` signed over `signed`, but with `sent` as the body
+async fn send_form(
+    mock: &MockSchoology,
+    signed: &[(&str, &str)],
+    sent: &[(&str, &str)],
+) -> reqwest::Response {
+    let url = mock.base_url().join("oauth/request_token").unwrap();
+
+    let header = OAuth1AHeader::new("POST".to_string(), url.to_string(), None, None).get_header(
+        "POST",
+        &url,
+        Some(signed.to_vec()),
+        mock.consumer_key(),
+        mock.consumer_secret(),
+    );
+
+    reqwest::Client::new()
+        .post(url)
+        .header("Authorization", header)
+        .form(sent)
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn signs_form_bodies() {
+    let (mock, client) = setup().await;
+
+    // Reserved characters are encoded the same way in the body and the signature
+    let response = client
+        .post(
+            "oauth/request_token",
+            SchoologyRequest::new().with_form_body(vec![
+                ("scope".to_string(), "all & more".to_string()),
+                ("note".to_string(), "a+b=c/d".to_string()),
+            ]),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.text().await.unwrap().contains("oauth_token="));
+    assert_eq!(mock.requests(), 1);
+}
+
+#[tokio::test]
+async fn rejects_tampered_form_bodies() {
+    let (mock, _client) = setup().await;
+
+    let body = [("scope", "read")];
+
+    let response = send_form(&mock, &body, &body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The body was changed after it was signed
+    let response = send_form(&mock, &body, &[("scope", "write")]).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response.text().await.unwrap(), "Invalid signature");
+
+    // Or signed without it
+    let response = send_form(&mock, &[], &body).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    assert_eq!(mock.requests(), 1);
+}
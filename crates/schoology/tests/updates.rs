@@ -0,0 +1,109 @@
+//! Tests of the update endpoints against the mock Schoology server
+
+use schoology::updates::{
+    get_recent_updates, get_section_updates, get_update_comments, AttachmentKind, UpdateRealm,
+};
+use schoology_mock::{MockAttachment, MockComment, MockSection, MockUpdate, MockUser};
+
+mod common;
+
+use common::{setup, token};
+
+#[tokio::test]
+async fn recent_updates() {
+    let (mock, client) = setup().await;
+    mock.add_user(MockUser::new(1));
+    mock.add_section(MockSection::new(10, 100));
+    mock.add_section(MockSection::new(11, 101));
+    mock.enroll(10, 1, false);
+    mock.set_max_page_size(2);
+
+    let mut announcement = MockUpdate::new(1, Some(10), 2, 1_697_900_000);
+    announcement.likes = 3;
+    announcement.attachments = vec![
+        MockAttachment {
+            id: 5,
+            kind: "file".to_string(),
+            title: "Syllabus".to_string(),
+            url: "https://example.com/syllabus".to_string(),
+        },
+        MockAttachment {
+            id: 6,
+            kind: "link".to_string(),
+            title: "Khan Academy".to_string(),
+            url: "https://www.khanacademy.org".to_string(),
+        },
+    ];
+    announcement.comments = vec![MockComment {
+        id: 7,
+        author_id: 1,
+        body: "Thanks!".to_string(),
+        created: 1_697_900_100,
+    }];
+
+    mock.add_update(announcement);
+    mock.add_update(MockUpdate::new(2, None, 3, 1_697_800_000));
+    mock.add_update(MockUpdate::new(3, Some(10), 2, 1_697_700_000));
+    // Not enrolled
+    mock.add_update(MockUpdate::new(4, Some(11), 2, 1_697_950_000));
+
+    let token = token(&mock, 1);
+
+    let page = get_recent_updates(&client, &token, 0, 2).await.unwrap();
+    let ids = page.items.iter().map(|u| u.id).collect::<Vec<usize>>();
+    assert_eq!(ids, vec![1, 2]);
+    assert_eq!(page.total, Some(3));
+    assert!(page.has_more);
+
+    let update = &page.items[0];
+    assert_eq!(update.realm, UpdateRealm::Section(10));
+    assert_eq!(update.author_id, 2);
+    assert_eq!(update.likes, 3);
+    assert_eq!(update.comment_count, 1);
+    assert_eq!(update.created.unwrap().timestamp(), 1_697_900_000);
+    assert_eq!(update.attachments.len(), 2);
+    assert_eq!(update.attachments[0].kind, AttachmentKind::File);
+    assert_eq!(update.attachments[0].url, "https://example.com/syllabus");
+    assert_eq!(update.attachments[1].kind, AttachmentKind::Link);
+
+    assert_eq!(page.items[1].realm, UpdateRealm::School(1));
+    assert!(page.items[1].attachments.is_empty());
+
+    let page = get_recent_updates(&client, &token, 2, 2).await.unwrap();
+    let ids = page.items.iter().map(|u| u.id).collect::<Vec<usize>>();
+    assert_eq!(ids, vec![3]);
+    assert!(!page.has_more);
+
+    let comments = get_update_comments(&client, &token, update.realm, update.id)
+        .await
+        .unwrap();
+    assert_eq!(comments.len(), 1);
+    assert_eq!(comments[0].body, "Thanks!");
+    assert_eq!(comments[0].author_id, 1);
+    assert_eq!(comments[0].parent_id, None);
+}
+
+#[tokio::test]
+async fn section_updates() {
+    let (mock, client) = setup().await;
+    mock.add_section(MockSection::new(10, 100));
+
+    mock.add_update(MockUpdate::new(1, Some(10), 2, 1_697_700_000));
+    mock.add_update(MockUpdate::new(2, Some(10), 2, 1_697_800_000));
+    mock.add_update(MockUpdate::new(3, None, 3, 1_697_900_000));
+
+    let page = get_section_updates(&client, &token(&mock, 1), 10, 0, 20)
+        .await
+        .unwrap();
+
+    let ids = page.items.iter().map(|u| u.id).collect::<Vec<usize>>();
+    assert_eq!(ids, vec![2, 1]);
+    assert!(!page.has_more);
+    // The realm is known from the section
+    assert_eq!(page.items[0].realm, UpdateRealm::Section(10));
+
+    assert!(get_section_updates(&client, &token(&mock, 1), 12, 0, 20)
+        .await
+        .unwrap_err()
+        .is_not_found());
+}